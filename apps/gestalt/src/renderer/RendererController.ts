@@ -139,16 +139,584 @@ export function setDepthPrepass(enabled: boolean) {
   renderer?.set_depth_prepass(enabled);
 }
 
-/** Load an OBJ model: parse → voxelize → upload → render. Returns model info for camera framing. */
-export function loadModel(objText: string, resolution: number): { center: [number, number, number]; extent: number } {
+/** Chunks voxelized per step of a mesh load before yielding to the event loop. */
+const VOXELIZE_CHUNKS_PER_STEP = 4;
+
+/** Called between steps of a mesh load with the chunks voxelized so far and in total. */
+export type VoxelizeProgress = (done: number, total: number) => void;
+
+/** Settles after every async load queued so far; never rejects. */
+let loadQueue: Promise<unknown> = Promise.resolve();
+
+/**
+ * Run an async load once every load queued before it has settled, so two loads never step
+ * the renderer's one stepped load (or volume stream) at the same time.
+ */
+function queueLoad<T>(load: (r: Renderer) => Promise<T>): Promise<T> {
+  if (!renderer) return Promise.reject(new Error("Renderer not initialized"));
+  const r = renderer;
+  const run = loadQueue.then(() => {
+    if (renderer !== r) throw new Error("Renderer destroyed during load");
+    return load(r);
+  });
+  loadQueue = run.catch(() => undefined);
+  return run;
+}
+
+/**
+ * Drive the stepped load with `token` from a `begin_*_voxelize` call: voxelize a few chunks, yield
+ * so the page keeps rendering and handling input, repeat, then upload. Returns the model ID.
+ * Rejects if a synchronous load abandons it in between; on any failure the job is cancelled.
+ */
+async function runVoxelize(
+  r: Renderer,
+  token: number,
+  onProgress?: VoxelizeProgress,
+): Promise<number> {
+  try {
+    while (!r.step_voxelize(token, VOXELIZE_CHUNKS_PER_STEP)) {
+      const [done, total] = r.get_voxelize_progress(token);
+      onProgress?.(done, total);
+      await new Promise((resolve) => setTimeout(resolve, 0));
+      if (renderer !== r) throw new Error("Renderer destroyed during load");
+    }
+    return r.finish_voxelize(token);
+  } catch (err) {
+    if (renderer === r) r.cancel_voxelize(token);
+    throw err;
+  }
+}
+
+/** Model center/extent from WASM for orbit camera reset. */
+function meshFraming(r: Renderer): { center: [number, number, number]; extent: number } {
+  const c = r.get_mesh_center();
+  const e = r.get_mesh_extent();
+  return { center: [c[0], c[1], c[2]], extent: e };
+}
+
+/**
+ * Load an OBJ model: parse → voxelize → upload → render. Returns model info for camera framing.
+ * `mtlFiles` maps `mtllib` file names to MTL text; without it materials get hashed colors.
+ * Voxelization is spread over event-loop turns; `onProgress` reports it.
+ */
+export async function loadModel(
+  objText: string,
+  resolution: number,
+  mtlFiles?: Map<string, string>,
+  onProgress?: VoxelizeProgress,
+): Promise<{ center: [number, number, number]; extent: number }> {
+  return queueLoad(async (r) => {
+    const token = r.begin_obj_voxelize(objText, mtlFiles ?? new Map(), resolution, false);
+    await runVoxelize(r, token, onProgress);
+    return meshFraming(r);
+  });
+}
+
+/** Toggle strict OBJ parsing: the first malformed statement fails the load with its line/column. */
+export function setObjStrict(enabled: boolean) {
+  renderer?.set_obj_strict(enabled);
+}
+
+/** Toggle solid voxelization: mesh loaders fill the interior of closed meshes. */
+export function setSolidFill(enabled: boolean) {
+  renderer?.set_solid_fill(enabled);
+}
+
+/** Material for solid-fill interior voxels. Pass null to inherit the surface material. */
+export function setInteriorMaterial(
+  material: { color: [number, number, number]; roughness: number } | null,
+) {
+  if (!material) {
+    renderer?.clear_interior_material();
+    return;
+  }
+  const [r, g, b] = material.color;
+  renderer?.set_interior_material(r, g, b, material.roughness);
+}
+
+/**
+ * How mesh loads pick the material of a voxel covered by several materials: the first triangle
+ * in file order, the most area inside the voxel, the triangle closest to the voxel center, or
+ * the earliest material name in a priority list.
+ */
+export function setMaterialConflict(
+  policy: "first" | "area" | "closest" | { priority: string[] },
+) {
+  if (!renderer) return;
+  if (typeof policy === "object") {
+    renderer.set_material_conflict(3, policy.priority);
+    return;
+  }
+  const modes = { first: 0, area: 1, closest: 2 };
+  renderer.set_material_conflict(modes[policy], []);
+}
+
+/**
+ * Where mesh loads take surface voxel colors from: the triangle's material, the OBJ's per-vertex
+ * colors, or a texture sampled at each voxel's texcoord. Pass the decoded image for "texture".
+ */
+export function setColorSource(
+  source:
+    | "material"
+    | "vertex"
+    | { texture: { width: number; height: number; data: Uint8ClampedArray | Uint8Array } },
+) {
+  if (!renderer) return;
+  if (typeof source === "object") {
+    const { width, height, data } = source.texture;
+    const rgba = new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+    renderer.set_texture(width, height, rgba);
+    renderer.set_color_source(2);
+    return;
+  }
+  renderer.clear_texture();
+  renderer.set_color_source(source === "vertex" ? 1 : 0);
+}
+
+/**
+ * Anchor mesh loads to a shared world grid so several models line up voxel-for-voxel.
+ * Pass null to fit the grid to each model at the requested resolution again.
+ */
+export function setWorldGrid(grid: { voxelSize: number; origin: [number, number, number] } | null) {
+  if (!renderer) return;
+  if (!grid) {
+    renderer.clear_world_grid();
+    return;
+  }
+  renderer.set_world_grid(grid.voxelSize, grid.origin[0], grid.origin[1], grid.origin[2]);
+}
+
+/** Model-to-world transform for subsequent mesh loads (rotation is a quaternion x, y, z, w). */
+export function setModelTransform(
+  translation: [number, number, number],
+  rotation: [number, number, number, number],
+  scale: [number, number, number],
+) {
+  renderer?.set_model_trs(
+    new Float32Array(translation),
+    new Float32Array(rotation),
+    new Float32Array(scale),
+  );
+}
+
+/** Load a glTF 2.0 model (.glb, or .gltf with embedded buffers). */
+export async function loadGltfModel(
+  bytes: Uint8Array,
+  resolution: number,
+  onProgress?: VoxelizeProgress,
+): Promise<{ center: [number, number, number]; extent: number }> {
+  return queueLoad(async (r) => {
+    await runVoxelize(r, r.begin_gltf_voxelize(bytes, resolution, false), onProgress);
+    return meshFraming(r);
+  });
+}
+
+/** Load an STL model (binary or ASCII). */
+export async function loadStlModel(
+  bytes: Uint8Array,
+  resolution: number,
+  onProgress?: VoxelizeProgress,
+): Promise<{ center: [number, number, number]; extent: number }> {
+  return queueLoad(async (r) => {
+    await runVoxelize(r, r.begin_stl_voxelize(bytes, resolution, false), onProgress);
+    return meshFraming(r);
+  });
+}
+
+/** Load a MagicaVoxel .vox file (voxels map 1:1, no voxelization). */
+export function loadVoxModel(
+  bytes: Uint8Array,
+): { center: [number, number, number]; extent: number } {
+  if (!renderer) throw new Error("Renderer not initialized");
+  renderer.load_vox_model(bytes);
+  const c = renderer.get_mesh_center();
+  const e = renderer.get_mesh_extent();
+  return { center: [c[0], c[1], c[2]], extent: e };
+}
+
+/**
+ * Load a seeded procedural world: fractal hills over stone with caves and ore veins. Covers
+ * `chunksXZ` × `chunksXZ` chunk columns, `chunksBelow` chunk layers under y = 0 and
+ * `chunksAbove` from y = 0 up. The same seed always produces the same world.
+ */
+export function loadProceduralWorld(
+  seed: number,
+  chunksXZ: number,
+  chunksBelow = 1,
+  chunksAbove = 1,
+): { center: [number, number, number]; extent: number } {
+  if (!renderer) throw new Error("Renderer not initialized");
+  renderer.load_procedural_world(seed >>> 0, chunksXZ, chunksBelow, chunksAbove);
+  const c = renderer.get_mesh_center();
+  const e = renderer.get_mesh_extent();
+  return { center: [c[0], c[1], c[2]], extent: e };
+}
+
+interface HeightmapBand {
+  /** Highest voxel Y the band covers (default unbounded). */
+  maxHeight?: number;
+  /** Steepest slope in degrees the band covers (default unbounded). */
+  maxSlope?: number;
+  color?: [number, number, number];
+  roughness?: number;
+  emissive?: [number, number, number];
+}
+
+/**
+ * Load solid terrain from a heightmap: raw 16-bit little-endian samples of the given size, or a
+ * PGM file (its header gives the size). Each voxel takes the first band whose height and slope
+ * limits it meets; omitted options keep the defaults (64 voxels of relief, sand/grass/rock/snow).
+ */
+export function loadHeightmap(
+  bytes: Uint8Array,
+  width: number,
+  height: number,
+  options: { verticalScale?: number; floor?: number; bands?: HeightmapBand[] } = {},
+): { center: [number, number, number]; extent: number } {
+  if (!renderer) throw new Error("Renderer not initialized");
+  renderer.load_heightmap(bytes, width, height, JSON.stringify(options));
+  const c = renderer.get_mesh_center();
+  const e = renderer.get_mesh_extent();
+  return { center: [c[0], c[1], c[2]], extent: e };
+}
+
+/**
+ * Load a point cloud (PLY, ASCII or binary, or XYZ text) binned into voxels of `voxelSize`.
+ * Voxels with fewer than `minPoints` points are dropped as noise; point colors are averaged.
+ */
+export function loadPointCloud(
+  bytes: Uint8Array,
+  voxelSize: number,
+  minPoints = 1,
+): { center: [number, number, number]; extent: number } {
   if (!renderer) throw new Error("Renderer not initialized");
-  renderer.load_obj_model(objText, resolution);
-  // Read back model center/extent from WASM for orbit camera reset
+  renderer.load_point_cloud(bytes, voxelSize, minPoints);
   const c = renderer.get_mesh_center();
   const e = renderer.get_mesh_extent();
   return { center: [c[0], c[1], c[2]], extent: e };
 }
 
+/** Add a point cloud to the current scene on its grid. Returns the model ID. */
+export function addPointCloudModel(bytes: Uint8Array, voxelSize: number, minPoints = 1): number {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.add_point_cloud_model(bytes, voxelSize, minPoints);
+}
+
+interface VolumeMaterial {
+  color?: [number, number, number];
+  roughness?: number;
+  emissive?: [number, number, number];
+}
+
+/**
+ * Load a dense scalar volume: an NRRD file (raw encoding), or headerless 8/16-bit samples whose
+ * `dims` and `type` must be given. Samples at or above `threshold` become solid, or each takes
+ * the first `transfer` range containing it; the default threshold keeps every positive sample.
+ */
+export function loadVolume(
+  bytes: Uint8Array,
+  options: VolumeOptions = {},
+): { center: [number, number, number]; extent: number } {
+  if (!renderer) throw new Error("Renderer not initialized");
+  renderer.load_volume(bytes, JSON.stringify(options));
+  const c = renderer.get_mesh_center();
+  const e = renderer.get_mesh_extent();
+  return { center: [c[0], c[1], c[2]], extent: e };
+}
+
+type VolumeOptions = {
+  dims?: [number, number, number];
+  spacing?: [number, number, number];
+  type?: "uint8" | "uint16" | "int16";
+  endian?: "little" | "big";
+  threshold?: number;
+  transfer?: ({ min?: number; max?: number } & VolumeMaterial)[];
+} & VolumeMaterial;
+
+/**
+ * Load a volume file too large for one buffer in wasm memory (a multi-GiB scan): it is read and
+ * imported `pieceBytes` at a time, so only one piece is held at once. Same options as
+ * `loadVolume`.
+ */
+export async function loadVolumeFile(
+  file: Blob,
+  options: VolumeOptions = {},
+  pieceBytes = 64 << 20,
+): Promise<{ center: [number, number, number]; extent: number }> {
+  return queueLoad(async (r) => {
+    r.begin_volume(JSON.stringify(options));
+    for (let start = 0; start < file.size; start += pieceBytes) {
+      const piece = await file.slice(start, start + pieceBytes).arrayBuffer();
+      if (renderer !== r) throw new Error("Renderer destroyed during load");
+      r.push_volume(new Uint8Array(piece));
+    }
+    r.finish_volume();
+    return meshFraming(r);
+  });
+}
+
+type SdfNode = {
+  type: "sphere" | "box" | "cylinder" | "torus" | "capsule" | "union" | "intersect" | "subtract"
+    | "smoothUnion";
+  /** Index into the scene's `materials` (primitives only, default 0). */
+  material?: number;
+  children?: SdfNode[];
+  translate?: [number, number, number];
+  /** XYZ Euler angles in degrees. */
+  rotate?: [number, number, number];
+  scale?: number;
+  [member: string]: unknown;
+};
+
+/**
+ * Load a signed-distance scene: primitives (`sphere`, `box`, `cylinder`, `torus`, `capsule`)
+ * combined with `union`/`intersect`/`subtract`/`smoothUnion` and per-node transforms. One voxel
+ * per world unit; see `SdfScene::from_json` for each node's members.
+ */
+export function loadSdfScene(scene: {
+  materials?: {
+    color?: [number, number, number];
+    roughness?: number;
+    emissive?: [number, number, number];
+  }[];
+  root: SdfNode;
+}): { center: [number, number, number]; extent: number } {
+  if (!renderer) throw new Error("Renderer not initialized");
+  renderer.load_sdf_scene(JSON.stringify(scene));
+  const c = renderer.get_mesh_center();
+  const e = renderer.get_mesh_extent();
+  return { center: [c[0], c[1], c[2]], extent: e };
+}
+
+/**
+ * Add a model to the current scene without clearing it. Unless a world grid is set, it snaps
+ * to the scene's grid so overlapping models line up. Returns the model ID for `removeModel`.
+ */
+export async function addModel(
+  objText: string,
+  resolution: number,
+  mtlFiles?: Map<string, string>,
+  onProgress?: VoxelizeProgress,
+): Promise<number> {
+  return queueLoad((r) => {
+    const token = r.begin_obj_voxelize(objText, mtlFiles ?? new Map(), resolution, true);
+    return runVoxelize(r, token, onProgress);
+  });
+}
+
+/** Add a glTF 2.0 model to the current scene. Returns the model ID. */
+export async function addGltfModel(
+  bytes: Uint8Array,
+  resolution: number,
+  onProgress?: VoxelizeProgress,
+): Promise<number> {
+  return queueLoad((r) =>
+    runVoxelize(r, r.begin_gltf_voxelize(bytes, resolution, true), onProgress),
+  );
+}
+
+/** Add an STL model to the current scene. Returns the model ID. */
+export async function addStlModel(
+  bytes: Uint8Array,
+  resolution: number,
+  onProgress?: VoxelizeProgress,
+): Promise<number> {
+  return queueLoad((r) =>
+    runVoxelize(r, r.begin_stl_voxelize(bytes, resolution, true), onProgress),
+  );
+}
+
+/** Add a MagicaVoxel .vox model to the current scene. Returns the model ID. */
+export function addVoxModel(bytes: Uint8Array): number {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.add_vox_model(bytes);
+}
+
+/** Remove a model; only the chunks it covered are re-uploaded and re-meshed. */
+export function removeModel(id: number) {
+  renderer?.remove_model(id);
+}
+
+/** IDs of the models in the scene, in load order. */
+export function getModelIds(): number[] {
+  return Array.from(renderer?.get_model_ids() ?? []);
+}
+
+/**
+ * Make one chunk resident at chunk coord (x, y, z), replacing any chunk there.
+ * Data is in upload layout: 8192-word occupancy, palette material IDs (entry 0
+ * = empty), and per-voxel palette indices packed at the palette's bit width.
+ */
+export function makeResident(
+  x: number,
+  y: number,
+  z: number,
+  occupancy: Uint32Array,
+  palette: Uint16Array,
+  indexBuf: Uint32Array,
+) {
+  if (!renderer) throw new Error("Renderer not initialized");
+  renderer.make_resident(x, y, z, occupancy, palette, indexBuf);
+}
+
+/** Evict the chunk at chunk coord (x, y, z); false if none was resident. */
+export function evictChunk(x: number, y: number, z: number): boolean {
+  return renderer?.evict(x, y, z) ?? false;
+}
+
+/**
+ * Set the voxel at grid position (x, y, z) to a scene material ID; 0 clears
+ * it. Returns the number of chunks re-uploaded.
+ */
+export function setVoxel(x: number, y: number, z: number, material: number): number {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.set_voxel(x, y, z, material);
+}
+
+/** Fill the inclusive grid box between corners `a` and `b`; material 0 clears. */
+export function fillBox(
+  a: [number, number, number],
+  b: [number, number, number],
+  material: number,
+): number {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.fill_box(new Int32Array(a), new Int32Array(b), material);
+}
+
+/** Fill every voxel whose center is within `radius` of a grid position; material 0 clears. */
+export function fillSphere(
+  center: [number, number, number],
+  radius: number,
+  material: number,
+): number {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.fill_sphere(center[0], center[1], center[2], radius, material);
+}
+
+/** Replace one scene material with another in every resident chunk. */
+export function replaceMaterial(from: number, to: number): number {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.replace_material(from, to);
+}
+
+/** Export the resident scene as MagicaVoxel .vox bytes. */
+export function exportVox(): Uint8Array {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.export_vox();
+}
+
+/**
+ * Export the resident scene's greedy mesh as OBJ text plus its MTL library.
+ * `weld` merges vertices shared across chunk borders.
+ */
+export function exportObj(
+  weld = false,
+  mtlName = "scene.mtl",
+): { obj: string; mtl: string } {
+  if (!renderer) throw new Error("Renderer not initialized");
+  const [obj, mtl] = renderer.export_obj(weld, mtlName);
+  return { obj, mtl };
+}
+
+/** Export the resident scene's greedy mesh as binary glTF (.glb) bytes. */
+export function exportGlb(weld = false): Uint8Array {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.export_glb(weld);
+}
+
+/** Save the resident scene in the native scene format. */
+export function saveScene(): Uint8Array {
+  if (!renderer) throw new Error("Renderer not initialized");
+  return renderer.save_scene();
+}
+
+/** Load a scene saved by `saveScene`. */
+export function loadScene(
+  bytes: Uint8Array,
+): { center: [number, number, number]; extent: number } {
+  if (!renderer) throw new Error("Renderer not initialized");
+  renderer.load_scene(bytes);
+  const c = renderer.get_mesh_center();
+  const e = renderer.get_mesh_extent();
+  return { center: [c[0], c[1], c[2]], extent: e };
+}
+
+/** Line-numbered warnings from the last OBJ load (skipped faces, malformed statements). */
+export function getObjWarnings(): string[] {
+  return renderer?.get_obj_warnings() ?? [];
+}
+
+interface QuantizeStats {
+  colorsIn: number;
+  colorsOut: number;
+  voxels: number;
+  /** Voxel-weighted mean Oklab distance between a color and the color it became. */
+  meanError: number;
+  maxError: number;
+}
+
+/**
+ * Color error from the last model load: colored voxels fitted into the material table, then
+ * materials merged to fit chunk palettes. All zero when nothing had to merge.
+ */
+export function getQuantizeStats(): { materials: QuantizeStats; palettes: QuantizeStats } | null {
+  if (!renderer) return null;
+  const s = renderer.get_quantize_stats();
+  const stage = (i: number): QuantizeStats => ({
+    colorsIn: s[i],
+    colorsOut: s[i + 1],
+    voxels: s[i + 2],
+    meanError: s[i + 3],
+    maxError: s[i + 4],
+  });
+  return { materials: stage(0), palettes: stage(5) };
+}
+
+export interface IndexBufStats {
+  usedWords: number;
+  freeWords: number;
+  liveRanges: number;
+  freeRanges: number;
+  largestFree: number;
+  /** Share of free space outside the largest free range (0 = unfragmented). */
+  fragmentation: number;
+}
+
+/** Occupancy of the shared per-voxel index buffer pool. */
+export function getIndexBufStats(): IndexBufStats | null {
+  if (!renderer) return null;
+  const s = renderer.get_index_buf_stats();
+  return {
+    usedWords: s[0],
+    freeWords: s[1],
+    liveRanges: s[2],
+    freeRanges: s[3],
+    largestFree: s[4],
+    fragmentation: s[5],
+  };
+}
+
+/** Compact the index buffer pool on the GPU; returns the number of ranges moved. */
+export function compactIndexBuf(): number {
+  return renderer?.compact_index_buf() ?? 0;
+}
+
+export interface SlotTableStats {
+  entries: number;
+  capacity: number;
+  /** Longest probe sequence a GPU lookup may walk. */
+  maxProbe: number;
+  /** Resident chunks missing from the table, invisible to GI traversal. */
+  unreachable: number;
+}
+
+/** Occupancy of the GPU chunk→slot hash table used by GI traversal. */
+export function getSlotTableStats(): SlotTableStats | null {
+  if (!renderer) return null;
+  const s = renderer.get_slot_table_stats();
+  return { entries: s[0], capacity: s[1], maxProbe: s[2], unreachable: s[3] };
+}
+
 /** Toggle CPU mesh path (bypasses GPU mesh_rebuild compute shader). */
 export function setUseCpuMesh(enabled: boolean) {
   renderer?.set_use_cpu_mesh(enabled);
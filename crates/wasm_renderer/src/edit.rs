@@ -0,0 +1,665 @@
+//! Voxel editing on resident chunks — no GPU, no JS types.
+//!
+//! Edits address voxels by scene grid position and write straight into the
+//! CPU chunk map the renderer uploads from, adding palette entries as new
+//! materials appear (the upload repacks the index buffer at the palette's
+//! new bits per entry). Each edit returns the chunk coords it changed, so
+//! only those are re-uploaded and re-meshed.
+//!
+//! That includes the face neighbors of every boundary voxel whose
+//! occupancy changed: the edit mirrors it into the neighbor's padding
+//! shell, which the neighbor's face culling reads. Materials are only ever
+//! read from the owning chunk, so padding carries occupancy alone. Writes
+//! into unloaded space create chunks; chunks whose interior an edit leaves
+//! empty are removed from the map.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::pool::*;
+use crate::scene::{ChunkData, IndexBufBuilder, OccupancyBuilder, PaletteBuilder};
+
+/// Most chunks one edit may span, so a stray box can't walk the whole grid.
+/// An edit filling more than this could never be resident anyway.
+pub const MAX_EDIT_CHUNKS: u64 = MAX_SLOTS as u64;
+
+/// Why an edit was rejected. Nothing is written when an edit fails.
+///
+/// An edit that succeeds here can still fail to upload; the [`Undo`] it
+/// filled puts the chunks back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditError {
+    /// The material ID is outside the scene material table.
+    MaterialOutOfRange(u16),
+    /// `replace_material` can't replace the empty material or replace with it.
+    EmptyReplacement,
+    /// The chunk's palette holds `MAX_PALETTE_ENTRIES` materials, all in use.
+    PaletteFull(ChunkCoord),
+    /// The edit spans more than `MAX_EDIT_CHUNKS` chunks.
+    TooLarge { chunks: u64 },
+    /// The sphere's center or radius is not finite, or the radius is negative.
+    InvalidSphere,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::MaterialOutOfRange(id) => {
+                write!(f, "material {id} is outside the material table ({MAX_MATERIALS} entries)")
+            }
+            EditError::EmptyReplacement => {
+                write!(f, "cannot replace the empty material or replace a material with it")
+            }
+            EditError::PaletteFull(c) => write!(
+                f,
+                "chunk ({},{},{}) already uses {MAX_PALETTE_ENTRIES} materials",
+                c.x, c.y, c.z
+            ),
+            EditError::TooLarge { chunks } => {
+                write!(f, "edit spans {chunks} chunks (max {MAX_EDIT_CHUNKS})")
+            }
+            EditError::InvalidSphere => write!(f, "sphere center and radius must be finite"),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+// ─── Dirty tracking ─────────────────────────────────────────────────────
+
+/// Chunk faces a voxel lies on, in usable local coordinates [1, CS].
+///
+/// Used to determine which neighbor chunks need to be marked dirty when a
+/// voxel on the boundary is modified.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BoundaryFlags {
+    pub neg_x: bool,
+    pub pos_x: bool,
+    pub neg_y: bool,
+    pub pos_y: bool,
+    pub neg_z: bool,
+    pub pos_z: bool,
+}
+
+impl BoundaryFlags {
+    /// Faces touched by the voxel at padded local position `local`.
+    pub fn from_local(local: [u32; 3]) -> Self {
+        Self {
+            neg_x: local[0] == 1,
+            pos_x: local[0] == CS,
+            neg_y: local[1] == 1,
+            pos_y: local[1] == CS,
+            neg_z: local[2] == 1,
+            pos_z: local[2] == CS,
+        }
+    }
+
+    /// Check if any boundary flag is set.
+    pub fn any(&self) -> bool {
+        self.neg_x || self.pos_x || self.neg_y || self.pos_y || self.neg_z || self.pos_z
+    }
+
+    /// Neighbor chunk offsets, as [dx, dy, dz], for each boundary that is set.
+    pub fn affected_neighbors(&self) -> Vec<[i32; 3]> {
+        let mut neighbors = Vec::with_capacity(6);
+        if self.neg_x { neighbors.push([-1, 0, 0]); }
+        if self.pos_x { neighbors.push([1, 0, 0]); }
+        if self.neg_y { neighbors.push([0, -1, 0]); }
+        if self.pos_y { neighbors.push([0, 1, 0]); }
+        if self.neg_z { neighbors.push([0, 0, -1]); }
+        if self.pos_z { neighbors.push([0, 0, 1]); }
+        neighbors
+    }
+}
+
+/// Set of chunks whose GPU copy and mesh are out of date.
+#[derive(Debug, Default)]
+pub struct DirtyTracker {
+    dirty_chunks: HashSet<ChunkCoord>,
+}
+
+impl DirtyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_dirty(&mut self, coord: ChunkCoord) {
+        self.dirty_chunks.insert(coord);
+    }
+
+    /// Mark chunk and boundary-affected neighbors as dirty.
+    ///
+    /// When a voxel on a chunk boundary is modified, the adjacent chunk
+    /// also needs to rebuild its mesh since the face culling may change.
+    pub fn mark_dirty_with_neighbors(&mut self, coord: ChunkCoord, boundary: BoundaryFlags) {
+        self.dirty_chunks.insert(coord);
+        for offset in boundary.affected_neighbors() {
+            self.dirty_chunks.insert(offset_coord(coord, offset));
+        }
+    }
+
+    pub fn is_dirty(&self, coord: ChunkCoord) -> bool {
+        self.dirty_chunks.contains(&coord)
+    }
+
+    /// Take all dirty chunks in coordinate order, clearing the set.
+    pub fn take_dirty(&mut self) -> Vec<ChunkCoord> {
+        let mut coords: Vec<_> = self.dirty_chunks.drain().collect();
+        coords.sort_by_key(|c| (c.x, c.y, c.z));
+        coords
+    }
+}
+
+// ─── Undo ───────────────────────────────────────────────────────────────
+
+/// Chunks as they were before an edit changed them, so an edit the pool
+/// cannot hold can be taken back.
+#[derive(Default)]
+pub struct Undo {
+    /// `None` for chunks the edit created.
+    previous: HashMap<ChunkCoord, Option<ChunkData>>,
+}
+
+impl Undo {
+    /// Keep `chunk` as the state of `coord`, unless an earlier change did.
+    fn save(&mut self, coord: ChunkCoord, chunk: Option<&ChunkData>) {
+        self.previous.entry(coord).or_insert_with(|| chunk.cloned());
+    }
+
+    /// Put every changed chunk back and drop the ones the edit created.
+    pub fn restore(self, chunks: &mut HashMap<ChunkCoord, ChunkData>) {
+        for (coord, chunk) in self.previous {
+            match chunk {
+                Some(chunk) => chunks.insert(coord, chunk),
+                None => chunks.remove(&coord),
+            };
+        }
+    }
+}
+
+// ─── Edits ──────────────────────────────────────────────────────────────
+
+/// Set the voxel at grid position `g` to `material`; `MATERIAL_EMPTY`
+/// clears it. Every edit records the chunks it changes in `undo`.
+pub fn set_voxel(
+    chunks: &mut HashMap<ChunkCoord, ChunkData>,
+    g: [i32; 3],
+    material: u16,
+    undo: &mut Undo,
+) -> Result<Vec<ChunkCoord>, EditError> {
+    edit_region(chunks, g, g, material, |_| true, undo)
+}
+
+/// Set every voxel in the inclusive box between `a` and `b` to `material`.
+pub fn fill_box(
+    chunks: &mut HashMap<ChunkCoord, ChunkData>,
+    a: [i32; 3],
+    b: [i32; 3],
+    material: u16,
+    undo: &mut Undo,
+) -> Result<Vec<ChunkCoord>, EditError> {
+    let min = [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])];
+    let max = [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])];
+    edit_region(chunks, min, max, material, |_| true, undo)
+}
+
+/// Set every voxel whose center lies within `radius` of `center` to
+/// `material`. Both are in grid units; voxel `g` spans `[g, g + 1)`.
+pub fn fill_sphere(
+    chunks: &mut HashMap<ChunkCoord, ChunkData>,
+    center: [f32; 3],
+    radius: f32,
+    material: u16,
+    undo: &mut Undo,
+) -> Result<Vec<ChunkCoord>, EditError> {
+    if !(radius >= 0.0 && radius.is_finite() && center.iter().all(|c| c.is_finite())) {
+        return Err(EditError::InvalidSphere);
+    }
+    let min = center.map(|c| (c - radius - 0.5).ceil() as i32);
+    let max = center.map(|c| (c + radius - 0.5).floor() as i32);
+    let r2 = radius as f64 * radius as f64;
+    let inside = |g: [i32; 3]| {
+        let d = [0, 1, 2].map(|a| g[a] as f64 + 0.5 - center[a] as f64);
+        d[0] * d[0] + d[1] * d[1] + d[2] * d[2] <= r2
+    };
+    edit_region(chunks, min, max, material, inside, undo)
+}
+
+/// Replace `from` with `to` in every chunk that uses it. Occupancy doesn't
+/// change, so neighbors are unaffected.
+pub fn replace_material(
+    chunks: &mut HashMap<ChunkCoord, ChunkData>,
+    from: u16,
+    to: u16,
+    undo: &mut Undo,
+) -> Result<Vec<ChunkCoord>, EditError> {
+    check_material(from)?;
+    check_material(to)?;
+    if from == MATERIAL_EMPTY || to == MATERIAL_EMPTY {
+        return Err(EditError::EmptyReplacement);
+    }
+    if from == to {
+        return Ok(Vec::new());
+    }
+    let mut dirty = DirtyTracker::new();
+    for (&coord, chunk) in chunks.iter_mut() {
+        if chunk.palette.entries().contains(&from) {
+            undo.save(coord, Some(&*chunk));
+            remap_palette(chunk, |m| if m == from { to } else { m });
+            dirty.mark_dirty(coord);
+        }
+    }
+    Ok(dirty.take_dirty())
+}
+
+// ─── Internals ──────────────────────────────────────────────────────────
+
+/// Write `material` to every voxel of the inclusive box [min, max] that
+/// `inside` selects. Palettes are checked for room before anything is
+/// written, so a full palette leaves the chunks' voxels untouched (making
+/// room only drops unused entries, so it isn't recorded in `undo`).
+fn edit_region(
+    chunks: &mut HashMap<ChunkCoord, ChunkData>,
+    min: [i32; 3],
+    max: [i32; 3],
+    material: u16,
+    inside: impl Fn([i32; 3]) -> bool,
+    undo: &mut Undo,
+) -> Result<Vec<ChunkCoord>, EditError> {
+    check_material(material)?;
+    if (0..3).any(|a| min[a] > max[a]) {
+        return Ok(Vec::new());
+    }
+    let cs = CS as i32;
+    let lo = min.map(|v| v.div_euclid(cs));
+    let hi = max.map(|v| v.div_euclid(cs));
+    let span = (0..3)
+        .map(|a| (hi[a] as i64 - lo[a] as i64 + 1) as u64)
+        .fold(1u64, u64::saturating_mul);
+    if span > MAX_EDIT_CHUNKS {
+        return Err(EditError::TooLarge { chunks: span });
+    }
+    let mut coords = Vec::with_capacity(span as usize);
+    for x in lo[0]..=hi[0] {
+        for y in lo[1]..=hi[1] {
+            for z in lo[2]..=hi[2] {
+                coords.push(ChunkCoord { x, y, z });
+            }
+        }
+    }
+
+    if material != MATERIAL_EMPTY {
+        for coord in &coords {
+            if let Some(chunk) = chunks.get_mut(coord) {
+                ensure_palette_room(chunk, material)?;
+            }
+        }
+    }
+
+    let mut dirty = DirtyTracker::new();
+    let mut removed = Vec::new();
+    // (neighbor, padded local, occupied): boundary voxels to mirror once
+    // every chunk is written
+    let mut mirrors = Vec::new();
+    for coord in coords {
+        let created = !chunks.contains_key(&coord);
+        if created && material == MATERIAL_EMPTY {
+            continue;
+        }
+        if created {
+            undo.save(coord, None);
+        }
+        let mut saved = created;
+        let chunk = chunks.entry(coord).or_insert_with(|| ChunkData {
+            coord,
+            occupancy: OccupancyBuilder::new(),
+            palette: PaletteBuilder::new(),
+            index_buf: IndexBufBuilder::new(),
+        });
+        let origin = [coord.x * cs, coord.y * cs, coord.z * cs];
+        let from = [0, 1, 2].map(|a| (min[a] - origin[a]).max(0));
+        let to = [0, 1, 2].map(|a| (max[a] - origin[a]).min(cs - 1));
+        let mut palette_idx = None;
+        for ux in from[0]..=to[0] {
+            for uy in from[1]..=to[1] {
+                for uz in from[2]..=to[2] {
+                    let g = [origin[0] + ux, origin[1] + uy, origin[2] + uz];
+                    if !inside(g) {
+                        continue;
+                    }
+                    let local = [ux as u32 + 1, uy as u32 + 1, uz as u32 + 1];
+                    let [lx, ly, lz] = local;
+                    let occupied = chunk.occupancy.get(lx, ly, lz);
+                    if !saved && (occupied || material != MATERIAL_EMPTY) {
+                        undo.save(coord, Some(&*chunk));
+                        saved = true;
+                    }
+                    if material == MATERIAL_EMPTY {
+                        if !occupied {
+                            continue;
+                        }
+                        chunk.occupancy.clear(lx, ly, lz);
+                        chunk.index_buf.set(lx, ly, lz, 0);
+                    } else {
+                        let idx = *palette_idx.get_or_insert_with(|| chunk.palette.add(material));
+                        if occupied && chunk.index_buf.get(lx, ly, lz) == idx {
+                            continue;
+                        }
+                        chunk.occupancy.set(lx, ly, lz);
+                        chunk.index_buf.set(lx, ly, lz, idx);
+                        if occupied {
+                            // Material change only: neighbors never read it
+                            dirty.mark_dirty(coord);
+                            continue;
+                        }
+                    }
+                    let boundary = BoundaryFlags::from_local(local);
+                    dirty.mark_dirty_with_neighbors(coord, boundary);
+                    for offset in boundary.affected_neighbors() {
+                        let mirrored = [0, 1, 2].map(|a| match offset[a] {
+                            -1 => CS_P - 1,
+                            1 => 0,
+                            _ => local[a],
+                        });
+                        mirrors.push((offset_coord(coord, offset), mirrored, !occupied));
+                    }
+                }
+            }
+        }
+        if (created || dirty.is_dirty(coord)) && chunk.occupancy.interior_popcount() == 0 {
+            chunks.remove(&coord);
+            if !created {
+                removed.push(coord);
+            }
+        }
+    }
+
+    for (coord, [x, y, z], occupied) in mirrors {
+        if let Some(chunk) = chunks.get_mut(&coord) {
+            undo.save(coord, Some(&*chunk));
+            if occupied {
+                chunk.occupancy.set(x, y, z);
+            } else {
+                chunk.occupancy.clear(x, y, z);
+            }
+        }
+    }
+
+    let mut touched = dirty.take_dirty();
+    touched.retain(|c| chunks.contains_key(c) || removed.contains(c));
+    Ok(touched)
+}
+
+fn check_material(material: u16) -> Result<(), EditError> {
+    if material as u32 >= MAX_MATERIALS {
+        return Err(EditError::MaterialOutOfRange(material));
+    }
+    Ok(())
+}
+
+/// Make sure `material` fits in `chunk`'s palette, dropping unused entries
+/// if it is full.
+fn ensure_palette_room(chunk: &mut ChunkData, material: u16) -> Result<(), EditError> {
+    let has_room = |p: &PaletteBuilder| {
+        p.entries().contains(&material) || p.len() < MAX_PALETTE_ENTRIES as usize
+    };
+    if has_room(&chunk.palette) {
+        return Ok(());
+    }
+    remap_palette(chunk, |m| m);
+    if has_room(&chunk.palette) { Ok(()) } else { Err(EditError::PaletteFull(chunk.coord)) }
+}
+
+/// Rebuild `chunk`'s palette from the entries its voxels use, mapping each
+/// material through `map`. Unused entries are dropped and entries mapping
+/// to the same material merge.
+fn remap_palette(chunk: &mut ChunkData, map: impl Fn(u16) -> u16) {
+    let old = chunk.palette.entries().to_vec();
+    let mut palette = PaletteBuilder::new();
+    let mut remap: Vec<Option<u8>> = vec![None; old.len()];
+    for x in 0..CS_P {
+        for y in 0..CS_P {
+            for z in 0..CS_P {
+                let idx = chunk.index_buf.get(x, y, z) as usize;
+                if idx == 0 {
+                    continue;
+                }
+                // Indices past the palette (malformed uploads) read as empty
+                let new = match remap.get_mut(idx) {
+                    Some(slot) => *slot.get_or_insert_with(|| palette.add(map(old[idx]))),
+                    None => 0,
+                };
+                chunk.index_buf.set(x, y, z, new);
+            }
+        }
+    }
+    chunk.palette = palette;
+}
+
+fn offset_coord(coord: ChunkCoord, offset: [i32; 3]) -> ChunkCoord {
+    ChunkCoord { x: coord.x + offset[0], y: coord.y + offset[1], z: coord.z + offset[2] }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coord(x: i32, y: i32, z: i32) -> ChunkCoord {
+        ChunkCoord { x, y, z }
+    }
+
+    fn split(g: [i32; 3]) -> (ChunkCoord, [u32; 3]) {
+        let cs = CS as i32;
+        let c = coord(g[0].div_euclid(cs), g[1].div_euclid(cs), g[2].div_euclid(cs));
+        (c, g.map(|v| (v.rem_euclid(cs) + 1) as u32))
+    }
+
+    fn material_at(chunks: &HashMap<ChunkCoord, ChunkData>, g: [i32; 3]) -> Option<u16> {
+        let (c, [x, y, z]) = split(g);
+        let chunk = chunks.get(&c)?;
+        chunk
+            .occupancy
+            .get(x, y, z)
+            .then(|| chunk.palette.entries()[chunk.index_buf.get(x, y, z) as usize])
+    }
+
+    fn total_voxels(chunks: &HashMap<ChunkCoord, ChunkData>) -> u32 {
+        chunks.values().map(|c| c.occupancy.interior_popcount()).sum()
+    }
+
+    #[test]
+    fn boundary_flags_from_local() {
+        assert!(!BoundaryFlags::from_local([2, 30, 61]).any());
+        let corner = BoundaryFlags::from_local([1, CS, 1]);
+        assert_eq!(corner.affected_neighbors(), vec![[-1, 0, 0], [0, 1, 0], [0, 0, -1]]);
+    }
+
+    #[test]
+    fn mark_dirty_with_neighbors_corner() {
+        let mut tracker = DirtyTracker::new();
+        let boundary =
+            BoundaryFlags { neg_x: true, neg_y: true, neg_z: true, ..Default::default() };
+        tracker.mark_dirty_with_neighbors(coord(5, 5, 5), boundary);
+        let dirty = tracker.take_dirty();
+        assert_eq!(dirty, vec![coord(4, 5, 5), coord(5, 4, 5), coord(5, 5, 4), coord(5, 5, 5)]);
+        assert!(tracker.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn set_voxel_creates_and_removes_chunks() {
+        let mut chunks = HashMap::new();
+        let mut undo = Undo::default();
+        assert_eq!(set_voxel(&mut chunks, [-5, 3, 70], 9, &mut undo), Ok(vec![coord(-1, 0, 1)]));
+        assert_eq!(material_at(&chunks, [-5, 3, 70]), Some(9));
+        assert_eq!(chunks[&coord(-1, 0, 1)].palette.entries(), &[MATERIAL_EMPTY, 9]);
+        // Rewriting the same material changes nothing
+        assert_eq!(set_voxel(&mut chunks, [-5, 3, 70], 9, &mut undo), Ok(vec![]));
+        assert_eq!(set_voxel(&mut chunks, [-5, 3, 70], 4, &mut undo), Ok(vec![coord(-1, 0, 1)]));
+        assert_eq!(material_at(&chunks, [-5, 3, 70]), Some(4));
+        // Clearing the last voxel removes the chunk, and still reports it
+        let cleared = set_voxel(&mut chunks, [-5, 3, 70], MATERIAL_EMPTY, &mut undo);
+        assert_eq!(cleared, Ok(vec![coord(-1, 0, 1)]));
+        assert!(chunks.is_empty());
+        assert_eq!(set_voxel(&mut chunks, [0, 0, 0], MATERIAL_EMPTY, &mut undo), Ok(vec![]));
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn boundary_edit_mirrors_into_neighbor_padding() {
+        let mut chunks = HashMap::new();
+        let mut undo = Undo::default();
+        set_voxel(&mut chunks, [10, 10, 10], 1, &mut undo).unwrap();
+        set_voxel(&mut chunks, [70, 10, 10], 1, &mut undo).unwrap();
+        // Last usable x of chunk 0 faces chunk 1's -X padding
+        let touched = set_voxel(&mut chunks, [61, 10, 10], 2, &mut undo).unwrap();
+        assert_eq!(touched, vec![coord(0, 0, 0), coord(1, 0, 0)]);
+        assert!(chunks[&coord(1, 0, 0)].occupancy.get(0, 11, 11));
+        assert_eq!(chunks[&coord(1, 0, 0)].occupancy.interior_popcount(), 1);
+        // A material change leaves occupancy, and so the neighbor, alone
+        assert_eq!(set_voxel(&mut chunks, [61, 10, 10], 3, &mut undo), Ok(vec![coord(0, 0, 0)]));
+        // Neighbors that aren't resident are neither created nor reported
+        assert_eq!(set_voxel(&mut chunks, [0, 10, 10], 1, &mut undo), Ok(vec![coord(0, 0, 0)]));
+        assert!(!chunks.contains_key(&coord(-1, 0, 0)));
+        let touched = set_voxel(&mut chunks, [61, 10, 10], MATERIAL_EMPTY, &mut undo).unwrap();
+        assert_eq!(touched, vec![coord(0, 0, 0), coord(1, 0, 0)]);
+        assert!(!chunks[&coord(1, 0, 0)].occupancy.get(0, 11, 11));
+    }
+
+    #[test]
+    fn fill_box_spans_chunks() {
+        let mut chunks = HashMap::new();
+        let mut undo = Undo::default();
+        let touched = fill_box(&mut chunks, [64, 2, 2], [-3, -3, -3], 5, &mut undo).unwrap();
+        // x: chunks -1..=1, y and z: chunks -1..=0
+        assert_eq!(touched.len(), 12);
+        assert_eq!(chunks.len(), 12);
+        assert_eq!(total_voxels(&chunks), 68 * 6 * 6);
+        assert_eq!(material_at(&chunks, [64, -3, 2]), Some(5));
+        assert_eq!(material_at(&chunks, [65, 0, 0]), None);
+        // Shared faces between the new chunks are mirrored both ways
+        assert!(chunks[&coord(0, 0, 0)].occupancy.get(0, 1, 1));
+        assert!(chunks[&coord(-1, 0, 0)].occupancy.get(CS_P - 1, 1, 1));
+
+        let touched =
+            fill_box(&mut chunks, [-3, -3, -3], [64, 2, 2], MATERIAL_EMPTY, &mut undo).unwrap();
+        assert_eq!(touched.len(), 12);
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn fill_box_too_large() {
+        let mut chunks = HashMap::new();
+        let mut undo = Undo::default();
+        let result = fill_box(&mut chunks, [i32::MIN; 3], [i32::MAX; 3], 1, &mut undo);
+        assert!(matches!(result, Err(EditError::TooLarge { .. })));
+        assert!(chunks.is_empty());
+    }
+
+    #[test]
+    fn fill_sphere_selects_voxel_centers() {
+        let mut chunks = HashMap::new();
+        let mut undo = Undo::default();
+        // Radius 1 around a voxel center: the voxel and its 6 face neighbors
+        fill_sphere(&mut chunks, [10.5, 10.5, 10.5], 1.0, 3, &mut undo).unwrap();
+        assert_eq!(total_voxels(&chunks), 7);
+        assert_eq!(material_at(&chunks, [11, 10, 10]), Some(3));
+        assert_eq!(material_at(&chunks, [11, 11, 10]), None);
+
+        let mut chunks = HashMap::new();
+        fill_sphere(&mut chunks, [0.0; 3], 20.0, 3, &mut undo).unwrap();
+        let expected = (-21..21)
+            .flat_map(|x| (-21..21).flat_map(move |y| (-21..21).map(move |z| [x, y, z])))
+            .filter(|g: &[i32; 3]| {
+                g.iter().map(|&v| (v as f64 + 0.5).powi(2)).sum::<f64>() <= 400.0
+            })
+            .count();
+        assert_eq!(total_voxels(&chunks) as usize, expected);
+        assert_eq!(chunks.len(), 8);
+
+        for (center, radius) in [([0.0; 3], -1.0), ([f32::NAN, 0.0, 0.0], 1.0)] {
+            let result = fill_sphere(&mut chunks, center, radius, 3, &mut undo);
+            assert_eq!(result, Err(EditError::InvalidSphere));
+        }
+    }
+
+    #[test]
+    fn full_palette_compacts_or_rejects() {
+        let mut chunks = HashMap::new();
+        let mut undo = Undo::default();
+        for m in 1..MAX_PALETTE_ENTRIES as u16 {
+            set_voxel(&mut chunks, [m as i32 % 60, m as i32 / 60, 0], m, &mut undo).unwrap();
+        }
+        assert_eq!(chunks[&coord(0, 0, 0)].palette.len(), MAX_PALETTE_ENTRIES as usize);
+        let result = fill_box(&mut chunks, [0, 10, 0], [3, 10, 0], 300, &mut undo);
+        assert_eq!(result, Err(EditError::PaletteFull(coord(0, 0, 0))));
+        assert_eq!(material_at(&chunks, [0, 10, 0]), None, "nothing written");
+
+        // Freeing material 7's only voxel lets compaction make room
+        set_voxel(&mut chunks, [7, 0, 0], MATERIAL_EMPTY, &mut undo).unwrap();
+        set_voxel(&mut chunks, [0, 10, 0], 300, &mut undo).unwrap();
+        let chunk = &chunks[&coord(0, 0, 0)];
+        assert_eq!(chunk.palette.len(), MAX_PALETTE_ENTRIES as usize);
+        assert!(!chunk.palette.entries().contains(&7));
+        assert_eq!(material_at(&chunks, [0, 10, 0]), Some(300));
+        for m in (1..MAX_PALETTE_ENTRIES as i32).filter(|&m| m != 7) {
+            assert_eq!(material_at(&chunks, [m % 60, m / 60, 0]), Some(m as u16));
+        }
+    }
+
+    #[test]
+    fn replace_material_remaps_and_merges() {
+        let mut chunks = HashMap::new();
+        let mut undo = Undo::default();
+        fill_box(&mut chunks, [0, 0, 0], [3, 3, 3], 1, &mut undo).unwrap();
+        fill_box(&mut chunks, [0, 0, 0], [1, 1, 1], 2, &mut undo).unwrap();
+        set_voxel(&mut chunks, [100, 0, 0], 2, &mut undo).unwrap();
+        assert_eq!(replace_material(&mut chunks, 1, 2, &mut undo), Ok(vec![coord(0, 0, 0)]));
+        assert_eq!(chunks[&coord(0, 0, 0)].palette.entries(), &[MATERIAL_EMPTY, 2]);
+        assert_eq!(material_at(&chunks, [3, 3, 3]), Some(2));
+        assert_eq!(total_voxels(&chunks), 65);
+
+        let touched = replace_material(&mut chunks, 2, 6, &mut undo).unwrap();
+        assert_eq!(touched, vec![coord(0, 0, 0), coord(1, 0, 0)]);
+        assert_eq!(material_at(&chunks, [100, 0, 0]), Some(6));
+        assert_eq!(replace_material(&mut chunks, 6, 6, &mut undo), Ok(vec![]));
+        let result = replace_material(&mut chunks, 6, 0, &mut undo);
+        assert_eq!(result, Err(EditError::EmptyReplacement));
+        let result = replace_material(&mut chunks, 6, MAX_MATERIALS as u16, &mut undo);
+        assert_eq!(result, Err(EditError::MaterialOutOfRange(MAX_MATERIALS as u16)));
+    }
+
+    #[test]
+    fn undo_restores_changed_chunks() {
+        let mut chunks = HashMap::new();
+        fill_box(&mut chunks, [0, 0, 0], [61, 3, 3], 1, &mut Undo::default()).unwrap();
+        set_voxel(&mut chunks, [70, 0, 0], 2, &mut Undo::default()).unwrap();
+        let voxels = |chunks: &HashMap<ChunkCoord, ChunkData>| {
+            let mut all: Vec<_> = chunks
+                .values()
+                .map(|c| (c.coord.x, c.occupancy.as_words().to_vec(), c.palette.entries().to_vec()))
+                .collect();
+            all.sort();
+            all
+        };
+        let before = voxels(&chunks);
+
+        // Writes a chunk, mirrors into its neighbor and creates a new one
+        let mut undo = Undo::default();
+        let touched = fill_box(&mut chunks, [60, 0, 0], [130, 0, 0], 3, &mut undo).unwrap();
+        assert_eq!(touched, vec![coord(0, 0, 0), coord(1, 0, 0), coord(2, 0, 0)]);
+        undo.restore(&mut chunks);
+        assert_eq!(voxels(&chunks), before);
+
+        // Clearing removes chunks; undo brings them back
+        let mut undo = Undo::default();
+        fill_box(&mut chunks, [0, 0, 0], [80, 3, 3], MATERIAL_EMPTY, &mut undo).unwrap();
+        assert!(chunks.is_empty());
+        undo.restore(&mut chunks);
+        assert_eq!(voxels(&chunks), before);
+
+        let mut undo = Undo::default();
+        replace_material(&mut chunks, 1, 4, &mut undo).unwrap();
+        undo.restore(&mut chunks);
+        assert_eq!(voxels(&chunks), before);
+    }
+}
@@ -0,0 +1,496 @@
+//! Mesh export — no GPU, no JS types.
+//!
+//! Runs the CPU greedy mesher ([`mesh_cpu`]) over a set of chunks and
+//! writes the result as Wavefront OBJ + MTL or binary glTF (GLB). Positions
+//! are in world space; normals are unpacked from the vertex format; every
+//! material ID in use becomes one OBJ `usemtl` group / glTF primitive with
+//! its factors decoded from the packed [`MaterialEntry`].
+//!
+//! Each chunk's padding shell is first filled from its face neighbors in
+//! the exported set (loaders leave it empty), so solid runs that cross a
+//! chunk border get no internal faces there. Chunks are then meshed
+//! independently, so by default every chunk (and every quad) keeps its own
+//! vertices. Welding merges vertices with identical position and normal,
+//! which joins faces across chunk borders; the mesher computes border
+//! positions from integer grid coordinates, so they match bit for bit.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use crate::mesh_cpu;
+use crate::pool::{ChunkCoord, CS, CS_P, VERTEX_BYTES};
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const COMPONENT_FLOAT: u32 = 5126;
+
+/// A merged triangle mesh ready to write.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportMesh {
+    /// World-space positions.
+    pub positions: Vec<[f32; 3]>,
+    /// Unit normals, parallel to `positions`.
+    pub normals: Vec<[f32; 3]>,
+    /// Triangle-list indices per material ID, in ascending ID order.
+    pub primitives: Vec<(u16, Vec<u32>)>,
+}
+
+impl ExportMesh {
+    pub fn triangle_count(&self) -> usize {
+        self.primitives.iter().map(|(_, indices)| indices.len() / 3).sum()
+    }
+}
+
+/// Greedy-mesh `chunks` into one mesh. Chunks are processed in coordinate
+/// order, so the output does not depend on iteration order.
+pub fn mesh_chunks<'a>(
+    chunks: impl IntoIterator<Item = &'a ChunkData>,
+    voxel_size: f32,
+    grid_origin: [f32; 3],
+    weld: bool,
+) -> ExportMesh {
+    let mut chunks: Vec<&ChunkData> = chunks.into_iter().collect();
+    chunks.sort_by_key(|c| (c.coord.x, c.coord.y, c.coord.z));
+    let by_coord: HashMap<ChunkCoord, &ChunkData> = chunks.iter().map(|&c| (c.coord, c)).collect();
+
+    let mut mesh = ExportMesh::default();
+    let mut primitives: BTreeMap<u16, Vec<u32>> = BTreeMap::new();
+    // (position bits, packed normal) → vertex index
+    let mut welded: HashMap<([u32; 3], u32), u32> = HashMap::new();
+    for chunk in chunks {
+        let palette = chunk.palette.as_words();
+        let bpe = IndexBufBuilder::bits_per_entry(chunk.palette.len());
+        let index_buf = chunk.index_buf.pack(bpe);
+        let occupancy = padded_occupancy(chunk, &by_coord);
+        let masks = mesh_cpu::cull_faces_cpu(&occupancy);
+        let quads = mesh_cpu::greedy_merge(&occupancy, &masks, &palette, &index_buf, bpe as u32);
+        let coord = [chunk.coord.x, chunk.coord.y, chunk.coord.z];
+        let (vertices, indices) = mesh_cpu::expand_quads(&quads, coord, voxel_size, grid_origin);
+
+        let remap: Vec<u32> = vertices
+            .chunks_exact(VERTEX_BYTES as usize)
+            .map(|v| {
+                let word = |i: usize| u32::from_le_bytes([v[i], v[i + 1], v[i + 2], v[i + 3]]);
+                let position = [0, 4, 8].map(|i| f32::from_bits(word(i)));
+                let packed = word(12);
+                let mut push = || {
+                    mesh.positions.push(position);
+                    mesh.normals.push(mesh_cpu::unpack_normal_material(packed).0);
+                    mesh.positions.len() as u32 - 1
+                };
+                if !weld {
+                    return push();
+                }
+                // + 0.0 folds -0.0 into 0.0 so both weld together
+                let key = (position.map(|c| (c + 0.0).to_bits()), packed & 0x00FF_FFFF);
+                *welded.entry(key).or_insert_with(push)
+            })
+            .collect();
+        // Six indices per quad; the material comes from the quad itself, as
+        // the vertex format only keeps its low 8 bits
+        for (quad, corners) in quads.iter().zip(indices.chunks_exact(6)) {
+            let primitive = primitives.entry(quad.material_id).or_default();
+            primitive.extend(corners.iter().map(|&i| remap[i as usize]));
+        }
+    }
+    mesh.primitives = primitives.into_iter().collect();
+    mesh
+}
+
+/// Name of material `id` in OBJ/MTL and glTF output.
+pub fn material_name(id: u16) -> String {
+    format!("material_{id}")
+}
+
+/// Write `mesh` as OBJ text referencing the material library `mtl_name`.
+pub fn write_obj(mesh: &ExportMesh, mtl_name: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Gestalt voxel mesh export");
+    let _ = writeln!(out, "mtllib {mtl_name}");
+    for [x, y, z] in &mesh.positions {
+        let _ = writeln!(out, "v {x} {y} {z}");
+    }
+    for [x, y, z] in &mesh.normals {
+        let _ = writeln!(out, "vn {x} {y} {z}");
+    }
+    for (material, indices) in &mesh.primitives {
+        let _ = writeln!(out, "usemtl {}", material_name(*material));
+        for tri in indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0] + 1, tri[1] + 1, tri[2] + 1];
+            let _ = writeln!(out, "f {a}//{a} {b}//{b} {c}//{c}");
+        }
+    }
+    out
+}
+
+/// Write the materials `mesh` uses as an MTL library. Roughness becomes
+/// the Phong exponent the MTL importer maps back (`Ns`) plus the PBR
+/// extension's `Pr`.
+pub fn write_mtl(mesh: &ExportMesh, materials: &[MaterialEntry]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Gestalt voxel mesh export");
+    for &(id, _) in &mesh.primitives {
+        let material = material_entry(materials, id);
+        let [r, g, b] = material.albedo();
+        let [er, eg, eb] = material.emissive();
+        let roughness = material.roughness();
+        // Inverse of sqrt(2 / (Ns + 2)), capped for mirror-smooth materials
+        let ns = (2.0 / roughness.max(0.01).powi(2) - 2.0).min(1000.0);
+        let _ = writeln!(out, "\nnewmtl {}", material_name(id));
+        let _ = writeln!(out, "Kd {r} {g} {b}");
+        let _ = writeln!(out, "Ke {er} {eg} {eb}");
+        let _ = writeln!(out, "Ns {ns}");
+        let _ = writeln!(out, "Pr {roughness}");
+        let _ = writeln!(out, "d {}", material.opacity());
+    }
+    out
+}
+
+/// Write `mesh` as a binary glTF 2.0 file: one mesh with shared POSITION
+/// and NORMAL accessors and one primitive per material.
+pub fn write_glb(mesh: &ExportMesh, materials: &[MaterialEntry]) -> Vec<u8> {
+    let mut bin: Vec<u8> = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut view = |bin: &mut Vec<u8>, bytes: Vec<u8>, target: u32| {
+        views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{target}}}"#,
+            bin.len(),
+            bytes.len()
+        ));
+        bin.extend_from_slice(&bytes);
+        views.len() - 1
+    };
+
+    let mut meshes = String::new();
+    let mut gltf_materials = Vec::new();
+    let mut extensions_used = Vec::new();
+    if !mesh.positions.is_empty() {
+        let floats = |v: &[[f32; 3]]| v.iter().flatten().flat_map(|c| c.to_le_bytes()).collect();
+        let (lo, hi) = mesh.positions.iter().fold(
+            ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]),
+            |(lo, hi), p| ([0, 1, 2].map(|a| lo[a].min(p[a])), [0, 1, 2].map(|a| hi[a].max(p[a]))),
+        );
+        let count = mesh.positions.len();
+        let positions = view(&mut bin, floats(&mesh.positions), TARGET_ARRAY_BUFFER);
+        accessors.push(format!(
+            concat!(
+                r#"{{"bufferView":{},"componentType":{},"count":{},"type":"VEC3","#,
+                r#""min":{},"max":{}}}"#
+            ),
+            positions,
+            COMPONENT_FLOAT,
+            count,
+            json_floats(&lo),
+            json_floats(&hi)
+        ));
+        let normals = view(&mut bin, floats(&mesh.normals), TARGET_ARRAY_BUFFER);
+        accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{},"count":{},"type":"VEC3"}}"#,
+            normals, COMPONENT_FLOAT, count
+        ));
+
+        let mut primitives = Vec::new();
+        for (id, indices) in &mesh.primitives {
+            let bytes = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+            let indices_view = view(&mut bin, bytes, TARGET_ELEMENT_ARRAY_BUFFER);
+            accessors.push(format!(
+                r#"{{"bufferView":{},"componentType":{},"count":{},"type":"SCALAR"}}"#,
+                indices_view,
+                COMPONENT_UNSIGNED_INT,
+                indices.len()
+            ));
+            primitives.push(format!(
+                r#"{{"attributes":{{"POSITION":0,"NORMAL":1}},"indices":{},"material":{}}}"#,
+                accessors.len() - 1,
+                gltf_materials.len()
+            ));
+            gltf_materials.push(gltf_material(*id, &material_entry(materials, *id)));
+        }
+        meshes = format!(r#""meshes":[{{"primitives":[{}]}}],"#, primitives.join(","));
+        if gltf_materials.iter().any(|m| m.contains("KHR_materials_emissive_strength")) {
+            extensions_used.push(r#""KHR_materials_emissive_strength""#);
+        }
+    }
+
+    let node = if meshes.is_empty() { "{}" } else { r#"{"mesh":0}"# };
+    let mut json = format!(
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"Gestalt"}},"#,
+            r#""scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{}],{}"#
+        ),
+        node, meshes
+    );
+    if !gltf_materials.is_empty() {
+        let _ = write!(json, r#""materials":[{}],"#, gltf_materials.join(","));
+    }
+    if !extensions_used.is_empty() {
+        let _ = write!(json, r#""extensionsUsed":[{}],"#, extensions_used.join(","));
+    }
+    if !bin.is_empty() {
+        let _ = write!(
+            json,
+            r#""buffers":[{{"byteLength":{}}}],"bufferViews":[{}],"accessors":[{}],"#,
+            bin.len(),
+            views.join(","),
+            accessors.join(",")
+        );
+    }
+    json.pop(); // trailing ','
+    json.push('}');
+
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+    let bin_chunk = if bin.is_empty() { 0 } else { 8 + bin.len() };
+    let total = 12 + 8 + json.len() + bin_chunk;
+
+    let mut out = Vec::with_capacity(total);
+    out.extend_from_slice(GLB_MAGIC);
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&(total as u32).to_le_bytes());
+    out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    out.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+    out.extend_from_slice(&json);
+    if !bin.is_empty() {
+        out.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        out.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+        out.extend_from_slice(&bin);
+    }
+    out
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+/// Table entry for `id`, mid gray if the table is short.
+fn material_entry(materials: &[MaterialEntry], id: u16) -> MaterialEntry {
+    materials
+        .get(id as usize)
+        .copied()
+        .unwrap_or_else(|| MaterialEntry::new([0.5; 3], 0.5, [0.0; 3], 1.0))
+}
+
+/// glTF material object. HDR emissive is split into a 0–1 factor and
+/// `KHR_materials_emissive_strength`.
+fn gltf_material(id: u16, material: &MaterialEntry) -> String {
+    let [r, g, b] = material.albedo();
+    let opacity = material.opacity();
+    let emissive = material.emissive();
+    let strength = emissive.iter().copied().fold(1.0, f32::max);
+    let mut out = format!(
+        concat!(
+            r#"{{"name":"{}","pbrMetallicRoughness":{{"baseColorFactor":{},"#,
+            r#""metallicFactor":0,"roughnessFactor":{}}}"#
+        ),
+        material_name(id),
+        json_floats(&[r, g, b, opacity]),
+        material.roughness()
+    );
+    if emissive.iter().any(|&c| c > 0.0) {
+        let factor = emissive.map(|c| c / strength);
+        let _ = write!(out, r#","emissiveFactor":{}"#, json_floats(&factor));
+    }
+    if strength > 1.0 {
+        let _ = write!(
+            out,
+            r#","extensions":{{"KHR_materials_emissive_strength":{{"emissiveStrength":{}}}}}"#,
+            strength
+        );
+    }
+    if opacity < 1.0 {
+        out.push_str(r#","alphaMode":"BLEND""#);
+    }
+    out.push('}');
+    out
+}
+
+fn json_floats(values: &[f32]) -> String {
+    let items: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", items.join(","))
+}
+
+/// `chunk`'s occupancy with its face padding filled from the neighbors in
+/// `by_coord`: padding layer 0 / 63 on each axis takes the neighbor's last /
+/// first usable layer. Edge and corner padding is never read by the face
+/// culler and is left alone, as is the padding toward absent neighbors.
+fn padded_occupancy(chunk: &ChunkData, by_coord: &HashMap<ChunkCoord, &ChunkData>) -> Vec<u32> {
+    const INTERIOR_Y: u64 = ((1u64 << CS) - 1) << 1;
+    let mut out = chunk.occupancy.as_words().to_vec();
+    let c = chunk.coord;
+    let neighbor = |dx: i32, dy: i32, dz: i32| {
+        let coord = ChunkCoord { x: c.x + dx, y: c.y + dy, z: c.z + dz };
+        by_coord.get(&coord).map(|n| n.occupancy.as_words())
+    };
+    for (dx, pad, src) in [(-1, 0, CS), (1, CS_P - 1, 1)] {
+        if let Some(n) = neighbor(dx, 0, 0) {
+            for z in 1..=CS {
+                write_column(&mut out, pad, z, read_column(n, src, z) & INTERIOR_Y);
+            }
+        }
+    }
+    for (dz, pad, src) in [(-1, 0, CS), (1, CS_P - 1, 1)] {
+        if let Some(n) = neighbor(0, 0, dz) {
+            for x in 1..=CS {
+                write_column(&mut out, x, pad, read_column(n, x, src) & INTERIOR_Y);
+            }
+        }
+    }
+    for (dy, pad, src) in [(-1, 0, CS), (1, CS_P - 1, 1)] {
+        if let Some(n) = neighbor(0, dy, 0) {
+            for x in 1..=CS {
+                for z in 1..=CS {
+                    let bit = (read_column(n, x, z) >> src) & 1;
+                    let column = read_column(&out, x, z) & !(1 << pad) | bit << pad;
+                    write_column(&mut out, x, z, column);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn read_column(words: &[u32], x: u32, z: u32) -> u64 {
+    let i = ((x * CS_P + z) * 2) as usize;
+    words[i] as u64 | (words[i + 1] as u64) << 32
+}
+
+fn write_column(words: &mut [u32], x: u32, z: u32, column: u64) {
+    let i = ((x * CS_P + z) * 2) as usize;
+    words[i] = column as u32;
+    words[i + 1] = (column >> 32) as u32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gltf_parser::parse_gltf;
+    use crate::mtl_parser::parse_mtl;
+    use crate::obj_parser::parse_obj;
+    use crate::scene::{OccupancyBuilder, PaletteBuilder};
+
+    /// A `len`-long bar of voxels along X from grid (0, 0, 0), one material.
+    fn bar(len: i32, material: u16) -> Vec<ChunkData> {
+        bar_along(0, len, material)
+    }
+
+    /// A 1×1 bar of `len` voxels from the origin along `axis`.
+    fn bar_along(axis: usize, len: i32, material: u16) -> Vec<ChunkData> {
+        let cs = CS as i32;
+        let mut chunks: Vec<ChunkData> = Vec::new();
+        for i in 0..len {
+            let mut g = [0; 3];
+            g[axis] = i;
+            let coord = ChunkCoord {
+                x: g[0].div_euclid(cs),
+                y: g[1].div_euclid(cs),
+                z: g[2].div_euclid(cs),
+            };
+            if chunks.last().is_none_or(|c| c.coord != coord) {
+                let mut palette = PaletteBuilder::new();
+                palette.add(material);
+                chunks.push(ChunkData {
+                    coord,
+                    occupancy: OccupancyBuilder::new(),
+                    palette,
+                    index_buf: IndexBufBuilder::new(),
+                });
+            }
+            let chunk = chunks.last_mut().unwrap();
+            let [px, py, pz] = g.map(|v| (v.rem_euclid(cs) + 1) as u32);
+            chunk.occupancy.set(px, py, pz);
+            chunk.index_buf.set(px, py, pz, 1);
+        }
+        chunks
+    }
+
+    fn materials() -> Vec<MaterialEntry> {
+        let mut table = vec![MaterialEntry::new([0.5; 3], 0.5, [0.0; 3], 1.0); 300];
+        table[260] = MaterialEntry::new([1.0, 0.5, 0.0], 0.25, [2.0, 0.0, 0.0], 0.5);
+        table
+    }
+
+    #[test]
+    fn weld_joins_chunk_borders() {
+        // 70 voxels cross into a second chunk: the 4 long sides split at the
+        // border, while the padding fill culls the faces between the chunks
+        let chunks = bar(70, 260);
+        let loose = mesh_chunks(&chunks, 0.5, [1.0, 0.0, 0.0], false);
+        let welded = mesh_chunks(&chunks, 0.5, [1.0, 0.0, 0.0], true);
+
+        assert_eq!(loose.primitives.len(), 1);
+        assert_eq!(loose.primitives[0].0, 260, "full material ID, not the vertex byte");
+        assert_eq!(loose.triangle_count(), 2 * 10);
+        assert_eq!(loose.positions.len(), 4 * 10);
+        assert_eq!(welded.triangle_count(), loose.triangle_count());
+        // Per normal: the 4 long sides share their 2 border corners
+        assert_eq!(welded.positions.len(), 40 - 4 * 2);
+
+        let xs = loose.positions.iter().map(|p| p[0]);
+        assert_eq!(xs.clone().fold(f32::INFINITY, f32::min), 1.0);
+        assert_eq!(xs.fold(f32::NEG_INFINITY, f32::max), 1.0 + 70.0 * 0.5);
+        assert!(loose.normals.iter().all(|n| n.iter().map(|c| c * c).sum::<f32>() == 1.0));
+    }
+
+    #[test]
+    fn no_internal_faces_between_chunks() {
+        for axis in 0..3 {
+            let chunks = bar_along(axis, 130, 1);
+            assert_eq!(chunks.len(), 3);
+            // 4 long sides per chunk plus the 2 end caps
+            let mesh = mesh_chunks(&chunks, 1.0, [0.0; 3], false);
+            assert_eq!(mesh.triangle_count(), 2 * (4 * 3 + 2), "axis {axis}");
+            // Without the middle chunk the outer two are capped on both ends
+            let ends = [&chunks[0], &chunks[2]];
+            let mesh = mesh_chunks(ends, 1.0, [0.0; 3], false);
+            assert_eq!(mesh.triangle_count(), 2 * 2 * 6, "axis {axis}");
+        }
+    }
+
+    #[test]
+    fn obj_and_mtl_roundtrip() {
+        let chunks = bar(3, 260);
+        let mesh = mesh_chunks(&chunks, 1.0, [0.0; 3], true);
+        let obj = write_obj(&mesh, "scene.mtl");
+        let parsed = parse_obj(&obj);
+        assert_eq!(parsed.positions.len(), mesh.positions.len());
+        assert_eq!(parsed.triangles.len(), mesh.triangle_count());
+        assert_eq!(parsed.material_names.last().unwrap(), "material_260");
+        assert_eq!(parsed.material_libs, vec!["scene.mtl".to_string()]);
+
+        let mtl = parse_mtl(&write_mtl(&mesh, &materials()));
+        assert_eq!(mtl.len(), 1);
+        assert_eq!(mtl[0].diffuse, [1.0, 0.5, 0.0]);
+        assert_eq!(mtl[0].emissive, [2.0, 0.0, 0.0]);
+        assert_eq!(mtl[0].opacity, 0.5);
+        assert!((mtl[0].roughness() - 0.25).abs() < 1e-3);
+    }
+
+    #[test]
+    fn glb_roundtrip() {
+        let mut chunks = bar(3, 260);
+        let gray = chunks[0].palette.add(2);
+        chunks[0].index_buf.set(2, 1, 1, gray);
+        let mesh = mesh_chunks(&chunks, 1.0, [0.0; 3], false);
+        let glb = write_glb(&mesh, &materials());
+        assert_eq!(glb.len() % 4, 0);
+        assert_eq!(u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize, glb.len());
+
+        let parsed = parse_gltf(&glb).unwrap();
+        assert_eq!(parsed.mesh.triangles.len(), mesh.triangle_count());
+        assert_eq!(parsed.materials.len(), 2);
+        let hot = &parsed.materials[1];
+        assert_eq!(hot.name, "material_260");
+        assert_eq!(hot.base_color, [1.0, 0.5, 0.0, 0.5]);
+        assert_eq!(hot.emissive, [2.0, 0.0, 0.0], "strength extension restores HDR");
+        assert!(hot.alpha_blended);
+
+        let empty = write_glb(&ExportMesh::default(), &[]);
+        assert_eq!(parse_gltf(&empty).unwrap().mesh.triangles.len(), 0);
+    }
+}
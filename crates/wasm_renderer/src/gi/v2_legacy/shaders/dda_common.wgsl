@@ -7,7 +7,7 @@
 //   @group(?) @binding(?) var<storage, read> occupancy: array<u32>;
 //   @group(?) @binding(?) var<storage, read> flags:     array<u32>;
 //   @group(?) @binding(?) var<storage, read> slot_table: array<u32>;
-//   @group(?) @binding(?) var<uniform>       slot_table_params: vec4i; // xyz=origin, w=dim
+//   @group(?) @binding(?) var<uniform>       slot_table_params: vec4i; // x=mask, y=max probe
 //
 // See: docs/Resident Representation/traversal-acceleration.md
 
@@ -34,15 +34,37 @@ struct DdaHit {
 
 // ─── Slot table lookup ──────────────────────────────────────────────────
 
+// The slot table is an open-addressed spatial hash of 4-word entries
+// [x, y, z, slot + 1]; slot + 1 == 0 marks an empty entry. Mirrors
+// `pool::SlotHashTable::lookup` — the hash must match `pool::slot_table_hash`.
+
+fn dda_slot_hash(chunk: vec3i) -> u32 {
+    var h = (bitcast<u32>(chunk.x) * 0x8DA6B343u)
+          ^ (bitcast<u32>(chunk.y) * 0xD8163841u)
+          ^ (bitcast<u32>(chunk.z) * 0xCB1AB31Fu);
+    h ^= h >> 16u;
+    h *= 0x7FEB352Du;
+    h ^= h >> 15u;
+    return h;
+}
+
 fn dda_chunk_to_slot(chunk: vec3i) -> u32 {
-    let origin = slot_table_params.xyz;
-    let dim = slot_table_params.w;
-    let local = chunk - origin;
-    if (any(local < vec3i(0)) || any(local >= vec3i(dim))) {
-        return DDA_SENTINEL;
+    let mask = u32(slot_table_params.x);
+    let max_probe = u32(slot_table_params.y);
+    var idx = dda_slot_hash(chunk) & mask;
+    for (var i = 0u; i < max_probe; i++) {
+        let base = idx * 4u;
+        let stored = slot_table[base + 3u];
+        if (stored == 0u) {
+            return DDA_SENTINEL;
+        }
+        let key = vec3u(slot_table[base], slot_table[base + 1u], slot_table[base + 2u]);
+        if (all(bitcast<vec3i>(key) == chunk)) {
+            return stored - 1u;
+        }
+        idx = (idx + 1u) & mask;
     }
-    let idx = u32(local.x) + u32(local.y) * u32(dim) + u32(local.z) * u32(dim) * u32(dim);
-    return slot_table[idx];
+    return DDA_SENTINEL;
 }
 
 // ─── Occupancy bit test ─────────────────────────────────────────────────
@@ -121,16 +121,34 @@ fn v3_chunk_slot_to_probe_slot(chunk_slot: u32) -> u32 {
 /// Requires `slot_table` and `slot_table_params` bindings. The consumer
 /// declares them at whatever group/binding it likes.
 fn v3_chunk_to_chunk_slot(chunk: vec3i) -> u32 {
-    let origin = slot_table_params.xyz;
-    let dim = slot_table_params.w;
-    let local = chunk - origin;
-    if (any(local < vec3i(0)) || any(local >= vec3i(dim))) {
-        return V3_PROBE_SENTINEL;
+    let mask = u32(slot_table_params.x);
+    let max_probe = u32(slot_table_params.y);
+    var idx = v3_slot_hash(chunk) & mask;
+    for (var i = 0u; i < max_probe; i++) {
+        let base = idx * 4u;
+        let stored = slot_table[base + 3u];
+        if (stored == 0u) {
+            return V3_PROBE_SENTINEL;
+        }
+        let key = vec3u(slot_table[base], slot_table[base + 1u], slot_table[base + 2u]);
+        if (all(bitcast<vec3i>(key) == chunk)) {
+            return stored - 1u;
+        }
+        idx = (idx + 1u) & mask;
     }
-    let idx = u32(local.x)
-            + u32(local.y) * u32(dim)
-            + u32(local.z) * u32(dim) * u32(dim);
-    return slot_table[idx];
+    return V3_PROBE_SENTINEL;
+}
+
+/// Slot table hash of a chunk coord. Mirrors `dda_slot_hash` from
+/// dda_common.wgsl and `pool::slot_table_hash`.
+fn v3_slot_hash(chunk: vec3i) -> u32 {
+    var h = (bitcast<u32>(chunk.x) * 0x8DA6B343u)
+          ^ (bitcast<u32>(chunk.y) * 0xD8163841u)
+          ^ (bitcast<u32>(chunk.z) * 0xCB1AB31Fu);
+    h ^= h >> 16u;
+    h *= 0x7FEB352Du;
+    h ^= h >> 15u;
+    return h;
 }
 
 // ─── Chunk → world / probe coordinate mapping ───────────────────────────
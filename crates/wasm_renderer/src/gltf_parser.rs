@@ -0,0 +1,990 @@
+//! Pure Rust glTF 2.0 / GLB parser — no wasm_bindgen, no JS types.
+//!
+//! Reads triangle geometry from the default scene, flattening the node
+//! hierarchy into world space, and produces the same [`ParsedObj`] triangle
+//! soup the OBJ path feeds to the voxelizer. Materials are reduced to the
+//! `pbrMetallicRoughness` factors that map onto `MaterialEntry`; textures,
+//! normals, skins and morph targets are ignored.
+//!
+//! Buffers must be embedded: either the GLB `BIN` chunk or base64 `data:`
+//! URIs. External `.bin` files are not resolved.
+
+use std::fmt;
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::json::{parse_json, JsonError, JsonValue};
+use crate::obj_parser::ParsedObj;
+use crate::scene::MaterialEntry;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+const MODE_TRIANGLES: usize = 4;
+const MODE_TRIANGLE_STRIP: usize = 5;
+const MODE_TRIANGLE_FAN: usize = 6;
+
+const COMPONENT_BYTE: usize = 5120;
+const COMPONENT_UNSIGNED_BYTE: usize = 5121;
+const COMPONENT_SHORT: usize = 5122;
+const COMPONENT_UNSIGNED_SHORT: usize = 5123;
+const COMPONENT_UNSIGNED_INT: usize = 5125;
+const COMPONENT_FLOAT: usize = 5126;
+
+/// Most elements an accessor without a `bufferView` may zero-fill. Nothing
+/// in the file backs its size, so it is capped rather than trusted.
+const MAX_ZERO_FILL_COUNT: usize = 1 << 22;
+
+/// Required extensions this parser can honour (or safely ignore).
+const SUPPORTED_EXTENSIONS: &[&str] = &["KHR_materials_emissive_strength", "KHR_mesh_quantization"];
+
+/// Material factors from one glTF material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+    /// Material name, or `material_<index>` when unnamed.
+    pub name: String,
+    /// `baseColorFactor` (linear RGBA). Defaults to opaque white.
+    pub base_color: [f32; 4],
+    /// `roughnessFactor`. Defaults to 1.
+    pub roughness: f32,
+    /// `emissiveFactor`, pre-multiplied by `KHR_materials_emissive_strength`.
+    pub emissive: [f32; 3],
+    /// Whether `alphaMode` is `BLEND` or `MASK` (alpha taken from base color).
+    pub alpha_blended: bool,
+}
+
+impl GltfMaterial {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            base_color: [1.0; 4],
+            roughness: 1.0,
+            emissive: [0.0; 3],
+            alpha_blended: false,
+        }
+    }
+
+    /// Opacity: base color alpha for non-opaque alpha modes, otherwise 1.
+    pub fn opacity(&self) -> f32 {
+        if self.alpha_blended {
+            self.base_color[3].clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Convert to the GPU material layout.
+    pub fn to_material_entry(&self) -> MaterialEntry {
+        let [r, g, b, _] = self.base_color;
+        MaterialEntry::new(
+            [r, g, b],
+            self.roughness.clamp(0.0, 1.0),
+            self.emissive,
+            self.opacity(),
+        )
+    }
+}
+
+/// Parsed glTF scene: flattened triangles plus material factors.
+#[derive(Debug, Clone)]
+pub struct ParsedGltf {
+    /// World-space triangle soup. Material group 0 is "(default)" for
+    /// primitives without a material; glTF material `i` is group `i + 1`.
+    /// Object groups are mesh-bearing nodes in traversal order.
+    pub mesh: ParsedObj,
+    /// glTF materials in document order.
+    pub materials: Vec<GltfMaterial>,
+    /// Primitives skipped because they are points or lines.
+    pub skipped_primitives: usize,
+}
+
+impl ParsedGltf {
+    /// Per-group material entries for `voxelize_with_material_entries`.
+    ///
+    /// Group 0 is the glTF default material (white, fully rough).
+    pub fn group_material_entries(&self) -> Vec<Option<MaterialEntry>> {
+        let default = GltfMaterial::new(String::new()).to_material_entry();
+        std::iter::once(Some(default))
+            .chain(self.materials.iter().map(|m| Some(m.to_material_entry())))
+            .collect()
+    }
+}
+
+/// Why a glTF document could not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GltfError {
+    /// GLB container is truncated or malformed.
+    InvalidGlb(&'static str),
+    /// The JSON part is not valid UTF-8.
+    InvalidUtf8,
+    /// The JSON part failed to parse.
+    Json(JsonError),
+    /// `asset.version` is missing or not 2.x.
+    UnsupportedVersion(String),
+    /// A name in `extensionsRequired` this parser does not implement.
+    UnsupportedExtension(String),
+    /// Geometry references a buffer that is not embedded.
+    ExternalBuffer(String),
+    /// A structural problem in the document (bad index, out-of-bounds view…).
+    Invalid(String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::InvalidGlb(msg) => write!(f, "invalid GLB: {msg}"),
+            GltfError::InvalidUtf8 => write!(f, "glTF JSON is not valid UTF-8"),
+            GltfError::Json(e) => write!(f, "{e}"),
+            GltfError::UnsupportedVersion(v) => write!(f, "unsupported glTF version '{v}'"),
+            GltfError::UnsupportedExtension(name) => {
+                write!(f, "unsupported required extension {name}")
+            }
+            GltfError::ExternalBuffer(uri) => write!(f, "external buffer '{uri}' is not embedded"),
+            GltfError::Invalid(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+impl From<JsonError> for GltfError {
+    fn from(e: JsonError) -> Self {
+        GltfError::Json(e)
+    }
+}
+
+/// Parse a `.glb` or `.gltf` file. The container is detected from the magic bytes.
+pub fn parse_gltf(bytes: &[u8]) -> Result<ParsedGltf, GltfError> {
+    let (json_text, bin_chunk) = if bytes.starts_with(GLB_MAGIC) {
+        split_glb(bytes)?
+    } else {
+        let text = std::str::from_utf8(bytes).map_err(|_| GltfError::InvalidUtf8)?;
+        (text.trim_start_matches('\u{feff}'), None)
+    };
+    let doc = parse_json(json_text)?;
+
+    let version = doc
+        .get("asset")
+        .and_then(|a| a.get("version"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    if !version.starts_with("2.") {
+        return Err(GltfError::UnsupportedVersion(version.to_string()));
+    }
+    if let Some(required) = doc.get("extensionsRequired").and_then(|e| e.as_array()) {
+        for ext in required.iter().filter_map(|e| e.as_str()) {
+            if !SUPPORTED_EXTENSIONS.contains(&ext) {
+                return Err(GltfError::UnsupportedExtension(ext.to_string()));
+            }
+        }
+    }
+
+    let buffers = load_buffers(&doc, bin_chunk)?;
+    let materials = parse_materials(&doc);
+
+    let mut out = ParsedGltf {
+        mesh: ParsedObj {
+            positions: Vec::new(),
+            triangles: Vec::new(),
+            triangle_materials: Vec::new(),
+            material_names: std::iter::once("(default)".to_string())
+                .chain(materials.iter().map(|m| m.name.clone()))
+                .collect(),
+            material_libs: Vec::new(),
+            triangle_objects: Vec::new(),
+            object_names: vec!["(default)".to_string()],
+            colors: Vec::new(),
+            texcoords: Vec::new(),
+            triangle_texcoords: Vec::new(),
+        },
+        materials,
+        skipped_primitives: 0,
+    };
+
+    let nodes = array(&doc, "nodes");
+    let mut visited = vec![false; nodes.len()];
+    let mut stack: Vec<(usize, Mat4)> = scene_roots(&doc, nodes)?
+        .into_iter()
+        .rev()
+        .map(|n| (n, Mat4::IDENTITY))
+        .collect();
+
+    while let Some((node_idx, parent)) = stack.pop() {
+        let node = nodes
+            .get(node_idx)
+            .ok_or_else(|| GltfError::Invalid(format!("node {node_idx} does not exist")))?;
+        if std::mem::replace(&mut visited[node_idx], true) {
+            return Err(GltfError::Invalid(format!(
+                "node {node_idx} is reachable twice"
+            )));
+        }
+        let world = parent * node_transform(node);
+
+        if let Some(mesh_idx) = node.get("mesh").and_then(|m| m.as_usize()) {
+            let name = node
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("node_{node_idx}"));
+            let object = out.mesh.object_names.len() as u32;
+            out.mesh.object_names.push(name);
+            append_mesh(&doc, &buffers, mesh_idx, world, object, &mut out)?;
+        }
+
+        if let Some(children) = node.get("children").and_then(|c| c.as_array()) {
+            for child in children.iter().rev() {
+                let child = child.as_usize().ok_or_else(|| {
+                    GltfError::Invalid(format!("node {node_idx} has an invalid child"))
+                })?;
+                stack.push((child, world));
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+fn array<'a>(doc: &'a JsonValue, key: &str) -> &'a [JsonValue] {
+    doc.get(key).and_then(|v| v.as_array()).unwrap_or(&[])
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Split a GLB container into its JSON text and optional BIN chunk.
+fn split_glb(bytes: &[u8]) -> Result<(&str, Option<&[u8]>), GltfError> {
+    let version = read_u32_le(bytes, 4).ok_or(GltfError::InvalidGlb("truncated header"))?;
+    if version != 2 {
+        return Err(GltfError::UnsupportedVersion(version.to_string()));
+    }
+    let length = read_u32_le(bytes, 8).ok_or(GltfError::InvalidGlb("truncated header"))? as usize;
+    if length > bytes.len() {
+        return Err(GltfError::InvalidGlb("declared length exceeds data"));
+    }
+    let bytes = &bytes[..length];
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset < bytes.len() {
+        let chunk_len = read_u32_le(bytes, offset)
+            .ok_or(GltfError::InvalidGlb("truncated chunk header"))?
+            as usize;
+        let chunk_type = read_u32_le(bytes, offset + 4)
+            .ok_or(GltfError::InvalidGlb("truncated chunk header"))?;
+        let data = bytes
+            .get(offset + 8..offset + 8 + chunk_len)
+            .ok_or(GltfError::InvalidGlb("chunk exceeds data"))?;
+        match chunk_type {
+            GLB_CHUNK_JSON if json.is_none() => json = Some(data),
+            GLB_CHUNK_BIN if bin.is_none() => bin = Some(data),
+            _ => {}
+        }
+        offset += 8 + chunk_len;
+    }
+
+    let json = json.ok_or(GltfError::InvalidGlb("missing JSON chunk"))?;
+    let text = std::str::from_utf8(json).map_err(|_| GltfError::InvalidUtf8)?;
+    Ok((text.trim_end_matches('\0'), bin))
+}
+
+/// Resolve every buffer. Buffers that are external stay `Err(uri)` and only
+/// fail the parse if an accessor actually reads from them.
+fn load_buffers(
+    doc: &JsonValue,
+    bin_chunk: Option<&[u8]>,
+) -> Result<Vec<Result<Vec<u8>, String>>, GltfError> {
+    let mut buffers = Vec::new();
+    for (i, buffer) in array(doc, "buffers").iter().enumerate() {
+        let data = match buffer.get("uri").and_then(|u| u.as_str()) {
+            None => match (i, bin_chunk) {
+                (0, Some(bin)) => Ok(bin.to_vec()),
+                _ => Err(format!("buffer {i}")),
+            },
+            Some(uri) => match uri.strip_prefix("data:") {
+                Some(rest) => {
+                    let (header, payload) = rest.split_once(',').ok_or_else(|| {
+                        GltfError::Invalid(format!("buffer {i} has a malformed data URI"))
+                    })?;
+                    if !header.ends_with(";base64") {
+                        return Err(GltfError::Invalid(format!(
+                            "buffer {i} data URI is not base64"
+                        )));
+                    }
+                    Ok(decode_base64(payload).ok_or_else(|| {
+                        GltfError::Invalid(format!("buffer {i} has invalid base64"))
+                    })?)
+                }
+                None => Err(uri.to_string()),
+            },
+        };
+        if let (Ok(bytes), Some(len)) = (&data, buffer.get("byteLength").and_then(|l| l.as_usize()))
+        {
+            if bytes.len() < len {
+                return Err(GltfError::Invalid(format!(
+                    "buffer {i} is shorter than its byteLength"
+                )));
+            }
+        }
+        buffers.push(data);
+    }
+    Ok(buffers)
+}
+
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in input.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return None,
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn parse_materials(doc: &JsonValue) -> Vec<GltfMaterial> {
+    array(doc, "materials")
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let name = m
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("material_{i}"));
+            let mut mat = GltfMaterial::new(name);
+            if let Some(pbr) = m.get("pbrMetallicRoughness") {
+                if let Some(&[r, g, b, a]) = pbr
+                    .get("baseColorFactor")
+                    .and_then(|c| c.as_f32_vec())
+                    .as_deref()
+                {
+                    mat.base_color = [r, g, b, a];
+                }
+                if let Some(rough) = pbr.get("roughnessFactor").and_then(|r| r.as_f32()) {
+                    mat.roughness = rough;
+                }
+            }
+            if let Some(&[r, g, b]) = m
+                .get("emissiveFactor")
+                .and_then(|e| e.as_f32_vec())
+                .as_deref()
+            {
+                mat.emissive = [r, g, b];
+            }
+            if let Some(strength) = m
+                .get("extensions")
+                .and_then(|e| e.get("KHR_materials_emissive_strength"))
+                .and_then(|e| e.get("emissiveStrength"))
+                .and_then(|s| s.as_f32())
+            {
+                mat.emissive = mat.emissive.map(|c| c * strength);
+            }
+            mat.alpha_blended = matches!(
+                m.get("alphaMode").and_then(|a| a.as_str()),
+                Some("BLEND" | "MASK")
+            );
+            mat
+        })
+        .collect()
+}
+
+/// Root nodes of the default scene. Without `scenes`, every node that is no
+/// other node's child is a root.
+fn scene_roots(doc: &JsonValue, nodes: &[JsonValue]) -> Result<Vec<usize>, GltfError> {
+    let scenes = array(doc, "scenes");
+    if scenes.is_empty() {
+        let mut is_child = vec![false; nodes.len()];
+        for node in nodes {
+            for child in node
+                .get("children")
+                .and_then(|c| c.as_array())
+                .unwrap_or(&[])
+            {
+                if let Some(c) = child.as_usize().filter(|&c| c < nodes.len()) {
+                    is_child[c] = true;
+                }
+            }
+        }
+        return Ok((0..nodes.len()).filter(|&n| !is_child[n]).collect());
+    }
+
+    let scene_idx = doc.get("scene").and_then(|s| s.as_usize()).unwrap_or(0);
+    let scene = scenes
+        .get(scene_idx)
+        .ok_or_else(|| GltfError::Invalid(format!("scene {scene_idx} does not exist")))?;
+    array(scene, "nodes")
+        .iter()
+        .map(|n| {
+            n.as_usize()
+                .ok_or_else(|| GltfError::Invalid("scene has an invalid node index".to_string()))
+        })
+        .collect()
+}
+
+/// Local transform of a node: `matrix` if present, otherwise T * R * S.
+fn node_transform(node: &JsonValue) -> Mat4 {
+    if let Some(m) = node.get("matrix").and_then(|m| m.as_f32_vec()) {
+        if let Ok(cols) = <[f32; 16]>::try_from(m.as_slice()) {
+            return Mat4::from_cols_array(&cols);
+        }
+    }
+    let vec3 =
+        |key: &str, default: Vec3| match node.get(key).and_then(|v| v.as_f32_vec()).as_deref() {
+            Some(&[x, y, z]) => Vec3::new(x, y, z),
+            _ => default,
+        };
+    let rotation = match node.get("rotation").and_then(|v| v.as_f32_vec()).as_deref() {
+        Some(&[x, y, z, w]) => Quat::from_xyzw(x, y, z, w).normalize(),
+        _ => Quat::IDENTITY,
+    };
+    Mat4::from_scale_rotation_translation(
+        vec3("scale", Vec3::ONE),
+        rotation,
+        vec3("translation", Vec3::ZERO),
+    )
+}
+
+/// Append every triangle primitive of `mesh_idx`, transformed by `world`.
+fn append_mesh(
+    doc: &JsonValue,
+    buffers: &[Result<Vec<u8>, String>],
+    mesh_idx: usize,
+    world: Mat4,
+    object: u32,
+    out: &mut ParsedGltf,
+) -> Result<(), GltfError> {
+    let mesh = array(doc, "meshes")
+        .get(mesh_idx)
+        .ok_or_else(|| GltfError::Invalid(format!("mesh {mesh_idx} does not exist")))?;
+    let material_count = out.materials.len();
+
+    for primitive in array(mesh, "primitives") {
+        let mode = primitive
+            .get("mode")
+            .and_then(|m| m.as_usize())
+            .unwrap_or(MODE_TRIANGLES);
+        if !matches!(
+            mode,
+            MODE_TRIANGLES | MODE_TRIANGLE_STRIP | MODE_TRIANGLE_FAN
+        ) {
+            out.skipped_primitives += 1;
+            continue;
+        }
+
+        let position_acc = primitive
+            .get("attributes")
+            .and_then(|a| a.get("POSITION"))
+            .and_then(|p| p.as_usize())
+            .ok_or_else(|| {
+                GltfError::Invalid(format!("mesh {mesh_idx} has a primitive without POSITION"))
+            })?;
+        let positions = read_accessor(doc, buffers, position_acc, "VEC3")?;
+        let vertex_count = positions.len() / 3;
+
+        let indices: Vec<u32> = match primitive.get("indices").and_then(|i| i.as_usize()) {
+            Some(acc) => {
+                let raw = read_accessor(doc, buffers, acc, "SCALAR")?;
+                let mut indices = Vec::with_capacity(raw.len());
+                for v in raw {
+                    let idx = v as u32;
+                    if idx as usize >= vertex_count {
+                        return Err(GltfError::Invalid(format!(
+                            "mesh {mesh_idx} index {idx} exceeds vertex count {vertex_count}"
+                        )));
+                    }
+                    indices.push(idx);
+                }
+                indices
+            }
+            None => (0..vertex_count as u32).collect(),
+        };
+
+        let material = match primitive.get("material").and_then(|m| m.as_usize()) {
+            Some(m) if m < material_count => m as u32 + 1,
+            Some(m) => return Err(GltfError::Invalid(format!("material {m} does not exist"))),
+            None => 0,
+        };
+
+        let base = out.mesh.positions.len() as u32;
+        for p in positions.chunks_exact(3) {
+            let w = world.transform_point3(Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32));
+            out.mesh.positions.push([w.x, w.y, w.z]);
+        }
+
+        for [a, b, c] in assemble_triangles(mode, &indices) {
+            // Strips use repeated indices as restarts; skip those and any
+            // other degenerate triangle, as the OBJ path does.
+            if a == b || b == c || a == c {
+                continue;
+            }
+            out.mesh.triangles.push([base + a, base + b, base + c]);
+            out.mesh.triangle_materials.push(material);
+            out.mesh.triangle_objects.push(object);
+        }
+    }
+    Ok(())
+}
+
+/// Expand an index list into triangles for the given primitive mode.
+fn assemble_triangles(mode: usize, indices: &[u32]) -> Vec<[u32; 3]> {
+    match mode {
+        MODE_TRIANGLE_STRIP => indices
+            .windows(3)
+            .enumerate()
+            .map(|(i, w)| {
+                if i % 2 == 0 {
+                    [w[0], w[1], w[2]]
+                } else {
+                    [w[1], w[0], w[2]]
+                }
+            })
+            .collect(),
+        MODE_TRIANGLE_FAN => match indices.split_first() {
+            Some((&first, rest)) => rest.windows(2).map(|w| [first, w[0], w[1]]).collect(),
+            None => Vec::new(),
+        },
+        _ => indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect(),
+    }
+}
+
+/// Read an accessor as a flat list of `f64` components (normalized integers
+/// are mapped to [-1, 1] / [0, 1]). Accessors without a `bufferView` read
+/// as zeros, per the spec.
+fn read_accessor(
+    doc: &JsonValue,
+    buffers: &[Result<Vec<u8>, String>],
+    index: usize,
+    expected_type: &str,
+) -> Result<Vec<f64>, GltfError> {
+    let invalid = |msg: &str| GltfError::Invalid(format!("accessor {index}: {msg}"));
+    let accessor = array(doc, "accessors")
+        .get(index)
+        .ok_or_else(|| invalid("does not exist"))?;
+
+    let ty = accessor.get("type").and_then(|t| t.as_str()).unwrap_or("");
+    if ty != expected_type {
+        return Err(invalid(&format!("expected {expected_type}, found {ty}")));
+    }
+    if accessor.get("sparse").is_some() {
+        return Err(invalid("sparse accessors are not supported"));
+    }
+    let components = if ty == "VEC3" { 3 } else { 1 };
+    let count = accessor
+        .get("count")
+        .and_then(|c| c.as_usize())
+        .ok_or_else(|| invalid("missing count"))?;
+    let component_type = accessor
+        .get("componentType")
+        .and_then(|c| c.as_usize())
+        .unwrap_or(0);
+    let normalized = accessor
+        .get("normalized")
+        .and_then(|n| n.as_bool())
+        .unwrap_or(false);
+    let component_size = match component_type {
+        COMPONENT_BYTE | COMPONENT_UNSIGNED_BYTE => 1,
+        COMPONENT_SHORT | COMPONENT_UNSIGNED_SHORT => 2,
+        COMPONENT_UNSIGNED_INT | COMPONENT_FLOAT => 4,
+        other => return Err(invalid(&format!("unsupported componentType {other}"))),
+    };
+
+    let total = count
+        .checked_mul(components)
+        .ok_or_else(|| invalid("count overflows"))?;
+    let Some(view_idx) = accessor.get("bufferView").and_then(|v| v.as_usize()) else {
+        if count > MAX_ZERO_FILL_COUNT {
+            return Err(invalid(&format!(
+                "count {count} without a bufferView exceeds {MAX_ZERO_FILL_COUNT}"
+            )));
+        }
+        return Ok(vec![0.0; total]);
+    };
+    let view = array(doc, "bufferViews")
+        .get(view_idx)
+        .ok_or_else(|| invalid("bufferView does not exist"))?;
+    let buffer_idx = view
+        .get("buffer")
+        .and_then(|b| b.as_usize())
+        .ok_or_else(|| invalid("bufferView has no buffer"))?;
+    let buffer = match buffers.get(buffer_idx) {
+        Some(Ok(data)) => data,
+        Some(Err(uri)) => return Err(GltfError::ExternalBuffer(uri.clone())),
+        None => return Err(invalid("buffer does not exist")),
+    };
+
+    let element_size = component_size * components;
+    let stride = view
+        .get("byteStride")
+        .and_then(|s| s.as_usize())
+        .unwrap_or(element_size);
+    let view_offset = view
+        .get("byteOffset")
+        .and_then(|o| o.as_usize())
+        .unwrap_or(0);
+    let view_len = view
+        .get("byteLength")
+        .and_then(|l| l.as_usize())
+        .ok_or_else(|| invalid("bufferView has no byteLength"))?;
+    let start = view_offset
+        .checked_add(accessor.get("byteOffset").and_then(|o| o.as_usize()).unwrap_or(0))
+        .ok_or_else(|| invalid("byteOffset overflows"))?;
+    if stride < element_size {
+        return Err(invalid("byteStride smaller than element"));
+    }
+    if count > 0 {
+        let end = stride
+            .checked_mul(count - 1)
+            .and_then(|span| span.checked_add(start))
+            .and_then(|last| last.checked_add(element_size))
+            .ok_or_else(|| invalid("reads past the end of its bufferView"))?;
+        if end > view_offset.saturating_add(view_len) || end > buffer.len() {
+            return Err(invalid("reads past the end of its bufferView"));
+        }
+    }
+
+    let mut out = Vec::with_capacity(total);
+    for i in 0..count {
+        for c in 0..components {
+            let at = start + i * stride + c * component_size;
+            let b = &buffer[at..at + component_size];
+            let v = match component_type {
+                COMPONENT_BYTE => {
+                    let v = b[0] as i8 as f64;
+                    if normalized {
+                        (v / 127.0).max(-1.0)
+                    } else {
+                        v
+                    }
+                }
+                COMPONENT_UNSIGNED_BYTE => {
+                    let v = b[0] as f64;
+                    if normalized {
+                        v / 255.0
+                    } else {
+                        v
+                    }
+                }
+                COMPONENT_SHORT => {
+                    let v = i16::from_le_bytes([b[0], b[1]]) as f64;
+                    if normalized {
+                        (v / 32767.0).max(-1.0)
+                    } else {
+                        v
+                    }
+                }
+                COMPONENT_UNSIGNED_SHORT => {
+                    let v = u16::from_le_bytes([b[0], b[1]]) as f64;
+                    if normalized {
+                        v / 65535.0
+                    } else {
+                        v
+                    }
+                }
+                COMPONENT_UNSIGNED_INT => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            };
+            out.push(v);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let n = (chunk[0] as u32) << 16
+                | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+                | *chunk.get(2).unwrap_or(&0) as u32;
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    /// One triangle: three float positions followed by three u16 indices.
+    fn triangle_buffer() -> Vec<u8> {
+        let mut buf = Vec::new();
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        for i in [0u16, 1, 2] {
+            buf.extend_from_slice(&i.to_le_bytes());
+        }
+        buf
+    }
+
+    fn triangle_json(buffer_uri: Option<&str>, extra_node: &str) -> String {
+        let uri = buffer_uri
+            .map(|u| format!(r#""uri": "{u}","#))
+            .unwrap_or_default();
+        format!(
+            r#"{{
+  "asset": {{"version": "2.0"}},
+  "scene": 0,
+  "scenes": [{{"nodes": [0]}}],
+  "nodes": [{{"name": "tri", "mesh": 0 {extra_node}}}],
+  "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "indices": 1, "material": 0}}]}}],
+  "materials": [{{
+    "name": "glow",
+    "pbrMetallicRoughness": {{"baseColorFactor": [1, 0, 0, 1], "roughnessFactor": 0.25}},
+    "emissiveFactor": [1, 0.5, 0]
+  }}],
+  "buffers": [{{{uri} "byteLength": 42}}],
+  "bufferViews": [
+    {{"buffer": 0, "byteOffset": 0, "byteLength": 36}},
+    {{"buffer": 0, "byteOffset": 36, "byteLength": 6}}
+  ],
+  "accessors": [
+    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}},
+    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+  ]
+}}"#
+        )
+    }
+
+    fn make_glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+        let mut out = Vec::new();
+        out.extend_from_slice(GLB_MAGIC);
+        out.extend_from_slice(&2u32.to_le_bytes());
+        out.extend_from_slice(&(total as u32).to_le_bytes());
+        out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        out.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+        out.extend_from_slice(&json);
+        out.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        out.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+        out.extend_from_slice(&bin);
+        out
+    }
+
+    #[test]
+    fn parse_glb_triangle() {
+        let glb = make_glb(&triangle_json(None, ""), &triangle_buffer());
+        let parsed = parse_gltf(&glb).unwrap();
+        assert_eq!(parsed.mesh.positions.len(), 3);
+        assert_eq!(parsed.mesh.triangles, vec![[0, 1, 2]]);
+        assert_eq!(parsed.mesh.triangle_materials, vec![1]);
+        assert_eq!(parsed.mesh.material_names, vec!["(default)", "glow"]);
+        assert_eq!(parsed.mesh.object_names, vec!["(default)", "tri"]);
+        assert_eq!(parsed.mesh.triangle_objects, vec![1]);
+    }
+
+    #[test]
+    fn parse_gltf_with_data_uri() {
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            encode_base64(&triangle_buffer())
+        );
+        let json = triangle_json(Some(&uri), "");
+        let parsed = parse_gltf(json.as_bytes()).unwrap();
+        assert_eq!(parsed.mesh.triangles.len(), 1);
+        assert_eq!(parsed.mesh.positions[1], [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn material_factors_map_to_entry() {
+        let glb = make_glb(&triangle_json(None, ""), &triangle_buffer());
+        let parsed = parse_gltf(&glb).unwrap();
+        let mat = &parsed.materials[0];
+        assert_eq!(mat.base_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(mat.roughness, 0.25);
+        assert_eq!(mat.emissive, [1.0, 0.5, 0.0]);
+        assert_eq!(mat.opacity(), 1.0);
+
+        let entries = parsed.group_material_entries();
+        assert_eq!(entries.len(), 2);
+        let expected = MaterialEntry::new([1.0, 0.0, 0.0], 0.25, [1.0, 0.5, 0.0], 1.0);
+        let got = entries[1].unwrap();
+        assert_eq!(got.albedo_rg, expected.albedo_rg);
+        assert_eq!(got.albedo_b_roughness, expected.albedo_b_roughness);
+    }
+
+    #[test]
+    fn node_transform_applied() {
+        let glb = make_glb(
+            &triangle_json(None, r#", "translation": [10, 0, 0], "scale": [2, 2, 2]"#),
+            &triangle_buffer(),
+        );
+        let parsed = parse_gltf(&glb).unwrap();
+        assert_eq!(parsed.mesh.positions[0], [10.0, 0.0, 0.0]);
+        assert_eq!(parsed.mesh.positions[1], [12.0, 0.0, 0.0]);
+        assert_eq!(parsed.mesh.positions[2], [10.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn child_nodes_inherit_parent_transform() {
+        let json = triangle_json(None, "").replace(
+            r#""nodes": [{"name": "tri", "mesh": 0 }]"#,
+            r#""nodes": [{"translation": [0, 5, 0], "children": [1]}, {"name": "tri", "mesh": 0, "translation": [1, 0, 0]}]"#,
+        );
+        let parsed = parse_gltf(&make_glb(&json, &triangle_buffer())).unwrap();
+        assert_eq!(parsed.mesh.positions[0], [1.0, 5.0, 0.0]);
+    }
+
+    #[test]
+    fn mesh_instanced_twice() {
+        let json = triangle_json(None, "")
+            .replace(
+                r#""scenes": [{"nodes": [0]}]"#,
+                r#""scenes": [{"nodes": [0, 1]}]"#,
+            )
+            .replace(
+                r#""nodes": [{"name": "tri", "mesh": 0 }]"#,
+                r#""nodes": [{"mesh": 0}, {"mesh": 0, "translation": [0, 0, 3]}]"#,
+            );
+        let parsed = parse_gltf(&make_glb(&json, &triangle_buffer())).unwrap();
+        assert_eq!(parsed.mesh.triangles, vec![[0, 1, 2], [3, 4, 5]]);
+        assert_eq!(parsed.mesh.positions[3], [0.0, 0.0, 3.0]);
+        assert_eq!(
+            parsed.mesh.object_names,
+            vec!["(default)", "node_0", "node_1"]
+        );
+    }
+
+    #[test]
+    fn strip_and_fan_assembly() {
+        assert_eq!(
+            assemble_triangles(MODE_TRIANGLE_STRIP, &[0, 1, 2, 3]),
+            vec![[0, 1, 2], [2, 1, 3]]
+        );
+        assert_eq!(
+            assemble_triangles(MODE_TRIANGLE_FAN, &[0, 1, 2, 3]),
+            vec![[0, 1, 2], [0, 2, 3]]
+        );
+        assert_eq!(
+            assemble_triangles(MODE_TRIANGLES, &[0, 1, 2, 3]),
+            vec![[0, 1, 2]]
+        );
+    }
+
+    #[test]
+    fn non_triangle_primitives_skipped() {
+        let json =
+            triangle_json(None, "").replace(r#""material": 0"#, r#""material": 0, "mode": 1"#);
+        let parsed = parse_gltf(&make_glb(&json, &triangle_buffer())).unwrap();
+        assert!(parsed.mesh.triangles.is_empty());
+        assert_eq!(parsed.skipped_primitives, 1);
+    }
+
+    #[test]
+    fn external_buffer_rejected() {
+        let json = triangle_json(Some("mesh.bin"), "");
+        assert_eq!(
+            parse_gltf(json.as_bytes()).unwrap_err(),
+            GltfError::ExternalBuffer("mesh.bin".into())
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_accessor_rejected() {
+        let json = triangle_json(None, "").replace(
+            r#""count": 3, "type": "VEC3""#,
+            r#""count": 4, "type": "VEC3""#,
+        );
+        let err = parse_gltf(&make_glb(&json, &triangle_buffer())).unwrap_err();
+        assert!(matches!(err, GltfError::Invalid(msg) if msg.contains("accessor 0")));
+    }
+
+    #[test]
+    fn overflowing_accessor_rejected() {
+        let positions = r#""count": 3, "type": "VEC3""#;
+        let view = r#""byteOffset": 0, "byteLength": 36"#;
+        let with_view = r#"{"bufferView": 0, "componentType": 5126, "count": 3"#;
+        for (from, to) in [
+            (positions, r#""count": 6148914691236517206, "type": "VEC3""#),
+            (positions, r#""count": 4294967297, "type": "VEC3""#),
+            (view, r#""byteOffset": 18446744073709551615, "byteLength": 36"#),
+            // No bufferView: zero-filled, but not at any size
+            (with_view, r#"{"componentType": 5126, "count": 100000000"#),
+        ] {
+            let json = triangle_json(None, "").replace(from, to);
+            let err = parse_gltf(&make_glb(&json, &triangle_buffer())).unwrap_err();
+            assert!(matches!(err, GltfError::Invalid(msg) if msg.contains("accessor 0")), "{to}");
+        }
+    }
+
+    #[test]
+    fn unsupported_version_and_extension() {
+        let json = triangle_json(None, "").replace(r#""version": "2.0""#, r#""version": "1.0""#);
+        assert!(matches!(
+            parse_gltf(json.as_bytes()),
+            Err(GltfError::UnsupportedVersion(_))
+        ));
+
+        let json = triangle_json(None, "").replacen(
+            '{',
+            r#"{"extensionsRequired": ["KHR_draco_mesh_compression"],"#,
+            1,
+        );
+        assert_eq!(
+            parse_gltf(json.as_bytes()).unwrap_err(),
+            GltfError::UnsupportedExtension("KHR_draco_mesh_compression".into())
+        );
+    }
+
+    #[test]
+    fn truncated_glb_rejected() {
+        let glb = make_glb(&triangle_json(None, ""), &triangle_buffer());
+        assert!(matches!(
+            parse_gltf(&glb[..glb.len() - 8]),
+            Err(GltfError::InvalidGlb(_))
+        ));
+        assert!(matches!(
+            parse_gltf(&glb[..10]),
+            Err(GltfError::InvalidGlb(_))
+        ));
+    }
+
+    #[test]
+    fn node_cycle_rejected() {
+        let json = triangle_json(None, "").replace(
+            r#""nodes": [{"name": "tri", "mesh": 0 }]"#,
+            r#""nodes": [{"children": [1]}, {"children": [0]}]"#,
+        );
+        assert!(parse_gltf(&make_glb(&json, &triangle_buffer())).is_err());
+    }
+
+    #[test]
+    fn base64_roundtrip() {
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode_base64(&encode_base64(&data)).unwrap(), data);
+        assert!(decode_base64("ab$c").is_none());
+    }
+}
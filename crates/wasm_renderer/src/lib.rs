@@ -1,11 +1,26 @@
 //! GPU-Resident Voxel Renderer — WASM entry point for the WebGPU worker pipeline.
 
 pub mod camera;
+pub mod edit;
+pub mod export;
+pub mod gltf_parser;
+pub mod json;
 pub mod mesh_cpu;
+pub mod models;
+pub mod mtl_parser;
 pub mod obj_parser;
+pub mod pointcloud;
 pub mod pool;
+pub mod procgen;
+pub mod quantize;
 pub mod scene;
+pub mod scene_file;
+pub mod sdf;
+pub mod stl_parser;
 pub mod summary_cpu;
+pub mod terrain;
+pub mod volume;
+pub mod vox;
 pub mod voxelizer_cpu;
 
 // GPU module and Renderer struct are WASM-only (depend on web_sys, OffscreenCanvas).
@@ -68,10 +83,43 @@ pub struct Renderer {
     backface_culling: bool,
     depth_prepass_enabled: bool,
     use_cpu_mesh: bool,
+    obj_strict: bool,
+    /// Mesh loaders fill closed meshes instead of voxelizing a hollow shell.
+    solid_fill: bool,
+    /// Material for solid-fill interior voxels; `None` inherits the surface material.
+    interior_material: Option<scene::MaterialEntry>,
+    /// World grid `(voxel_size, grid_origin)` shared by mesh loads; `None`
+    /// fits the grid to each model's bounds at the requested resolution.
+    world_grid: Option<(f32, [f32; 3])>,
+    /// Model-to-world transform applied by mesh loaders.
+    model_transform: glam::Mat4,
+    /// How mesh loaders pick the material of voxels covered by several materials.
+    material_conflict: voxelizer_cpu::MaterialConflict,
+    /// Where mesh loaders take surface voxel colors from.
+    color_source: voxelizer_cpu::ColorSource,
+    /// Texture for [`voxelizer_cpu::ColorSource::Texture`], set from JS.
+    texture: Option<std::sync::Arc<voxelizer_cpu::Texture>>,
+    obj_warnings: obj_parser::ObjWarnings,
+    /// Color quantization error of the last model load.
+    quantize_report: quantize::QuantizeReport,
+    /// Stepped mesh load between `begin_*_voxelize` and `finish_voxelize`:
+    /// its token, the job, and whether it adds to the scene.
+    voxelize_job: Option<(u32, voxelizer_cpu::VoxelizeJob, bool)>,
+    /// Token of the last stepped mesh load started.
+    voxelize_token: u32,
+    /// Chunked volume load between `begin_volume` and `finish_volume`.
+    volume_stream: Option<volume::VolumeStream>,
     scene_voxel_size: f32,
     scene_grid_origin: [f32; 3],
     scene_mesh_center: [f32; 3],
     scene_mesh_extent: f32,
+    // CPU-side copy of every resident chunk, kept for export and for
+    // merging models into the scene.
+    cpu_chunks: std::collections::HashMap<pool::ChunkCoord, scene::ChunkData>,
+    // Models making up the scene, with the shared grid and material table.
+    models: models::SceneModels,
+    // CPU mesh path: last mesh per slot, reused when only some chunks change.
+    cpu_meshes: Vec<Option<mesh_cpu::MeshResult>>,
     freeze_cull: bool,
     hiz_cull_enabled: bool,
     frustum_cull_enabled: bool,
@@ -259,10 +307,26 @@ impl Renderer {
             backface_culling: true,
             depth_prepass_enabled: true,
             use_cpu_mesh: false,
+            obj_strict: false,
+            solid_fill: false,
+            interior_material: None,
+            world_grid: None,
+            model_transform: glam::Mat4::IDENTITY,
+            material_conflict: voxelizer_cpu::MaterialConflict::First,
+            color_source: voxelizer_cpu::ColorSource::Material,
+            texture: None,
+            obj_warnings: obj_parser::ObjWarnings::default(),
+            quantize_report: quantize::QuantizeReport::default(),
+            voxelize_job: None,
+            voxelize_token: 0,
+            volume_stream: None,
             scene_voxel_size: 1.0,
             scene_grid_origin: [0.0; 3],
             scene_mesh_center: [32.0, 32.0, 32.0],
             scene_mesh_extent: 64.0,
+            cpu_chunks: std::collections::HashMap::new(),
+            models: models::SceneModels::new(),
+            cpu_meshes: Vec::new(),
             freeze_cull: false,
             hiz_cull_enabled: true,
             frustum_cull_enabled: true,
@@ -354,6 +418,193 @@ impl Renderer {
     pub fn set_depth_prepass(&mut self, enabled: bool) { self.depth_prepass_enabled = enabled; }
     pub fn set_use_cpu_mesh(&mut self, enabled: bool) { self.use_cpu_mesh = enabled; }
     pub fn get_use_cpu_mesh(&self) -> bool { self.use_cpu_mesh }
+    pub fn set_obj_strict(&mut self, enabled: bool) { self.obj_strict = enabled; }
+    pub fn get_obj_strict(&self) -> bool { self.obj_strict }
+    pub fn set_solid_fill(&mut self, enabled: bool) { self.solid_fill = enabled; }
+    pub fn get_solid_fill(&self) -> bool { self.solid_fill }
+
+    /// Give solid-fill interior voxels their own material instead of
+    /// inheriting the surface material.
+    pub fn set_interior_material(&mut self, r: f32, g: f32, b: f32, roughness: f32) {
+        self.interior_material =
+            Some(scene::MaterialEntry::new([r, g, b], roughness, [0.0; 3], 1.0));
+    }
+
+    /// Interior voxels inherit the material of the surface they lie behind.
+    pub fn clear_interior_material(&mut self) { self.interior_material = None; }
+
+    /// Choose how mesh loaders pick the material of a voxel covered by
+    /// several materials: 0 = first triangle in file order, 1 = most area
+    /// inside the voxel, 2 = triangle closest to the voxel center,
+    /// 3 = earliest name in `priority`.
+    pub fn set_material_conflict(
+        &mut self,
+        mode: u8,
+        priority: Vec<String>,
+    ) -> Result<(), JsValue> {
+        self.material_conflict = match mode {
+            0 => voxelizer_cpu::MaterialConflict::First,
+            1 => voxelizer_cpu::MaterialConflict::AreaWeighted,
+            2 => voxelizer_cpu::MaterialConflict::Closest,
+            3 => voxelizer_cpu::MaterialConflict::Priority(priority),
+            _ => return Err(JsValue::from_str(&format!("invalid material conflict mode {mode}"))),
+        };
+        Ok(())
+    }
+
+    /// Choose where mesh loaders take surface voxel colors from: 0 = the
+    /// triangle's material, 1 = interpolated vertex colors, 2 = the texture
+    /// from [`set_texture`](Self::set_texture) at the interpolated texcoord.
+    pub fn set_color_source(&mut self, mode: u8) -> Result<(), JsValue> {
+        self.color_source = match mode {
+            0 => voxelizer_cpu::ColorSource::Material,
+            1 => voxelizer_cpu::ColorSource::VertexColors,
+            2 => match &self.texture {
+                Some(texture) => voxelizer_cpu::ColorSource::Texture(texture.clone()),
+                None => return Err(JsValue::from_str("no texture set")),
+            },
+            _ => return Err(JsValue::from_str(&format!("invalid color source {mode}"))),
+        };
+        Ok(())
+    }
+
+    /// Texture for color source 2 as decoded RGBA8 pixels, top row first.
+    pub fn set_texture(&mut self, width: u32, height: u32, rgba: Vec<u8>) -> Result<(), JsValue> {
+        let len = rgba.len();
+        let texture = voxelizer_cpu::Texture::new(width, height, rgba).ok_or_else(|| {
+            JsValue::from_str(&format!("{len} bytes is not a {width}x{height} RGBA image"))
+        })?;
+        let texture = std::sync::Arc::new(texture);
+        if let voxelizer_cpu::ColorSource::Texture(_) = self.color_source {
+            self.color_source = voxelizer_cpu::ColorSource::Texture(texture.clone());
+        }
+        self.texture = Some(texture);
+        Ok(())
+    }
+
+    /// Drop the texture; a texture color source falls back to materials.
+    pub fn clear_texture(&mut self) {
+        self.texture = None;
+        if let voxelizer_cpu::ColorSource::Texture(_) = self.color_source {
+            self.color_source = voxelizer_cpu::ColorSource::Material;
+        }
+    }
+
+    /// Anchor mesh loads to a fixed world grid: `voxel_size` world units per
+    /// voxel with voxel (0,0,0) at `(x, y, z)`. The `resolution` argument of
+    /// the loaders is ignored while set.
+    pub fn set_world_grid(
+        &mut self,
+        voxel_size: f32,
+        x: f32,
+        y: f32,
+        z: f32,
+    ) -> Result<(), JsValue> {
+        if !(voxel_size.is_finite() && voxel_size > 0.0) {
+            return Err(JsValue::from_str(&format!("invalid voxel size {voxel_size}")));
+        }
+        self.world_grid = Some((voxel_size, [x, y, z]));
+        Ok(())
+    }
+
+    /// Fit the grid to each loaded model's bounds again.
+    pub fn clear_world_grid(&mut self) { self.world_grid = None; }
+
+    /// Model-to-world transform for subsequent mesh loads, as 16 floats in
+    /// column-major order. Must be affine.
+    pub fn set_model_transform(&mut self, matrix: &[f32]) -> Result<(), JsValue> {
+        let cols: &[f32; 16] = matrix
+            .try_into()
+            .map_err(|_| JsValue::from_str("model transform needs 16 floats"))?;
+        self.model_transform = glam::Mat4::from_cols_array(cols);
+        Ok(())
+    }
+
+    /// Build the model transform from translation, rotation quaternion
+    /// (x, y, z, w) and per-axis scale.
+    pub fn set_model_trs(
+        &mut self,
+        translation: &[f32],
+        rotation: &[f32],
+        scale: &[f32],
+    ) -> Result<(), JsValue> {
+        let (Ok(t), Ok(r), Ok(s)) = (
+            <[f32; 3]>::try_from(translation),
+            <[f32; 4]>::try_from(rotation),
+            <[f32; 3]>::try_from(scale),
+        ) else {
+            return Err(JsValue::from_str("expected translation[3], rotation[4], scale[3]"));
+        };
+        self.model_transform = glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::from(s),
+            glam::Quat::from_array(r).normalize(),
+            glam::Vec3::from(t),
+        );
+        Ok(())
+    }
+
+    /// Warning counters from the last OBJ parse:
+    /// [degenerate_faces, out_of_range_faces, malformed_vertices, invalid_indices].
+    pub fn get_obj_warning_counts(&self) -> Vec<u32> {
+        let w = &self.obj_warnings;
+        vec![w.degenerate_faces, w.out_of_range_faces, w.malformed_vertices, w.invalid_indices]
+    }
+
+    /// Line-numbered messages for the first warnings of the last OBJ parse.
+    pub fn get_obj_warnings(&self) -> Vec<String> {
+        self.obj_warnings.details.iter().map(|w| w.to_string()).collect()
+    }
+
+    /// Color quantization of the last model load, material table stage then
+    /// chunk palette stage, each as
+    /// [colors_in, colors_out, voxels, mean_error, max_error] (Oklab distance).
+    pub fn get_quantize_stats(&self) -> Vec<f64> {
+        let r = &self.quantize_report;
+        [r.materials, r.palettes]
+            .iter()
+            .flat_map(|s| {
+                [
+                    s.colors_in as f64,
+                    s.colors_out as f64,
+                    s.voxels as f64,
+                    s.mean_error as f64,
+                    s.max_error as f64,
+                ]
+            })
+            .collect()
+    }
+
+    /// Index buffer pool occupancy as
+    /// [used_words, free_words, live_ranges, free_ranges, largest_free, fragmentation].
+    pub fn get_index_buf_stats(&self) -> Vec<f64> {
+        let s = self.pool.index_buf_stats();
+        vec![
+            s.used_words as f64,
+            s.free_words as f64,
+            s.live_ranges as f64,
+            s.free_ranges as f64,
+            s.largest_free as f64,
+            s.fragmentation() as f64,
+        ]
+    }
+
+    /// GPU slot table occupancy as [entries, capacity, max_probe, unreachable],
+    /// where `unreachable` counts resident chunks GI traversal can't see.
+    pub fn get_slot_table_stats(&self) -> Vec<u32> {
+        let s = self.pool.slot_table_stats();
+        vec![s.entries, s.capacity, s.max_probe, s.unreachable]
+    }
+
+    /// Compact the index buffer pool on the GPU. Returns the number of chunk
+    /// ranges moved.
+    pub fn compact_index_buf(&mut self) -> u32 {
+        let moved = self.pool.compact_index_buf(&self.device, &self.queue);
+        if moved > 0 {
+            log(&format!("Index buffer compacted: {moved} ranges moved"));
+        }
+        moved
+    }
+
     pub fn set_freeze_cull(&mut self, enabled: bool) { self.freeze_cull = enabled; }
     pub fn get_freeze_cull(&self) -> bool { self.freeze_cull }
     pub fn set_hiz_cull_enabled(&mut self, enabled: bool) { self.hiz_cull_enabled = enabled; }
@@ -455,6 +706,7 @@ impl Renderer {
 
     /// Generate and upload the Cornell box test scene (colored walls + emissive light + objects).
     pub fn load_test_scene(&mut self) -> Result<(), JsValue> {
+        self.voxelize_job = None;
         let (chunks, materials) = scene::generate_cornell_box();
 
         // Notify GI backend of scene reset.
@@ -479,7 +731,9 @@ impl Renderer {
             let bpe = scene::IndexBufBuilder::bits_per_entry(chunk.palette.len());
             let index_buf_words = chunk.index_buf.pack(bpe);
             let meta = scene::IndexBufBuilder::palette_meta(chunk.palette.len());
-            let ib_offset = self.pool.alloc_index_buf(index_buf_words.len() as u32);
+            let ib_offset = self.pool
+                .alloc_index_buf(&self.device, &self.queue, slot, index_buf_words.len() as u32)
+                .map_err(|e| JsValue::from_str(&format!("Alloc error: {e:?}")))?;
             self.pool.upload_chunk(
                 &self.queue,
                 slot,
@@ -516,16 +770,18 @@ impl Renderer {
             self.queue.submit(std::iter::once(encoder.finish()));
         }
 
-        self.resident_count = resident_count;
-        self.rebuild_meshes(&chunks);
+        self.resident_count = self.pool.allocator().slot_span();
+        self.models.reset(&chunks, &materials, self.scene_voxel_size, self.scene_grid_origin);
+        self.cpu_chunks = chunks.into_iter().map(|c| (c.coord, c)).collect();
+        self.rebuild_meshes(None);
 
         // CPU reference stats
         self.total_voxels = 0;
         self.mesh_verts = 0;
         self.mesh_indices = 0;
         self.mesh_quads = 0;
-        for chunk in &chunks {
-            self.total_voxels += chunk.occupancy.popcount();
+        for chunk in self.cpu_chunks.values() {
+            self.total_voxels += chunk.occupancy.interior_popcount();
             let pal_words = chunk.palette.as_words();
             let bpe = scene::IndexBufBuilder::bits_per_entry(chunk.palette.len());
             let idx_words = chunk.index_buf.pack(bpe);
@@ -552,7 +808,7 @@ impl Renderer {
 
         log(&format!(
             "Test scene loaded: {} chunks, {} materials, I-3 + R-1 dispatched for {} slots",
-            chunks.len(),
+            self.cpu_chunks.len(),
             materials.len(),
             resident_count,
         ));
@@ -560,21 +816,678 @@ impl Renderer {
     }
 
     /// Load an OBJ model: parse → voxelize → upload → dispatch I-3 + R-1.
-    /// Clears the current scene first.
+    /// Clears the current scene first. Materials get hashed colors; use
+    /// `load_obj_model_with_mtl` to supply `.mtl` libraries.
     pub fn load_obj_model(&mut self, obj_text: &str, resolution: u32) -> Result<(), JsValue> {
-        let parsed = obj_parser::parse_obj(obj_text);
+        self.load_obj_model_with_mtl(obj_text, &js_sys::Map::new(), resolution)
+    }
+
+    /// Load an OBJ model with its material libraries.
+    /// `mtl_files` maps file names referenced by `mtllib` to their MTL text.
+    /// Clears the current scene first. Parse problems are available from
+    /// `get_obj_warnings`; in strict mode (`set_obj_strict`) the first
+    /// malformed statement is returned as an error with its line and column.
+    pub fn load_obj_model_with_mtl(
+        &mut self,
+        obj_text: &str,
+        mtl_files: &js_sys::Map,
+        resolution: u32,
+    ) -> Result<(), JsValue> {
+        let job = self.obj_job(obj_text, mtl_files, &self.voxelize_options(resolution))?;
+        self.load_voxelized(job.finish())
+    }
+
+    /// Add an OBJ model to the current scene without clearing it; see
+    /// [`add_voxelized`](Self::add_voxelized). Returns the model ID.
+    pub fn add_obj_model(
+        &mut self,
+        obj_text: &str,
+        mtl_files: &js_sys::Map,
+        resolution: u32,
+    ) -> Result<u32, JsValue> {
+        let job = self.obj_job(obj_text, mtl_files, &self.add_options(resolution))?;
+        self.add_voxelized(job.finish())
+    }
+
+    /// Parse an OBJ model and plan its voxelization.
+    fn obj_job(
+        &mut self,
+        obj_text: &str,
+        mtl_files: &js_sys::Map,
+        options: &voxelizer_cpu::VoxelizeOptions,
+    ) -> Result<voxelizer_cpu::VoxelizeJob, JsValue> {
+        let mut files = std::collections::HashMap::new();
+        mtl_files.for_each(&mut |value, key| {
+            if let (Some(name), Some(text)) = (key.as_string(), value.as_string()) {
+                files.insert(name, text);
+            }
+        });
+
+        let parse_options = obj_parser::ObjParseOptions { strict: self.obj_strict };
+        let output = obj_parser::parse_obj_with_options(obj_text, parse_options)
+            .map_err(|e| JsValue::from_str(&format!("OBJ parse error: {e}")))?;
+        let parsed = output.parsed;
+        self.obj_warnings = output.warnings;
+        if !self.obj_warnings.is_empty() {
+            log(&format!(
+                "OBJ warnings: {} degenerate faces, {} out-of-range faces, {} malformed vertices, {} invalid indices",
+                self.obj_warnings.degenerate_faces,
+                self.obj_warnings.out_of_range_faces,
+                self.obj_warnings.malformed_vertices,
+                self.obj_warnings.invalid_indices,
+            ));
+        }
+
+        let library = obj_parser::load_mtl_libraries(&parsed.material_libs, &files);
         log(&format!(
-            "Parsed OBJ: {} vertices, {} triangles, {} materials",
+            "Parsed OBJ: {} vertices, {} triangles, {} materials ({} defined by MTL)",
             parsed.positions.len(),
             parsed.triangles.len(),
             parsed.material_names.len(),
+            library.len(),
         ));
 
         if parsed.triangles.is_empty() {
             return Err(JsValue::from_str("OBJ contains no triangles"));
         }
 
-        let result = voxelizer_cpu::voxelize(&parsed, resolution);
+        let group_materials = voxelizer_cpu::resolve_group_materials(&parsed, &library);
+        voxelizer_cpu::VoxelizeJob::new(parsed, group_materials, options)
+            .map_err(|e| JsValue::from_str(&format!("Voxelization error: {e}")))
+    }
+
+    /// Load a glTF 2.0 model (`.glb` or `.gltf` with embedded buffers),
+    /// voxelize it, and upload to the chunk pool.
+    ///
+    /// The default scene's node hierarchy is flattened into world space.
+    /// Material base color, roughness and emissive factors become the
+    /// material table; textures are ignored.
+    pub fn load_gltf_model(&mut self, bytes: &[u8], resolution: u32) -> Result<(), JsValue> {
+        let job = self.gltf_job(bytes, &self.voxelize_options(resolution))?;
+        self.load_voxelized(job.finish())
+    }
+
+    /// Add a glTF model to the current scene without clearing it.
+    /// Returns the model ID.
+    pub fn add_gltf_model(&mut self, bytes: &[u8], resolution: u32) -> Result<u32, JsValue> {
+        let job = self.gltf_job(bytes, &self.add_options(resolution))?;
+        self.add_voxelized(job.finish())
+    }
+
+    /// Parse a glTF model and plan its voxelization.
+    fn gltf_job(
+        &self,
+        bytes: &[u8],
+        options: &voxelizer_cpu::VoxelizeOptions,
+    ) -> Result<voxelizer_cpu::VoxelizeJob, JsValue> {
+        let parsed = gltf_parser::parse_gltf(bytes)
+            .map_err(|e| JsValue::from_str(&format!("glTF parse error: {e}")))?;
+        log(&format!(
+            "Parsed glTF: {} vertices, {} triangles, {} materials, {} nodes with meshes",
+            parsed.mesh.positions.len(),
+            parsed.mesh.triangles.len(),
+            parsed.materials.len(),
+            parsed.mesh.object_names.len() - 1,
+        ));
+        if parsed.skipped_primitives > 0 {
+            log(&format!(
+                "glTF: skipped {} point/line primitives",
+                parsed.skipped_primitives,
+            ));
+        }
+
+        if parsed.mesh.triangles.is_empty() {
+            return Err(JsValue::from_str("glTF contains no triangles"));
+        }
+
+        let group_materials = parsed.group_material_entries();
+        voxelizer_cpu::VoxelizeJob::new(parsed.mesh, group_materials, options)
+            .map_err(|e| JsValue::from_str(&format!("Voxelization error: {e}")))
+    }
+
+    /// Load an STL model (binary or ASCII, auto-detected), voxelize it, and
+    /// upload to the chunk pool.
+    ///
+    /// Binary facets are grouped into materials by their attribute word;
+    /// RGB555 attribute colors become the group's albedo.
+    pub fn load_stl_model(&mut self, bytes: &[u8], resolution: u32) -> Result<(), JsValue> {
+        let job = self.stl_job(bytes, &self.voxelize_options(resolution))?;
+        self.load_voxelized(job.finish())
+    }
+
+    /// Add a STL model to the current scene without clearing it.
+    /// Returns the model ID.
+    pub fn add_stl_model(&mut self, bytes: &[u8], resolution: u32) -> Result<u32, JsValue> {
+        let job = self.stl_job(bytes, &self.add_options(resolution))?;
+        self.add_voxelized(job.finish())
+    }
+
+    /// Parse an STL model and plan its voxelization.
+    fn stl_job(
+        &self,
+        bytes: &[u8],
+        options: &voxelizer_cpu::VoxelizeOptions,
+    ) -> Result<voxelizer_cpu::VoxelizeJob, JsValue> {
+        let parsed = stl_parser::parse_stl(bytes)
+            .map_err(|e| JsValue::from_str(&format!("STL parse error: {e}")))?;
+        log(&format!(
+            "Parsed STL: {} triangles, {} materials, {} degenerate facets skipped",
+            parsed.mesh.triangles.len(),
+            parsed.mesh.material_names.len(),
+            parsed.degenerate_facets,
+        ));
+
+        if parsed.mesh.triangles.is_empty() {
+            return Err(JsValue::from_str("STL contains no triangles"));
+        }
+
+        let group_materials = parsed.group_material_entries();
+        voxelizer_cpu::VoxelizeJob::new(parsed.mesh, group_materials, options)
+            .map_err(|e| JsValue::from_str(&format!("Voxelization error: {e}")))
+    }
+
+    /// Start a stepped OBJ load, so voxelizing a large model doesn't block
+    /// the page: the model is parsed and planned now, voxelized over calls to
+    /// [`step_voxelize`](Self::step_voxelize) and uploaded by
+    /// [`finish_voxelize`](Self::finish_voxelize). With `add` the current
+    /// scene is kept, as by [`add_obj_model`](Self::add_obj_model); otherwise
+    /// it is replaced.
+    ///
+    /// Returns the token the other stepped-load calls take. Only one stepped
+    /// load runs at a time: starting another abandons it, as does any load
+    /// that replaces the scene, and for a replacing stepped load any model
+    /// add (finishing it would drop the added model). Calls with the token
+    /// of an abandoned load fail.
+    pub fn begin_obj_voxelize(
+        &mut self,
+        obj_text: &str,
+        mtl_files: &js_sys::Map,
+        resolution: u32,
+        add: bool,
+    ) -> Result<u32, JsValue> {
+        self.voxelize_job = None;
+        let job = self.obj_job(obj_text, mtl_files, &self.stepped_options(resolution, add))?;
+        Ok(self.start_voxelize(job, add))
+    }
+
+    /// Start a stepped glTF load; see [`begin_obj_voxelize`](Self::begin_obj_voxelize).
+    pub fn begin_gltf_voxelize(
+        &mut self,
+        bytes: &[u8],
+        resolution: u32,
+        add: bool,
+    ) -> Result<u32, JsValue> {
+        self.voxelize_job = None;
+        let job = self.gltf_job(bytes, &self.stepped_options(resolution, add))?;
+        Ok(self.start_voxelize(job, add))
+    }
+
+    /// Start a stepped STL load; see [`begin_obj_voxelize`](Self::begin_obj_voxelize).
+    pub fn begin_stl_voxelize(
+        &mut self,
+        bytes: &[u8],
+        resolution: u32,
+        add: bool,
+    ) -> Result<u32, JsValue> {
+        self.voxelize_job = None;
+        let job = self.stl_job(bytes, &self.stepped_options(resolution, add))?;
+        Ok(self.start_voxelize(job, add))
+    }
+
+    /// Voxelize up to `max_chunks` more chunks of the stepped load `token`.
+    /// Returns true once only [`finish_voxelize`](Self::finish_voxelize) is
+    /// left.
+    pub fn step_voxelize(&mut self, token: u32, max_chunks: u32) -> Result<bool, JsValue> {
+        match &mut self.voxelize_job {
+            Some((t, job, _)) if *t == token => Ok(job.step(max_chunks as usize)),
+            _ => Err(abandoned_voxelize(token)),
+        }
+    }
+
+    /// Chunks of the stepped load `token` voxelized so far and in total, as
+    /// `[done, total]`; empty when that load is not in progress.
+    pub fn get_voxelize_progress(&self, token: u32) -> Vec<u32> {
+        match &self.voxelize_job {
+            Some((t, job, _)) if *t == token => {
+                let (done, total) = job.progress();
+                vec![done as u32, total as u32]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Abandon the stepped load `token`, if it is still in progress.
+    pub fn cancel_voxelize(&mut self, token: u32) {
+        if matches!(self.voxelize_job, Some((t, _, _)) if t == token) {
+            self.voxelize_job = None;
+        }
+    }
+
+    /// Voxelize what's left of the stepped load `token`, fill it for solid
+    /// mode and upload it. Returns the model ID.
+    pub fn finish_voxelize(&mut self, token: u32) -> Result<u32, JsValue> {
+        let (job, add) = match self.voxelize_job.take() {
+            Some((t, job, add)) if t == token => (job, add),
+            other => {
+                self.voxelize_job = other;
+                return Err(abandoned_voxelize(token));
+            }
+        };
+        if add {
+            return self.add_voxelized(job.finish());
+        }
+        self.load_voxelized(job.finish())?;
+        self.models.ids().last().ok_or_else(|| JsValue::from_str("Model load failed"))
+    }
+
+    /// Load a point cloud (PLY or XYZ text, auto-detected) binned into
+    /// voxels of `voxel_size`. Voxels with fewer than `min_points` points
+    /// are dropped; point colors are averaged per voxel. A world grid, if
+    /// set, overrides the voxel size and origin. Clouds spread over more
+    /// chunks than the pool has slots are rejected, here and when adding.
+    pub fn load_point_cloud(
+        &mut self,
+        bytes: &[u8],
+        voxel_size: f32,
+        min_points: u32,
+    ) -> Result<(), JsValue> {
+        let result = self.voxelize_points(bytes, voxel_size, min_points, self.world_grid)?;
+        self.load_voxelized(result)
+    }
+
+    /// Add a point cloud to the current scene without clearing it, on the
+    /// scene's grid unless a world grid is set. Returns the model ID.
+    pub fn add_point_cloud_model(
+        &mut self,
+        bytes: &[u8],
+        voxel_size: f32,
+        min_points: u32,
+    ) -> Result<u32, JsValue> {
+        let grid = self.world_grid.or(self.models.grid());
+        let result = self.voxelize_points(bytes, voxel_size, min_points, grid)?;
+        self.add_voxelized(result)
+    }
+
+    fn voxelize_points(
+        &self,
+        bytes: &[u8],
+        voxel_size: f32,
+        min_points: u32,
+        grid: Option<(f32, [f32; 3])>,
+    ) -> Result<voxelizer_cpu::VoxelizeResult, JsValue> {
+        let error = |e: pointcloud::PointCloudError| {
+            JsValue::from_str(&format!("Point cloud error: {e}"))
+        };
+        let cloud = pointcloud::PointCloud::parse(bytes).map_err(error)?;
+        log(&format!(
+            "Parsed point cloud: {} points{}",
+            cloud.positions.len(),
+            if cloud.colors.is_some() { " with colors" } else { "" },
+        ));
+        let options = pointcloud::PointCloudOptions {
+            voxel_size: grid.map_or(voxel_size, |(size, _)| size) as f64,
+            min_points,
+            grid_origin: grid.map(|(_, origin)| origin.map(|c| c as f64)),
+        };
+        pointcloud::voxelize_points(&cloud, &options).map_err(error)
+    }
+
+    /// Load a MagicaVoxel `.vox` file directly into the chunk pool.
+    ///
+    /// No mesh voxelization: voxels map 1:1 onto the grid (one voxel per
+    /// world unit) and the 255-color palette plus `MATL` data become the
+    /// material table.
+    pub fn load_vox_model(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        let file = vox::parse_vox(bytes)
+            .map_err(|e| JsValue::from_str(&format!("VOX parse error: {e}")))?;
+        log(&format!(
+            "Parsed VOX: {} models, {} instances, {} voxels",
+            file.models.len(),
+            file.instances.len(),
+            file.instances
+                .iter()
+                .map(|i| file.models[i.model].voxels.len())
+                .sum::<usize>(),
+        ));
+        self.load_voxelized(file.to_chunks())
+    }
+
+    /// Load a seeded procedural world (hills, caves, ore veins) covering
+    /// `chunks_xz × chunks_xz` chunk columns from the origin, `chunks_below`
+    /// chunk layers under y = 0 and `chunks_above` from y = 0 up. The same
+    /// seed always gives the same world. Worlds of more chunks than the pool
+    /// has slots are rejected before any are generated.
+    pub fn load_procedural_world(
+        &mut self,
+        seed: u32,
+        chunks_xz: u32,
+        chunks_below: u32,
+        chunks_above: u32,
+    ) -> Result<(), JsValue> {
+        let error = |e: procgen::WorldError| JsValue::from_str(&format!("World error: {e}"));
+        // Checked before the counts become coords, so large ones can't wrap
+        let span = (chunks_xz as u64 * chunks_xz as u64)
+            .saturating_mul(chunks_below as u64 + chunks_above as u64);
+        if span > pool::MAX_SLOTS as u64 {
+            return Err(error(procgen::WorldError::TooManyChunks { span }));
+        }
+        let options = procgen::WorldOptions { seed: seed as u64, ..Default::default() };
+        let min = pool::ChunkCoord { x: 0, y: -(chunks_below as i32), z: 0 };
+        let max = pool::ChunkCoord {
+            x: chunks_xz as i32 - 1,
+            y: chunks_above as i32 - 1,
+            z: chunks_xz as i32 - 1,
+        };
+        self.load_voxelized(procgen::generate_world(&options, min, max).map_err(error)?)
+    }
+
+    /// Load solid terrain from a heightmap: raw 16-bit little-endian
+    /// `width × height` samples, or a PGM (whose header gives the size).
+    /// `options` is JSON for [`terrain::HeightmapOptions::from_json`]
+    /// (vertical scale, floor, material bands); empty uses the defaults.
+    pub fn load_heightmap(
+        &mut self,
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+        options: &str,
+    ) -> Result<(), JsValue> {
+        let error =
+            |e: terrain::HeightmapError| JsValue::from_str(&format!("Heightmap error: {e}"));
+        let map = terrain::Heightmap::parse(bytes, width, height).map_err(error)?;
+        let options = terrain::HeightmapOptions::from_json(options).map_err(error)?;
+        log(&format!("Parsed heightmap: {}x{}", map.width, map.height));
+        self.load_voxelized(terrain::generate_terrain(&map, &options).map_err(error)?)
+    }
+
+    /// Load a dense scalar volume: an NRRD file, or raw 8/16-bit samples
+    /// whose layout `options` gives. `options` is JSON for
+    /// [`volume::VolumeOptions::from_json`] (layout, iso-threshold or
+    /// transfer function); empty thresholds NRRD data at any positive value.
+    pub fn load_volume(&mut self, bytes: &[u8], options: &str) -> Result<(), JsValue> {
+        let error = |e: volume::VolumeError| JsValue::from_str(&format!("Volume error: {e}"));
+        let options = volume::VolumeOptions::from_json(options).map_err(error)?;
+        let result = volume::load_volume(bytes, &options).map_err(error)?;
+        log(&format!("Imported volume: {} chunks", result.chunks.len()));
+        self.load_voxelized(result)
+    }
+
+    /// Start a volume load fed in pieces with [`push_volume`](Self::push_volume),
+    /// for files too large to pass to [`load_volume`](Self::load_volume) in
+    /// one buffer. Takes the same `options`; replaces any load in progress.
+    pub fn begin_volume(&mut self, options: &str) -> Result<(), JsValue> {
+        let options = volume::VolumeOptions::from_json(options)
+            .map_err(|e| JsValue::from_str(&format!("Volume error: {e}")))?;
+        self.volume_stream = Some(volume::VolumeStream::new(options));
+        Ok(())
+    }
+
+    /// Feed the next bytes of the volume, in pieces of any size. An error
+    /// abandons the load.
+    pub fn push_volume(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        let stream = self.volume_stream.as_mut()
+            .ok_or_else(|| JsValue::from_str("No volume load in progress"))?;
+        stream.push(bytes).map_err(|e| {
+            self.volume_stream = None;
+            JsValue::from_str(&format!("Volume error: {e}"))
+        })
+    }
+
+    /// Finish the volume load once every byte has been pushed, replacing
+    /// the scene.
+    pub fn finish_volume(&mut self) -> Result<(), JsValue> {
+        let stream = self.volume_stream.take()
+            .ok_or_else(|| JsValue::from_str("No volume load in progress"))?;
+        let result = stream.finish()
+            .map_err(|e| JsValue::from_str(&format!("Volume error: {e}")))?;
+        log(&format!("Imported volume: {} chunks", result.chunks.len()));
+        self.load_voxelized(result)
+    }
+
+    /// Load a signed-distance scene described as JSON (primitives, CSG
+    /// operators, transforms and per-primitive materials); see
+    /// [`sdf::SdfScene::from_json`] for the format.
+    pub fn load_sdf_scene(&mut self, json: &str) -> Result<(), JsValue> {
+        let error = |e: sdf::SdfError| JsValue::from_str(&format!("SDF error: {e}"));
+        let scene = sdf::SdfScene::from_json(json).map_err(error)?;
+        self.load_voxelized(scene.rasterize().map_err(error)?)
+    }
+
+    /// Add a MagicaVoxel `.vox` file to the current scene without clearing
+    /// it. The scene must use one world unit per voxel. Returns the model ID.
+    pub fn add_vox_model(&mut self, bytes: &[u8]) -> Result<u32, JsValue> {
+        let file = vox::parse_vox(bytes)
+            .map_err(|e| JsValue::from_str(&format!("VOX parse error: {e}")))?;
+        self.add_voxelized(file.to_chunks())
+    }
+
+    /// Remove a model added by one of the `add_*_model` methods (or the base
+    /// model of the last load). Only the chunks it covered are re-uploaded
+    /// and re-meshed; chunks left empty are evicted.
+    pub fn remove_model(&mut self, id: u32) -> Result<(), JsValue> {
+        let touched = self.models.remove(id, &mut self.cpu_chunks)
+            .ok_or_else(|| JsValue::from_str(&format!("No model with ID {id}")))?;
+        self.apply_chunk_changes(&touched)
+    }
+
+    /// IDs of the models in the scene, in load order.
+    pub fn get_model_ids(&self) -> Vec<u32> {
+        self.models.ids().collect()
+    }
+
+    /// Make one chunk resident at chunk coord (x, y, z), replacing any chunk
+    /// already there. The data is in upload layout: `occupancy` is the
+    /// 8192-word padded occupancy, `palette` the material IDs (entry 0 =
+    /// empty) and `index_buf` the per-voxel palette indices packed at the
+    /// palette's bits per entry. Only this chunk's slot, slot table entry,
+    /// summary and mesh are updated.
+    ///
+    /// The chunk belongs to no model: a later model add or remove covering
+    /// its coord recomposes it from the models alone.
+    pub fn make_resident(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        occupancy: &[u32],
+        palette: &[u16],
+        index_buf: &[u32],
+    ) -> Result<(), JsValue> {
+        let palette_words: Vec<u32> = palette
+            .chunks(2)
+            .map(|pair| pair[0] as u32 | pair.get(1).map_or(0, |&hi| (hi as u32) << 16))
+            .collect();
+        let bpe = scene::IndexBufBuilder::bits_per_entry(palette.len());
+        let chunk = scene::ChunkData {
+            coord: pool::ChunkCoord { x, y, z },
+            occupancy: scene::OccupancyBuilder::from_words(occupancy).ok_or_else(|| {
+                JsValue::from_str(&format!(
+                    "Occupancy must be {} words",
+                    pool::OCCUPANCY_WORDS_PER_SLOT
+                ))
+            })?,
+            palette: scene::PaletteBuilder::from_words(&palette_words, palette.len())
+                .ok_or_else(|| {
+                    JsValue::from_str(&format!(
+                        "Palette must have 1 to {} entries",
+                        pool::MAX_PALETTE_ENTRIES
+                    ))
+                })?,
+            index_buf: scene::IndexBufBuilder::unpack(index_buf, bpe).ok_or_else(|| {
+                JsValue::from_str(&format!("Index buffer too short for {bpe} bits per entry"))
+            })?,
+        };
+        self.make_chunk_resident(chunk)
+    }
+
+    /// Evict the chunk at chunk coord (x, y, z), freeing its slot and GPU
+    /// data. Returns false if no chunk was resident there.
+    pub fn evict(&mut self, x: i32, y: i32, z: i32) -> Result<bool, JsValue> {
+        Ok(self.evict_chunk(pool::ChunkCoord { x, y, z })?.is_some())
+    }
+
+    /// Set the voxel at scene grid position (x, y, z) to scene material
+    /// `material`; 0 clears it. Returns the number of chunks re-uploaded,
+    /// which includes face neighbors of a boundary voxel.
+    ///
+    /// Edits write to the resident chunks, not to models: a later model add
+    /// or remove covering an edited chunk recomposes it from the models alone.
+    pub fn set_voxel(&mut self, x: i32, y: i32, z: i32, material: u16) -> Result<u32, JsValue> {
+        self.apply_edit(|chunks, undo| edit::set_voxel(chunks, [x, y, z], material, undo))
+    }
+
+    /// Set every voxel in the inclusive box between grid positions `a` and
+    /// `b` (3 ints each) to `material`; 0 clears them. Returns the number of
+    /// chunks re-uploaded.
+    pub fn fill_box(&mut self, a: &[i32], b: &[i32], material: u16) -> Result<u32, JsValue> {
+        let (Ok(a), Ok(b)) = (<[i32; 3]>::try_from(a), <[i32; 3]>::try_from(b)) else {
+            return Err(JsValue::from_str("box corners need 3 ints each"));
+        };
+        self.apply_edit(|chunks, undo| edit::fill_box(chunks, a, b, material, undo))
+    }
+
+    /// Set every voxel whose center lies within `radius` voxels of grid
+    /// position (x, y, z) to `material`; 0 clears them. Returns the number
+    /// of chunks re-uploaded.
+    pub fn fill_sphere(
+        &mut self,
+        x: f32,
+        y: f32,
+        z: f32,
+        radius: f32,
+        material: u16,
+    ) -> Result<u32, JsValue> {
+        self.apply_edit(|chunks, undo| {
+            edit::fill_sphere(chunks, [x, y, z], radius, material, undo)
+        })
+    }
+
+    /// Replace scene material `from` with `to` in every resident chunk.
+    /// Returns the number of chunks re-uploaded.
+    pub fn replace_material(&mut self, from: u16, to: u16) -> Result<u32, JsValue> {
+        self.apply_edit(|chunks, undo| edit::replace_material(chunks, from, to, undo))
+    }
+
+    /// Export the resident scene as a MagicaVoxel `.vox` file.
+    ///
+    /// Large scenes are split into 256³ models; materials are reduced to
+    /// the 255-color `.vox` palette.
+    pub fn export_vox(&self) -> Vec<u8> {
+        let chunks = self
+            .pool
+            .allocator()
+            .allocated_slots()
+            .filter_map(|(_, coord)| self.cpu_chunks.get(&coord));
+        vox::write_vox(chunks, self.models.materials())
+    }
+
+    /// Export the resident scene's greedy mesh as Wavefront OBJ + MTL.
+    ///
+    /// Returns `[obj, mtl]`; the OBJ references the library as `mtl_name`.
+    /// With `weld`, vertices shared across chunk borders are merged.
+    pub fn export_obj(&self, weld: bool, mtl_name: &str) -> Vec<String> {
+        let mesh = self.export_mesh(weld);
+        vec![
+            export::write_obj(&mesh, mtl_name),
+            export::write_mtl(&mesh, self.models.materials()),
+        ]
+    }
+
+    /// Export the resident scene's greedy mesh as binary glTF, one
+    /// primitive per material.
+    pub fn export_glb(&self, weld: bool) -> Vec<u8> {
+        export::write_glb(&self.export_mesh(weld), self.models.materials())
+    }
+
+    /// Save the resident scene in the native Gestalt scene format.
+    ///
+    /// Chunks are stored as uploaded (occupancy, palette, packed indices)
+    /// with the material table and scene parameters, RLE-compressed per chunk.
+    pub fn save_scene(&self) -> Vec<u8> {
+        let chunks = self
+            .pool
+            .allocator()
+            .allocated_slots()
+            .filter_map(|(_, coord)| self.cpu_chunks.get(&coord));
+        let params = scene_file::SceneParams {
+            voxel_size: self.scene_voxel_size,
+            grid_origin: self.scene_grid_origin,
+            mesh_center: self.scene_mesh_center,
+            mesh_extent: self.scene_mesh_extent,
+        };
+        scene_file::write_scene(chunks, self.models.materials(), &params, true)
+    }
+
+    /// Load a scene previously written by [`save_scene`](Self::save_scene).
+    pub fn load_scene(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        let result = scene_file::read_scene(bytes)
+            .map_err(|e| JsValue::from_str(&format!("Scene load error: {e}")))?;
+        log(&format!(
+            "Loaded scene: {} chunks, {} materials",
+            result.chunks.len(),
+            result.materials.len(),
+        ));
+        self.load_voxelized(result)
+    }
+
+    /// Voxelizer options for mesh loaders from the current grid, transform,
+    /// fill, material conflict and color settings.
+    fn voxelize_options(&self, resolution: u32) -> voxelizer_cpu::VoxelizeOptions {
+        let fill = match self.solid_fill {
+            true => voxelizer_cpu::Fill::Solid { interior: self.interior_material },
+            false => voxelizer_cpu::Fill::Surface,
+        };
+        voxelizer_cpu::VoxelizeOptions {
+            resolution,
+            voxel_size: self.world_grid.map(|(size, _)| size),
+            grid_origin: self.world_grid.map(|(_, origin)| origin),
+            transform: self.model_transform,
+            fill,
+            conflict: self.material_conflict.clone(),
+            color: self.color_source.clone(),
+        }
+    }
+
+    /// Like [`voxelize_options`](Self::voxelize_options), but without an
+    /// explicit world grid the model snaps to the grid of the current scene.
+    /// Options for a stepped load that adds to the scene or replaces it.
+    /// Keep `job` as the stepped load and hand out its token.
+    fn start_voxelize(&mut self, job: voxelizer_cpu::VoxelizeJob, add: bool) -> u32 {
+        self.voxelize_token = self.voxelize_token.wrapping_add(1);
+        self.voxelize_job = Some((self.voxelize_token, job, add));
+        self.voxelize_token
+    }
+
+    fn stepped_options(&self, resolution: u32, add: bool) -> voxelizer_cpu::VoxelizeOptions {
+        match add {
+            true => self.add_options(resolution),
+            false => self.voxelize_options(resolution),
+        }
+    }
+
+    fn add_options(&self, resolution: u32) -> voxelizer_cpu::VoxelizeOptions {
+        let mut options = self.voxelize_options(resolution);
+        if let (None, Some((size, origin))) = (self.world_grid, self.models.grid()) {
+            options.voxel_size = Some(size);
+            options.grid_origin = Some(origin);
+        }
+        options
+    }
+
+    /// Greedy-mesh the resident chunks for [`export_obj`](Self::export_obj)
+    /// and [`export_glb`](Self::export_glb).
+    fn export_mesh(&self, weld: bool) -> export::ExportMesh {
+        let chunks = self
+            .pool
+            .allocator()
+            .allocated_slots()
+            .filter_map(|(_, coord)| self.cpu_chunks.get(&coord));
+        export::mesh_chunks(chunks, self.scene_voxel_size, self.scene_grid_origin, weld)
+    }
+
+    /// Upload a voxelizer result as the new scene: clear → upload → dispatch I-3 + R-1.
+    fn load_voxelized(&mut self, mut result: voxelizer_cpu::VoxelizeResult) -> Result<(), JsValue> {
+        self.voxelize_job = None;
         log(&format!(
             "Voxelized: {} chunks",
             result.chunks.len(),
@@ -583,18 +1496,18 @@ impl Renderer {
         if result.chunks.is_empty() {
             return Err(JsValue::from_str("Voxelization produced no chunks"));
         }
+        self.log_quantize(result.quantize);
 
         // Cap at pool capacity
         let chunk_limit = pool::MAX_SLOTS as usize;
-        let chunks_to_load = if result.chunks.len() > chunk_limit {
+        if result.chunks.len() > chunk_limit {
             log(&format!(
                 "WARNING: {} chunks exceeds MAX_SLOTS ({}), loading first {} only",
                 result.chunks.len(), chunk_limit, chunk_limit,
             ));
-            &result.chunks[..chunk_limit]
-        } else {
-            &result.chunks[..]
-        };
+            result.chunks.truncate(chunk_limit);
+        }
+        let chunks_to_load = &result.chunks[..];
 
         // Clear existing scene
         self.pool.allocator_mut().clear();
@@ -621,7 +1534,9 @@ impl Renderer {
             let bpe = scene::IndexBufBuilder::bits_per_entry(chunk.palette.len());
             let index_buf_words = chunk.index_buf.pack(bpe);
             let meta = scene::IndexBufBuilder::palette_meta(chunk.palette.len());
-            let ib_offset = self.pool.alloc_index_buf(index_buf_words.len() as u32);
+            let ib_offset = self.pool
+                .alloc_index_buf(&self.device, &self.queue, slot, index_buf_words.len() as u32)
+                .map_err(|e| JsValue::from_str(&format!("Alloc error: {e:?}")))?;
             self.pool.upload_chunk(
                 &self.queue,
                 slot,
@@ -652,12 +1567,19 @@ impl Renderer {
             self.queue.submit(std::iter::once(encoder.finish()));
         }
 
-        self.resident_count = resident_count;
-        self.rebuild_meshes(chunks_to_load);
+        self.resident_count = self.pool.allocator().slot_span();
+        self.total_voxels = chunks_to_load.iter()
+            .map(|c| c.occupancy.interior_popcount()).sum();
+        self.models.reset(
+            &result.chunks,
+            &result.materials,
+            result.voxel_size,
+            result.grid_origin,
+        );
+        self.cpu_chunks = result.chunks.into_iter().map(|c| (c.coord, c)).collect();
+        self.rebuild_meshes(None);
 
         // Update stats
-        self.total_voxels = chunks_to_load.iter()
-            .map(|c| c.occupancy.popcount()).sum();
         self.mesh_verts = 0;
         self.mesh_indices = 0;
         self.mesh_quads = 0;
@@ -669,12 +1591,216 @@ impl Renderer {
         );
 
         log(&format!(
-            "OBJ loaded: {} chunks, {} voxels, {} slots, extent={:.2}",
-            chunks_to_load.len(), self.total_voxels, resident_count, result.mesh_extent,
+            "Model loaded: {} chunks, {} voxels, {} slots, extent={:.2}",
+            self.cpu_chunks.len(), self.total_voxels, resident_count, result.mesh_extent,
         ));
         Ok(())
     }
 
+    /// Merge a voxelizer result into the resident scene as a new model.
+    /// An empty scene is loaded the usual way. Returns the model ID. If the
+    /// pool can't take the model's chunks, the scene is left without it.
+    fn add_voxelized(&mut self, result: voxelizer_cpu::VoxelizeResult) -> Result<u32, JsValue> {
+        if matches!(self.voxelize_job, Some((_, _, false))) {
+            self.voxelize_job = None;
+        }
+        if self.models.ids().next().is_none() {
+            self.load_voxelized(result)?;
+            return self.models.ids().last()
+                .ok_or_else(|| JsValue::from_str("Model load failed"));
+        }
+        log(&format!("Voxelized: {} chunks (additive)", result.chunks.len()));
+        self.log_quantize(result.quantize);
+        let (id, touched) = self.models.add(&result, &mut self.cpu_chunks)
+            .map_err(|e| JsValue::from_str(&format!("Add model error: {e}")))?;
+        if let Err(e) = self.apply_chunk_changes(&touched) {
+            // Pool full: recompose the touched chunks without the model, which
+            // also releases the slots its new coords took before the failure
+            // and the material IDs it appended
+            self.models.remove(id, &mut self.cpu_chunks);
+            self.apply_chunk_changes(&touched)?;
+            return Err(e);
+        }
+        log(&format!("Model {} added: {} chunks touched", id, touched.len()));
+        Ok(id)
+    }
+
+    /// Keep the quantization report of a load and log any merged colors.
+    fn log_quantize(&mut self, report: quantize::QuantizeReport) {
+        self.quantize_report = report;
+        for (stage, s) in [("material table", report.materials), ("palettes", report.palettes)] {
+            if s.colors_out < s.colors_in {
+                log(&format!(
+                    "Quantized {} colors to {} for {} (mean error {:.4}, max {:.4})",
+                    s.colors_in, s.colors_out, stage, s.mean_error, s.max_error,
+                ));
+            }
+        }
+    }
+
+    /// Re-upload the given chunk coords from `cpu_chunks` after a model add or
+    /// remove or an edit: new coords get a slot, vanished ones are evicted. Only the
+    /// affected slots are re-meshed, on the CPU, whichever mesh path is selected.
+    fn apply_chunk_changes(&mut self, touched: &[pool::ChunkCoord]) -> Result<(), JsValue> {
+        let mat_bytes: &[u8] = bytemuck::cast_slice(self.models.materials());
+        self.pool.upload_materials(&self.queue, mat_bytes);
+
+        let dirty = self.update_residency(touched)?;
+        self.total_voxels = self.cpu_chunks.values()
+            .map(|c| c.occupancy.interior_popcount()).sum();
+        self.rebuild_meshes(Some(&dirty));
+        Ok(())
+    }
+
+    /// Run an edit on `cpu_chunks`, then re-upload and re-mesh the chunks it
+    /// touched. If the pool can't hold them (say a fill creates more chunks
+    /// than there are free slots), the edit is undone and the same coords are
+    /// brought back in line, so nothing is left on the CPU but not resident.
+    fn apply_edit(
+        &mut self,
+        edit: impl FnOnce(
+            &mut std::collections::HashMap<pool::ChunkCoord, scene::ChunkData>,
+            &mut edit::Undo,
+        ) -> Result<Vec<pool::ChunkCoord>, edit::EditError>,
+    ) -> Result<u32, JsValue> {
+        let mut undo = edit::Undo::default();
+        let touched = edit(&mut self.cpu_chunks, &mut undo)
+            .map_err(|e| JsValue::from_str(&format!("Edit error: {e}")))?;
+        if touched.is_empty() {
+            return Ok(0);
+        }
+        if let Err(e) = self.apply_chunk_changes(&touched) {
+            undo.restore(&mut self.cpu_chunks);
+            self.apply_chunk_changes(&touched)?;
+            return Err(e);
+        }
+        Ok(touched.len() as u32)
+    }
+
+    /// Make one chunk resident, replacing the chunk at its coord if there is
+    /// one. On failure the coord is left as it was: the previous chunk is
+    /// restored and re-uploaded, or the coord is emptied.
+    fn make_chunk_resident(&mut self, chunk: scene::ChunkData) -> Result<(), JsValue> {
+        let coord = chunk.coord;
+        let added = chunk.occupancy.interior_popcount();
+        let old = self.cpu_chunks.insert(coord, chunk);
+        match self.update_residency(&[coord]) {
+            Ok(dirty) => {
+                self.total_voxels += added;
+                if let Some(old) = &old {
+                    self.total_voxels -= old.occupancy.interior_popcount();
+                }
+                self.rebuild_meshes(Some(&dirty));
+                Ok(())
+            }
+            Err(e) => {
+                // Pool full: the old chunk's index buffer range fits again, and
+                // a new coord releases whatever slot it still holds
+                match old {
+                    Some(old) => self.cpu_chunks.insert(coord, old),
+                    None => self.cpu_chunks.remove(&coord),
+                };
+                let dirty = self.update_residency(&[coord])?;
+                self.rebuild_meshes(Some(&dirty));
+                Err(e)
+            }
+        }
+    }
+
+    /// Evict the chunk at `coord`, returning its data, or `None` if it was
+    /// not resident.
+    fn evict_chunk(
+        &mut self,
+        coord: pool::ChunkCoord,
+    ) -> Result<Option<scene::ChunkData>, JsValue> {
+        let chunk = self.cpu_chunks.remove(&coord);
+        if let Some(chunk) = &chunk {
+            self.total_voxels -= chunk.occupancy.interior_popcount();
+        }
+        let dirty = self.update_residency(&[coord])?;
+        self.rebuild_meshes(Some(&dirty));
+        Ok(chunk)
+    }
+
+    /// Bring the pool in line with `cpu_chunks` at the `touched` coords: new
+    /// coords get a slot, vanished ones are evicted, and their slot table
+    /// entries, GI hooks and summaries are updated for those slots alone —
+    /// the summary on the CPU, rather than a dispatch over the whole pool.
+    /// Returns the slots whose meshes need rebuilding.
+    fn update_residency(&mut self, touched: &[pool::ChunkCoord]) -> Result<Vec<u32>, JsValue> {
+        let empty_occupancy = vec![0u32; pool::OCCUPANCY_WORDS_PER_SLOT as usize];
+        let mut dirty = Vec::with_capacity(touched.len());
+        for coord in touched {
+            let resident = self.pool.allocator().lookup(coord);
+            let Some(chunk) = self.cpu_chunks.get(coord) else {
+                if let Some(slot) = resident {
+                    // Zero occupancy so the freed slot meshes and summarizes as empty
+                    self.pool.upload_occupancy(&self.queue, slot, &empty_occupancy);
+                    let mut summary = summary_cpu::compute_summary(
+                        &empty_occupancy,
+                        &[],
+                        self.models.materials(),
+                        [coord.x, coord.y, coord.z],
+                        self.scene_voxel_size,
+                        self.scene_grid_origin,
+                    );
+                    summary.flags &= !summary_cpu::FLAG_IS_RESIDENT;
+                    self.pool.upload_summary(&self.queue, slot, &summary);
+                    self.pool.dealloc_slot(slot, &self.queue)
+                        .map_err(|e| JsValue::from_str(&format!("Dealloc error: {e:?}")))?;
+                    self.pool.patch_slot_table(&self.queue, *coord, pool::SLOT_TABLE_SENTINEL)
+                        .map_err(|e| JsValue::from_str(&format!("Slot table error: {e:?}")))?;
+                    self.gi_backend.on_chunk_evicted(&self.queue, slot, *coord);
+                    dirty.push(slot);
+                }
+                continue;
+            };
+            let slot = match resident {
+                Some(slot) => slot,
+                None => {
+                    let slot = self.pool.alloc_slot(*coord)
+                        .map_err(|e| JsValue::from_str(&format!("Alloc error: {e:?}")))?;
+                    self.pool.patch_slot_table(&self.queue, *coord, slot)
+                        .map_err(|e| JsValue::from_str(&format!("Slot table error: {e:?}")))?;
+                    self.gi_backend.on_chunk_resident(&self.queue, slot, *coord);
+                    slot
+                }
+            };
+            let palette_words = chunk.palette.as_words();
+            let bpe = scene::IndexBufBuilder::bits_per_entry(chunk.palette.len());
+            let index_buf_words = chunk.index_buf.pack(bpe);
+            let meta = scene::IndexBufBuilder::palette_meta(chunk.palette.len());
+            // Frees the slot's previous range first, so an unchanged bpe re-uploads in place
+            let ib_offset = self.pool
+                .alloc_index_buf(&self.device, &self.queue, slot, index_buf_words.len() as u32)
+                .map_err(|e| JsValue::from_str(&format!("Alloc error: {e:?}")))?;
+            self.pool.upload_chunk(
+                &self.queue,
+                slot,
+                *coord,
+                chunk.occupancy.as_words(),
+                &palette_words,
+                &index_buf_words,
+                ib_offset,
+                meta,
+            );
+            let summary = summary_cpu::compute_summary(
+                chunk.occupancy.as_words(),
+                &palette_words,
+                self.models.materials(),
+                [coord.x, coord.y, coord.z],
+                self.scene_voxel_size,
+                self.scene_grid_origin,
+            );
+            self.pool.upload_summary(&self.queue, slot, &summary);
+            dirty.push(slot);
+        }
+
+        self.gi_backend.on_residency_settled(&self.queue, self.pool.allocator());
+        self.resident_count = self.pool.allocator().slot_span();
+        Ok(dirty)
+    }
+
     pub fn render_frame(&mut self) -> Result<(), JsValue> {
         let surface_texture = match self.surface.get_current_texture() {
             wgpu::CurrentSurfaceTexture::Success(tex)
@@ -1102,104 +2228,64 @@ impl Renderer {
 
     /// Rebuild meshes for all chunks — three-pass GPU pipeline or CPU upload.
     /// Also initializes visibility and builds indirect draw args.
-    fn rebuild_meshes(&mut self, chunks: &[scene::ChunkData]) {
+    /// Rebuild meshes for slots `0..resident_count`. `dirty` lists the slots whose chunk
+    /// changed; they are re-meshed on the CPU, reusing cached results for the rest.
+    /// `None` re-meshes everything, with the pool-wide GPU passes unless `use_cpu_mesh`.
+    ///
+    /// The CPU path sub-allocates each slot's mesh in the vertex/index pools, so dirty
+    /// slots are moved and their draw entries patched without touching the others. A full
+    /// rebuild, or a dirty mesh that no longer fits, repacks every slot — the defrag path.
+    /// The GPU passes lay the pools out by prefix sum instead, so the first dirty rebuild
+    /// after them meshes and repacks every slot once before patches can apply.
+    fn rebuild_meshes(&mut self, dirty: Option<&[u32]>) {
         let resident_count = self.resident_count;
 
-        self.pool.init_visibility(&self.queue, resident_count);
-
-        if self.use_cpu_mesh {
-            // CPU path: run CPU mesher, compute prefix sum on CPU, upload at variable offsets
+        if self.use_cpu_mesh || dirty.is_some() {
+            // CPU path: run CPU mesher, sub-allocate pool ranges, upload at those offsets
             log("Using CPU mesh path");
-            let mut quad_counts = Vec::with_capacity(chunks.len());
-            let mut results = Vec::with_capacity(chunks.len());
-
-            // Pass 1 (CPU): mesh all chunks, collect counts
-            for chunk in chunks {
-                let pal_words = chunk.palette.as_words();
-                let bpe = scene::IndexBufBuilder::bits_per_entry(chunk.palette.len());
-                let idx_words = chunk.index_buf.pack(bpe);
-                let meta_val = scene::IndexBufBuilder::palette_meta(chunk.palette.len());
-                let result = mesh_cpu::mesh_rebuild_cpu(
-                    chunk.occupancy.as_words(),
-                    &pal_words,
-                    &idx_words,
-                    meta_val,
-                    [chunk.coord.x, chunk.coord.y, chunk.coord.z],
-                    self.scene_voxel_size,
-                    self.scene_grid_origin,
-                );
-                quad_counts.push(result.quad_count);
-                results.push(result);
+            self.cpu_meshes.resize_with(resident_count as usize, || None);
+
+            // Pass 1 (CPU): mesh dirty chunks (all on a full rebuild), reuse cached results
+            let mut meshed = 0u32;
+            for slot in 0..resident_count {
+                let cached = self.cpu_meshes[slot as usize].is_some();
+                if cached && dirty.is_some_and(|d| !d.contains(&slot)) {
+                    continue;
+                }
+                let chunk = self.pool.allocator().coord_of(slot)
+                    .and_then(|coord| self.cpu_chunks.get(&coord));
+                self.cpu_meshes[slot as usize] = chunk.map(|chunk| {
+                    let pal_words = chunk.palette.as_words();
+                    let bpe = scene::IndexBufBuilder::bits_per_entry(chunk.palette.len());
+                    let idx_words = chunk.index_buf.pack(bpe);
+                    let meta_val = scene::IndexBufBuilder::palette_meta(chunk.palette.len());
+                    mesh_cpu::mesh_rebuild_cpu(
+                        chunk.occupancy.as_words(),
+                        &pal_words,
+                        &idx_words,
+                        meta_val,
+                        [chunk.coord.x, chunk.coord.y, chunk.coord.z],
+                        self.scene_voxel_size,
+                        self.scene_grid_origin,
+                    )
+                });
+                meshed += 1;
             }
+            log(&format!("  CPU re-meshed {} of {} slots", meshed, resident_count));
 
-            // Pass 2 (CPU): prefix sum → offsets (5 u32 per slot)
-            let mut offset_table = vec![0u32; pool::MAX_SLOTS as usize * 5];
-            let mut vert_offset = 0u32;
-            let mut idx_offset = 0u32;
-            for (slot, &qc) in quad_counts.iter().enumerate() {
-                let vc = qc * 4;
-                let ic = qc * 6;
-                let base = slot * 5;
-                offset_table[base] = vert_offset;
-                offset_table[base + 1] = vc;
-                offset_table[base + 2] = idx_offset;
-                offset_table[base + 3] = ic;
-                offset_table[base + 4] = 0; // write_counter
-                vert_offset += vc;
-                idx_offset += ic;
-            }
-            log(&format!(
-                "  CPU totals: {} vertices, {} indices across {} chunks",
-                vert_offset, idx_offset, chunks.len(),
-            ));
-
-            // Upload mesh_offset_table
-            self.queue.write_buffer(
-                self.pool.mesh_offset_table_buf(),
-                0,
-                bytemuck::cast_slice(&offset_table),
-            );
-
-            // Pass 3 (CPU): upload vertices/indices at computed offsets
-            for (slot, result) in results.iter().enumerate() {
-                let base = slot * 5;
-                let vo = offset_table[base];
-                let vc = offset_table[base + 1];
-                let io = offset_table[base + 2];
-                let ic = offset_table[base + 3];
-                if vc == 0 { continue; }
-                let vert_bytes = (vc * pool::VERTEX_BYTES) as usize;
-                let idx_bytes = (ic * pool::INDEX_BYTES) as usize;
-                self.queue.write_buffer(
-                    self.pool.vertex_pool_buf(),
-                    vo as u64 * pool::VERTEX_BYTES as u64,
-                    &result.vertices[..vert_bytes],
-                );
-                self.queue.write_buffer(
-                    self.pool.index_pool_buf(),
-                    io as u64 * pool::INDEX_BYTES as u64,
-                    bytemuck::cast_slice(&result.indices[..ic as usize]),
-                );
-                log(&format!(
-                    "  CPU mesh slot {}: {} verts @{}, {} indices @{}",
-                    slot, vc, vo, ic, io,
-                ));
+            let patched = match dirty {
+                Some(dirty) if self.pool.mesh_alloc_valid() => self.patch_cpu_meshes(dirty),
+                _ => false,
+            };
+            if !patched {
+                self.repack_cpu_meshes(resident_count);
             }
-
-            // Build indirect from offset table
-            let mut encoder = self.device.create_command_encoder(
-                &wgpu::CommandEncoderDescriptor { label: Some("cpu-mesh-indirect") },
-            );
-            let bi_bg = self.build_indirect_pass.create_bind_group(
-                &self.device,
-                self.pool.mesh_offset_table_buf(),
-                self.pool.indirect_buffer(),
-                self.pool.visibility_buf(),
-            );
-            self.build_indirect_pass.dispatch(&mut encoder, &bi_bg, resident_count);
-            self.queue.submit(std::iter::once(encoder.finish()));
         } else {
             // GPU three-pass pipeline: Count → Prefix Sum → Write
+            // Cached CPU meshes go stale while the GPU path owns the pool.
+            self.cpu_meshes.clear();
+            self.pool.invalidate_mesh_alloc();
+            self.pool.init_visibility(&self.queue, resident_count);
 
             // Zero mesh_counts before counting
             let zeros = vec![0u8; pool::MESH_COUNTS_ENTRY_BYTES as usize * resident_count as usize];
@@ -1249,9 +2335,123 @@ impl Renderer {
         self.build_wireframe_pass = None;
     }
 
+    /// Move the re-meshed `dirty` slots to fresh pool ranges and patch only their
+    /// draw entries. Returns false when a mesh does not fit; the repack that follows
+    /// rewrites every slot, including those already patched.
+    fn patch_cpu_meshes(&mut self, dirty: &[u32]) -> bool {
+        // Free first, so dirty meshes can reuse each other's space
+        for &slot in dirty {
+            self.pool.free_mesh(slot);
+        }
+        for &slot in dirty {
+            let mesh = self.cpu_meshes.get(slot as usize).and_then(Option::as_ref);
+            let meta = match mesh {
+                Some(m) if m.quad_count > 0 => {
+                    let Some(meta) = self.pool.alloc_mesh(slot, m.quad_count * 4, m.quad_count * 6)
+                    else {
+                        log("  Mesh pool fragmented — repacking all slots");
+                        return false;
+                    };
+                    meta
+                }
+                _ => pool::DrawMeta::default(),
+            };
+            let (vertices, indices) = mesh
+                .map_or((&[][..], &[][..]), |m| (&m.vertices[..], &m.indices[..]));
+            self.pool.write_slot_mesh(&self.queue, slot, &meta, vertices, indices);
+        }
+        log(&format!(
+            "  CPU patched {} slots: {} vertices, {} indices in use",
+            dirty.len(), self.pool.vertex_alloc.used(), self.pool.index_alloc.used(),
+        ));
+        true
+    }
+
+    /// Lay out every cached CPU mesh in slot order — the layout of a prefix sum — and
+    /// rebuild all offset table, draw metadata and indirect entries.
+    fn repack_cpu_meshes(&mut self, resident_count: u32) {
+        self.pool.init_visibility(&self.queue, resident_count);
+        self.pool.reset_mesh_alloc();
+
+        // Pass 2 (CPU): allocate → offsets (5 u32 per slot)
+        let mut offset_table = vec![0u32; pool::MAX_SLOTS as usize * 5];
+        let mut draw_meta = vec![pool::DrawMeta::default(); resident_count as usize];
+        for (slot, result) in self.cpu_meshes.iter().enumerate() {
+            let Some(result) = result else { continue };
+            let qc = result.quad_count;
+            if qc == 0 { continue; }
+            let Some(meta) = self.pool.alloc_mesh(slot as u32, qc * 4, qc * 6) else {
+                log(&format!("  Mesh pool full: slot {slot} ({qc} quads) not drawn"));
+                continue;
+            };
+            let base = slot * 5;
+            offset_table[base] = meta.vertex_offset;
+            offset_table[base + 1] = meta.vertex_count;
+            offset_table[base + 2] = meta.index_offset;
+            offset_table[base + 3] = meta.index_count;
+            offset_table[base + 4] = 0; // write_counter
+            draw_meta[slot] = meta;
+        }
+        log(&format!(
+            "  CPU totals: {} vertices, {} indices across {} chunks",
+            self.pool.vertex_alloc.used(), self.pool.index_alloc.used(), self.cpu_chunks.len(),
+        ));
+
+        // Upload mesh_offset_table and draw metadata
+        self.queue.write_buffer(
+            self.pool.mesh_offset_table_buf(),
+            0,
+            bytemuck::cast_slice(&offset_table),
+        );
+        self.queue.write_buffer(self.pool.draw_meta_buf(), 0, bytemuck::cast_slice(&draw_meta));
+
+        // Pass 3 (CPU): upload vertices/indices at allocated offsets
+        for (slot, result) in self.cpu_meshes.iter().enumerate() {
+            let Some(result) = result else { continue };
+            let meta = draw_meta[slot];
+            let (vo, vc, io, ic) =
+                (meta.vertex_offset, meta.vertex_count, meta.index_offset, meta.index_count);
+            if vc == 0 { continue; }
+            let vert_bytes = (vc * pool::VERTEX_BYTES) as usize;
+            self.queue.write_buffer(
+                self.pool.vertex_pool_buf(),
+                vo as u64 * pool::VERTEX_BYTES as u64,
+                &result.vertices[..vert_bytes],
+            );
+            self.queue.write_buffer(
+                self.pool.index_pool_buf(),
+                io as u64 * pool::INDEX_BYTES as u64,
+                bytemuck::cast_slice(&result.indices[..ic as usize]),
+            );
+            log(&format!(
+                "  CPU mesh slot {}: {} verts @{}, {} indices @{}",
+                slot, vc, vo, ic, io,
+            ));
+        }
+
+        // Build indirect from offset table
+        let mut encoder = self.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor { label: Some("cpu-mesh-indirect") },
+        );
+        let bi_bg = self.build_indirect_pass.create_bind_group(
+            &self.device,
+            self.pool.mesh_offset_table_buf(),
+            self.pool.indirect_buffer(),
+            self.pool.visibility_buf(),
+        );
+        self.build_indirect_pass.dispatch(&mut encoder, &bi_bg, resident_count);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
 }
 
 #[cfg(target_arch = "wasm32")]
 fn log(msg: &str) {
     web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(&format!("[wasm_renderer] {msg}")));
 }
+
+/// Error for a stepped-load call whose load was abandoned or never started.
+#[cfg(target_arch = "wasm32")]
+fn abandoned_voxelize(token: u32) -> JsValue {
+    JsValue::from_str(&format!("Voxelization {token} is not in progress; a later load abandons it"))
+}
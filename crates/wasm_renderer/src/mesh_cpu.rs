@@ -141,7 +141,7 @@ pub struct Quad {
     /// Face direction (0..5).
     pub face: usize,
     /// Global MaterialId (truncated to u8 for vertex packing).
-    pub material_id: u8,
+    pub material_id: u16,
 }
 
 /// Processed bitmap for a 62×62 slice (3844 bits packed into 121 u32).
@@ -292,7 +292,7 @@ fn merge_y_slice(
                 width,
                 height,
                 face,
-                material_id: seed_mat as u8,
+                material_id: seed_mat as u16,
             });
         }
     }
@@ -375,7 +375,7 @@ fn merge_x_slice(
                 width,
                 height,
                 face,
-                material_id: seed_mat as u8,
+                material_id: seed_mat as u16,
             });
         }
     }
@@ -458,7 +458,7 @@ fn merge_z_slice(
                 width,
                 height,
                 face,
-                material_id: seed_mat as u8,
+                material_id: seed_mat as u16,
             });
         }
     }
@@ -475,6 +475,12 @@ pub fn pack_normal_material(nx: f32, ny: f32, nz: f32, material_id: u8) -> u32 {
     (snx as u32) | ((sny as u32) << 8) | ((snz as u32) << 16) | ((material_id as u32) << 24)
 }
 
+/// Inverse of [`pack_normal_material`]: the snorm8 normal and material_id.
+pub fn unpack_normal_material(packed: u32) -> ([f32; 3], u8) {
+    let snorm = |shift: u32| ((packed >> shift) as u8 as i8 as f32 / 127.0).max(-1.0);
+    ([snorm(0), snorm(8), snorm(16)], (packed >> 24) as u8)
+}
+
 /// Normal vectors for each face direction.
 const FACE_NORMALS: [[f32; 3]; 6] = [
     [0.0, 1.0, 0.0],   // +Y
@@ -509,7 +515,7 @@ pub fn expand_quads(
 
     for q in quads {
         let [nx, ny, nz] = FACE_NORMALS[q.face];
-        let nm = pack_normal_material(nx, ny, nz, q.material_id);
+        let nm = pack_normal_material(nx, ny, nz, q.material_id as u8);
 
         // Integer global voxel coordinate, then single float multiply
         let bx = (chunk_base[0] + q.x as i32 + 1) as f32 * vs + grid_origin[0];
@@ -866,6 +872,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn normal_material_roundtrip() {
+        for (i, &[nx, ny, nz]) in FACE_NORMALS.iter().enumerate() {
+            let packed = pack_normal_material(nx, ny, nz, 200 + i as u8);
+            assert_eq!(unpack_normal_material(packed), ([nx, ny, nz], 200 + i as u8));
+        }
+    }
+
     #[test]
     fn draw_meta_counts_match() {
         let occ = occ_with_voxel(32, 32, 32);
@@ -1053,7 +1067,7 @@ f 2 6 7 3
 f 1 4 8 5
 ";
         let parsed = obj_parser::parse_obj(obj);
-        let result = voxelizer_cpu::voxelize(&parsed, 100);
+        let result = voxelizer_cpu::voxelize(&parsed, 100).unwrap();
 
         println!("Voxelized into {} chunks:", result.chunks.len());
         for chunk in &result.chunks {
@@ -1137,7 +1151,7 @@ f 1 4 8 5
     
         let obj = "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\nv -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\nf 1 2 3 4\nf 5 8 7 6\nf 1 5 6 2\nf 3 7 8 4\nf 2 6 7 3\nf 1 4 8 5\n";
         let parsed = obj_parser::parse_obj(obj);
-        let result = voxelizer_cpu::voxelize(&parsed, 100);
+        let result = voxelizer_cpu::voxelize(&parsed, 100).unwrap();
     
         let mut bad_count = 0u32;
         let mut total_tris = 0u32;
@@ -1229,7 +1243,7 @@ f 1 4 8 5
         // Create a big cube: vertices span [-1, 1] so 2 units wide
         let obj = "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\nv -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\nf 1 2 3 4\nf 5 8 7 6\nf 1 5 6 2\nf 3 7 8 4\nf 2 6 7 3\nf 1 4 8 5\n";
         let parsed = obj_parser::parse_obj(obj);
-        let result = voxelizer_cpu::voxelize(&parsed, 100);
+        let result = voxelizer_cpu::voxelize(&parsed, 100).unwrap();
     
         println!("Chunks: {}", result.chunks.len());
     
@@ -1341,7 +1355,7 @@ f 2 6 7 3
 f 1 4 8 5
 ";
         let parsed = obj_parser::parse_obj(obj);
-        let result = voxelizer_cpu::voxelize(&parsed, 100);
+        let result = voxelizer_cpu::voxelize(&parsed, 100).unwrap();
 
         for chunk in &result.chunks {
             let pal_words = chunk.palette.as_words();
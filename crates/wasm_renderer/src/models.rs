@@ -0,0 +1,711 @@
+//! Multi-model scene composition — no GPU, no JS types.
+//!
+//! Tracks which voxels each model contributed so models can be added to and
+//! removed from a scene that shares one world grid. Each model keeps a sparse
+//! per-chunk voxel list in scene material IDs; any chunk touched by an add or
+//! remove is recomposed from every model that covers it, in load order, so
+//! later models win where they overlap.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::pool::*;
+use crate::quantize;
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
+use crate::voxelizer_cpu::VoxelizeResult;
+
+/// Handle for a model in a [`SceneModels`].
+pub type ModelId = u32;
+
+/// Bits of a packed voxel holding the scene material ID; the voxel index
+/// (`x * CS_P² + y * CS_P + z`, 18 bits) sits above them.
+const MATERIAL_BITS: u32 = 12;
+const MATERIAL_MASK: u32 = (1 << MATERIAL_BITS) - 1;
+
+/// Why a model could not be added to the scene.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelError {
+    /// The model was voxelized at a different voxel size than the scene.
+    VoxelSizeMismatch { scene: f32, model: f32 },
+    /// The model's grid origin is not a whole number of voxels from the scene's.
+    GridMisaligned,
+    /// The scene material table has no room for the model's materials.
+    MaterialTableFull,
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::VoxelSizeMismatch { scene, model } => {
+                write!(
+                    f,
+                    "model voxel size {model} does not match scene voxel size {scene}"
+                )
+            }
+            ModelError::GridMisaligned => write!(f, "model grid is not aligned to the scene grid"),
+            ModelError::MaterialTableFull => {
+                write!(f, "scene material table is full ({MAX_MATERIALS} entries)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+/// One model's voxels: packed `index << MATERIAL_BITS | material` per chunk.
+struct ModelVoxels {
+    id: ModelId,
+    chunks: HashMap<ChunkCoord, Vec<u32>>,
+    /// Scene material IDs the voxels use.
+    materials: Vec<u16>,
+}
+
+/// The models making up the resident scene, plus the shared grid and
+/// material table they were merged into.
+pub struct SceneModels {
+    /// In load order.
+    models: Vec<ModelVoxels>,
+    next_id: ModelId,
+    /// Scene `(voxel_size, grid_origin)`, set by the first model.
+    grid: Option<(f32, [f32; 3])>,
+    materials: Vec<MaterialEntry>,
+    /// First unassigned material ID.
+    material_len: usize,
+    /// IDs below `material_len` released by [`remove`](Self::remove), in
+    /// ascending order.
+    free_materials: Vec<u16>,
+}
+
+impl SceneModels {
+    pub fn new() -> Self {
+        Self {
+            models: Vec::new(),
+            next_id: 1,
+            grid: None,
+            materials: empty_material_table(),
+            material_len: MATERIAL_DEFAULT as usize + 1,
+            free_materials: Vec::new(),
+        }
+    }
+
+    /// Drop every model and the scene grid.
+    pub fn clear(&mut self) {
+        *self = Self {
+            next_id: self.next_id,
+            ..Self::new()
+        };
+    }
+
+    /// Start a new scene whose only model is `chunks`. The grid and material
+    /// table are taken as-is, so material IDs are not remapped.
+    pub fn reset<'a>(
+        &mut self,
+        chunks: impl IntoIterator<Item = &'a ChunkData>,
+        materials: &[MaterialEntry],
+        voxel_size: f32,
+        grid_origin: [f32; 3],
+    ) -> ModelId {
+        self.clear();
+        self.grid = Some((voxel_size, grid_origin));
+        let n = materials.len().min(MAX_MATERIALS as usize);
+        self.materials[..n].copy_from_slice(&materials[..n]);
+
+        let mut voxels = HashMap::new();
+        for chunk in chunks {
+            let list = collect_voxels(chunk, 0..CS_P, |mat| mat);
+            if let Some(max) = list.iter().map(|v| v & MATERIAL_MASK).max() {
+                self.material_len = self.material_len.max(max as usize + 1);
+            }
+            if !list.is_empty() {
+                voxels.insert(chunk.coord, list);
+            }
+        }
+        self.push(voxels)
+    }
+
+    /// Merge a voxelized model into the scene.
+    ///
+    /// The model must share the scene's voxel size, and its grid origin must
+    /// be a whole number of voxels away (the first model sets the grid).
+    /// Its materials are appended to the scene table, reusing identical
+    /// entries. Returns the new model's ID and the chunk coords that changed;
+    /// `chunks` is updated in place.
+    pub fn add(
+        &mut self,
+        result: &VoxelizeResult,
+        chunks: &mut HashMap<ChunkCoord, ChunkData>,
+    ) -> Result<(ModelId, Vec<ChunkCoord>), ModelError> {
+        let shift = match self.grid {
+            None => [0; 3],
+            Some((voxel_size, origin)) => grid_shift(voxel_size, origin, result)?,
+        };
+
+        // Scene material ID for every material the model's chunks use
+        let mut remap: HashMap<u16, u16> = HashMap::new();
+        let mut materials = self.materials.clone();
+        let mut material_len = self.material_len;
+        let mut free = self.free_materials.clone();
+        for chunk in &result.chunks {
+            for &mat in chunk.palette.entries() {
+                if mat <= MATERIAL_DEFAULT || remap.contains_key(&mat) {
+                    continue;
+                }
+                let entry = result
+                    .materials
+                    .get(mat as usize)
+                    .copied()
+                    .unwrap_or(materials[MATERIAL_DEFAULT as usize]);
+                let id = match (MATERIAL_DEFAULT as usize + 1..material_len).find(|&i| {
+                    !free.contains(&(i as u16)) && same_material(&materials[i], &entry)
+                }) {
+                    Some(i) => i,
+                    None if !free.is_empty() => {
+                        let i = free.remove(0) as usize;
+                        materials[i] = entry;
+                        i
+                    }
+                    None if material_len < MAX_MATERIALS as usize => {
+                        materials[material_len] = entry;
+                        material_len += 1;
+                        material_len - 1
+                    }
+                    None => return Err(ModelError::MaterialTableFull),
+                };
+                remap.insert(mat, id as u16);
+            }
+        }
+
+        // Re-bucket voxels into scene chunks at the shifted grid position
+        let cs = CS as i32;
+        let mut voxels: HashMap<ChunkCoord, Vec<u32>> = HashMap::new();
+        for chunk in &result.chunks {
+            let base = [chunk.coord.x * cs, chunk.coord.y * cs, chunk.coord.z * cs];
+            let remap = |mat| *remap.get(&mat).unwrap_or(&mat);
+            for packed in collect_voxels(chunk, 1..CS + 1, remap) {
+                let (x, y, z) = unpack_index(packed >> MATERIAL_BITS);
+                let g = [
+                    base[0] + x as i32 - 1 + shift[0],
+                    base[1] + y as i32 - 1 + shift[1],
+                    base[2] + z as i32 - 1 + shift[2],
+                ];
+                let coord = ChunkCoord {
+                    x: g[0].div_euclid(cs),
+                    y: g[1].div_euclid(cs),
+                    z: g[2].div_euclid(cs),
+                };
+                let local = g.map(|c| (c.rem_euclid(cs) + 1) as u32);
+                let index = local[0] * CS_P2 + local[1] * CS_P + local[2];
+                let mat = packed & MATERIAL_MASK;
+                voxels
+                    .entry(coord)
+                    .or_default()
+                    .push(index << MATERIAL_BITS | mat);
+            }
+        }
+
+        if self.grid.is_none() {
+            self.grid = Some((result.voxel_size, result.grid_origin));
+        }
+        self.materials = materials;
+        self.material_len = material_len;
+        self.free_materials = free;
+
+        let mut touched: Vec<ChunkCoord> = voxels.keys().copied().collect();
+        sort_coords(&mut touched);
+        let id = self.push(voxels);
+        for &coord in &touched {
+            self.recompose(coord, chunks);
+        }
+        Ok((id, touched))
+    }
+
+    /// Remove a model and recompose the chunks it covered. Chunks left
+    /// without voxels are removed from `chunks`. Returns the changed chunk
+    /// coords, or `None` if no model has that ID.
+    ///
+    /// Materials no longer used by any model or any chunk in `chunks` (which
+    /// may hold edits) are cleared and reused by later adds, so removing the
+    /// model an add just returned leaves the material table as it was.
+    pub fn remove(
+        &mut self,
+        id: ModelId,
+        chunks: &mut HashMap<ChunkCoord, ChunkData>,
+    ) -> Option<Vec<ChunkCoord>> {
+        let pos = self.models.iter().position(|m| m.id == id)?;
+        let model = self.models.remove(pos);
+        let mut touched: Vec<ChunkCoord> = model.chunks.into_keys().collect();
+        sort_coords(&mut touched);
+        for &coord in &touched {
+            self.recompose(coord, chunks);
+        }
+        self.release_materials(chunks);
+        Some(touched)
+    }
+
+    /// Model IDs in load order.
+    pub fn ids(&self) -> impl Iterator<Item = ModelId> + '_ {
+        self.models.iter().map(|m| m.id)
+    }
+
+    /// Scene `(voxel_size, grid_origin)`, or `None` before the first model.
+    pub fn grid(&self) -> Option<(f32, [f32; 3])> {
+        self.grid
+    }
+
+    /// Scene material table (`MAX_MATERIALS` entries).
+    pub fn materials(&self) -> &[MaterialEntry] {
+        &self.materials
+    }
+
+    fn push(&mut self, chunks: HashMap<ChunkCoord, Vec<u32>>) -> ModelId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut materials: Vec<u16> = chunks
+            .values()
+            .flatten()
+            .map(|&packed| (packed & MATERIAL_MASK) as u16)
+            .collect();
+        materials.sort_unstable();
+        materials.dedup();
+        self.models.push(ModelVoxels { id, chunks, materials });
+        id
+    }
+
+    /// Clear every assigned material ID that no model and no chunk uses,
+    /// then shrink `material_len` past the free IDs at its end.
+    fn release_materials(&mut self, chunks: &HashMap<ChunkCoord, ChunkData>) {
+        let mut used = vec![false; self.material_len];
+        let model_ids = self.models.iter().flat_map(|m| m.materials.iter());
+        let chunk_ids = chunks.values().flat_map(|c| c.palette.entries());
+        for &id in model_ids.chain(chunk_ids) {
+            if let Some(slot) = used.get_mut(id as usize) {
+                *slot = true;
+            }
+        }
+        let unused = MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0);
+        self.free_materials.clear();
+        for (id, used) in used.iter().enumerate().skip(MATERIAL_DEFAULT as usize + 1) {
+            if !used {
+                self.materials[id] = unused;
+                self.free_materials.push(id as u16);
+            }
+        }
+        while self.free_materials.last() == Some(&(self.material_len as u16 - 1)) {
+            self.free_materials.pop();
+            self.material_len -= 1;
+        }
+    }
+
+    /// Rebuild the chunk at `coord` from every model that covers it.
+    fn recompose(&self, coord: ChunkCoord, chunks: &mut HashMap<ChunkCoord, ChunkData>) {
+        const UNSET: u32 = u32::MAX;
+        let mut cells = vec![UNSET; CS_P3 as usize];
+        let mut any = false;
+        for list in self.models.iter().filter_map(|m| m.chunks.get(&coord)) {
+            for &packed in list {
+                cells[(packed >> MATERIAL_BITS) as usize] = packed & MATERIAL_MASK;
+                any = true;
+            }
+        }
+        if !any {
+            chunks.remove(&coord);
+            return;
+        }
+
+        // Overlapping models can bring more materials than one palette holds
+        let mut counts: HashMap<u16, u32> = HashMap::new();
+        for &mat in cells.iter().filter(|&&mat| mat != UNSET) {
+            *counts.entry(mat as u16).or_default() += 1;
+        }
+        let (remap, _) = quantize::limit_palette(&counts, &self.materials);
+
+        let mut occupancy = OccupancyBuilder::new();
+        let mut palette = PaletteBuilder::new();
+        let mut index_buf = IndexBufBuilder::new();
+        for (i, &mat) in cells.iter().enumerate() {
+            if mat == UNSET {
+                continue;
+            }
+            let (x, y, z) = unpack_index(i as u32);
+            let mat = mat as u16;
+            occupancy.set(x, y, z);
+            index_buf.set(x, y, z, palette.add(*remap.get(&mat).unwrap_or(&mat)));
+        }
+        chunks.insert(
+            coord,
+            ChunkData {
+                coord,
+                occupancy,
+                palette,
+                index_buf,
+            },
+        );
+    }
+}
+
+impl Default for SceneModels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+fn empty_material_table() -> Vec<MaterialEntry> {
+    let mut table = vec![MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0); MAX_MATERIALS as usize];
+    table[MATERIAL_DEFAULT as usize] = MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0);
+    table
+}
+
+/// Whole-voxel offset from the model's grid to the scene's.
+fn grid_shift(
+    voxel_size: f32,
+    origin: [f32; 3],
+    result: &VoxelizeResult,
+) -> Result<[i32; 3], ModelError> {
+    if (result.voxel_size - voxel_size).abs() > voxel_size * 1e-5 {
+        return Err(ModelError::VoxelSizeMismatch {
+            scene: voxel_size,
+            model: result.voxel_size,
+        });
+    }
+    let mut shift = [0; 3];
+    for axis in 0..3 {
+        let offset = (result.grid_origin[axis] - origin[axis]) / voxel_size;
+        if (offset - offset.round()).abs() > 1e-3 {
+            return Err(ModelError::GridMisaligned);
+        }
+        shift[axis] = offset.round() as i32;
+    }
+    Ok(shift)
+}
+
+/// Occupied voxels of a chunk with local coords in `range` on every axis,
+/// packed with their (remapped) material ID.
+fn collect_voxels(
+    chunk: &ChunkData,
+    range: std::ops::Range<u32>,
+    remap: impl Fn(u16) -> u16,
+) -> Vec<u32> {
+    let entries = chunk.palette.entries();
+    let mut out = Vec::new();
+    for x in range.clone() {
+        for z in range.clone() {
+            for y in range.clone() {
+                if !chunk.occupancy.get(x, y, z) {
+                    continue;
+                }
+                let mat = entries
+                    .get(chunk.index_buf.get(x, y, z) as usize)
+                    .copied()
+                    .unwrap_or(0);
+                let mat = remap(mat) as u32 & MATERIAL_MASK;
+                out.push((x * CS_P2 + y * CS_P + z) << MATERIAL_BITS | mat);
+            }
+        }
+    }
+    out
+}
+
+fn unpack_index(index: u32) -> (u32, u32, u32) {
+    (index / CS_P2, (index / CS_P) % CS_P, index % CS_P)
+}
+
+fn same_material(a: &MaterialEntry, b: &MaterialEntry) -> bool {
+    bytemuck::bytes_of(a) == bytemuck::bytes_of(b)
+}
+
+fn sort_coords(coords: &mut [ChunkCoord]) {
+    coords.sort_by_key(|c| (c.x, c.y, c.z));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene;
+    use crate::voxelizer_cpu::{voxelize_with_options, VoxelizeOptions};
+
+    fn cube(min: [f32; 3], size: f32) -> crate::obj_parser::ParsedObj {
+        let src = format!(
+            "v {x0} {y0} {z0}\nv {x1} {y0} {z0}\nv {x1} {y1} {z0}\nv {x0} {y1} {z0}\n\
+             v {x0} {y0} {z1}\nv {x1} {y0} {z1}\nv {x1} {y1} {z1}\nv {x0} {y1} {z1}\n\
+             usemtl box\n\
+             f 1 3 2\nf 1 4 3\nf 5 6 7\nf 5 7 8\nf 1 2 6\nf 1 6 5\n\
+             f 4 7 3\nf 4 8 7\nf 2 3 7\nf 2 7 6\nf 1 5 8\nf 1 8 4\n",
+            x0 = min[0],
+            y0 = min[1],
+            z0 = min[2],
+            x1 = min[0] + size,
+            y1 = min[1] + size,
+            z1 = min[2] + size,
+        );
+        crate::obj_parser::parse_obj(&src)
+    }
+
+    fn voxelize(min: [f32; 3], size: f32, albedo: [f32; 3]) -> VoxelizeResult {
+        let entry = MaterialEntry::new(albedo, 0.5, [0.0; 3], 1.0);
+        let parsed = cube(min, size);
+        let groups = vec![Some(entry); parsed.material_names.len()];
+        voxelize_with_options(&parsed, &groups, &VoxelizeOptions::world(0.5, [-0.25; 3])).unwrap()
+    }
+
+    fn total_voxels(chunks: &HashMap<ChunkCoord, ChunkData>) -> u32 {
+        chunks.values().map(|c| c.occupancy.popcount()).sum()
+    }
+
+    fn material_at(chunks: &HashMap<ChunkCoord, ChunkData>, g: [i32; 3]) -> Option<u16> {
+        let cs = CS as i32;
+        let coord = ChunkCoord {
+            x: g[0].div_euclid(cs),
+            y: g[1].div_euclid(cs),
+            z: g[2].div_euclid(cs),
+        };
+        let [x, y, z] = g.map(|c| (c.rem_euclid(cs) + 1) as u32);
+        let chunk = chunks.get(&coord)?;
+        chunk
+            .occupancy
+            .get(x, y, z)
+            .then(|| chunk.palette.entries()[chunk.index_buf.get(x, y, z) as usize])
+    }
+
+    /// One chunk with 200 voxels in row `row`, each its own material: a red
+    /// gradient with blue channel `blue`.
+    fn gradient(row: u32, blue: f32) -> VoxelizeResult {
+        let mut materials = scene::generate_test_scene().1;
+        let mut occupancy = OccupancyBuilder::new();
+        let mut palette = PaletteBuilder::new();
+        let mut index_buf = IndexBufBuilder::new();
+        for i in 0..200u32 {
+            let mat = i as u16 + 2;
+            materials[mat as usize] =
+                MaterialEntry::new([i as f32 / 200.0, 0.5, blue], 0.5, [0.0; 3], 1.0);
+            let (x, z) = (i % 50 + 1, i / 50 + 1);
+            occupancy.set(x, row, z);
+            index_buf.set(x, row, z, palette.add(mat));
+        }
+        VoxelizeResult {
+            chunks: vec![ChunkData {
+                coord: ChunkCoord { x: 0, y: 0, z: 0 },
+                occupancy,
+                palette,
+                index_buf,
+            }],
+            materials,
+            voxel_size: 1.0,
+            grid_origin: [0.0; 3],
+            mesh_center: [0.0; 3],
+            mesh_extent: 1.0,
+            quantize: Default::default(),
+        }
+    }
+
+    #[test]
+    fn add_merges_into_shared_chunks() {
+        let mut models = SceneModels::new();
+        let mut chunks = HashMap::new();
+        let a = voxelize([0.0; 3], 4.0, [1.0, 0.0, 0.0]);
+        let b = voxelize([10.0, 0.0, 0.0], 4.0, [0.0, 0.0, 1.0]);
+        let count_a: u32 = a.chunks.iter().map(|c| c.occupancy.popcount()).sum();
+        let count_b: u32 = b.chunks.iter().map(|c| c.occupancy.popcount()).sum();
+
+        let (id_a, _) = models.add(&a, &mut chunks).unwrap();
+        let (id_b, touched) = models.add(&b, &mut chunks).unwrap();
+        assert_ne!(id_a, id_b);
+        assert_eq!(touched, vec![ChunkCoord { x: 0, y: 0, z: 0 }]);
+        assert_eq!(chunks.len(), 1, "both cubes share chunk (0,0,0)");
+        assert_eq!(total_voxels(&chunks), count_a + count_b);
+
+        // Each model got its own scene material
+        let red = material_at(&chunks, [0, 0, 0]).unwrap();
+        let blue = material_at(&chunks, [20, 0, 0]).unwrap();
+        assert_ne!(red, blue);
+        let albedo = |id: u16| models.materials()[id as usize].albedo();
+        assert_eq!(albedo(red), [1.0, 0.0, 0.0]);
+        assert_eq!(albedo(blue), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn identical_materials_are_shared() {
+        let mut models = SceneModels::new();
+        let mut chunks = HashMap::new();
+        models
+            .add(&voxelize([0.0; 3], 2.0, [0.5, 0.5, 0.5]), &mut chunks)
+            .unwrap();
+        models
+            .add(
+                &voxelize([5.0, 0.0, 0.0], 2.0, [0.5, 0.5, 0.5]),
+                &mut chunks,
+            )
+            .unwrap();
+        assert_eq!(
+            material_at(&chunks, [0, 0, 0]),
+            material_at(&chunks, [10, 0, 0])
+        );
+    }
+
+    #[test]
+    fn remove_restores_previous_state() {
+        let mut models = SceneModels::new();
+        let mut chunks = HashMap::new();
+        let a = voxelize([0.0; 3], 4.0, [1.0, 0.0, 0.0]);
+        let (id_a, _) = models.add(&a, &mut chunks).unwrap();
+        let before = total_voxels(&chunks);
+        let red = material_at(&chunks, [0, 0, 0]);
+
+        // Overlapping model wins where it overlaps…
+        let b = voxelize([0.0; 3], 2.0, [0.0, 1.0, 0.0]);
+        let (id_b, _) = models.add(&b, &mut chunks).unwrap();
+        assert_ne!(material_at(&chunks, [0, 0, 0]), red);
+
+        // …and removing it restores the covered voxels
+        assert_eq!(
+            models.remove(id_b, &mut chunks),
+            Some(vec![ChunkCoord { x: 0, y: 0, z: 0 }])
+        );
+        assert_eq!(total_voxels(&chunks), before);
+        assert_eq!(material_at(&chunks, [0, 0, 0]), red);
+
+        assert!(models.remove(id_b, &mut chunks).is_none());
+        models.remove(id_a, &mut chunks).unwrap();
+        assert!(chunks.is_empty(), "emptied chunks are dropped");
+        assert_eq!(models.ids().count(), 0);
+    }
+
+    #[test]
+    fn overlapping_models_fit_one_palette() {
+        // Two 200-material models in one chunk: 400 materials, 255 palette slots
+        let mut models = SceneModels::new();
+        let mut chunks = HashMap::new();
+        models.add(&gradient(1, 0.0), &mut chunks).unwrap();
+        models.add(&gradient(2, 1.0), &mut chunks).unwrap();
+
+        let chunk = &chunks[&ChunkCoord { x: 0, y: 0, z: 0 }];
+        assert_eq!(chunk.palette.len(), MAX_PALETTE_ENTRIES as usize);
+        assert_eq!(chunk.occupancy.popcount(), 400);
+        for i in 0..400u32 {
+            let (x, y, z) = (i % 50 + 1, i / 200 + 1, i / 50 % 4 + 1);
+            assert_ne!(chunk.index_buf.get(x, y, z), 0, "voxel lost its material");
+        }
+        // Merged voxels keep a color close to their own
+        let blue = |g: [i32; 3]| {
+            models.materials()[material_at(&chunks, g).unwrap() as usize].albedo()[2]
+        };
+        assert!(blue([0, 0, 0]) < 0.5 && blue([0, 1, 0]) > 0.5);
+    }
+
+    #[test]
+    fn add_shifts_models_onto_scene_grid() {
+        let mut models = SceneModels::new();
+        let mut chunks = HashMap::new();
+        let (base, materials) = scene::generate_test_scene();
+        models.reset(&base, &materials, 1.0, [0.0; 3]);
+        for chunk in base {
+            chunks.insert(chunk.coord, chunk);
+        }
+        let before = total_voxels(&chunks);
+
+        // A single-voxel model one chunk below the scene, on a grid offset by -62
+        let mut occupancy = OccupancyBuilder::new();
+        let mut palette = PaletteBuilder::new();
+        let mut index_buf = IndexBufBuilder::new();
+        occupancy.set(1, 1, 1);
+        index_buf.set(1, 1, 1, palette.add(2));
+        let model = VoxelizeResult {
+            chunks: vec![ChunkData {
+                coord: ChunkCoord { x: 0, y: 0, z: 0 },
+                occupancy,
+                palette,
+                index_buf,
+            }],
+            materials: materials.clone(),
+            voxel_size: 1.0,
+            grid_origin: [0.0, -62.0, 0.0],
+            mesh_center: [0.0; 3],
+            mesh_extent: 1.0,
+            quantize: Default::default(),
+        };
+        let (_, touched) = models.add(&model, &mut chunks).unwrap();
+        assert_eq!(touched, vec![ChunkCoord { x: 0, y: -1, z: 0 }]);
+        assert_eq!(total_voxels(&chunks), before + 1);
+        assert!(material_at(&chunks, [0, -62, 0]).is_some());
+
+        let misaligned = VoxelizeResult {
+            grid_origin: [0.5, 0.0, 0.0],
+            ..model
+        };
+        assert_eq!(
+            models.add(&misaligned, &mut chunks).unwrap_err(),
+            ModelError::GridMisaligned
+        );
+        let scaled = VoxelizeResult {
+            voxel_size: 2.0,
+            ..misaligned
+        };
+        assert!(matches!(
+            models.add(&scaled, &mut chunks),
+            Err(ModelError::VoxelSizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn remove_releases_materials() {
+        let mut models = SceneModels::new();
+        let mut chunks = HashMap::new();
+        models.add(&gradient(1, 0.0), &mut chunks).unwrap();
+        let table = models.materials().to_vec();
+        let len = models.material_len;
+
+        // Dropping a model right after adding it restores the table
+        let (id, _) = models.add(&gradient(2, 1.0), &mut chunks).unwrap();
+        assert!(models.material_len > len);
+        models.remove(id, &mut chunks).unwrap();
+        assert_eq!(models.material_len, len);
+        assert!(table.iter().zip(models.materials()).all(|(a, b)| same_material(a, b)));
+
+        // Distinct colors added and removed in turn never fill the table
+        for i in 0..30 {
+            let (id, _) = models.add(&gradient(2, i as f32 / 30.0), &mut chunks).unwrap();
+            models.remove(id, &mut chunks).unwrap();
+        }
+        assert_eq!(models.material_len, len);
+    }
+
+    #[test]
+    fn remove_keeps_materials_still_in_use() {
+        let mut models = SceneModels::new();
+        let mut chunks = HashMap::new();
+        let (a, _) = models.add(&gradient(1, 0.0), &mut chunks).unwrap();
+        let (b, _) = models.add(&gradient(2, 1.0), &mut chunks).unwrap();
+        let (c, _) = models.add(&gradient(3, 0.5), &mut chunks).unwrap();
+        let blue = material_at(&chunks, [0, 1, 0]).unwrap();
+        let entry = models.materials()[blue as usize];
+
+        // Freed IDs in the middle of the table go to the next add
+        models.remove(a, &mut chunks).unwrap();
+        assert!(same_material(&models.materials()[blue as usize], &entry));
+        assert_eq!(models.free_materials.len(), 200);
+        let (d, _) = models.add(&gradient(1, 0.25), &mut chunks).unwrap();
+        assert!(models.free_materials.is_empty());
+
+        for id in [b, c, d] {
+            models.remove(id, &mut chunks).unwrap();
+        }
+        assert_eq!(models.material_len, MATERIAL_DEFAULT as usize + 1);
+    }
+
+    #[test]
+    fn reset_keeps_material_ids() {
+        let mut models = SceneModels::new();
+        let (base, materials) = scene::generate_cornell_box();
+        models.reset(&base, &materials, 1.0, [0.0; 3]);
+        assert_eq!(models.ids().count(), 1);
+        assert_eq!(models.grid(), Some((1.0, [0.0; 3])));
+        for id in [scene::MAT_WHITE, scene::MAT_RED, scene::MAT_LIGHT] {
+            assert!(same_material(
+                &models.materials()[id as usize],
+                &materials[id as usize]
+            ));
+        }
+        assert!(models.material_len > scene::MAT_LIGHT as usize);
+    }
+}
@@ -0,0 +1,200 @@
+//! Pure Rust MTL parser — no wasm_bindgen, no JS types.
+//!
+//! Parses the subset of Wavefront material libraries that maps onto the
+//! renderer's `MaterialEntry`: `Kd` (albedo), `Ns` (roughness), `Ke`
+//! (emissive) and `d`/`Tr` (opacity). Texture maps and illumination models
+//! are ignored.
+
+use crate::scene::MaterialEntry;
+
+/// One `newmtl` block from an MTL file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MtlMaterial {
+    /// Name given by `newmtl`; matched against OBJ `usemtl` names.
+    pub name: String,
+    /// Diffuse color (`Kd`). Defaults to mid gray.
+    pub diffuse: [f32; 3],
+    /// Specular exponent (`Ns`), if present.
+    pub specular_exponent: Option<f32>,
+    /// Emissive color (`Ke`). Defaults to black.
+    pub emissive: [f32; 3],
+    /// Opacity from `d`, or `1 - Tr`. Defaults to fully opaque.
+    pub opacity: f32,
+}
+
+impl MtlMaterial {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            diffuse: [0.5, 0.5, 0.5],
+            specular_exponent: None,
+            emissive: [0.0; 3],
+            opacity: 1.0,
+        }
+    }
+
+    /// Perceptual roughness derived from the Phong specular exponent.
+    ///
+    /// Uses the Blinn-Phong ↔ GGX approximation `sqrt(2 / (Ns + 2))`, so
+    /// `Ns = 0` is fully rough and large exponents approach mirror-smooth.
+    /// Materials without `Ns` get 0.5, matching the hashed fallback.
+    pub fn roughness(&self) -> f32 {
+        match self.specular_exponent {
+            Some(ns) => (2.0 / (ns.max(0.0) + 2.0)).sqrt().clamp(0.0, 1.0),
+            None => 0.5,
+        }
+    }
+
+    /// Convert to the GPU material layout.
+    pub fn to_material_entry(&self) -> MaterialEntry {
+        MaterialEntry::new(self.diffuse, self.roughness(), self.emissive, self.opacity)
+    }
+}
+
+/// Parse an MTL string into its material definitions, in file order.
+///
+/// Statements before the first `newmtl` and malformed values are ignored.
+/// If both `d` and `Tr` are given, the last one in the block wins.
+pub fn parse_mtl(input: &str) -> Vec<MtlMaterial> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+
+    for line in input.lines() {
+        let trimmed = line.trim();
+        let mut parts = trimmed.split_whitespace();
+        let Some(keyword) = parts.next() else {
+            continue;
+        };
+
+        if keyword == "newmtl" {
+            let name = trimmed["newmtl".len()..].trim();
+            materials.push(MtlMaterial::new(name));
+            continue;
+        }
+
+        let Some(current) = materials.last_mut() else {
+            continue;
+        };
+        let values: Vec<f32> = parts.filter_map(|p| p.parse::<f32>().ok()).collect();
+
+        match keyword {
+            "Kd" => {
+                if let Some(rgb) = parse_rgb(&values) {
+                    current.diffuse = rgb;
+                }
+            }
+            "Ke" => {
+                if let Some(rgb) = parse_rgb(&values) {
+                    current.emissive = rgb;
+                }
+            }
+            "Ns" => {
+                if let Some(&ns) = values.first() {
+                    current.specular_exponent = Some(ns);
+                }
+            }
+            "d" => {
+                if let Some(&d) = values.first() {
+                    current.opacity = d.clamp(0.0, 1.0);
+                }
+            }
+            "Tr" => {
+                if let Some(&tr) = values.first() {
+                    current.opacity = (1.0 - tr).clamp(0.0, 1.0);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    materials
+}
+
+/// Accept `r g b`, or a single value meaning gray (`Kd 0.8`).
+fn parse_rgb(values: &[f32]) -> Option<[f32; 3]> {
+    match values {
+        [r, g, b, ..] => Some([*r, *g, *b]),
+        [v] => Some([*v, *v, *v]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_basic_material() {
+        let mtl = "\
+newmtl red
+Kd 0.8 0.1 0.1
+Ns 98.0
+d 1.0
+";
+        let mats = parse_mtl(mtl);
+        assert_eq!(mats.len(), 1);
+        assert_eq!(mats[0].name, "red");
+        assert_eq!(mats[0].diffuse, [0.8, 0.1, 0.1]);
+        assert_eq!(mats[0].specular_exponent, Some(98.0));
+        assert_eq!(mats[0].opacity, 1.0);
+    }
+
+    #[test]
+    fn parse_multiple_materials_in_order() {
+        let mtl = "\
+# exported
+newmtl a
+Kd 1 0 0
+newmtl b
+Kd 0 1 0
+";
+        let mats = parse_mtl(mtl);
+        assert_eq!(mats.len(), 2);
+        assert_eq!(mats[0].name, "a");
+        assert_eq!(mats[1].name, "b");
+        assert_eq!(mats[1].diffuse, [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn parse_emissive_and_tr() {
+        let mtl = "\
+newmtl lamp
+Ke 4.0 3.5 2.0
+Tr 0.25
+";
+        let mats = parse_mtl(mtl);
+        assert_eq!(mats[0].emissive, [4.0, 3.5, 2.0]);
+        assert!((mats[0].opacity - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn statements_before_newmtl_ignored() {
+        let mats = parse_mtl("Kd 1 1 1\nnewmtl x\n");
+        assert_eq!(mats.len(), 1);
+        assert_eq!(mats[0].diffuse, [0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn single_value_kd_is_gray() {
+        let mats = parse_mtl("newmtl g\nKd 0.25\n");
+        assert_eq!(mats[0].diffuse, [0.25, 0.25, 0.25]);
+    }
+
+    #[test]
+    fn roughness_from_specular_exponent() {
+        let mut m = MtlMaterial::new("m");
+        assert_eq!(m.roughness(), 0.5);
+        m.specular_exponent = Some(0.0);
+        assert_eq!(m.roughness(), 1.0);
+        m.specular_exponent = Some(1000.0);
+        assert!(m.roughness() < 0.05);
+    }
+
+    #[test]
+    fn material_entry_packs_diffuse() {
+        let mats = parse_mtl("newmtl a\nKd 1 0 0\n");
+        let entry = mats[0].to_material_entry();
+        let expected = MaterialEntry::new([1.0, 0.0, 0.0], 0.5, [0.0; 3], 1.0);
+        assert_eq!(entry.albedo_rg, expected.albedo_rg);
+        assert_eq!(entry.albedo_b_roughness, expected.albedo_b_roughness);
+    }
+}
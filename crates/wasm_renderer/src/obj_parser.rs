@@ -1,11 +1,30 @@
 //! Pure Rust OBJ parser — no wasm_bindgen, no JS types.
 //!
-//! Parses vertex positions, face indices (with fan triangulation for n-gons),
-//! and `usemtl` material group assignments. Normals and texcoords are ignored
-//! (voxelization discards them).
+//! Parses vertex positions and colors (`v x y z r g b`), texture coordinates,
+//! face indices (with fan triangulation for n-gons), `usemtl` material group
+//! assignments, `o`/`g` object groups and `mtllib` references. Normals are
+//! ignored (voxelization discards them).
+//!
+//! Two modes share one implementation: the lenient default (`parse_obj`)
+//! skips anything it cannot read, while strict mode (`parse_obj_strict`)
+//! stops at the first malformed statement with its line and column. Both
+//! report non-fatal problems (degenerate faces, out-of-range indices) as
+//! [`ObjWarnings`].
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::mtl_parser::{parse_mtl, MtlMaterial};
+
+/// Entry of [`ParsedObj::triangle_texcoords`] for a face without texcoords.
+pub const NO_TEXCOORD: u32 = u32::MAX;
+
+/// Maximum number of individual warnings kept in [`ObjWarnings::details`].
+/// Counters keep counting past this limit.
+pub const MAX_WARNING_DETAILS: usize = 32;
 
 /// Parsed OBJ mesh data.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ParsedObj {
     /// Vertex positions, one [x, y, z] per vertex.
     pub positions: Vec<[f32; 3]>,
@@ -16,81 +35,445 @@ pub struct ParsedObj {
     /// Material group names in order of first appearance.
     /// Index 0 is always "(default)" for faces before any `usemtl`.
     pub material_names: Vec<String>,
+    /// Material library file names from `mtllib`, in order of appearance.
+    pub material_libs: Vec<String>,
+    /// Object group index per triangle (indices into `object_names`).
+    pub triangle_objects: Vec<u32>,
+    /// Object/group names from `o` and `g`, in order of first appearance.
+    /// Index 0 is always "(default)" for faces before any `o`/`g`.
+    pub object_names: Vec<String>,
+    /// Per-vertex RGB colors (0..1) from `v x y z r g b`, parallel to
+    /// `positions`. Empty if no vertex has a color; otherwise vertices
+    /// without one are white.
+    pub colors: Vec<[f32; 3]>,
+    /// Texture coordinates from `vt`.
+    pub texcoords: Vec<[f32; 2]>,
+    /// Texcoord indices per triangle, parallel to `triangles`, or
+    /// [`NO_TEXCOORD`] for faces that don't reference texcoords on every
+    /// vertex. Empty if no face references texcoords.
+    pub triangle_texcoords: Vec<[u32; 3]>,
+}
+
+/// Parser configuration.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ObjParseOptions {
+    /// Fail on the first malformed `v` or `f` statement instead of skipping it.
+    pub strict: bool,
+}
+
+/// Parsed mesh plus the non-fatal problems found while parsing it.
+#[derive(Debug, Clone)]
+pub struct ObjParseOutput {
+    pub parsed: ParsedObj,
+    pub warnings: ObjWarnings,
+}
+
+/// What went wrong in a strict-mode parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjErrorKind {
+    /// A `v` statement has fewer than three coordinates.
+    MissingCoordinate,
+    /// A `v` coordinate is not a number.
+    InvalidNumber(String),
+    /// A face vertex reference is not an integer.
+    InvalidIndex(String),
+    /// A face references vertex 0, which OBJ indexing does not define.
+    ZeroIndex,
+}
+
+/// A fatal strict-mode parse error. `line` and `column` are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ObjErrorKind,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ObjErrorKind::MissingCoordinate => write!(f, "vertex needs 3 coordinates"),
+            ObjErrorKind::InvalidNumber(tok) => write!(f, "invalid number '{tok}'"),
+            ObjErrorKind::InvalidIndex(tok) => write!(f, "invalid vertex index '{tok}'"),
+            ObjErrorKind::ZeroIndex => write!(f, "vertex index 0 is not valid (OBJ is 1-based)"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Category of a non-fatal parse problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjWarningKind {
+    /// Face with fewer than 3 distinct vertices. Skipped.
+    DegenerateFace,
+    /// Face referencing a vertex that does not exist. Skipped.
+    IndexOutOfRange,
+    /// Malformed `v` statement (lenient mode only). Skipped.
+    MalformedVertex,
+    /// Unreadable face vertex reference (lenient mode only). Dropped from its face.
+    InvalidIndex,
+}
+
+/// One non-fatal parse problem. `line` is 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjWarning {
+    pub line: usize,
+    pub kind: ObjWarningKind,
 }
 
-/// Parse an OBJ string into mesh data.
+impl fmt::Display for ObjWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            ObjWarningKind::DegenerateFace => "degenerate face skipped",
+            ObjWarningKind::IndexOutOfRange => "face with out-of-range index skipped",
+            ObjWarningKind::MalformedVertex => "malformed vertex skipped",
+            ObjWarningKind::InvalidIndex => "invalid vertex index dropped",
+        };
+        write!(f, "line {}: {what}", self.line)
+    }
+}
+
+/// Summary of non-fatal problems found while parsing.
+#[derive(Debug, Clone, Default)]
+pub struct ObjWarnings {
+    pub degenerate_faces: u32,
+    pub out_of_range_faces: u32,
+    pub malformed_vertices: u32,
+    pub invalid_indices: u32,
+    /// The first [`MAX_WARNING_DETAILS`] warnings in line order.
+    pub details: Vec<ObjWarning>,
+}
+
+impl ObjWarnings {
+    /// Total number of warnings, including those past the detail limit.
+    pub fn total(&self) -> u32 {
+        self.degenerate_faces + self.out_of_range_faces + self.malformed_vertices + self.invalid_indices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    fn push(&mut self, line: usize, kind: ObjWarningKind) {
+        match kind {
+            ObjWarningKind::DegenerateFace => self.degenerate_faces += 1,
+            ObjWarningKind::IndexOutOfRange => self.out_of_range_faces += 1,
+            ObjWarningKind::MalformedVertex => self.malformed_vertices += 1,
+            ObjWarningKind::InvalidIndex => self.invalid_indices += 1,
+        }
+        self.details.push(ObjWarning { line, kind });
+    }
+}
+
+/// Parse an OBJ string into mesh data, skipping anything malformed.
 ///
-/// Handles `v` (vertex positions), `f` (faces with fan triangulation),
-/// and `usemtl` (material group switching). Face indices may use the
-/// `v/vt/vn` format — only the vertex index is extracted.
+/// Handles `v` (vertex positions, optionally followed by an RGB color),
+/// `vt` (texture coordinates), `f` (faces with fan triangulation),
+/// `usemtl` (material group switching), `o`/`g` (object groups) and
+/// `mtllib` (library references). Face indices may use the `v/vt/vn`
+/// format; normal indices are ignored. Negative (relative) indices count
+/// back from the most recent vertex or texcoord.
 ///
-/// Negative indices are not supported. Degenerate faces (< 3 vertices)
-/// are silently skipped.
+/// Degenerate faces and faces with out-of-range indices are skipped.
+/// Use [`parse_obj_with_options`] to see what was skipped.
 pub fn parse_obj(input: &str) -> ParsedObj {
+    match parse_obj_with_options(input, ObjParseOptions::default()) {
+        Ok(output) => output.parsed,
+        Err(e) => unreachable!("lenient OBJ parsing cannot fail: {e}"),
+    }
+}
+
+/// Parse an OBJ string, failing on the first malformed `v` or `f` statement.
+pub fn parse_obj_strict(input: &str) -> Result<ObjParseOutput, ObjError> {
+    parse_obj_with_options(input, ObjParseOptions { strict: true })
+}
+
+/// Parse an OBJ string with explicit options, returning warnings alongside the mesh.
+///
+/// Only strict mode returns `Err`. Out-of-range indices and degenerate faces
+/// are warnings in both modes, since the rest of the mesh is still usable.
+pub fn parse_obj_with_options(
+    input: &str,
+    options: ObjParseOptions,
+) -> Result<ObjParseOutput, ObjError> {
     let mut positions: Vec<[f32; 3]> = Vec::new();
-    let mut triangles: Vec<[u32; 3]> = Vec::new();
+    let mut colors: Vec<Option<[f32; 3]>> = Vec::new();
+    let mut texcoords: Vec<[f32; 2]> = Vec::new();
     let mut material_names: Vec<String> = vec!["(default)".to_string()];
-    let mut triangle_materials: Vec<u32> = Vec::new();
+    let mut object_names: Vec<String> = vec!["(default)".to_string()];
+    let mut material_libs: Vec<String> = Vec::new();
+    let mut faces: Vec<RawFace> = Vec::new();
+    let mut warnings = ObjWarnings::default();
     let mut current_material: u32 = 0;
+    let mut current_object: u32 = 0;
 
-    for line in input.lines() {
-        let trimmed = line.trim();
-
-        if trimmed.starts_with("usemtl ") {
-            let name = trimmed["usemtl ".len()..].trim();
-            current_material =
-                if let Some(idx) = material_names.iter().position(|n| n == name) {
-                    idx as u32
-                } else {
-                    let idx = material_names.len() as u32;
-                    material_names.push(name.to_string());
-                    idx
-                };
-        } else if trimmed.starts_with("v ") {
-            let parts: Vec<&str> = trimmed.split_whitespace().collect();
-            if parts.len() >= 4 {
-                if let (Ok(x), Ok(y), Ok(z)) = (
-                    parts[1].parse::<f32>(),
-                    parts[2].parse::<f32>(),
-                    parts[3].parse::<f32>(),
-                ) {
-                    positions.push([x, y, z]);
+    for (line_idx, line) in input.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        // Remainder of the line after the keyword, for name statements.
+        let rest = line.trim_start()[keyword.len()..].trim();
+
+        match keyword {
+            "mtllib" => {
+                for lib in tokens {
+                    if !material_libs.iter().any(|l| l == lib) {
+                        material_libs.push(lib.to_string());
+                    }
                 }
             }
-        } else if trimmed.starts_with("f ") {
-            let parts: Vec<&str> = trimmed.split_whitespace().collect();
-            if parts.len() >= 4 {
-                let mut face_indices: Vec<u32> = Vec::new();
-                for part in parts.iter().skip(1) {
-                    // Handle v/vt/vn format — extract only the vertex index.
-                    let raw = part.split('/').next().unwrap_or("");
-                    if let Ok(idx) = raw.parse::<i32>() {
-                        if idx > 0 {
-                            face_indices.push((idx - 1) as u32);
+            "usemtl" if !rest.is_empty() => {
+                current_material = intern(&mut material_names, rest);
+            }
+            "o" | "g" => {
+                current_object = if rest.is_empty() { 0 } else { intern(&mut object_names, rest) };
+            }
+            "v" => {
+                let mut coords = [0.0f32; 3];
+                let mut failure: Option<ObjError> = None;
+                for coord in coords.iter_mut() {
+                    match tokens.next() {
+                        Some(tok) => match tok.parse::<f32>() {
+                            Ok(val) => *coord = val,
+                            Err(_) => {
+                                failure = Some(ObjError {
+                                    line: line_no,
+                                    column: column_of(line, tok),
+                                    kind: ObjErrorKind::InvalidNumber(tok.to_string()),
+                                });
+                                break;
+                            }
+                        },
+                        None => {
+                            failure = Some(ObjError {
+                                line: line_no,
+                                column: line.trim_end().len() + 1,
+                                kind: ObjErrorKind::MissingCoordinate,
+                            });
+                            break;
                         }
                     }
                 }
-
-                // Fan triangulation: first vertex is the pivot.
-                if face_indices.len() >= 3 {
-                    let base = face_indices[0];
-                    for i in 1..face_indices.len() - 1 {
-                        triangles.push([base, face_indices[i], face_indices[i + 1]]);
-                        triangle_materials.push(current_material);
+                match failure {
+                    None => {
+                        positions.push(coords);
+                        // Trailing `r g b` after x y z (a lone w is not a color)
+                        let rest: Vec<f32> = tokens.filter_map(|t| t.parse().ok()).collect();
+                        colors.push(match rest[..] {
+                            [r, g, b] => Some([r, g, b]),
+                            _ => None,
+                        });
+                    }
+                    Some(e) if options.strict => return Err(e),
+                    Some(_) => warnings.push(line_no, ObjWarningKind::MalformedVertex),
+                }
+            }
+            "vt" => {
+                let mut uv = [0.0f32; 2];
+                let parsed = uv.iter_mut().zip(&mut tokens).all(|(c, tok)| match tok.parse() {
+                    Ok(val) => {
+                        *c = val;
+                        true
                     }
+                    Err(_) => false,
+                });
+                // Keep numbering aligned with the file even for unreadable entries
+                texcoords.push(if parsed { uv } else { [0.0; 2] });
+            }
+            "f" => {
+                let mut indices: Vec<i64> = Vec::new();
+                let mut uv_indices: Option<Vec<i64>> = Some(Vec::new());
+                for tok in tokens {
+                    // Handle v/vt/vn format — the normal index is ignored.
+                    let mut parts = tok.split('/');
+                    let raw = parts.next().unwrap_or("");
+                    let uv = match parts.next().map(str::parse::<i64>) {
+                        Some(Ok(idx)) if idx > 0 => Some(idx - 1),
+                        Some(Ok(idx)) if idx < 0 => Some(texcoords.len() as i64 + idx),
+                        _ => None,
+                    };
+                    match (uv, uv_indices.as_mut()) {
+                        (Some(idx), Some(list)) => list.push(idx),
+                        _ => uv_indices = None,
+                    }
+                    let kind = match raw.parse::<i64>() {
+                        Ok(0) => ObjErrorKind::ZeroIndex,
+                        // 1-based absolute index
+                        Ok(idx) if idx > 0 => {
+                            indices.push(idx - 1);
+                            continue;
+                        }
+                        // Relative index: -1 is the most recent vertex
+                        Ok(idx) => {
+                            indices.push(positions.len() as i64 + idx);
+                            continue;
+                        }
+                        Err(_) => ObjErrorKind::InvalidIndex(raw.to_string()),
+                    };
+                    if options.strict {
+                        return Err(ObjError { line: line_no, column: column_of(line, tok), kind });
+                    }
+                    warnings.push(line_no, ObjWarningKind::InvalidIndex);
                 }
+                // Texcoords only count if every vertex of the face has one
+                let texcoords = uv_indices.filter(|uv| uv.len() == indices.len());
+                faces.push(RawFace {
+                    line: line_no,
+                    indices,
+                    texcoords,
+                    material: current_material,
+                    object: current_object,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // Triangulate once all vertices are known, so positive indices may
+    // reference vertices declared later in the file.
+    let vertex_count = positions.len() as i64;
+    let mut triangles: Vec<[u32; 3]> = Vec::new();
+    let mut triangle_materials: Vec<u32> = Vec::new();
+    let mut triangle_objects: Vec<u32> = Vec::new();
+    let mut triangle_texcoords: Vec<[u32; 3]> = Vec::new();
+    let texcoord_count = texcoords.len() as i64;
+
+    for face in &faces {
+        if face.indices.iter().any(|&i| i < 0 || i >= vertex_count) {
+            warnings.push(face.line, ObjWarningKind::IndexOutOfRange);
+            continue;
+        }
+        let mut distinct = face.indices.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if distinct.len() < 3 {
+            warnings.push(face.line, ObjWarningKind::DegenerateFace);
+            continue;
+        }
+
+        // Fan triangulation: first vertex is the pivot.
+        let uv = face
+            .texcoords
+            .as_ref()
+            .filter(|uv| uv.iter().all(|&i| i >= 0 && i < texcoord_count));
+        let base = face.indices[0] as u32;
+        for (i, pair) in face.indices[1..].windows(2).enumerate() {
+            triangles.push([base, pair[0] as u32, pair[1] as u32]);
+            triangle_materials.push(face.material);
+            triangle_objects.push(face.object);
+            triangle_texcoords.push(match uv {
+                Some(uv) => [uv[0] as u32, uv[i + 1] as u32, uv[i + 2] as u32],
+                None => [NO_TEXCOORD; 3],
+            });
+        }
+    }
+    if triangle_texcoords.iter().all(|t| t[0] == NO_TEXCOORD) {
+        triangle_texcoords.clear();
+    }
+    let colors: Vec<[f32; 3]> = match colors.iter().any(Option::is_some) {
+        true => colors.into_iter().map(|c| c.unwrap_or([1.0; 3])).collect(),
+        false => Vec::new(),
+    };
+
+    warnings.details.sort_by_key(|w| w.line);
+    warnings.details.truncate(MAX_WARNING_DETAILS);
+
+    Ok(ObjParseOutput {
+        parsed: ParsedObj {
+            positions,
+            triangles,
+            triangle_materials,
+            material_names,
+            material_libs,
+            triangle_objects,
+            object_names,
+            colors,
+            texcoords,
+            triangle_texcoords,
+        },
+        warnings,
+    })
+}
+
+/// Parse an OBJ string together with the contents of its material libraries.
+///
+/// Lenient-mode convenience over [`parse_obj`] + [`load_mtl_libraries`].
+pub fn parse_obj_with_mtl(
+    input: &str,
+    mtl_files: &HashMap<String, String>,
+) -> (ParsedObj, Vec<MtlMaterial>) {
+    let parsed = parse_obj(input);
+    let materials = load_mtl_libraries(&parsed.material_libs, mtl_files);
+    (parsed, materials)
+}
+
+/// Resolve `mtllib` references against supplied MTL file contents.
+///
+/// `mtl_files` maps file names to MTL text. Each reference is looked up by
+/// its exact name first, then by its base name (so `mtllib tex/a.mtl`
+/// matches an entry keyed `a.mtl`). Missing libraries are skipped; their
+/// materials fall back to hashed colors in the voxelizer. When two libraries
+/// define the same material name, the first definition wins.
+pub fn load_mtl_libraries(
+    libs: &[String],
+    mtl_files: &HashMap<String, String>,
+) -> Vec<MtlMaterial> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+
+    for lib in libs {
+        let text = mtl_files
+            .get(lib)
+            .or_else(|| mtl_files.get(base_name(lib)));
+        let Some(text) = text else {
+            continue;
+        };
+        for mat in parse_mtl(text) {
+            if !materials.iter().any(|m| m.name == mat.name) {
+                materials.push(mat);
             }
         }
     }
 
-    ParsedObj {
-        positions,
-        triangles,
-        triangle_materials,
-        material_names,
+    materials
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+/// A face as written, before index validation and triangulation.
+struct RawFace {
+    line: usize,
+    /// 0-based vertex indices; may be out of range until checked.
+    indices: Vec<i64>,
+    /// 0-based texcoord indices, if every vertex has one; unchecked.
+    texcoords: Option<Vec<i64>>,
+    material: u32,
+    object: u32,
+}
+
+/// Index of `name` in `names`, appending it if new.
+fn intern(names: &mut Vec<String>, name: &str) -> u32 {
+    if let Some(idx) = names.iter().position(|n| n == name) {
+        idx as u32
+    } else {
+        names.push(name.to_string());
+        (names.len() - 1) as u32
     }
 }
 
+/// 1-based column of `token`, which must be a subslice of `line`.
+fn column_of(line: &str, token: &str) -> usize {
+    token.as_ptr() as usize - line.as_ptr() as usize + 1
+}
+
+/// File name without any leading directories (either separator).
+fn base_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,6 +543,56 @@ f 1/1/1 2/2/1 3/3/1
         assert_eq!(parsed.triangles[0], [0, 1, 2]);
     }
 
+    #[test]
+    fn parse_vertex_colors() {
+        let obj = "\
+v 0 0 0 1 0 0
+v 1 0 0 0 1 0
+v 0 1 0
+v 0 0 1 0.5
+f 1 2 3
+f 1 2 4
+";
+        let parsed = parse_obj(obj);
+        assert_eq!(
+            parsed.colors,
+            vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0; 3], [1.0; 3]],
+        );
+        assert!(parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").colors.is_empty());
+    }
+
+    #[test]
+    fn parse_texcoords_per_triangle() {
+        let obj = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+f 1/1 2/2 3/3 4/4
+f 1/-4/1 2/-3/1 3/-2/1
+f 1 2 3
+f 1/1 2 3/3
+";
+        let parsed = parse_obj(obj);
+        assert_eq!(parsed.texcoords.len(), 4);
+        assert_eq!(parsed.texcoords[2], [1.0, 1.0]);
+        assert_eq!(
+            parsed.triangle_texcoords,
+            vec![
+                [0, 1, 2],
+                [0, 2, 3],
+                [0, 1, 2],
+                [NO_TEXCOORD; 3],
+                [NO_TEXCOORD; 3],
+            ],
+        );
+        assert!(parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").triangle_texcoords.is_empty());
+    }
+
     #[test]
     fn parse_usemtl_groups() {
         let obj = "\
@@ -236,6 +669,171 @@ f 1 2
         assert_eq!(parsed.triangles.len(), 0);
     }
 
+    #[test]
+    fn parse_mtllib_references() {
+        let obj = "\
+mtllib scene.mtl extra.mtl
+mtllib scene.mtl
+v 0.0 0.0 0.0
+";
+        let parsed = parse_obj(obj);
+        assert_eq!(parsed.material_libs, vec!["scene.mtl", "extra.mtl"]);
+    }
+
+    #[test]
+    fn parse_with_mtl_resolves_libraries() {
+        let obj = "\
+mtllib models/scene.mtl
+mtllib missing.mtl
+v 0.0 0.0 0.0
+v 1.0 0.0 0.0
+v 0.0 1.0 0.0
+usemtl red
+f 1 2 3
+";
+        let mut files = HashMap::new();
+        files.insert(
+            "scene.mtl".to_string(),
+            "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n".to_string(),
+        );
+        let (parsed, materials) = parse_obj_with_mtl(obj, &files);
+        assert_eq!(parsed.triangles.len(), 1);
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].diffuse, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn parse_with_mtl_first_definition_wins() {
+        let obj = "mtllib a.mtl b.mtl\n";
+        let mut files = HashMap::new();
+        files.insert("a.mtl".to_string(), "newmtl m\nKd 1 0 0\n".to_string());
+        files.insert("b.mtl".to_string(), "newmtl m\nKd 0 1 0\n".to_string());
+        let (_, materials) = parse_obj_with_mtl(obj, &files);
+        assert_eq!(materials.len(), 1);
+        assert_eq!(materials[0].diffuse, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn parse_negative_indices() {
+        let obj = "\
+v 0.0 0.0 0.0
+v 1.0 0.0 0.0
+v 0.0 1.0 0.0
+f -3 -2 -1
+v 5.0 0.0 0.0
+f 1 -3 -1
+";
+        let parsed = parse_obj(obj);
+        assert_eq!(parsed.triangles, vec![[0, 1, 2], [0, 1, 3]]);
+    }
+
+    #[test]
+    fn parse_object_groups() {
+        let obj = "\
+v 0.0 0.0 0.0
+v 1.0 0.0 0.0
+v 0.0 1.0 0.0
+f 1 2 3
+o body
+f 1 2 3
+g wheel left
+f 1 2 3
+o body
+f 1 2 3
+";
+        let parsed = parse_obj(obj);
+        assert_eq!(parsed.object_names, vec!["(default)", "body", "wheel left"]);
+        assert_eq!(parsed.triangle_objects, vec![0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn out_of_range_face_is_warning() {
+        let obj = "\
+v 0.0 0.0 0.0
+v 1.0 0.0 0.0
+v 0.0 1.0 0.0
+f 1 2 3
+f 1 2 9
+f -5 1 2
+";
+        let output = parse_obj_strict(obj).unwrap();
+        assert_eq!(output.parsed.triangles.len(), 1);
+        assert_eq!(output.warnings.out_of_range_faces, 2);
+        assert_eq!(output.warnings.details[0].line, 5);
+        assert_eq!(output.warnings.details[1].line, 6);
+    }
+
+    #[test]
+    fn degenerate_faces_are_counted() {
+        let obj = "\
+v 0.0 0.0 0.0
+v 1.0 0.0 0.0
+f 1 2
+f 1 1 2
+";
+        let output = parse_obj_with_options(obj, ObjParseOptions::default()).unwrap();
+        assert!(output.parsed.triangles.is_empty());
+        assert_eq!(output.warnings.degenerate_faces, 2);
+        assert_eq!(output.warnings.total(), 2);
+    }
+
+    #[test]
+    fn strict_rejects_bad_vertex_with_position() {
+        let obj = "v 0.0 0.0 0.0\nv 1.0 abc 0.0\n";
+        let err = parse_obj_strict(obj).unwrap_err();
+        assert_eq!(err.line, 2);
+        assert_eq!(err.column, 7);
+        assert_eq!(err.kind, ObjErrorKind::InvalidNumber("abc".to_string()));
+        assert_eq!(err.to_string(), "line 2, column 7: invalid number 'abc'");
+    }
+
+    #[test]
+    fn strict_rejects_short_vertex() {
+        let err = parse_obj_strict("v 1.0 2.0\n").unwrap_err();
+        assert_eq!(err.line, 1);
+        assert_eq!(err.kind, ObjErrorKind::MissingCoordinate);
+    }
+
+    #[test]
+    fn strict_rejects_zero_and_garbage_indices() {
+        let base = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+        let err = parse_obj_strict(&format!("{base}f 0 1 2\n")).unwrap_err();
+        assert_eq!((err.line, err.column), (4, 3));
+        assert_eq!(err.kind, ObjErrorKind::ZeroIndex);
+
+        let err = parse_obj_strict(&format!("{base}f 1 2 x/1\n")).unwrap_err();
+        assert_eq!((err.line, err.column), (4, 7));
+        assert_eq!(err.kind, ObjErrorKind::InvalidIndex("x".to_string()));
+    }
+
+    #[test]
+    fn lenient_counts_skipped_statements() {
+        let obj = "\
+v 0.0 0.0 0.0
+v 1.0 bad 0.0
+v 1.0 0.0 0.0
+v 0.0 1.0 0.0
+f 1 2 3 x
+";
+        let output = parse_obj_with_options(obj, ObjParseOptions::default()).unwrap();
+        assert_eq!(output.parsed.positions.len(), 3);
+        assert_eq!(output.parsed.triangles.len(), 1);
+        assert_eq!(output.warnings.malformed_vertices, 1);
+        assert_eq!(output.warnings.invalid_indices, 1);
+    }
+
+    #[test]
+    fn warning_details_are_capped() {
+        let mut obj = String::from("v 0 0 0\n");
+        for _ in 0..(MAX_WARNING_DETAILS + 10) {
+            obj.push_str("f 1 1 1\n");
+        }
+        let output = parse_obj_with_options(&obj, ObjParseOptions::default()).unwrap();
+        assert_eq!(output.warnings.details.len(), MAX_WARNING_DETAILS);
+        assert_eq!(output.warnings.degenerate_faces as usize, MAX_WARNING_DETAILS + 10);
+    }
+
     #[test]
     fn parse_cube() {
         let obj = "\
@@ -0,0 +1,628 @@
+//! Point-cloud importer — no GPU, no JS types.
+//!
+//! Reads PLY (ASCII or binary) vertex elements and plain-text XYZ files
+//! (`x y z`, optionally followed by an intensity and/or `r g b`), then bins
+//! the points straight into voxels, no mesh in between. Voxels with fewer
+//! than a minimum number of points are dropped as noise. A voxel's color is
+//! the linear-space average of its points, reduced to the material table
+//! and chunk palettes by [`quantize`](crate::quantize).
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::pool::*;
+use crate::quantize::{
+    self, linear_to_oklab, linear_to_srgb, oklab_to_linear, srgb_to_linear, ColorItem,
+    QuantizeReport, QuantizeStats,
+};
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
+use crate::voxelizer_cpu::VoxelizeResult;
+
+/// Why a point cloud could not be read or voxelized.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointCloudError {
+    /// Malformed or unsupported PLY header or data.
+    Ply(String),
+    /// Unparseable XYZ line (1-based).
+    Xyz { line: usize, message: String },
+    /// Invalid voxelization options.
+    Options(String),
+    /// The occupied voxels fall in more chunks than the pool has slots.
+    TooManyChunks { chunks: usize },
+}
+
+impl fmt::Display for PointCloudError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointCloudError::Ply(msg) => write!(f, "PLY: {msg}"),
+            PointCloudError::Xyz { line, message } => write!(f, "XYZ line {line}: {message}"),
+            PointCloudError::Options(msg) => write!(f, "point cloud options: {msg}"),
+            PointCloudError::TooManyChunks { chunks } => write!(
+                f,
+                "points fall in {chunks} chunks, more than the {MAX_SLOTS} pool slots; \
+                 increase the voxel size"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PointCloudError {}
+
+/// Points and optional sRGB colors, one per point.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointCloud {
+    /// Kept in `f64` so georeferenced coordinates (hundreds of kilometres
+    /// from the origin) still bin at centimetre voxel sizes.
+    pub positions: Vec<[f64; 3]>,
+    pub colors: Option<Vec<[u8; 3]>>,
+}
+
+impl PointCloud {
+    /// PLY if `bytes` starts with the `ply` magic, XYZ text otherwise.
+    pub fn parse(bytes: &[u8]) -> Result<Self, PointCloudError> {
+        if bytes.starts_with(b"ply") {
+            parse_ply(bytes)
+        } else {
+            parse_xyz(&String::from_utf8_lossy(bytes))
+        }
+    }
+}
+
+/// How [`voxelize_points`] bins a cloud.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointCloudOptions {
+    /// Edge length of a voxel in the cloud's units.
+    pub voxel_size: f64,
+    /// Voxels with fewer points stay empty.
+    pub min_points: u32,
+    /// Grid origin; the cloud's minimum corner when `None`.
+    pub grid_origin: Option<[f64; 3]>,
+}
+
+impl Default for PointCloudOptions {
+    fn default() -> Self {
+        Self { voxel_size: 1.0, min_points: 1, grid_origin: None }
+    }
+}
+
+/// Parse the `vertex` element of a PLY file (`ascii`,
+/// `binary_little_endian` or `binary_big_endian`). Colors come from
+/// `red`/`green`/`blue` (or `r`/`g`/`b`, `diffuse_*`) properties; float
+/// colors are taken as 0–1.
+pub fn parse_ply(bytes: &[u8]) -> Result<PointCloud, PointCloudError> {
+    let error = |msg: &str| PointCloudError::Ply(msg.to_string());
+    let end = find(bytes, b"end_header").ok_or_else(|| error("missing end_header"))?;
+    let header = String::from_utf8_lossy(&bytes[..end]);
+    let data_start = bytes[end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |p| end + p + 1);
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for line in header.lines().skip(1) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", kind, _] => format = Some(kind.to_string()),
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| error(&format!("bad count in \"{line}\"")))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("property before element"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    kind: PlyType::parse(item).ok_or_else(|| error(&format!("type \"{item}\"")))?,
+                    list: Some(
+                        PlyType::parse(count).ok_or_else(|| error(&format!("type \"{count}\"")))?,
+                    ),
+                });
+            }
+            ["property", kind, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("property before element"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    kind: PlyType::parse(kind).ok_or_else(|| error(&format!("type \"{kind}\"")))?,
+                    list: None,
+                });
+            }
+            _ => {}
+        }
+    }
+    let mut reader = match format.as_deref() {
+        Some("ascii") => PlyReader::Ascii(
+            std::str::from_utf8(&bytes[data_start..])
+                .map_err(|_| error("ASCII data is not UTF-8"))?
+                .split_ascii_whitespace(),
+        ),
+        Some("binary_little_endian") => {
+            PlyReader::Binary { bytes: &bytes[data_start..], pos: 0, big_endian: false }
+        }
+        Some("binary_big_endian") => {
+            PlyReader::Binary { bytes: &bytes[data_start..], pos: 0, big_endian: true }
+        }
+        _ => return Err(error("unsupported or missing format")),
+    };
+
+    for element in &elements {
+        if element.name != "vertex" {
+            // Elements before the vertices still have to be read past
+            for _ in 0..element.count {
+                for property in &element.properties {
+                    reader.skip(property)?;
+                }
+            }
+            continue;
+        }
+        let position = |axis| {
+            element
+                .property(axis)
+                .ok_or_else(|| error(&format!("vertex has no {axis} property")))
+        };
+        let xyz = [position("x")?, position("y")?, position("z")?];
+        let channel = |names: [&str; 3]| names.iter().find_map(|n| element.property(n));
+        let rgb = [
+            channel(["red", "r", "diffuse_red"]),
+            channel(["green", "g", "diffuse_green"]),
+            channel(["blue", "b", "diffuse_blue"]),
+        ];
+        let rgb = match rgb {
+            [Some(r), Some(g), Some(b)] => Some([r, g, b]),
+            _ => None,
+        };
+
+        let mut cloud = PointCloud {
+            positions: Vec::with_capacity(element.count.min(1 << 24)),
+            colors: rgb.map(|_| Vec::with_capacity(element.count.min(1 << 24))),
+        };
+        let mut values = vec![0.0f64; element.properties.len()];
+        for _ in 0..element.count {
+            for (value, property) in values.iter_mut().zip(&element.properties) {
+                *value = match property.list {
+                    None => reader.read(property.kind)?,
+                    Some(_) => {
+                        reader.skip(property)?;
+                        0.0
+                    }
+                };
+            }
+            cloud.positions.push(xyz.map(|i| values[i]));
+            if let (Some(colors), Some(rgb)) = (&mut cloud.colors, rgb) {
+                colors.push(rgb.map(|i| {
+                    let scale = if element.properties[i].kind.is_float() { 255.0 } else { 1.0 };
+                    (values[i] * scale).round().clamp(0.0, 255.0) as u8
+                }));
+            }
+        }
+        return Ok(cloud);
+    }
+    Err(error("no vertex element"))
+}
+
+/// Parse XYZ text: one point per line as `x y z`, `x y z i`, `x y z r g b`
+/// or `x y z i r g b`, separated by spaces, tabs or commas. Colors are
+/// 0–255; a file either has them on every line or on none. Blank lines and
+/// `#`/`//` comments are skipped.
+pub fn parse_xyz(text: &str) -> Result<PointCloud, PointCloudError> {
+    let mut cloud = PointCloud::default();
+    let mut colors = Vec::new();
+    let mut colored = None;
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: String| PointCloudError::Xyz { line: i + 1, message };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+        let values = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty())
+            .map(|w| w.parse::<f64>().map_err(|_| error(format!("invalid number \"{w}\""))))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rgb = match values.len() {
+            3 | 4 => None,
+            6 => Some(&values[3..6]),
+            7 => Some(&values[4..7]),
+            n => return Err(error(format!("expected 3, 4, 6 or 7 values, got {n}"))),
+        };
+        if *colored.get_or_insert(rgb.is_some()) != rgb.is_some() {
+            return Err(error("colors on some lines but not others".to_string()));
+        }
+        cloud.positions.push([values[0], values[1], values[2]]);
+        if let Some(rgb) = rgb {
+            colors.push([rgb[0], rgb[1], rgb[2]].map(|c| c.round().clamp(0.0, 255.0) as u8));
+        }
+    }
+    cloud.colors = (colored == Some(true)).then_some(colors);
+    Ok(cloud)
+}
+
+/// Bin `cloud` into voxels. Colored clouds get one material per distinct
+/// averaged color, median-cut down to the free material entries; each
+/// chunk's palette is then limited as for meshes. Uncolored clouds use
+/// material 2, a neutral gray. Clouds whose voxels fall in more than
+/// [`MAX_SLOTS`] chunks are rejected before any chunk is built.
+pub fn voxelize_points(
+    cloud: &PointCloud,
+    options: &PointCloudOptions,
+) -> Result<VoxelizeResult, PointCloudError> {
+    if !(options.voxel_size.is_finite() && options.voxel_size > 0.0) {
+        return Err(PointCloudError::Options(format!(
+            "invalid voxel size {}",
+            options.voxel_size
+        )));
+    }
+    let mut lo = [f64::INFINITY; 3];
+    let mut hi = [f64::NEG_INFINITY; 3];
+    for p in cloud.positions.iter().filter(|p| p.iter().all(|c| c.is_finite())) {
+        for a in 0..3 {
+            lo[a] = lo[a].min(p[a]);
+            hi[a] = hi[a].max(p[a]);
+        }
+    }
+    let origin = options.grid_origin.unwrap_or(lo);
+
+    // Point count and summed linear color per voxel
+    let mut bins: HashMap<[i32; 3], (u32, [f32; 3])> = HashMap::new();
+    for (i, p) in cloud.positions.iter().enumerate() {
+        let g = [0, 1, 2].map(|a| ((p[a] - origin[a]) / options.voxel_size).floor());
+        if !g.iter().all(|c| (i32::MIN as f64..=i32::MAX as f64).contains(c)) {
+            continue;
+        }
+        let bin = bins.entry(g.map(|c| c as i32)).or_default();
+        bin.0 += 1;
+        if let Some(colors) = &cloud.colors {
+            for (sum, c) in bin.1.iter_mut().zip(colors[i]) {
+                *sum += srgb_to_linear(c);
+            }
+        }
+    }
+    bins.retain(|_, (count, _)| *count >= options.min_points.max(1));
+
+    let cs = CS as i32;
+    let occupied: HashSet<[i32; 3]> = bins.keys().map(|g| g.map(|c| c.div_euclid(cs))).collect();
+    if occupied.len() > MAX_SLOTS as usize {
+        return Err(PointCloudError::TooManyChunks { chunks: occupied.len() });
+    }
+
+    let mut materials =
+        vec![MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0); MAX_MATERIALS as usize];
+    materials[MATERIAL_DEFAULT as usize] = MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0);
+    materials[2] = MaterialEntry::new([0.5, 0.5, 0.5], 0.8, [0.0; 3], 1.0);
+    let mut quantize = QuantizeReport::default();
+    let voxels: Vec<([i32; 3], u16)> = if cloud.colors.is_some() {
+        let (voxels, stats) = assign_point_colors(&bins, &mut materials);
+        quantize.materials = stats;
+        voxels
+    } else {
+        bins.keys().map(|&g| (g, 2)).collect()
+    };
+
+    // Group by chunk, sorted by coord for deterministic output
+    let mut by_chunk: HashMap<[i32; 3], Vec<([i32; 3], u16)>> = HashMap::new();
+    for (g, material) in voxels {
+        by_chunk.entry(g.map(|c| c.div_euclid(cs))).or_default().push((g, material));
+    }
+    let mut by_chunk: Vec<_> = by_chunk.into_iter().collect();
+    by_chunk.sort_unstable_by_key(|&(coord, _)| coord);
+
+    let mut chunks = Vec::with_capacity(by_chunk.len());
+    for ([x, y, z], voxels) in by_chunk {
+        let mut counts: HashMap<u16, u32> = HashMap::new();
+        for &(_, material) in &voxels {
+            *counts.entry(material).or_default() += 1;
+        }
+        let (remap, stats) = quantize::limit_palette(&counts, &materials);
+        quantize.palettes.merge(&stats);
+
+        let mut occupancy = OccupancyBuilder::new();
+        let mut palette = PaletteBuilder::new();
+        let mut index_buf = IndexBufBuilder::new();
+        let mut palette_index = HashMap::new();
+        for (g, material) in voxels {
+            let material = remap.get(&material).copied().unwrap_or(material);
+            let index = *palette_index.entry(material).or_insert_with(|| palette.add(material));
+            let [px, py, pz] = g.map(|c| (c.rem_euclid(cs) + 1) as u32);
+            occupancy.set(px, py, pz);
+            index_buf.set(px, py, pz, index);
+        }
+        chunks.push(ChunkData { coord: ChunkCoord { x, y, z }, occupancy, palette, index_buf });
+    }
+
+    let (center, extent) = if lo[0] <= hi[0] {
+        let center = [0, 1, 2].map(|a| ((lo[a] + hi[a]) * 0.5) as f32);
+        (center, (0..3).map(|a| (hi[a] - lo[a]) as f32).fold(0.0, f32::max))
+    } else {
+        ([0.0; 3], 0.0)
+    };
+    Ok(VoxelizeResult {
+        chunks,
+        materials,
+        voxel_size: options.voxel_size as f32,
+        grid_origin: origin.map(|c| if c.is_finite() { c as f32 } else { 0.0 }),
+        mesh_center: center,
+        mesh_extent: extent,
+        quantize,
+    })
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+/// Averaged voxel colors to material IDs from 2 up. Distinct sRGB colors
+/// are median-cut down to the free table entries in Oklab; each box's
+/// material is its voxel-weighted mean.
+fn assign_point_colors(
+    bins: &HashMap<[i32; 3], (u32, [f32; 3])>,
+    materials: &mut [MaterialEntry],
+) -> (Vec<([i32; 3], u16)>, QuantizeStats) {
+    let voxel_colors: Vec<([i32; 3], [u8; 3])> = bins
+        .iter()
+        .map(|(&g, &(count, sum))| (g, sum.map(|c| linear_to_srgb(c / count as f32))))
+        .collect();
+    let mut counts: HashMap<[u8; 3], u64> = HashMap::new();
+    for &(_, color) in &voxel_colors {
+        *counts.entry(color).or_default() += 1;
+    }
+    let mut keys: Vec<[u8; 3]> = counts.keys().copied().collect();
+    keys.sort_unstable();
+    let items = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| ColorItem {
+            color: linear_to_oklab(key.map(srgb_to_linear)),
+            weight: counts[key],
+            id: i as u32,
+        })
+        .collect();
+
+    let boxes = quantize::median_cut(vec![items], materials.len() - 2);
+    let mut ids = vec![0u16; keys.len()];
+    let mut representatives = Vec::with_capacity(boxes.len());
+    for (i, items) in boxes.iter().enumerate() {
+        let lab = quantize::mean(items);
+        materials[i + 2] = MaterialEntry::new(oklab_to_linear(lab), 0.8, [0.0; 3], 1.0);
+        representatives.push(lab);
+        for item in items {
+            ids[item.id as usize] = i as u16 + 2;
+        }
+    }
+    let stats = QuantizeStats::measure(&boxes, &representatives);
+
+    let index: HashMap<[u8; 3], u16> = keys.into_iter().zip(ids).collect();
+    let voxels = voxel_colors.into_iter().map(|(g, color)| (g, index[&color])).collect();
+    (voxels, stats)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+    fn property(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|p| p.list.is_none() && p.name == name)
+    }
+}
+
+struct PlyProperty {
+    name: String,
+    kind: PlyType,
+    /// Count type of a list property.
+    list: Option<PlyType>,
+}
+
+#[derive(Clone, Copy)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => PlyType::I8,
+            "uchar" | "uint8" => PlyType::U8,
+            "short" | "int16" => PlyType::I16,
+            "ushort" | "uint16" => PlyType::U16,
+            "int" | "int32" => PlyType::I32,
+            "uint" | "uint32" => PlyType::U32,
+            "float" | "float32" => PlyType::F32,
+            "double" | "float64" => PlyType::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PlyType::I8 | PlyType::U8 => 1,
+            PlyType::I16 | PlyType::U16 => 2,
+            PlyType::I32 | PlyType::U32 | PlyType::F32 => 4,
+            PlyType::F64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, PlyType::F32 | PlyType::F64)
+    }
+}
+
+enum PlyReader<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], pos: usize, big_endian: bool },
+}
+
+impl PlyReader<'_> {
+    fn read(&mut self, kind: PlyType) -> Result<f64, PointCloudError> {
+        let truncated = || PointCloudError::Ply("data ends early".to_string());
+        match self {
+            PlyReader::Ascii(words) => {
+                let word = words.next().ok_or_else(truncated)?;
+                word.parse()
+                    .map_err(|_| PointCloudError::Ply(format!("invalid number \"{word}\"")))
+            }
+            PlyReader::Binary { bytes, pos, big_endian } => {
+                let raw = bytes.get(*pos..*pos + kind.size()).ok_or_else(truncated)?;
+                *pos += kind.size();
+                let mut buf = [0u8; 8];
+                buf[..raw.len()].copy_from_slice(raw);
+                if *big_endian {
+                    buf[..raw.len()].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = buf;
+                Ok(match kind {
+                    PlyType::I8 => b0 as i8 as f64,
+                    PlyType::U8 => b0 as f64,
+                    PlyType::I16 => i16::from_le_bytes([b0, b1]) as f64,
+                    PlyType::U16 => u16::from_le_bytes([b0, b1]) as f64,
+                    PlyType::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    PlyType::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    PlyType::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    PlyType::F64 => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+
+    fn skip(&mut self, property: &PlyProperty) -> Result<(), PointCloudError> {
+        let count = match property.list {
+            None => 1,
+            Some(kind) => self.read(kind)? as usize,
+        };
+        for _ in 0..count {
+            self.read(property.kind)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voxel(result: &VoxelizeResult, g: [i32; 3]) -> Option<u16> {
+        let cs = CS as i32;
+        let coord = g.map(|c| c.div_euclid(cs));
+        let [x, y, z] = g.map(|c| (c.rem_euclid(cs) + 1) as u32);
+        let chunk = result
+            .chunks
+            .iter()
+            .find(|c| [c.coord.x, c.coord.y, c.coord.z] == coord)?;
+        chunk
+            .occupancy
+            .get(x, y, z)
+            .then(|| chunk.palette.entries()[chunk.index_buf.get(x, y, z) as usize])
+    }
+
+    #[test]
+    fn ascii_and_binary_ply() {
+        let ascii = b"ply\nformat ascii 1.0\ncomment scan\nelement face 1\n\
+            property list uchar int vertex_indices\nelement vertex 2\nproperty float x\n\
+            property float y\nproperty float z\nproperty uchar red\nproperty uchar green\n\
+            property uchar blue\nend_header\n3 0 1 1\n0 0 0 255 0 0\n1.5 2 -3 0 0 255\n";
+        let cloud = parse_ply(ascii).unwrap();
+        assert_eq!(cloud.positions, vec![[0.0; 3], [1.5, 2.0, -3.0]]);
+        assert_eq!(cloud.colors, Some(vec![[255, 0, 0], [0, 0, 255]]));
+
+        let mut binary = b"ply\nformat binary_little_endian 1.0\nelement vertex 2\n\
+            property double x\nproperty double y\nproperty double z\nproperty float intensity\n\
+            end_header\n"
+            .to_vec();
+        for p in [[1.0f64, 2.0, 3.0], [-4.0, 5.0, 6.5]] {
+            p.iter().for_each(|c| binary.extend_from_slice(&c.to_le_bytes()));
+            binary.extend_from_slice(&0.5f32.to_le_bytes());
+        }
+        let cloud = PointCloud::parse(&binary).unwrap();
+        assert_eq!(cloud.positions, vec![[1.0, 2.0, 3.0], [-4.0, 5.0, 6.5]]);
+        assert_eq!(cloud.colors, None);
+
+        let truncated = parse_ply(&binary[..binary.len() - 1]);
+        assert!(matches!(truncated, Err(PointCloudError::Ply(_))));
+    }
+
+    #[test]
+    fn xyz_columns() {
+        let cloud = parse_xyz("# header\n1 2 3 0.5 10 20 30\n4,5,6,0.7,40,50,60\n\n").unwrap();
+        assert_eq!(cloud.positions, vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        assert_eq!(cloud.colors, Some(vec![[10, 20, 30], [40, 50, 60]]));
+        assert_eq!(parse_xyz("1 2 3\n4 5 6 7").unwrap().colors, None);
+        assert!(matches!(
+            parse_xyz("1 2 3\n1 2 3 4 5 6"),
+            Err(PointCloudError::Xyz { line: 2, .. })
+        ));
+        assert!(matches!(parse_xyz("1 2"), Err(PointCloudError::Xyz { line: 1, .. })));
+    }
+
+    #[test]
+    fn density_threshold_and_averaged_colors() {
+        // Voxel (0,0,0): red and blue points; (3,0,0): one stray point
+        let cloud = PointCloud {
+            positions: vec![[0.1, 0.1, 0.1], [0.4, 0.3, 0.2], [1.6, 0.1, 0.1], [0.2; 3]],
+            colors: Some(vec![[255, 0, 0], [0, 0, 255], [0, 255, 0], [255, 0, 0]]),
+        };
+        let options =
+            PointCloudOptions { voxel_size: 0.5, min_points: 2, grid_origin: Some([0.0; 3]) };
+        let result = voxelize_points(&cloud, &options).unwrap();
+
+        assert_eq!(result.chunks.len(), 1);
+        assert_eq!(result.chunks[0].occupancy.popcount(), 1);
+        let material = voxel(&result, [0, 0, 0]).unwrap();
+        assert_eq!(voxel(&result, [3, 0, 0]), None, "below the density threshold");
+        // Two red points and one blue, averaged in linear space
+        let albedo = result.materials[material as usize].albedo();
+        assert!((albedo[0] - 2.0 / 3.0).abs() < 0.01 && (albedo[2] - 1.0 / 3.0).abs() < 0.01);
+        assert_eq!(result.voxel_size, 0.5);
+
+        let uncolored = PointCloud { colors: None, ..cloud };
+        let gray = voxelize_points(&uncolored, &PointCloudOptions::default()).unwrap();
+        assert_eq!(gray.chunks.iter().map(|c| c.occupancy.popcount()).sum::<u32>(), 2);
+        assert_eq!(gray.grid_origin, [0.1, 0.1, 0.1]);
+    }
+
+    #[test]
+    fn many_colors_fit_chunk_palettes() {
+        // 40³ points with distinct colors, all in one chunk
+        let mut cloud = PointCloud { colors: Some(Vec::new()), ..Default::default() };
+        for x in 0..40u8 {
+            for y in 0..40u8 {
+                for z in 0..40u8 {
+                    cloud.positions.push([x as f64, y as f64, z as f64]);
+                    cloud.colors.as_mut().unwrap().push([x * 6, y * 6, z * 6]);
+                }
+            }
+        }
+        let result = voxelize_points(&cloud, &PointCloudOptions::default()).unwrap();
+        assert_eq!(result.chunks.len(), 1);
+        assert_eq!(result.chunks[0].occupancy.popcount(), 40 * 40 * 40);
+        assert_eq!(result.chunks[0].palette.len(), MAX_PALETTE_ENTRIES as usize);
+        assert_eq!(result.quantize.materials.colors_in, 40 * 40 * 40);
+        assert!(result.quantize.palettes.colors_out <= 255);
+        assert!(result.quantize.palettes.mean_error < 0.05);
+    }
+
+    #[test]
+    fn sparse_clouds_past_the_pool_size_rejected() {
+        // One point per chunk along a line: one chunk more than the pool holds
+        let spread = |n: usize| PointCloud {
+            positions: (0..n).map(|i| [i as f64 * CS as f64, 0.0, 0.0]).collect(),
+            colors: None,
+        };
+        let options = PointCloudOptions { grid_origin: Some([0.0; 3]), ..Default::default() };
+        let fits = voxelize_points(&spread(MAX_SLOTS as usize), &options).unwrap();
+        assert_eq!(fits.chunks.len(), MAX_SLOTS as usize);
+        assert_eq!(
+            voxelize_points(&spread(MAX_SLOTS as usize + 1), &options).err(),
+            Some(PointCloudError::TooManyChunks { chunks: MAX_SLOTS as usize + 1 })
+        );
+    }
+}
@@ -14,7 +14,7 @@
 //! - occupancy summary: 64 B (16 × u32)
 //! - draw metadata: 32 B
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 // ─── Chunk geometry constants ──────────────────────────────────────────────
 
@@ -102,19 +102,223 @@ pub const MESH_COUNTS_ENTRY_BYTES: u32 = 4;
 /// Scene params uniform: vec4f = 16 bytes (grid_origin.xyz, voxel_size).
 pub const SCENE_PARAMS_BYTES: u32 = 16;
 
-// ─── GPU slot table (coord→slot lookup for DDA traversal) ────────────────
-
-/// Slot table grid dimension per axis. Covers chunk coords in [-DIM/2, DIM/2).
-pub const SLOT_TABLE_DIM: u32 = 32;
-/// Total entries in the flat 3D slot table.
-pub const SLOT_TABLE_ENTRIES: u32 = SLOT_TABLE_DIM * SLOT_TABLE_DIM * SLOT_TABLE_DIM;
-/// Bytes for the slot table buffer (one u32 per entry).
-pub const SLOT_TABLE_BYTES: u64 = SLOT_TABLE_ENTRIES as u64 * 4;
-/// Slot table params uniform: origin.xyz (i32×3) + dim (u32) = 16 bytes.
+// ─── GPU slot table (coord→slot spatial hash for DDA traversal) ──────────
+
+/// Slot table capacity in entries. A power of two, twice `MAX_SLOTS`, so the
+/// load factor never exceeds one half and every resident chunk has an entry.
+pub const SLOT_TABLE_CAPACITY: u32 = MAX_SLOTS * 2;
+/// u32 words per slot table entry: chunk x, y, z (i32 bits) and slot + 1.
+pub const SLOT_TABLE_ENTRY_WORDS: u32 = 4;
+/// Bytes for the slot table buffer.
+pub const SLOT_TABLE_BYTES: u64 = SLOT_TABLE_CAPACITY as u64 * SLOT_TABLE_ENTRY_WORDS as u64 * 4;
+/// Slot table params uniform: capacity mask, max probe length, entry count,
+/// unused (i32×4) = 16 bytes.
 pub const SLOT_TABLE_PARAMS_BYTES: u64 = 16;
-/// Sentinel value indicating no chunk at a slot table entry.
+/// Sentinel value returned by slot table lookups that miss.
 pub const SLOT_TABLE_SENTINEL: u32 = 0xFFFFFFFF;
 
+const _: () = assert!(SLOT_TABLE_CAPACITY.is_power_of_two() && SLOT_TABLE_CAPACITY > MAX_SLOTS);
+
+/// Slot table hash of a chunk coord. Mirrors `dda_slot_hash` in
+/// dda_common.wgsl and `v3_slot_hash` in cascade_common.wgsl — all three
+/// must stay bit-identical.
+pub fn slot_table_hash(coord: ChunkCoord) -> u32 {
+    let mut h = (coord.x as u32).wrapping_mul(0x8DA6_B343)
+        ^ (coord.y as u32).wrapping_mul(0xD816_3841)
+        ^ (coord.z as u32).wrapping_mul(0xCB1A_B31F);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h
+}
+
+/// Occupancy of the slot table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlotTableStats {
+    pub entries: u32,
+    pub capacity: u32,
+    /// Longest probe sequence a lookup may walk.
+    pub max_probe: u32,
+    /// Resident chunks with no slot table entry, invisible to GI traversal.
+    pub unreachable: u32,
+}
+
+/// CPU mirror of the GPU slot table: an open-addressed, linearly probed hash
+/// from chunk coord to slot, with no bound on the coords it can hold.
+///
+/// Entries are `[x, y, z, slot + 1]` words, so a zeroed buffer is an empty
+/// table. Lookups walk from `slot_table_hash(coord) & mask` until they find
+/// the coord, an empty entry, or `max_probe` entries — exactly what the
+/// shaders' `dda_chunk_to_slot` does. Removal shifts the rest of the probe
+/// chain back instead of leaving tombstones. Every entry an edit writes is
+/// queued for [`take_dirty`](Self::take_dirty), so the GPU copy can be
+/// patched entry by entry.
+pub struct SlotHashTable {
+    entries: Vec<[u32; 4]>,
+    len: u32,
+    /// Upper bound on probe length; only [`clear`](Self::clear) lowers it.
+    max_probe: u32,
+    dirty: Vec<u32>,
+}
+
+impl SlotHashTable {
+    /// Create an empty table of `capacity` entries (a power of two).
+    pub fn new(capacity: u32) -> Self {
+        assert!(capacity.is_power_of_two(), "slot table capacity must be a power of two");
+        Self {
+            entries: vec![[0; 4]; capacity as usize],
+            len: 0,
+            max_probe: 0,
+            dirty: Vec::new(),
+        }
+    }
+
+    /// Remove every entry. The whole table must be re-uploaded afterwards.
+    pub fn clear(&mut self) {
+        self.entries.fill([0; 4]);
+        self.len = 0;
+        self.max_probe = 0;
+        self.dirty.clear();
+    }
+
+    /// Map `coord` to `slot`, replacing any previous mapping. Returns false
+    /// if the table is full.
+    pub fn insert(&mut self, coord: ChunkCoord, slot: u32) -> bool {
+        debug_assert_ne!(slot, SLOT_TABLE_SENTINEL);
+        let entry = [coord.x as u32, coord.y as u32, coord.z as u32, slot + 1];
+        let (idx, found) = self.probe(coord);
+        let Some(idx) = idx else {
+            return false;
+        };
+        if !found {
+            self.len += 1;
+            let distance = idx.wrapping_sub(self.home(coord)) & self.mask();
+            self.max_probe = self.max_probe.max(distance + 1);
+        }
+        self.entries[idx as usize] = entry;
+        self.dirty.push(idx);
+        true
+    }
+
+    /// Unmap `coord`, returning the slot it pointed at.
+    pub fn remove(&mut self, coord: ChunkCoord) -> Option<u32> {
+        let (Some(mut hole), true) = self.probe(coord) else {
+            return None;
+        };
+        let slot = self.entries[hole as usize][3] - 1;
+        self.len -= 1;
+        self.entries[hole as usize] = [0; 4];
+        self.dirty.push(hole);
+        // Backward-shift: pull later chain members into the hole unless that
+        // would move them before their home entry. The hole is always empty,
+        // so the walk ends even in a full table.
+        let mask = self.mask();
+        let mut idx = hole;
+        loop {
+            idx = (idx + 1) & mask;
+            let entry = self.entries[idx as usize];
+            if entry[3] == 0 {
+                break;
+            }
+            let home = self.home(Self::entry_coord(entry));
+            if (idx.wrapping_sub(home) & mask) >= (idx.wrapping_sub(hole) & mask) {
+                self.entries[hole as usize] = entry;
+                self.entries[idx as usize] = [0; 4];
+                self.dirty.push(idx);
+                hole = idx;
+            }
+        }
+        Some(slot)
+    }
+
+    /// Slot mapped to `coord`, if any. Probes exactly as the shader lookup
+    /// does, bounded by `max_probe`.
+    pub fn lookup(&self, coord: ChunkCoord) -> Option<u32> {
+        let mask = self.mask();
+        let mut idx = self.home(coord);
+        for _ in 0..self.max_probe {
+            let entry = self.entries[idx as usize];
+            if entry[3] == 0 {
+                return None;
+            }
+            if Self::entry_coord(entry) == coord {
+                return Some(entry[3] - 1);
+            }
+            idx = (idx + 1) & mask;
+        }
+        None
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.entries.len() as u32
+    }
+
+    pub fn max_probe(&self) -> u32 {
+        self.max_probe
+    }
+
+    /// The table as GPU buffer words.
+    pub fn words(&self) -> &[u32] {
+        bytemuck::cast_slice(&self.entries)
+    }
+
+    /// Entry `idx` as GPU buffer words.
+    pub fn entry(&self, idx: u32) -> [u32; 4] {
+        self.entries[idx as usize]
+    }
+
+    /// Contents of the params uniform for this table.
+    pub fn params(&self) -> [i32; 4] {
+        [self.mask() as i32, self.max_probe as i32, self.len as i32, 0]
+    }
+
+    /// Entry indices written since the last call, deduplicated.
+    pub fn take_dirty(&mut self) -> Vec<u32> {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.sort_unstable();
+        dirty.dedup();
+        dirty
+    }
+
+    fn mask(&self) -> u32 {
+        self.capacity() - 1
+    }
+
+    fn home(&self, coord: ChunkCoord) -> u32 {
+        slot_table_hash(coord) & self.mask()
+    }
+
+    fn entry_coord(entry: [u32; 4]) -> ChunkCoord {
+        ChunkCoord { x: entry[0] as i32, y: entry[1] as i32, z: entry[2] as i32 }
+    }
+
+    /// Walk `coord`'s probe chain. Returns the entry holding it and true, or
+    /// the first empty entry and false; `None` if the table is full.
+    fn probe(&self, coord: ChunkCoord) -> (Option<u32>, bool) {
+        let mask = self.mask();
+        let mut idx = self.home(coord);
+        for _ in 0..self.capacity() {
+            let entry = self.entries[idx as usize];
+            if entry[3] == 0 {
+                return (Some(idx), false);
+            }
+            if Self::entry_coord(entry) == coord {
+                return (Some(idx), true);
+            }
+            idx = (idx + 1) & mask;
+        }
+        (None, false)
+    }
+}
+
 
 // ─── Wireframe (still fixed allocation, to be variablized in Phase 5) ─────
 
@@ -248,6 +452,10 @@ pub struct ChunkCoord {
 pub enum AllocError {
     PoolFull,
     CoordAlreadyResident,
+    /// No index_buf range is large enough, even after compaction.
+    IndexBufFull,
+    /// The GPU slot table has no free entry for the chunk's coord.
+    SlotTableFull,
 }
 
 /// Error returned when a slot cannot be deallocated.
@@ -326,6 +534,12 @@ impl SlotAllocator {
         MAX_SLOTS - self.free_count()
     }
 
+    /// One past the highest allocated slot, 0 when empty. Per-slot dispatches cover
+    /// `0..slot_span()`, which includes holes left by deallocation.
+    pub fn slot_span(&self) -> u32 {
+        self.slot_to_coord.iter().rposition(Option::is_some).map_or(0, |s| s as u32 + 1)
+    }
+
     /// Whether the pool is completely full.
     pub fn is_full(&self) -> bool {
         self.free_slots.is_empty()
@@ -347,43 +561,384 @@ impl SlotAllocator {
     }
 }
 
-// ─── Index buffer bump allocator ──────────────────────────────────────────
+// ─── Index buffer allocator ───────────────────────────────────────────────
+
+/// Word size of the index_buf pool.
+pub const INDEX_BUF_POOL_WORDS: u32 = (INDEX_BUF_POOL_CAPACITY / 4) as u32;
+/// Staging buffer for index_buf compaction; larger moves are copied in pieces.
+pub const INDEX_BUF_COMPACT_SCRATCH_BYTES: u64 = 4 * 1024 * 1024;
+
+/// One live range relocated by [`IndexBufAllocator::compact`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexBufMove {
+    pub from: u32,
+    pub to: u32,
+    pub words: u32,
+}
+
+/// Occupancy of the index_buf pool, in u32 words.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexBufStats {
+    pub used_words: u32,
+    pub free_words: u32,
+    pub live_ranges: u32,
+    pub free_ranges: u32,
+    pub largest_free: u32,
+}
 
-/// CPU-side bump allocator for the shared index_buf pool.
-/// Reset on scene load. Allocates sequentially — zero fragmentation for batch loads.
+impl IndexBufStats {
+    /// Share of free space outside the largest free range: 0 when it is all
+    /// one range, approaching 1 as churn splinters it.
+    pub fn fragmentation(&self) -> f32 {
+        if self.free_words == 0 {
+            return 0.0;
+        }
+        1.0 - self.largest_free as f32 / self.free_words as f32
+    }
+}
+
+/// CPU-side free-list allocator for the shared index_buf pool.
+///
+/// First fit in offset order keeps live data packed toward the front of the
+/// pool; freed ranges coalesce with their free neighbors, so freeing and
+/// re-allocating a chunk at the same size lands it back in place. Space
+/// splintered by churn is recovered by [`compact`](Self::compact).
 pub struct IndexBufAllocator {
-    head: u32, // next free word offset
+    capacity: u32,
+    /// Free ranges, offset → words. Never two adjacent entries.
+    free: BTreeMap<u32, u32>,
+    /// Allocated ranges, offset → words.
+    live: BTreeMap<u32, u32>,
 }
 
 impl IndexBufAllocator {
     pub fn new() -> Self {
-        Self { head: 0 }
+        Self::with_capacity(INDEX_BUF_POOL_WORDS)
+    }
+
+    /// Allocator over a pool of `capacity` words.
+    pub fn with_capacity(capacity: u32) -> Self {
+        let mut alloc = Self {
+            capacity,
+            free: BTreeMap::new(),
+            live: BTreeMap::new(),
+        };
+        alloc.reset();
+        alloc
+    }
+
+    /// Free everything.
+    pub fn reset(&mut self) {
+        self.live.clear();
+        self.free.clear();
+        if self.capacity > 0 {
+            self.free.insert(0, self.capacity);
+        }
+    }
+
+    /// Allocate `words` from the pool. Returns the word offset, or `None` if
+    /// no free range is large enough (see [`stats`](Self::stats) for
+    /// whether compacting would help). Empty requests get offset 0 and hold
+    /// no space.
+    pub fn alloc(&mut self, words: u32) -> Option<u32> {
+        if words == 0 {
+            return Some(0);
+        }
+        let (&offset, &len) = self.free.iter().find(|(_, &len)| len >= words)?;
+        self.free.remove(&offset);
+        if len > words {
+            self.free.insert(offset + words, len - words);
+        }
+        self.live.insert(offset, words);
+        Some(offset)
+    }
+
+    /// Return a range from [`alloc`](Self::alloc) to the pool.
+    ///
+    /// Panics if `offset` is not the start of a live range of `words` words.
+    pub fn free(&mut self, offset: u32, words: u32) {
+        if words == 0 {
+            return;
+        }
+        assert_eq!(
+            self.live.remove(&offset),
+            Some(words),
+            "index_buf range {offset}+{words} is not allocated"
+        );
+        let mut start = offset;
+        let mut len = words;
+        if let Some((&prev, &prev_len)) = self.free.range(..offset).next_back() {
+            if prev + prev_len == offset {
+                self.free.remove(&prev);
+                start = prev;
+                len += prev_len;
+            }
+        }
+        if let Some(next_len) = self.free.remove(&(offset + words)) {
+            len += next_len;
+        }
+        self.free.insert(start, len);
+    }
+
+    /// Slide every live range down to close the gaps between them, in
+    /// offset order. Returns the moves to apply to the GPU copy; each has
+    /// `to < from`, and applying them in order never overwrites a range
+    /// that has yet to move.
+    pub fn compact(&mut self) -> Vec<IndexBufMove> {
+        let mut moves = Vec::new();
+        let mut cursor = 0;
+        let live = std::mem::take(&mut self.live);
+        for (from, words) in live {
+            if from != cursor {
+                moves.push(IndexBufMove { from, to: cursor, words });
+            }
+            self.live.insert(cursor, words);
+            cursor += words;
+        }
+        self.free.clear();
+        if cursor < self.capacity {
+            self.free.insert(cursor, self.capacity - cursor);
+        }
+        moves
+    }
+
+    pub fn stats(&self) -> IndexBufStats {
+        let free_words: u32 = self.free.values().sum();
+        IndexBufStats {
+            used_words: self.capacity - free_words,
+            free_words,
+            live_ranges: self.live.len() as u32,
+            free_ranges: self.free.len() as u32,
+            largest_free: self.free.values().copied().max().unwrap_or(0),
+        }
     }
 
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+}
+
+// ─── Mesh pool allocator ──────────────────────────────────────────────────
+
+/// Second-level subdivisions per power of two in [`MeshAllocator`].
+const TLSF_SL_BITS: u32 = 4;
+const TLSF_SL_COUNT: u32 = 1 << TLSF_SL_BITS;
+/// First-level classes: linear sizes below `TLSF_SL_COUNT`, then one per
+/// power of two up to 2^31.
+const TLSF_FL_COUNT: u32 = 32 - TLSF_SL_BITS + 1;
+
+/// Two-level segregated fit (TLSF) allocator over one of the mesh pools,
+/// in elements (vertices or indices).
+///
+/// Free blocks are binned by size class — a power of two split into
+/// `TLSF_SL_COUNT` steps — with a bitmap per level, so alloc and free are
+/// O(1): alloc takes any block from the smallest non-empty class whose
+/// every block fits, free merges with the free blocks on either side.
+/// Lets the CPU mesh path move a single chunk's mesh without re-running the
+/// pool-wide prefix sum.
+pub struct MeshAllocator {
+    capacity: u32,
+    fl_bitmap: u32,
+    sl_bitmaps: [u32; TLSF_FL_COUNT as usize],
+    /// Free block offsets per size class.
+    classes: Vec<Vec<u32>>,
+    /// Free blocks: offset → (len, position in its class list).
+    free: HashMap<u32, (u32, usize)>,
+    /// Free blocks by end offset, for merging with the block before.
+    free_ends: HashMap<u32, u32>,
+    /// Allocated blocks: offset → len.
+    live: HashMap<u32, u32>,
+    used: u32,
+}
+
+impl MeshAllocator {
+    pub fn new(capacity: u32) -> Self {
+        let mut alloc = Self {
+            capacity,
+            fl_bitmap: 0,
+            sl_bitmaps: [0; TLSF_FL_COUNT as usize],
+            classes: vec![Vec::new(); (TLSF_FL_COUNT * TLSF_SL_COUNT) as usize],
+            free: HashMap::new(),
+            free_ends: HashMap::new(),
+            live: HashMap::new(),
+            used: 0,
+        };
+        alloc.reset();
+        alloc
+    }
+
+    /// Free everything.
     pub fn reset(&mut self) {
-        self.head = 0;
+        self.fl_bitmap = 0;
+        self.sl_bitmaps = [0; TLSF_FL_COUNT as usize];
+        self.classes.iter_mut().for_each(Vec::clear);
+        self.free.clear();
+        self.free_ends.clear();
+        self.live.clear();
+        self.used = 0;
+        if self.capacity > 0 {
+            self.insert_free(0, self.capacity);
+        }
+    }
+
+    /// Allocate `len` elements. Returns the offset, or `None` if no free
+    /// block is large enough. A full repack (reset, then allocating in
+    /// order) always succeeds while the total fits.
+    pub fn alloc(&mut self, len: u32) -> Option<u32> {
+        if len == 0 {
+            return Some(0);
+        }
+        if len > self.capacity {
+            return None;
+        }
+        // Round up to the next class boundary, so any block found fits
+        let rounded = if len < TLSF_SL_COUNT {
+            len
+        } else {
+            let step = (1u64 << (31 - len.leading_zeros() - TLSF_SL_BITS)) - 1;
+            (len as u64 + step).min(u32::MAX as u64) as u32
+        };
+        let (fl, sl) = size_class(rounded);
+        let sl_map = self.sl_bitmaps[fl as usize] & (u32::MAX << sl);
+        let (fl, sl) = if sl_map != 0 {
+            (fl, sl_map.trailing_zeros())
+        } else {
+            let fl_map = self.fl_bitmap & u32::MAX.checked_shl(fl + 1).unwrap_or(0);
+            if fl_map == 0 {
+                return self.alloc_exact(len);
+            }
+            let fl = fl_map.trailing_zeros();
+            (fl, self.sl_bitmaps[fl as usize].trailing_zeros())
+        };
+        let offset = *self.classes[(fl * TLSF_SL_COUNT + sl) as usize].last()?;
+        Some(self.take(offset, len))
+    }
+
+    /// Return a block from [`alloc`](Self::alloc) to the pool.
+    ///
+    /// Panics if `offset` is not the start of a live block of `len` elements.
+    pub fn free(&mut self, offset: u32, len: u32) {
+        if len == 0 {
+            return;
+        }
+        assert_eq!(
+            self.live.remove(&offset),
+            Some(len),
+            "mesh pool block {offset}+{len} is not allocated"
+        );
+        self.used -= len;
+        let mut start = offset;
+        let mut end = offset + len;
+        if let Some(&prev) = self.free_ends.get(&offset) {
+            let prev_len = self.free[&prev].0;
+            self.remove_free(prev, prev_len);
+            start = prev;
+        }
+        if let Some(&(next_len, _)) = self.free.get(&end) {
+            self.remove_free(end, next_len);
+            end += next_len;
+        }
+        self.insert_free(start, end - start);
+    }
+
+    pub fn used(&self) -> u32 {
+        self.used
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn free_blocks(&self) -> u32 {
+        self.free.len() as u32
     }
 
-    /// Allocate `words` from the pool. Returns the word offset.
-    pub fn alloc(&mut self, words: u32) -> u32 {
-        let offset = self.head;
-        self.head += words;
+    /// Size of the largest free block.
+    pub fn largest_free(&self) -> u32 {
+        if self.fl_bitmap == 0 {
+            return 0;
+        }
+        let fl = 31 - self.fl_bitmap.leading_zeros();
+        let sl = 31 - self.sl_bitmaps[fl as usize].leading_zeros();
+        let class = &self.classes[(fl * TLSF_SL_COUNT + sl) as usize];
+        class.iter().map(|offset| self.free[offset].0).max().unwrap_or(0)
+    }
+
+    // ── Internals ──
+
+    /// Fallback when rounding up left no class: the largest class may still
+    /// hold a block of exactly `len` or more.
+    fn alloc_exact(&mut self, len: u32) -> Option<u32> {
+        let (fl, sl) = size_class(len);
+        let class = &self.classes[(fl * TLSF_SL_COUNT + sl) as usize];
+        let offset = class.iter().copied().find(|offset| self.free[offset].0 >= len)?;
+        Some(self.take(offset, len))
+    }
+
+    /// Allocate the front of free block `offset`, returning the rest.
+    fn take(&mut self, offset: u32, len: u32) -> u32 {
+        let block_len = self.free[&offset].0;
+        self.remove_free(offset, block_len);
+        if block_len > len {
+            self.insert_free(offset + len, block_len - len);
+        }
+        self.live.insert(offset, len);
+        self.used += len;
         offset
     }
 
-    pub fn total_words(&self) -> u32 {
-        self.head
+    fn insert_free(&mut self, offset: u32, len: u32) {
+        let (fl, sl) = size_class(len);
+        let class = &mut self.classes[(fl * TLSF_SL_COUNT + sl) as usize];
+        self.free.insert(offset, (len, class.len()));
+        self.free_ends.insert(offset + len, offset);
+        class.push(offset);
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmaps[fl as usize] |= 1 << sl;
     }
 
-    pub fn total_bytes(&self) -> u64 {
-        self.head as u64 * 4
+    fn remove_free(&mut self, offset: u32, len: u32) {
+        let (fl, sl) = size_class(len);
+        let (_, pos) = self.free.remove(&offset).expect("free block");
+        self.free_ends.remove(&(offset + len));
+        let class = &mut self.classes[(fl * TLSF_SL_COUNT + sl) as usize];
+        class.swap_remove(pos);
+        if let Some(&moved) = class.get(pos) {
+            self.free.get_mut(&moved).expect("free block").1 = pos;
+        }
+        if class.is_empty() {
+            self.sl_bitmaps[fl as usize] &= !(1 << sl);
+            if self.sl_bitmaps[fl as usize] == 0 {
+                self.fl_bitmap &= !(1 << fl);
+            }
+        }
     }
 }
 
+/// TLSF (first level, second level) class of a block of `len` elements.
+fn size_class(len: u32) -> (u32, u32) {
+    if len < TLSF_SL_COUNT {
+        return (0, len);
+    }
+    let log2 = 31 - len.leading_zeros();
+    (log2 - TLSF_SL_BITS + 1, (len >> (log2 - TLSF_SL_BITS)) ^ TLSF_SL_COUNT)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Deterministic xorshift32 stream for the churn tests.
+    fn xorshift(seed: u32) -> impl FnMut() -> u32 {
+        let mut state = seed;
+        move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        }
+    }
+
     #[test]
     fn occupancy_addressing_roundtrip() {
         // Verify column_index formula covers all (x, z) without collision
@@ -617,6 +1172,23 @@ mod tests {
         assert_eq!(alloc.resident_count(), 1);
     }
 
+    #[test]
+    fn slot_span_covers_holes() {
+        let mut alloc = SlotAllocator::new();
+        assert_eq!(alloc.slot_span(), 0);
+        for i in 0..4 {
+            alloc.alloc(ChunkCoord { x: i, y: 0, z: 0 }).unwrap();
+        }
+        alloc.dealloc(1).unwrap();
+        assert_eq!(alloc.resident_count(), 3);
+        assert_eq!(alloc.slot_span(), 4);
+        alloc.dealloc(3).unwrap();
+        assert_eq!(alloc.slot_span(), 3);
+        alloc.dealloc(2).unwrap();
+        alloc.dealloc(0).unwrap();
+        assert_eq!(alloc.slot_span(), 0);
+    }
+
     #[test]
     fn dealloc_invalid_slot() {
         let mut alloc = SlotAllocator::new();
@@ -656,4 +1228,350 @@ mod tests {
         }
         assert_eq!(alloc.resident_count(), 4);
     }
+
+    #[test]
+    fn index_buf_free_coalesces() {
+        let mut alloc = IndexBufAllocator::with_capacity(100);
+        let a = alloc.alloc(10).unwrap();
+        let b = alloc.alloc(20).unwrap();
+        let c = alloc.alloc(30).unwrap();
+        assert_eq!((a, b, c), (0, 10, 30));
+
+        alloc.free(a, 10);
+        alloc.free(c, 30);
+        let stats = alloc.stats();
+        assert_eq!(stats.used_words, 20);
+        assert_eq!((stats.free_ranges, stats.largest_free), (2, 70));
+        assert!((stats.fragmentation() - 0.125).abs() < 1e-6);
+
+        // Freeing the middle joins both neighbors into one range
+        alloc.free(b, 20);
+        let stats = alloc.stats();
+        assert_eq!((stats.free_ranges, stats.largest_free, stats.live_ranges), (1, 100, 0));
+        assert_eq!(stats.fragmentation(), 0.0);
+    }
+
+    #[test]
+    fn index_buf_realloc_in_place() {
+        let mut alloc = IndexBufAllocator::with_capacity(100);
+        for _ in 0..3 {
+            alloc.alloc(10).unwrap();
+        }
+        alloc.free(10, 10);
+        assert_eq!(alloc.alloc(10), Some(10), "same size returns to its old range");
+        alloc.free(10, 10);
+        assert_eq!(alloc.alloc(8), Some(10), "smaller fits in place");
+        assert_eq!(alloc.alloc(12), Some(30), "larger skips the 2-word hole");
+        assert_eq!(alloc.alloc(0), Some(0));
+        alloc.free(0, 0);
+        assert_eq!(alloc.stats().live_ranges, 4);
+    }
+
+    #[test]
+    fn index_buf_full() {
+        let mut alloc = IndexBufAllocator::with_capacity(16);
+        let a = alloc.alloc(6).unwrap();
+        alloc.alloc(6).unwrap();
+        alloc.free(a, 6);
+        assert_eq!(alloc.alloc(7), None);
+        assert_eq!(alloc.stats().free_words, 10);
+
+        // Compaction joins the hole and the tail
+        let moves = alloc.compact();
+        assert_eq!(moves, vec![IndexBufMove { from: 6, to: 0, words: 6 }]);
+        assert_eq!(alloc.alloc(7), Some(6));
+        assert_eq!(IndexBufAllocator::new().capacity(), INDEX_BUF_POOL_WORDS);
+    }
+
+    #[test]
+    #[should_panic(expected = "not allocated")]
+    fn index_buf_double_free() {
+        let mut alloc = IndexBufAllocator::with_capacity(16);
+        let a = alloc.alloc(4).unwrap();
+        alloc.free(a, 4);
+        alloc.free(a, 4);
+    }
+
+    #[test]
+    fn index_buf_churn_and_compact() {
+        const CAPACITY: u32 = 4096;
+        let mut alloc = IndexBufAllocator::with_capacity(CAPACITY);
+        // Simulated pool contents: each live range filled with its tag
+        let mut pool = vec![0u32; CAPACITY as usize];
+        let mut live: Vec<(u32, u32, u32)> = Vec::new(); // (offset, words, tag)
+        let mut next = xorshift(0x2545_f491);
+
+        for tag in 1..=5000u32 {
+            if next() % 5 < 3 || live.is_empty() {
+                let words = 1 + next() % 64;
+                let Some(offset) = alloc.alloc(words) else { continue };
+                let range = offset as usize..(offset + words) as usize;
+                assert!(pool[range.clone()].iter().all(|&w| w == 0), "overlapping alloc");
+                pool[range].fill(tag);
+                live.push((offset, words, tag));
+            } else {
+                let (offset, words, _) = live.swap_remove(next() as usize % live.len());
+                alloc.free(offset, words);
+                pool[offset as usize..(offset + words) as usize].fill(0);
+            }
+
+            // Free runs in the simulation match the allocator's free list
+            let mut runs = Vec::new();
+            let mut run = 0;
+            for &w in pool.iter().chain([1].iter()) {
+                if w == 0 {
+                    run += 1;
+                } else if run > 0 {
+                    runs.push(run);
+                    run = 0;
+                }
+            }
+            let stats = alloc.stats();
+            assert_eq!(stats.free_ranges as usize, runs.len());
+            assert_eq!(stats.free_words, runs.iter().sum::<u32>());
+            assert_eq!(stats.largest_free, runs.iter().copied().max().unwrap_or(0));
+            assert_eq!(stats.live_ranges as usize, live.len());
+        }
+        assert!(alloc.stats().fragmentation() > 0.0);
+
+        let used = alloc.stats().used_words;
+        let moves = alloc.compact();
+        for m in &moves {
+            assert!(m.to < m.from);
+            let from = m.from as usize;
+            pool.copy_within(from..from + m.words as usize, m.to as usize);
+        }
+        for (offset, words, tag) in &mut live {
+            if let Some(m) = moves.iter().find(|m| m.from == *offset) {
+                *offset = m.to;
+            }
+            let range = *offset as usize..(*offset + *words) as usize;
+            assert!(pool[range].iter().all(|w| w == tag), "range {tag} lost in compaction");
+        }
+        let stats = alloc.stats();
+        assert_eq!(stats.used_words, used);
+        assert_eq!((stats.free_ranges, stats.largest_free), (1, CAPACITY - used));
+        assert_eq!(stats.fragmentation(), 0.0);
+        for (offset, words, _) in live {
+            alloc.free(offset, words);
+        }
+        assert_eq!(alloc.stats().free_words, CAPACITY);
+    }
+
+    fn coord(x: i32, y: i32, z: i32) -> ChunkCoord {
+        ChunkCoord { x, y, z }
+    }
+
+    #[test]
+    fn slot_hash_reaches_distant_chunks() {
+        let mut table = SlotHashTable::new(SLOT_TABLE_CAPACITY);
+        let coords = [
+            coord(0, 0, 0),
+            coord(-1, 0, 0),
+            coord(1_000_000, -3, 7),
+            coord(i32::MIN, i32::MAX, 0),
+            coord(-40, 2, 40),
+        ];
+        for (slot, &c) in coords.iter().enumerate() {
+            assert!(table.insert(c, slot as u32));
+        }
+        for (slot, &c) in coords.iter().enumerate() {
+            assert_eq!(table.lookup(c), Some(slot as u32));
+        }
+        assert_eq!(table.lookup(coord(1, 0, 0)), None);
+        assert_eq!(table.len(), 5);
+        // Remapping an existing coord doesn't add an entry
+        assert!(table.insert(coords[2], 9));
+        assert_eq!((table.lookup(coords[2]), table.len()), (Some(9), 5));
+        assert_eq!(table.params()[0], SLOT_TABLE_CAPACITY as i32 - 1);
+    }
+
+    #[test]
+    fn slot_hash_removal_keeps_chains_intact() {
+        // A tiny table forces long, wrapping probe chains
+        let mut table = SlotHashTable::new(8);
+        let coords: Vec<_> = (0..7).map(|i| coord(i * 31, -i, i * i)).collect();
+        for (slot, &c) in coords.iter().enumerate() {
+            assert!(table.insert(c, slot as u32));
+        }
+        for (i, &c) in coords.iter().enumerate().step_by(2) {
+            assert_eq!(table.remove(c), Some(i as u32));
+            assert_eq!(table.remove(c), None);
+        }
+        for (i, &c) in coords.iter().enumerate() {
+            let expected = (i % 2 == 1).then_some(i as u32);
+            assert_eq!(table.lookup(c), expected, "coord {c:?}");
+        }
+    }
+
+    #[test]
+    fn slot_hash_full_table_refuses_inserts() {
+        let mut table = SlotHashTable::new(4);
+        for i in 0..4 {
+            assert!(table.insert(coord(i, 0, 0), i as u32));
+        }
+        assert!(!table.insert(coord(4, 0, 0), 4));
+        assert!(table.insert(coord(2, 0, 0), 7), "remapping still works when full");
+        assert_eq!(table.remove(coord(0, 0, 0)), Some(0));
+        assert!(table.insert(coord(4, 0, 0), 4));
+        assert_eq!(table.lookup(coord(4, 0, 0)), Some(4));
+    }
+
+    #[test]
+    fn slot_hash_dirty_entries_match_words() {
+        let mut table = SlotHashTable::new(64);
+        assert!(table.words().iter().all(|&w| w == 0), "zeroed buffer is an empty table");
+        let mut gpu = table.words().to_vec();
+        let mut sync = |table: &mut SlotHashTable| {
+            for idx in table.take_dirty() {
+                let base = (idx * SLOT_TABLE_ENTRY_WORDS) as usize;
+                gpu[base..base + 4].copy_from_slice(&table.entry(idx));
+            }
+            assert_eq!(gpu, table.words());
+        };
+        for i in 0..40 {
+            table.insert(coord(i % 5, i / 5, -i), i as u32);
+            sync(&mut table);
+        }
+        for i in (0..40).step_by(3) {
+            table.remove(coord(i % 5, i / 5, -i));
+            sync(&mut table);
+        }
+        assert!(table.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn slot_hash_churn_matches_hashmap() {
+        let mut table = SlotHashTable::new(SLOT_TABLE_CAPACITY);
+        let mut reference: HashMap<ChunkCoord, u32> = HashMap::new();
+        let mut next = xorshift(0x9e37_79b9);
+        for slot in 0..50_000u32 {
+            // A wide, clustered world: coords far beyond any fixed window
+            let c = coord(
+                (next() % 200) as i32 - 100 + 10_000 * (next() % 3) as i32,
+                (next() % 16) as i32 - 8,
+                (next() % 200) as i32 - 100,
+            );
+            if next().is_multiple_of(3) || reference.len() >= MAX_SLOTS as usize {
+                assert_eq!(table.remove(c), reference.remove(&c));
+            } else {
+                assert!(table.insert(c, slot));
+                reference.insert(c, slot);
+            }
+        }
+        assert_eq!(table.len() as usize, reference.len());
+        for (&c, &slot) in &reference {
+            assert_eq!(table.lookup(c), Some(slot));
+        }
+        assert!(table.max_probe() < 64, "probe chains stay short: {}", table.max_probe());
+    }
+
+    #[test]
+    fn slot_table_hash_values() {
+        // Pinned so the WGSL mirrors can be checked against the same values
+        assert_eq!(slot_table_hash(coord(0, 0, 0)), 0);
+        assert_eq!(slot_table_hash(coord(1, 0, 0)), 0xA874_27AB);
+        assert_eq!(slot_table_hash(coord(-1, 2, -3)), 0x8C79_00BE);
+    }
+
+    #[test]
+    fn tlsf_size_classes() {
+        assert_eq!(size_class(1), (0, 1));
+        assert_eq!(size_class(15), (0, 15));
+        assert_eq!(size_class(16), (1, 0));
+        assert_eq!(size_class(31), (1, 15));
+        assert_eq!(size_class(32), (2, 0));
+        assert_eq!(size_class(34), (2, 1));
+        assert_eq!(size_class(u32::MAX), (TLSF_FL_COUNT - 1, TLSF_SL_COUNT - 1));
+        // Class floors are monotonic in size
+        let mut last = (0, 0);
+        for len in 1..5000 {
+            let class = size_class(len);
+            assert!(class >= last, "class of {len} went backwards");
+            last = class;
+        }
+    }
+
+    #[test]
+    fn mesh_alloc_packs_sequentially_and_merges() {
+        let mut alloc = MeshAllocator::new(MESH_VERTEX_POOL_CAPACITY);
+        let counts = [400, 24, 4, 1000, 4096];
+        let mut expected = 0;
+        for &n in &counts {
+            assert_eq!(alloc.alloc(n), Some(expected), "fresh pool packs like a prefix sum");
+            expected += n;
+        }
+        assert_eq!(alloc.used(), expected);
+
+        // Free all but the first, out of order: they merge with the tail
+        alloc.free(400, 24);
+        alloc.free(1428, 4096);
+        alloc.free(428, 1000);
+        assert_eq!(alloc.free_blocks(), 2);
+        alloc.free(424, 4);
+        assert_eq!(alloc.free_blocks(), 1);
+        assert_eq!(alloc.largest_free(), MESH_VERTEX_POOL_CAPACITY - 400);
+
+        // A hole between live blocks is reused in place
+        let a = alloc.alloc(100).unwrap();
+        let b = alloc.alloc(100).unwrap();
+        alloc.alloc(100).unwrap();
+        alloc.free(b, 100);
+        assert_eq!(alloc.free_blocks(), 2);
+        assert_eq!(alloc.alloc(90), Some(b));
+        assert_eq!((a, alloc.used()), (400, 400 + 290));
+    }
+
+    #[test]
+    fn mesh_alloc_exhaustion() {
+        let mut alloc = MeshAllocator::new(100);
+        assert_eq!(alloc.alloc(101), None);
+        assert_eq!(alloc.alloc(0), Some(0));
+        let a = alloc.alloc(40).unwrap();
+        alloc.alloc(20).unwrap();
+        alloc.free(a, 40);
+        // 80 free words, but split 40 + 40
+        assert_eq!(alloc.alloc(50), None);
+        // Exactly-sized blocks are found even when rounding up misses them
+        assert_eq!(alloc.alloc(40), Some(0));
+        assert_eq!(alloc.alloc(40), Some(60));
+        assert_eq!((alloc.used(), alloc.largest_free()), (100, 0));
+        alloc.reset();
+        assert_eq!(alloc.largest_free(), 100);
+    }
+
+    #[test]
+    fn mesh_alloc_churn() {
+        const CAPACITY: u32 = 1 << 16;
+        let mut alloc = MeshAllocator::new(CAPACITY);
+        let mut owner = vec![0u32; CAPACITY as usize];
+        let mut live: Vec<(u32, u32)> = Vec::new();
+        let mut next = xorshift(0x9e37_79b9);
+        for tag in 1..=20_000u32 {
+            if next().is_multiple_of(2) || live.is_empty() {
+                // Mesh-like sizes: 4 vertices per quad, skewed small
+                let len = 4 * (1 + next() % (1 + next() % 512));
+                let Some(offset) = alloc.alloc(len) else { continue };
+                let range = offset as usize..(offset + len) as usize;
+                assert!(owner[range.clone()].iter().all(|&o| o == 0), "overlapping alloc");
+                owner[range].fill(tag);
+                live.push((offset, len));
+            } else {
+                let (offset, len) = live.swap_remove(next() as usize % live.len());
+                alloc.free(offset, len);
+                owner[offset as usize..(offset + len) as usize].fill(0);
+            }
+            assert_eq!(alloc.used(), live.iter().map(|&(_, len)| len).sum::<u32>());
+        }
+        // Neighbouring free blocks always merge: none of them touch
+        let mut free: Vec<(u32, u32)> = alloc.free.iter().map(|(&o, &(l, _))| (o, l)).collect();
+        free.sort();
+        assert!(free.windows(2).all(|w| w[0].0 + w[0].1 < w[1].0));
+        assert_eq!(free.iter().map(|&(_, l)| l).sum::<u32>(), CAPACITY - alloc.used());
+
+        for (offset, len) in live {
+            alloc.free(offset, len);
+        }
+        assert_eq!((alloc.free_blocks(), alloc.largest_free()), (1, CAPACITY));
+    }
 }
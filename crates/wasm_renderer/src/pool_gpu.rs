@@ -3,7 +3,10 @@
 //! WASM-only. Owns all wgpu::Buffers for the 1024-slot chunk pool.
 //! CPU-side slot management lives in `pool::SlotAllocator` (platform-independent).
 
+use std::collections::HashMap;
+
 use crate::pool::*;
+use crate::summary_cpu::SummaryResult;
 
 /// Owns all GPU buffers for the chunk pool.
 ///
@@ -30,6 +33,12 @@ pub struct ChunkPool {
     pub(crate) mesh_counts_buf: wgpu::Buffer,      // Pass 1 output: per-slot quad count
     pub(crate) mesh_offset_table: wgpu::Buffer,     // Pass 2 output: per-slot offsets (vec4u)
     pub(crate) mesh_total_buf: wgpu::Buffer,        // Pass 2 output: total verts + indices (2 u32)
+    pub(crate) vertex_alloc: MeshAllocator,         // CPU mesh path sub-allocation
+    pub(crate) index_alloc: MeshAllocator,
+    /// Per slot: the pool ranges of its CPU mesh. Only meaningful while
+    /// `mesh_alloc_valid` — the GPU passes lay the pools out themselves.
+    pub(crate) mesh_ranges: Vec<Option<DrawMeta>>,
+    pub(crate) mesh_alloc_valid: bool,
 
     // ── Wireframe (F8: lazy allocation — None until wireframe mode first activated) ──
     pub(crate) wire_index_pool: Option<wgpu::Buffer>,
@@ -39,6 +48,10 @@ pub struct ChunkPool {
     pub(crate) index_buf_pool: wgpu::Buffer,
     pub(crate) palette_meta_buf: wgpu::Buffer,
     pub(crate) index_buf_alloc: IndexBufAllocator,
+    /// Per slot: the (word offset, words) range it holds in `index_buf_pool`.
+    pub(crate) index_buf_ranges: Vec<Option<(u32, u32)>>,
+    /// Staging buffer for compaction copies (lazy — None until first compaction).
+    pub(crate) index_buf_scratch: Option<wgpu::Buffer>,
 
     // ── Per-slot visibility (R-4 output) ──
     pub(crate) visibility_buf: wgpu::Buffer,
@@ -52,6 +65,8 @@ pub struct ChunkPool {
     // ── DDA slot table (coord→slot lookup for GI traversal) ──
     pub(crate) slot_table_buf: wgpu::Buffer,
     pub(crate) slot_table_params_buf: wgpu::Buffer,
+    /// CPU mirror of the slot table contents.
+    pub(crate) slot_table: SlotHashTable,
 
     // ── Bind group layouts (read-only, for render stages) ──
     pub(crate) chunk_meta_layout: wgpu::BindGroupLayout,
@@ -125,14 +140,14 @@ impl ChunkPool {
         let summary_buf = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("chunk-summary"),
             size: SUMMARY_BYTES_PER_SLOT as u64 * MAX_SLOTS as u64,
-            usage: wgpu::BufferUsages::STORAGE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         let aabb_buf = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("chunk-aabb"),
             size: AABB_BYTES as u64 * MAX_SLOTS as u64,
-            usage: wgpu::BufferUsages::STORAGE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
@@ -204,7 +219,9 @@ impl ChunkPool {
         let index_buf_pool = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("chunk-index-buf-pool"),
             size: INDEX_BUF_POOL_CAPACITY,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
@@ -504,11 +521,17 @@ impl ChunkPool {
             mesh_counts_buf,
             mesh_offset_table,
             mesh_total_buf,
+            vertex_alloc: MeshAllocator::new(MESH_VERTEX_POOL_CAPACITY),
+            index_alloc: MeshAllocator::new(MESH_INDEX_POOL_CAPACITY),
+            mesh_ranges: vec![None; MAX_SLOTS as usize],
+            mesh_alloc_valid: false,
             wire_index_pool: None,
             wire_indirect_buf: None,
             index_buf_pool,
             palette_meta_buf,
             index_buf_alloc: IndexBufAllocator::new(),
+            index_buf_ranges: vec![None; MAX_SLOTS as usize],
+            index_buf_scratch: None,
             visibility_buf,
             pass1_visibility_buf,
             scene_params_buf,
@@ -516,6 +539,7 @@ impl ChunkPool {
             indirect_draw_buf,
             slot_table_buf,
             slot_table_params_buf,
+            slot_table: SlotHashTable::new(SLOT_TABLE_CAPACITY),
             chunk_meta_layout,
             mesh_draw_layout,
             scene_global_layout,
@@ -540,13 +564,17 @@ impl ChunkPool {
         self.allocator.alloc(coord)
     }
 
-    /// Deallocate a slot. Writes version=0 to GPU to mark the slot as invalid.
+    /// Deallocate a slot. Writes version=0 to GPU to mark the slot as invalid
+    /// and returns its index_buf range to the pool.
     pub fn dealloc_slot(
         &mut self,
         slot: u32,
         queue: &wgpu::Queue,
     ) -> Result<ChunkCoord, DeallocError> {
         let coord = self.allocator.dealloc(slot)?;
+        if let Some((offset, words)) = self.index_buf_ranges[slot as usize].take() {
+            self.index_buf_alloc.free(offset, words);
+        }
         // Zero the version on GPU so no consumer reads stale data
         queue.write_buffer(
             &self.version_buf,
@@ -703,6 +731,133 @@ impl ChunkPool {
         }
     }
 
+    // ── Mesh pool sub-allocation (CPU mesh path) ──
+
+    /// Free the whole vertex and index pools for a repack by the CPU mesh
+    /// path, which then allocates every slot in order.
+    pub fn reset_mesh_alloc(&mut self) {
+        self.vertex_alloc.reset();
+        self.index_alloc.reset();
+        self.mesh_ranges.fill(None);
+        self.mesh_alloc_valid = true;
+    }
+
+    /// Mark the sub-allocation stale: the GPU mesh passes have laid out the
+    /// pools by prefix sum.
+    pub fn invalidate_mesh_alloc(&mut self) {
+        self.mesh_alloc_valid = false;
+    }
+
+    /// Whether the pools hold the CPU path's sub-allocated layout, so single
+    /// slots can be re-meshed in place.
+    pub fn mesh_alloc_valid(&self) -> bool {
+        self.mesh_alloc_valid
+    }
+
+    /// Reserve pool space for `slot`'s mesh, releasing what it held before.
+    /// Returns the draw metadata to upload, or `None` (with the slot left
+    /// empty) if either pool has no block large enough.
+    pub fn alloc_mesh(
+        &mut self,
+        slot: u32,
+        vertex_count: u32,
+        index_count: u32,
+    ) -> Option<DrawMeta> {
+        self.free_mesh(slot);
+        let vertex_offset = self.vertex_alloc.alloc(vertex_count)?;
+        let Some(index_offset) = self.index_alloc.alloc(index_count) else {
+            self.vertex_alloc.free(vertex_offset, vertex_count);
+            return None;
+        };
+        let meta = DrawMeta {
+            vertex_offset,
+            vertex_count,
+            index_offset,
+            index_count,
+            ..Default::default()
+        };
+        self.mesh_ranges[slot as usize] = Some(meta);
+        Some(meta)
+    }
+
+    /// Release `slot`'s mesh ranges, if any.
+    pub fn free_mesh(&mut self, slot: u32) {
+        assert!(slot < MAX_SLOTS, "slot {slot} out of range");
+        if let Some(meta) = self.mesh_ranges[slot as usize].take() {
+            self.vertex_alloc.free(meta.vertex_offset, meta.vertex_count);
+            self.index_alloc.free(meta.index_offset, meta.index_count);
+        }
+    }
+
+    /// Write one slot's mesh at the ranges in `meta` and patch only that
+    /// slot's offset table entry, draw metadata, visibility and indirect
+    /// args. An empty `meta` clears the slot's draw.
+    pub fn write_slot_mesh(
+        &self,
+        queue: &wgpu::Queue,
+        slot: u32,
+        meta: &DrawMeta,
+        vertices: &[u8],
+        indices: &[u32],
+    ) {
+        assert!(slot < MAX_SLOTS, "slot {slot} out of range");
+        let vertex_bytes = (meta.vertex_count * VERTEX_BYTES) as usize;
+        if vertex_bytes > 0 {
+            queue.write_buffer(
+                &self.vertex_pool,
+                meta.vertex_offset as u64 * VERTEX_BYTES as u64,
+                &vertices[..vertex_bytes],
+            );
+            queue.write_buffer(
+                &self.index_pool,
+                meta.index_offset as u64 * INDEX_BYTES as u64,
+                bytemuck::cast_slice(&indices[..meta.index_count as usize]),
+            );
+        }
+        let entry = [meta.vertex_offset, meta.vertex_count, meta.index_offset, meta.index_count, 0];
+        queue.write_buffer(
+            &self.mesh_offset_table,
+            slot as u64 * MESH_OFFSET_ENTRY_BYTES as u64,
+            bytemuck::cast_slice(&entry),
+        );
+        self.upload_draw_meta(queue, slot, meta);
+        queue.write_buffer(&self.visibility_buf, slot as u64 * 4, bytemuck::bytes_of(&1u32));
+        // Same layout build_indirect writes: DrawIndexedIndirect, 5 × u32
+        let args = [
+            meta.index_count,
+            (meta.index_count > 0) as u32,
+            meta.index_offset,
+            meta.vertex_offset,
+            0,
+        ];
+        queue.write_buffer(
+            &self.indirect_draw_buf,
+            slot as u64 * DRAW_INDIRECT_BYTES as u64,
+            bytemuck::cast_slice(&args),
+        );
+    }
+
+    /// Write CPU-computed I-3 outputs (summary, flags, AABB) for one slot,
+    /// in place of a summary dispatch over the whole pool.
+    pub fn upload_summary(&self, queue: &wgpu::Queue, slot: u32, summary: &SummaryResult) {
+        assert!(slot < MAX_SLOTS, "slot {slot} out of range");
+        queue.write_buffer(
+            &self.summary_buf,
+            slot as u64 * SUMMARY_BYTES_PER_SLOT as u64,
+            bytemuck::cast_slice(&summary.summary),
+        );
+        queue.write_buffer(
+            &self.flags_buf,
+            slot as u64 * FLAGS_BYTES as u64,
+            bytemuck::bytes_of(&summary.flags),
+        );
+        queue.write_buffer(
+            &self.aabb_buf,
+            slot as u64 * AABB_BYTES as u64,
+            bytemuck::cast_slice(&[summary.aabb_min, summary.aabb_max]),
+        );
+    }
+
     /// Upload draw metadata for a single slot from CPU-side data.
     pub fn upload_draw_meta(&self, queue: &wgpu::Queue, slot: u32, meta: &DrawMeta) {
         assert!(slot < MAX_SLOTS, "slot {slot} out of range");
@@ -831,9 +986,89 @@ impl ChunkPool {
     }
     pub fn reset_index_buf_alloc(&mut self) {
         self.index_buf_alloc.reset();
+        self.index_buf_ranges.fill(None);
+    }
+
+    /// Allocate `words` of index_buf for `slot`, freeing the range it held
+    /// before, so a re-upload at the same or smaller size stays in place.
+    /// When only fragmentation stands in the way, the pool is compacted
+    /// first.
+    pub fn alloc_index_buf(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        slot: u32,
+        words: u32,
+    ) -> Result<u32, AllocError> {
+        assert!(slot < MAX_SLOTS, "slot {slot} out of range");
+        if let Some((offset, old_words)) = self.index_buf_ranges[slot as usize].take() {
+            self.index_buf_alloc.free(offset, old_words);
+        }
+        let offset = match self.index_buf_alloc.alloc(words) {
+            Some(offset) => offset,
+            None if self.index_buf_alloc.stats().free_words >= words => {
+                self.compact_index_buf(device, queue);
+                self.index_buf_alloc.alloc(words).ok_or(AllocError::IndexBufFull)?
+            }
+            None => return Err(AllocError::IndexBufFull),
+        };
+        self.index_buf_ranges[slot as usize] = Some((offset, words));
+        Ok(offset)
+    }
+
+    /// Close the gaps in `index_buf_pool`: live ranges are copied down
+    /// through a staging buffer and the moved slots' palette_meta offsets
+    /// rewritten. Returns the number of ranges moved.
+    pub fn compact_index_buf(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> u32 {
+        let moves = self.index_buf_alloc.compact();
+        if moves.is_empty() {
+            return 0;
+        }
+        let scratch = self.index_buf_scratch.get_or_insert_with(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("index-buf-compact-scratch"),
+                size: INDEX_BUF_COMPACT_SCRATCH_BYTES,
+                usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+
+        // WebGPU forbids copies within one buffer, so each move bounces
+        // through the scratch buffer. Moves only go down and run in offset
+        // order, so piecewise front-to-back copies never clobber live data.
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("index-buf-compact"),
+        });
+        for m in &moves {
+            let (from, to, len) = (m.from as u64 * 4, m.to as u64 * 4, m.words as u64 * 4);
+            let mut done = 0;
+            while done < len {
+                let n = (len - done).min(INDEX_BUF_COMPACT_SCRATCH_BYTES);
+                encoder.copy_buffer_to_buffer(&self.index_buf_pool, from + done, scratch, 0, n);
+                encoder.copy_buffer_to_buffer(scratch, 0, &self.index_buf_pool, to + done, n);
+                done += n;
+            }
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        // Queue writes land after the submitted copies
+        let moved: HashMap<u32, u32> = moves.iter().map(|m| (m.from, m.to)).collect();
+        for (slot, range) in self.index_buf_ranges.iter_mut().enumerate() {
+            let Some((offset, _)) = range else { continue };
+            if let Some(&to) = moved.get(offset) {
+                *offset = to;
+                queue.write_buffer(
+                    &self.palette_meta_buf,
+                    slot as u64 * PALETTE_META_BYTES as u64 + 4,
+                    bytemuck::bytes_of(&to),
+                );
+            }
+        }
+        moves.len() as u32
     }
-    pub fn alloc_index_buf(&mut self, words: u32) -> u32 {
-        self.index_buf_alloc.alloc(words)
+
+    pub fn index_buf_stats(&self) -> IndexBufStats {
+        self.index_buf_alloc.stats()
     }
 
     /// Upload scene params: grid_origin (xyz) + voxel_size (w) packed as vec4f.
@@ -895,52 +1130,55 @@ impl ChunkPool {
 
     /// Rebuild and upload the GPU slot table from the current allocator state.
     /// Call after loading/unloading chunks.
-    pub fn upload_slot_table(&self, queue: &wgpu::Queue) {
-        // Compute bounding box of all allocated chunk coords
-        let mut min_coord = [i32::MAX; 3];
-        let mut max_coord = [i32::MIN; 3];
-        let mut has_any = false;
-
-        for (_slot, coord) in self.allocator.allocated_slots() {
-            has_any = true;
-            min_coord[0] = min_coord[0].min(coord.x);
-            min_coord[1] = min_coord[1].min(coord.y);
-            min_coord[2] = min_coord[2].min(coord.z);
-            max_coord[0] = max_coord[0].max(coord.x);
-            max_coord[1] = max_coord[1].max(coord.y);
-            max_coord[2] = max_coord[2].max(coord.z);
+    pub fn upload_slot_table(&mut self, queue: &wgpu::Queue) {
+        self.slot_table.clear();
+        for (slot, coord) in self.allocator.allocated_slots() {
+            // Can't fail: the table holds twice MAX_SLOTS entries
+            self.slot_table.insert(coord, slot);
         }
+        self.slot_table.take_dirty();
+        queue.write_buffer(&self.slot_table_buf, 0, bytemuck::cast_slice(self.slot_table.words()));
+        let params = self.slot_table.params();
+        queue.write_buffer(&self.slot_table_params_buf, 0, bytemuck::cast_slice(&params));
+    }
 
-        if !has_any {
-            return;
+    /// Point the slot table entry for `coord` at `slot` (`SLOT_TABLE_SENTINEL`
+    /// removes it), writing only the entries that changed. Fails with
+    /// `AllocError::SlotTableFull` if `coord` can't be added, leaving the
+    /// chunk unreachable by GI traversal.
+    pub fn patch_slot_table(
+        &mut self,
+        queue: &wgpu::Queue,
+        coord: ChunkCoord,
+        slot: u32,
+    ) -> Result<(), AllocError> {
+        let inserted = if slot == SLOT_TABLE_SENTINEL {
+            self.slot_table.remove(coord);
+            true
+        } else {
+            self.slot_table.insert(coord, slot)
+        };
+        for idx in self.slot_table.take_dirty() {
+            let offset = idx as u64 * SLOT_TABLE_ENTRY_WORDS as u64 * 4;
+            let entry = self.slot_table.entry(idx);
+            queue.write_buffer(&self.slot_table_buf, offset, bytemuck::cast_slice(&entry));
         }
+        let params = self.slot_table.params();
+        queue.write_buffer(&self.slot_table_params_buf, 0, bytemuck::cast_slice(&params));
+        if inserted { Ok(()) } else { Err(AllocError::SlotTableFull) }
+    }
 
-        // Center the origin so coords map into [0, DIM)
-        let dim = SLOT_TABLE_DIM as i32;
-        let origin = [
-            (min_coord[0] + max_coord[0]) / 2 - dim / 2,
-            (min_coord[1] + max_coord[1]) / 2 - dim / 2,
-            (min_coord[2] + max_coord[2]) / 2 - dim / 2,
-        ];
-
-        // Fill table with sentinel
-        let mut table = vec![SLOT_TABLE_SENTINEL; SLOT_TABLE_ENTRIES as usize];
-
-        for (slot, coord) in self.allocator.allocated_slots() {
-            let lx = coord.x - origin[0];
-            let ly = coord.y - origin[1];
-            let lz = coord.z - origin[2];
-            if lx >= 0 && lx < dim && ly >= 0 && ly < dim && lz >= 0 && lz < dim {
-                let idx = lx as u32 + ly as u32 * SLOT_TABLE_DIM + lz as u32 * SLOT_TABLE_DIM * SLOT_TABLE_DIM;
-                table[idx as usize] = slot;
-            }
+    /// Slot table occupancy, counting resident chunks it can't reach.
+    pub fn slot_table_stats(&self) -> SlotTableStats {
+        let reachable = self.allocator.allocated_slots()
+            .filter(|&(slot, coord)| self.slot_table.lookup(coord) == Some(slot))
+            .count() as u32;
+        SlotTableStats {
+            entries: self.slot_table.len(),
+            capacity: self.slot_table.capacity(),
+            max_probe: self.slot_table.max_probe(),
+            unreachable: self.allocator.resident_count() - reachable,
         }
-
-        queue.write_buffer(&self.slot_table_buf, 0, bytemuck::cast_slice(&table));
-
-        // Upload params: origin.xyz + dim
-        let params: [i32; 4] = [origin[0], origin[1], origin[2], dim];
-        queue.write_buffer(&self.slot_table_params_buf, 0, bytemuck::cast_slice(&params));
     }
 }
 
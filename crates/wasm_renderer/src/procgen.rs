@@ -0,0 +1,517 @@
+//! Seeded procedural worlds — no GPU, no JS types.
+//!
+//! A fractal Perlin heightfield gives the surface (grass over dirt over
+//! stone), pairs of 3D noise fields carve tunnel caves where both are near
+//! zero, and further 3D fields lay ore veins into the stone. Every noise
+//! value is a pure function of the seed and the global voxel position, so
+//! [`generate_chunk`] can build any [`ChunkCoord`] on its own, in any order,
+//! and always gets the same voxels. Only `+ - * /` and `floor` touch floats,
+//! which keeps results identical across targets.
+//!
+//! 3D fields are sampled every [`LATTICE`] voxels and interpolated, which is
+//! both much cheaper than per-voxel noise and smooth enough at these scales.
+
+use std::fmt;
+
+use crate::pool::*;
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
+use crate::voxelizer_cpu::VoxelizeResult;
+
+/// Material IDs used by procedural worlds.
+pub const MAT_GRASS: u16 = 2;
+pub const MAT_DIRT: u16 = 3;
+pub const MAT_STONE: u16 = 4;
+/// First vein material; vein `i` of [`WorldOptions::veins`] uses `MAT_VEIN + i`.
+pub const MAT_VEIN: u16 = 5;
+
+/// Spacing of the 3D noise lattice in voxels.
+pub const LATTICE: i32 = 4;
+
+/// Dirt layers between the grass and the stone.
+const DIRT_DEPTH: i32 = 3;
+
+/// Why a world could not be generated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldError {
+    /// The requested chunk range spans more chunks than the pool has slots.
+    TooManyChunks { span: u64 },
+}
+
+impl fmt::Display for WorldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldError::TooManyChunks { span } => write!(
+                f,
+                "world spans {span} chunks, more than the {MAX_SLOTS} pool slots"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WorldError {}
+
+/// An ore or material vein: voxels of stone where the vein's noise field
+/// exceeds `threshold`.
+#[derive(Clone, Copy)]
+pub struct Vein {
+    pub material: MaterialEntry,
+    /// Feature size in voxels.
+    pub scale: f32,
+    /// Noise level above which stone becomes vein, roughly 0.3 (common) to
+    /// 0.6 (rare).
+    pub threshold: f32,
+    /// Veins only form at or below this voxel Y.
+    pub max_height: i32,
+}
+
+/// Shape of a procedural world.
+#[derive(Clone)]
+pub struct WorldOptions {
+    pub seed: u64,
+    /// Mean surface height in voxels.
+    pub base_height: f32,
+    /// Largest surface deviation from `base_height`, in voxels.
+    pub amplitude: f32,
+    /// Horizontal size of the largest hills, in voxels.
+    pub hill_scale: f32,
+    /// Heightfield octaves; each adds detail at half the size and amplitude.
+    pub octaves: u32,
+    /// Size of cave tunnel bends, in voxels.
+    pub cave_scale: f32,
+    /// Tunnel width as a noise band around zero; 0 disables caves.
+    pub cave_width: f32,
+    /// At most `MAX_PALETTE_ENTRIES - 4` (palette slots left after empty,
+    /// grass, dirt and stone).
+    pub veins: Vec<Vein>,
+}
+
+impl Default for WorldOptions {
+    fn default() -> Self {
+        let vein = |albedo, roughness, scale, threshold, max_height| Vein {
+            material: MaterialEntry::new(albedo, roughness, [0.0; 3], 1.0),
+            scale,
+            threshold,
+            max_height,
+        };
+        Self {
+            seed: 0,
+            base_height: 32.0,
+            amplitude: 24.0,
+            hill_scale: 128.0,
+            octaves: 5,
+            cave_scale: 48.0,
+            cave_width: 0.08,
+            veins: vec![
+                // Coal: common, everywhere
+                vein([0.08, 0.08, 0.08], 0.9, 12.0, 0.45, 64),
+                // Iron: mid depth
+                vein([0.62, 0.45, 0.35], 0.7, 10.0, 0.5, 16),
+                // Gold: rare, deep
+                vein([0.83, 0.69, 0.22], 0.3, 8.0, 0.55, -16),
+            ],
+        }
+    }
+}
+
+/// Material table for a world: grass, dirt, stone, then one entry per vein.
+pub fn world_materials(options: &WorldOptions) -> Vec<MaterialEntry> {
+    let mut table = vec![MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0); MAX_MATERIALS as usize];
+    table[MATERIAL_DEFAULT as usize] = MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0);
+    table[MAT_GRASS as usize] = MaterialEntry::new([0.24, 0.42, 0.14], 0.9, [0.0; 3], 1.0);
+    table[MAT_DIRT as usize] = MaterialEntry::new([0.36, 0.25, 0.16], 0.95, [0.0; 3], 1.0);
+    table[MAT_STONE as usize] = MaterialEntry::new([0.42, 0.41, 0.40], 0.85, [0.0; 3], 1.0);
+    for (i, vein) in options.veins.iter().enumerate() {
+        if let Some(entry) = table.get_mut(MAT_VEIN as usize + i) {
+            *entry = vein.material;
+        }
+    }
+    table
+}
+
+/// Generate the chunk at `coord`. `None` if it holds no voxels (all air).
+pub fn generate_chunk(coord: ChunkCoord, options: &WorldOptions) -> Option<ChunkData> {
+    let cs = CS as i32;
+    let base = [coord.x * cs, coord.y * cs, coord.z * cs];
+    let seeds = Seeds::new(options.seed);
+
+    // Surface height per column
+    let mut tops = [[0i32; CS as usize]; CS as usize];
+    let mut highest = i32::MIN;
+    for (lx, row) in tops.iter_mut().enumerate() {
+        for (lz, top) in row.iter_mut().enumerate() {
+            let (x, z) = ((base[0] + lx as i32) as f32, (base[2] + lz as i32) as f32);
+            let scale = options.hill_scale;
+            let n = fbm2(seeds.height, x / scale, z / scale, options.octaves);
+            *top = (options.base_height + n * options.amplitude).floor() as i32;
+            highest = highest.max(*top);
+        }
+    }
+    if highest < base[1] {
+        return None;
+    }
+
+    // 3D fields on the lattice, only where the chunk has ground
+    let lattice = |seed, scale| Lattice::sample(base, seed, scale);
+    let caves = (options.cave_width > 0.0).then(|| {
+        (lattice(seeds.cave_a, options.cave_scale), lattice(seeds.cave_b, options.cave_scale))
+    });
+    let veins: Vec<(u16, Lattice, &Vein)> = options
+        .veins
+        .iter()
+        .take(MAX_PALETTE_ENTRIES as usize - 4)
+        .enumerate()
+        .filter(|(_, v)| v.max_height >= base[1])
+        .map(|(i, v)| (MAT_VEIN + i as u16, lattice(seeds.vein(i), v.scale), v))
+        .collect();
+
+    let mut occupancy = OccupancyBuilder::new();
+    let mut palette = PaletteBuilder::new();
+    let mut index_buf = IndexBufBuilder::new();
+    let mut palette_index = std::collections::HashMap::new();
+    for (lx, row) in tops.iter().enumerate() {
+        for (lz, &top) in row.iter().enumerate() {
+            let hi = top.min(base[1] + cs - 1);
+            for y in base[1]..=hi {
+                let l = [lx as i32, y - base[1], lz as i32];
+                if let Some((a, b)) = &caves {
+                    let w = options.cave_width;
+                    if a.at(l).abs() < w && b.at(l).abs() < w {
+                        continue;
+                    }
+                }
+                let material = match top - y {
+                    0 => MAT_GRASS,
+                    d if d <= DIRT_DEPTH => MAT_DIRT,
+                    _ => veins
+                        .iter()
+                        .find(|(_, field, v)| y <= v.max_height && field.at(l) > v.threshold)
+                        .map_or(MAT_STONE, |&(material, ..)| material),
+                };
+                let index = *palette_index.entry(material).or_insert_with(|| palette.add(material));
+                let [px, py, pz] = l.map(|c| c as u32 + 1);
+                occupancy.set(px, py, pz);
+                index_buf.set(px, py, pz, index);
+            }
+        }
+    }
+
+    (occupancy.popcount() > 0).then_some(ChunkData { coord, occupancy, palette, index_buf })
+}
+
+/// Generate every non-empty chunk with coords in `min..=max`. Ranges that
+/// span more than [`MAX_SLOTS`] chunks are rejected before any work.
+pub fn generate_world(
+    options: &WorldOptions,
+    min: ChunkCoord,
+    max: ChunkCoord,
+) -> Result<VoxelizeResult, WorldError> {
+    let (lo, hi) = ([min.x, min.y, min.z], [max.x, max.y, max.z]);
+    let span = (0..3)
+        .map(|a| (hi[a] as i64 - lo[a] as i64 + 1).max(0) as u64)
+        .fold(1u64, u64::saturating_mul);
+    if span > MAX_SLOTS as u64 {
+        return Err(WorldError::TooManyChunks { span });
+    }
+    let mut chunks = Vec::new();
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                chunks.extend(generate_chunk(ChunkCoord { x, y, z }, options));
+            }
+        }
+    }
+    let cs = CS as f32;
+    let lo = [min.x, min.y, min.z].map(|c| c as f32 * cs);
+    let hi = [max.x, max.y, max.z].map(|c| (c + 1) as f32 * cs);
+    Ok(VoxelizeResult {
+        chunks,
+        materials: world_materials(options),
+        voxel_size: 1.0,
+        grid_origin: [0.0; 3],
+        mesh_center: [0, 1, 2].map(|a| (lo[a] + hi[a]) * 0.5),
+        mesh_extent: (0..3).map(|a| hi[a] - lo[a]).fold(0.0, f32::max),
+        quantize: Default::default(),
+    })
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+/// Independent seeds for each noise field, derived from the world seed.
+struct Seeds {
+    height: u64,
+    cave_a: u64,
+    cave_b: u64,
+    root: u64,
+}
+
+impl Seeds {
+    fn new(seed: u64) -> Self {
+        Self {
+            height: mix(seed ^ 0x4845_4947_4854_0000),
+            cave_a: mix(seed ^ 0x4341_5645_4100),
+            cave_b: mix(seed ^ 0x4341_5645_4200),
+            root: seed,
+        }
+    }
+
+    fn vein(&self, i: usize) -> u64 {
+        mix(self.root ^ 0x5645_494E_0000 ^ i as u64)
+    }
+}
+
+/// A 3D noise field sampled every [`LATTICE`] voxels over one chunk.
+struct Lattice {
+    /// Voxels from sample `(0, 0, 0)` to the chunk's first interior voxel.
+    gap: [i32; 3],
+    values: Vec<f32>,
+}
+
+/// Lattice samples per axis: enough to cover `CS` voxels plus a gap of up
+/// to `LATTICE - 1`, and the far corner of the last cell.
+const LATTICE_DIM: usize = ((CS as i32 + 2 * LATTICE - 2) / LATTICE + 1) as usize;
+
+impl Lattice {
+    fn sample(base: [i32; 3], seed: u64, scale: f32) -> Self {
+        let origin = base.map(|b| b.div_euclid(LATTICE) * LATTICE);
+        let mut values = Vec::with_capacity(LATTICE_DIM.pow(3));
+        for i in 0..LATTICE_DIM as i32 {
+            for j in 0..LATTICE_DIM as i32 {
+                for k in 0..LATTICE_DIM as i32 {
+                    let p = [i, j, k].map(|c| c * LATTICE);
+                    let p = [0, 1, 2].map(|a| (origin[a] + p[a]) as f32 / scale);
+                    values.push(perlin3(seed, p));
+                }
+            }
+        }
+        Self { gap: [0, 1, 2].map(|a| base[a] - origin[a]), values }
+    }
+
+    /// Trilinear value at chunk-local voxel `l` (0-based interior coords).
+    fn at(&self, l: [i32; 3]) -> f32 {
+        let dim = LATTICE_DIM;
+        let mut cell = [0usize; 3];
+        let mut t = [0f32; 3];
+        for a in 0..3 {
+            let offset = l[a] + self.gap[a];
+            cell[a] = (offset / LATTICE) as usize;
+            t[a] = (offset % LATTICE) as f32 / LATTICE as f32;
+        }
+        let v = |i: usize, j: usize, k: usize| {
+            self.values[((cell[0] + i) * dim + cell[1] + j) * dim + cell[2] + k]
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let x00 = lerp(v(0, 0, 0), v(1, 0, 0), t[0]);
+        let x10 = lerp(v(0, 1, 0), v(1, 1, 0), t[0]);
+        let x01 = lerp(v(0, 0, 1), v(1, 0, 1), t[0]);
+        let x11 = lerp(v(0, 1, 1), v(1, 1, 1), t[0]);
+        lerp(lerp(x00, x10, t[1]), lerp(x01, x11, t[1]), t[2])
+    }
+}
+
+/// SplitMix64 finalizer.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Hash of a lattice corner.
+fn corner_hash(seed: u64, x: i32, y: i32, z: i32) -> u64 {
+    let h = seed ^ (x as u32 as u64).wrapping_mul(0x8CB9_2BA7_2F3D_8DD7);
+    let h = mix(h ^ (y as u32 as u64).wrapping_mul(0xD6E8_FEB8_6659_FD93));
+    mix(h ^ (z as u32 as u64).wrapping_mul(0xA076_1D64_78BD_642F))
+}
+
+/// Quintic fade curve.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Improved Perlin noise, roughly -1..1.
+fn perlin3(seed: u64, p: [f32; 3]) -> f32 {
+    let cell = p.map(|c| c.floor());
+    let f = [0, 1, 2].map(|a| p[a] - cell[a]);
+    let c = cell.map(|c| c as i32);
+    // Gradients toward the 12 cube edge midpoints
+    let grad = |i: i32, j: i32, k: i32| {
+        let (x, y, z) = (f[0] - i as f32, f[1] - j as f32, f[2] - k as f32);
+        match corner_hash(seed, c[0] + i, c[1] + j, c[2] + k) % 12 {
+            0 => x + y,
+            1 => -x + y,
+            2 => x - y,
+            3 => -x - y,
+            4 => x + z,
+            5 => -x + z,
+            6 => x - z,
+            7 => -x - z,
+            8 => y + z,
+            9 => -y + z,
+            10 => y - z,
+            _ => -y - z,
+        }
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let u = f.map(fade);
+    let x00 = lerp(grad(0, 0, 0), grad(1, 0, 0), u[0]);
+    let x10 = lerp(grad(0, 1, 0), grad(1, 1, 0), u[0]);
+    let x01 = lerp(grad(0, 0, 1), grad(1, 0, 1), u[0]);
+    let x11 = lerp(grad(0, 1, 1), grad(1, 1, 1), u[0]);
+    lerp(lerp(x00, x10, u[1]), lerp(x01, x11, u[1]), u[2])
+}
+
+/// Fractal sum of 2D Perlin noise (a slice of the 3D field), normalized to
+/// roughly -1..1.
+fn fbm2(seed: u64, x: f32, z: f32, octaves: u32) -> f32 {
+    let (mut sum, mut amplitude, mut frequency, mut norm) = (0.0, 1.0, 1.0, 0.0);
+    for octave in 0..octaves.max(1) {
+        sum += amplitude * perlin3(seed ^ octave as u64, [x * frequency, 0.5, z * frequency]);
+        norm += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / norm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// FNV-1a over everything that would be uploaded for a chunk.
+    fn chunk_hash(chunk: &ChunkData) -> u64 {
+        let bpe = IndexBufBuilder::bits_per_entry(chunk.palette.len());
+        let words = chunk
+            .occupancy
+            .as_words()
+            .iter()
+            .chain(&chunk.palette.as_words())
+            .chain(&chunk.index_buf.pack(bpe))
+            .copied()
+            .collect::<Vec<u32>>();
+        let mut hash = 0xCBF2_9CE4_8422_2325u64;
+        let coord = [chunk.coord.x, chunk.coord.y, chunk.coord.z].map(|c| c as u32);
+        for word in coord.iter().chain(&words) {
+            for byte in word.to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x100_0000_01B3);
+            }
+        }
+        hash
+    }
+
+    fn hashes(options: &WorldOptions, coords: &[ChunkCoord]) -> Vec<Option<u64>> {
+        coords.iter().map(|&c| generate_chunk(c, options).as_ref().map(chunk_hash)).collect()
+    }
+
+    fn coords() -> Vec<ChunkCoord> {
+        [(0, 0, 0), (-1, 0, 2), (3, -1, -2), (0, 1, 0), (0, -2, 0)]
+            .map(|(x, y, z)| ChunkCoord { x, y, z })
+            .to_vec()
+    }
+
+    #[test]
+    fn chunks_are_deterministic_and_order_independent() {
+        let options = WorldOptions { seed: 42, ..WorldOptions::default() };
+        let forward = hashes(&options, &coords());
+        let mut reversed = coords();
+        reversed.reverse();
+        let mut backward = hashes(&options, &reversed);
+        backward.reverse();
+        assert_eq!(forward, backward);
+
+        // Pinned hashes: any change to the noise or layering shows up here
+        assert_eq!(
+            forward,
+            vec![
+                Some(0x7025_1A1E_3C57_3E33),
+                Some(0x636F_33DB_DDD2_74C1),
+                Some(0x5734_DEDB_DCE2_57B5),
+                None,
+                Some(0x5DF9_27D3_B5A6_3322),
+            ]
+        );
+
+        let other = WorldOptions { seed: 43, ..WorldOptions::default() };
+        assert_ne!(hashes(&other, &coords())[0], forward[0]);
+    }
+
+    #[test]
+    fn layers_and_features() {
+        let options = WorldOptions { seed: 7, ..WorldOptions::default() };
+        let seeds = Seeds::new(options.seed);
+        let coord = ChunkCoord { x: 0, y: 0, z: 0 };
+        let chunk = generate_chunk(coord, &options).unwrap();
+        let material = |x: u32, y: u32, z: u32| {
+            chunk
+                .occupancy
+                .get(x, y, z)
+                .then(|| chunk.palette.entries()[chunk.index_buf.get(x, y, z) as usize])
+        };
+
+        // Grass on top of each uncarved column, nothing above it
+        let mut grass = 0;
+        for lx in 0..CS {
+            for lz in 0..CS {
+                let n = fbm2(seeds.height, lx as f32 / 128.0, lz as f32 / 128.0, options.octaves);
+                let top = (options.base_height + n * options.amplitude).floor() as i32;
+                assert!((0..CS as i32 - 1).contains(&top), "surface inside chunk 0");
+                let py = top as u32 + 1;
+                assert_eq!(material(lx + 1, py + 1, lz + 1), None);
+                match material(lx + 1, py, lz + 1) {
+                    Some(MAT_GRASS) => grass += 1,
+                    None => {} // cave opening
+                    other => panic!("unexpected surface {other:?}"),
+                }
+            }
+        }
+        assert!(grass > (CS * CS * 9 / 10) as i32);
+
+        // Deep chunks are mostly stone, with some caves and veins
+        let deep = generate_chunk(ChunkCoord { x: 0, y: -2, z: 0 }, &options).unwrap();
+        let count = deep.occupancy.popcount();
+        assert!(count < CS * CS * CS, "caves carve some voxels");
+        assert!(count > CS * CS * CS * 3 / 4);
+        assert!(deep.palette.entries().contains(&MAT_STONE));
+        assert!(deep.palette.entries().iter().any(|&m| m >= MAT_VEIN));
+
+        // Sky
+        assert!(generate_chunk(ChunkCoord { x: 0, y: 2, z: 0 }, &options).is_none());
+    }
+
+    #[test]
+    fn oversized_worlds_rejected() {
+        let options = WorldOptions::default();
+        let min = ChunkCoord { x: 0, y: -2, z: 0 };
+        let max = ChunkCoord { x: 199, y: 1, z: 199 };
+        match generate_world(&options, min, max) {
+            Err(WorldError::TooManyChunks { span }) => assert_eq!(span, 200 * 200 * 4),
+            _ => panic!("expected TooManyChunks"),
+        }
+        let far = ChunkCoord { x: i32::MAX, y: i32::MAX, z: i32::MAX };
+        let min = ChunkCoord { x: i32::MIN, y: i32::MIN, z: i32::MIN };
+        assert!(matches!(
+            generate_world(&options, min, far),
+            Err(WorldError::TooManyChunks { span: u64::MAX })
+        ));
+        // An empty range is fine
+        let result = generate_world(&options, max, min).unwrap();
+        assert!(result.chunks.is_empty());
+    }
+
+    #[test]
+    fn lattice_matches_direct_noise_at_lattice_points() {
+        let base = [-62, 62, 124];
+        let lattice = Lattice::sample(base, 9, 20.0);
+        for l in [[2, 2, 0], [6, 10, 0], [58, 54, 60]] {
+            let g = [0, 1, 2].map(|a| base[a] + l[a]);
+            assert!(g.iter().all(|c| c % LATTICE == 0));
+            let direct = perlin3(9, g.map(|c| c as f32 / 20.0));
+            assert!((lattice.at(l) - direct).abs() < 1e-6);
+        }
+        // Noise stays in range and is zero on integer lattice points
+        for i in 0..1000 {
+            let p = [i as f32 * 0.37, i as f32 * 0.11 - 50.0, i as f32 * 0.73];
+            assert!(perlin3(1, p).abs() <= 1.5);
+        }
+        assert_eq!(perlin3(1, [3.0, -2.0, 5.0]), 0.0);
+    }
+}
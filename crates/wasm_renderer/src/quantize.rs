@@ -0,0 +1,339 @@
+//! Color quantization — no GPU, no JS types.
+//!
+//! True-color sources (textures, vertex colors, merged models) can produce
+//! more colors than the 4096-entry material table or a 256-entry chunk
+//! palette holds. Colors are reduced with a voxel-weighted median cut in
+//! Oklab, where equal distances look about equally different, and every
+//! reduction reports its error as Oklab distance ([`QuantizeStats`]).
+//!
+//! Two stages use it: the voxelizer turns colored voxels into at most the
+//! free material entries, then each chunk folds its rarest materials into
+//! similar ones until its palette fits ([`limit_palette`]).
+
+use std::collections::HashMap;
+
+use crate::pool::MAX_PALETTE_ENTRIES;
+use crate::scene::MaterialEntry;
+
+/// A color to quantize.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorItem {
+    /// Coordinates in the space being cut (Oklab for everything in this
+    /// module).
+    pub color: [f32; 3],
+    /// Voxels with this color.
+    pub weight: u64,
+    /// Caller's handle for the color; also the tie-breaker for splits.
+    pub id: u32,
+}
+
+/// How much a reduction changed its colors.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QuantizeStats {
+    /// Distinct colors before and after.
+    pub colors_in: usize,
+    pub colors_out: usize,
+    /// Voxels covered.
+    pub voxels: u64,
+    /// Voxel-weighted mean and largest Oklab distance from a color to its
+    /// representative. Oklab lightness runs 0–1; 0.02 is roughly the
+    /// smallest visible step.
+    pub mean_error: f32,
+    pub max_error: f32,
+}
+
+impl QuantizeStats {
+    /// Stats for `boxes` whose members are replaced by `representatives`.
+    pub fn measure(boxes: &[Vec<ColorItem>], representatives: &[[f32; 3]]) -> Self {
+        let mut stats = Self {
+            colors_in: boxes.iter().map(Vec::len).sum(),
+            colors_out: boxes.len(),
+            ..Self::default()
+        };
+        let mut error_sum = 0.0f64;
+        for (items, rep) in boxes.iter().zip(representatives) {
+            for item in items {
+                let error = distance(item.color, *rep);
+                error_sum += error as f64 * item.weight as f64;
+                stats.voxels += item.weight;
+                stats.max_error = stats.max_error.max(error);
+            }
+        }
+        if stats.voxels > 0 {
+            stats.mean_error = (error_sum / stats.voxels as f64) as f32;
+        }
+        stats
+    }
+
+    /// Fold in the stats of another, independent reduction.
+    pub fn merge(&mut self, other: &Self) {
+        let voxels = self.voxels + other.voxels;
+        if voxels > 0 {
+            let sum = self.mean_error as f64 * self.voxels as f64
+                + other.mean_error as f64 * other.voxels as f64;
+            self.mean_error = (sum / voxels as f64) as f32;
+        }
+        self.colors_in += other.colors_in;
+        self.colors_out += other.colors_out;
+        self.voxels = voxels;
+        self.max_error = self.max_error.max(other.max_error);
+    }
+}
+
+/// Stats for both quantization stages of a load.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QuantizeReport {
+    /// Colored voxels reduced to material table entries.
+    pub materials: QuantizeStats,
+    /// Materials folded together to fit chunk palettes, summed over chunks.
+    pub palettes: QuantizeStats,
+}
+
+/// Split `boxes` further into at most `max_boxes` groups by repeatedly
+/// halving the group with the widest color range at its weighted median.
+/// Items in different starting boxes never share a group; if there are
+/// already `max_boxes` or more, they are returned as is.
+pub fn median_cut(boxes: Vec<Vec<ColorItem>>, max_boxes: usize) -> Vec<Vec<ColorItem>> {
+    let mut boxes: Vec<Vec<ColorItem>> = boxes.into_iter().filter(|b| !b.is_empty()).collect();
+    // Widest `(range, axis)` per box, refreshed only for the boxes a split
+    // creates
+    let mut widths: Vec<(f32, usize)> = boxes.iter().map(|b| widest_axis(b)).collect();
+    while boxes.len() < max_boxes {
+        let widest = widths
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| boxes[i].len() > 1)
+            // Identical colors still split while boxes remain, so distinct
+            // items keep distinct groups when they fit.
+            .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0).then(b.0.cmp(&a.0)));
+        let Some((index, &(_, axis))) = widest else {
+            break;
+        };
+
+        let mut group = boxes.remove(index);
+        widths.remove(index);
+        group.sort_by(|x, y| x.color[axis].total_cmp(&y.color[axis]).then(x.id.cmp(&y.id)));
+        let total: u64 = group.iter().map(|c| c.weight).sum();
+        let mut acc = 0u64;
+        let mut split = group.len() / 2;
+        for (i, c) in group.iter().enumerate() {
+            acc += c.weight;
+            if acc * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, group.len() - 1);
+        let upper = group.split_off(split);
+        widths.insert(index, widest_axis(&upper));
+        widths.insert(index, widest_axis(&group));
+        boxes.insert(index, upper);
+        boxes.insert(index, group);
+    }
+    boxes
+}
+
+/// Voxel-weighted mean color of a group.
+pub fn mean(items: &[ColorItem]) -> [f32; 3] {
+    let total: f64 = items.iter().map(|c| c.weight as f64).sum::<f64>().max(1.0);
+    [0, 1, 2].map(|a| {
+        let sum: f64 = items.iter().map(|c| c.color[a] as f64 * c.weight as f64).sum();
+        (sum / total) as f32
+    })
+}
+
+/// Member covering the most voxels; ties go to the lower id.
+pub fn heaviest(items: &[ColorItem]) -> Option<&ColorItem> {
+    items.iter().max_by_key(|c| (c.weight, std::cmp::Reverse(c.id)))
+}
+
+/// Fold a chunk's materials into at most `MAX_PALETTE_ENTRIES - 1` (entry 0
+/// is empty). `counts` holds voxels per material ID; the result maps each
+/// merged ID to the most used material of its group, by albedo. Empty when
+/// the materials already fit.
+pub fn limit_palette(
+    counts: &HashMap<u16, u32>,
+    materials: &[MaterialEntry],
+) -> (HashMap<u16, u16>, QuantizeStats) {
+    let limit = MAX_PALETTE_ENTRIES as usize - 1;
+    if counts.len() <= limit {
+        return (HashMap::new(), QuantizeStats::default());
+    }
+    let mut items: Vec<ColorItem> = counts
+        .iter()
+        .map(|(&mat, &n)| ColorItem {
+            color: linear_to_oklab(materials.get(mat as usize).map_or([0.5; 3], |m| m.albedo())),
+            weight: n as u64,
+            id: mat as u32,
+        })
+        .collect();
+    items.sort_by_key(|c| c.id);
+
+    let boxes = median_cut(vec![items], limit);
+    let mut remap = HashMap::new();
+    let mut representatives = Vec::with_capacity(boxes.len());
+    for items in &boxes {
+        let Some(rep) = heaviest(items) else { continue };
+        representatives.push(rep.color);
+        for item in items.iter().filter(|c| c.id != rep.id) {
+            remap.insert(item.id as u16, rep.id as u16);
+        }
+    }
+    let stats = QuantizeStats::measure(&boxes, &representatives);
+    (remap, stats)
+}
+
+/// Oklab distance.
+pub fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// sRGB-encoded 0–255 channel to linear 0–1.
+pub fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Linear 0–1 channel to sRGB-encoded 0–255.
+pub fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let encoded = if c <= 0.003_130_8 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (encoded * 255.0).round() as u8
+}
+
+/// Linear sRGB to Oklab (Björn Ottosson's matrices).
+pub fn linear_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| c.max(0.0));
+    let l = (0.412_221_47 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+/// Oklab to linear sRGB, clamped to 0–1.
+pub fn oklab_to_linear(lab: [f32; 3]) -> [f32; 3] {
+    let [l, a, b] = lab;
+    let l_ = (l + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m_ = (l - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s_ = (l - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    [
+        4.076_741_7 * l_ - 3.307_711_6 * m_ + 0.230_969_94 * s_,
+        -1.268_438 * l_ + 2.609_757_4 * m_ - 0.341_319_38 * s_,
+        -0.004_196_086_3 * l_ - 0.703_418_6 * m_ + 1.707_614_7 * s_,
+    ]
+    .map(|c| c.clamp(0.0, 1.0))
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+/// `(range, axis)` of the widest color axis of a box.
+fn widest_axis(items: &[ColorItem]) -> (f32, usize) {
+    let range = [0, 1, 2].map(|a| {
+        let lo = items.iter().map(|c| c.color[a]).fold(f32::MAX, f32::min);
+        let hi = items.iter().map(|c| c.color[a]).fold(f32::MIN, f32::max);
+        hi - lo
+    });
+    let axis = (0..3).max_by(|&x, &y| range[x].total_cmp(&range[y])).unwrap_or(0);
+    (range[axis], axis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(color: [f32; 3], weight: u64, id: u32) -> ColorItem {
+        ColorItem { color, weight, id }
+    }
+
+    #[test]
+    fn median_cut_caps_boxes() {
+        let items: Vec<ColorItem> = (0..1000u32)
+            .map(|i| item([(i % 256) as f32, (i / 4 % 256) as f32, 0.0], 1, i))
+            .collect();
+        let boxes = median_cut(vec![items], 255);
+        assert_eq!(boxes.len(), 255);
+        assert_eq!(boxes.iter().map(|b| b.len()).sum::<usize>(), 1000);
+
+        // Identical colors still get their own box while there is room
+        let few: Vec<ColorItem> = (0..3).map(|i| item([10.0; 3], 1, i)).collect();
+        assert_eq!(median_cut(vec![few], 255).len(), 3);
+    }
+
+    #[test]
+    fn median_cut_keeps_starting_boxes_apart() {
+        let a: Vec<ColorItem> = (0..10).map(|i| item([0.5; 3], 1, i)).collect();
+        let b: Vec<ColorItem> = (10..20).map(|i| item([0.5; 3], 1, i)).collect();
+        let boxes = median_cut(vec![a, b], 5);
+        assert_eq!(boxes.len(), 5);
+        for items in &boxes {
+            assert!(items.iter().all(|c| c.id < 10) || items.iter().all(|c| c.id >= 10));
+        }
+        // Already over the limit: nothing merges
+        let singles: Vec<Vec<ColorItem>> = (0..4).map(|i| vec![item([0.0; 3], 1, i)]).collect();
+        assert_eq!(median_cut(singles, 2).len(), 4);
+    }
+
+    #[test]
+    fn oklab_roundtrip() {
+        assert!(distance(linear_to_oklab([1.0; 3]), [1.0, 0.0, 0.0]) < 1e-3);
+        assert!(distance(linear_to_oklab([0.0; 3]), [0.0; 3]) < 1e-6);
+        for rgb in [[1.0, 0.0, 0.0], [0.2, 0.7, 0.1], [0.05, 0.05, 0.9]] {
+            let back = oklab_to_linear(linear_to_oklab(rgb));
+            for c in 0..3 {
+                assert!((back[c] - rgb[c]).abs() < 1e-3, "{rgb:?} -> {back:?}");
+            }
+        }
+        assert_eq!(srgb_to_linear(255), 1.0);
+        assert!((srgb_to_linear(128) - 0.2158).abs() < 1e-3);
+        assert!((0..=255).all(|c| linear_to_srgb(srgb_to_linear(c)) == c));
+    }
+
+    #[test]
+    fn stats_are_voxel_weighted() {
+        let boxes = vec![
+            vec![item([0.0; 3], 3, 0), item([0.4, 0.0, 0.0], 1, 1)],
+            vec![item([1.0, 0.0, 0.0], 4, 2)],
+        ];
+        let stats = QuantizeStats::measure(&boxes, &[[0.0; 3], [1.0, 0.0, 0.0]]);
+        assert_eq!((stats.colors_in, stats.colors_out, stats.voxels), (3, 2, 8));
+        assert!((stats.mean_error - 0.05).abs() < 1e-6);
+        assert!((stats.max_error - 0.4).abs() < 1e-6);
+
+        let mut merged = stats;
+        let exact = QuantizeStats { colors_in: 1, colors_out: 1, voxels: 8, ..Default::default() };
+        merged.merge(&exact);
+        assert_eq!((merged.colors_in, merged.voxels), (4, 16));
+        assert!((merged.mean_error - 0.025).abs() < 1e-6);
+        assert_eq!(merged.max_error, stats.max_error);
+    }
+
+    #[test]
+    fn palette_limit_merges_similar_materials() {
+        let materials: Vec<_> = (0..400)
+            .map(|i| MaterialEntry::new([i as f32 / 400.0; 3], 0.5, [0.0; 3], 1.0))
+            .collect();
+        let few: HashMap<u16, u32> = (2..257).map(|m| (m, 1)).collect();
+        assert!(limit_palette(&few, &materials).0.is_empty());
+
+        let counts: HashMap<u16, u32> = (2..400).map(|m| (m, 1 + (m % 3) as u32)).collect();
+        let (remap, stats) = limit_palette(&counts, &materials);
+        let kept: std::collections::HashSet<u16> =
+            counts.keys().map(|m| *remap.get(m).unwrap_or(m)).collect();
+        assert_eq!(kept.len(), MAX_PALETTE_ENTRIES as usize - 1);
+        // Representatives are kept materials, and merged grays stay close
+        assert!(remap.values().all(|to| !remap.contains_key(to)));
+        for (&from, &to) in &remap {
+            assert!((from as i32 - to as i32).abs() <= 4, "{from} -> {to}");
+        }
+        assert_eq!((stats.colors_in, stats.colors_out), (398, 255));
+        assert!(stats.max_error > 0.0 && stats.mean_error < stats.max_error);
+    }
+}
@@ -9,6 +9,7 @@ use crate::pool::*;
 
 /// Writable occupancy grid for a single 64³ chunk.
 /// Column-major layout: column_index = x * CS_P + z, bit y in that column's u64.
+#[derive(Clone)]
 pub struct OccupancyBuilder {
     /// 8192 u32 words (4096 columns × 2 words per column).
     words: Vec<u32>,
@@ -54,6 +55,15 @@ impl OccupancyBuilder {
         (self.words[u32_idx] >> bit) & 1 != 0
     }
 
+    /// Rebuild from words previously returned by [`as_words`](Self::as_words).
+    /// Returns `None` if the length is not `OCCUPANCY_WORDS_PER_SLOT`.
+    pub fn from_words(words: &[u32]) -> Option<Self> {
+        if words.len() != OCCUPANCY_WORDS_PER_SLOT as usize {
+            return None;
+        }
+        Some(Self { words: words.to_vec() })
+    }
+
     /// Return the occupancy data as a slice for upload.
     pub fn as_words(&self) -> &[u32] {
         &self.words
@@ -63,12 +73,28 @@ impl OccupancyBuilder {
     pub fn popcount(&self) -> u32 {
         self.words.iter().map(|w| w.count_ones()).sum()
     }
+
+    /// Count occupied voxels inside the usable [1, 62]³ region, skipping the
+    /// padding shell that mirrors neighboring chunks.
+    pub fn interior_popcount(&self) -> u32 {
+        let y_mask = ((1u64 << CS) - 1) << 1;
+        let mut count = 0;
+        for x in 1..CS_P - 1 {
+            for z in 1..CS_P - 1 {
+                let i = ((x * CS_P + z) * 2) as usize;
+                let column = self.words[i] as u64 | (self.words[i + 1] as u64) << 32;
+                count += (column & y_mask).count_ones();
+            }
+        }
+        count
+    }
 }
 
 // ─── Palette builder ────────────────────────────────────────────────────
 
 /// Simple palette: maps voxel positions to material IDs via a per-voxel grid.
 /// For the test scene we use a small fixed palette.
+#[derive(Clone)]
 pub struct PaletteBuilder {
     entries: Vec<u16>,
 }
@@ -82,6 +108,10 @@ impl PaletteBuilder {
 
     /// Add a material to the palette. Returns the palette index.
     /// Silently returns existing index if already present.
+    ///
+    /// A full palette (`MAX_PALETTE_ENTRIES`) returns 0, the empty entry, so
+    /// builders with more materials than that reduce them first with
+    /// [`quantize::limit_palette`](crate::quantize::limit_palette).
     pub fn add(&mut self, material_id: u16) -> u8 {
         if let Some(pos) = self.entries.iter().position(|&m| m == material_id) {
             return pos as u8;
@@ -94,6 +124,19 @@ impl PaletteBuilder {
         (self.entries.len() - 1) as u8
     }
 
+    /// Rebuild a palette of `len` entries from [`as_words`](Self::as_words)
+    /// output. Returns `None` if `len` is 0, exceeds `MAX_PALETTE_ENTRIES`,
+    /// or does not fit in `words`.
+    pub fn from_words(words: &[u32], len: usize) -> Option<Self> {
+        if len == 0 || len > MAX_PALETTE_ENTRIES as usize || words.len() * 2 < len {
+            return None;
+        }
+        let entries = (0..len)
+            .map(|i| (words[i / 2] >> ((i & 1) * 16)) as u16)
+            .collect();
+        Some(Self { entries })
+    }
+
     /// Return palette data packed as u32 words (2 × u16 per word).
     pub fn as_words(&self) -> Vec<u32> {
         let mut words = vec![0u32; (self.entries.len() + 1) / 2];
@@ -108,6 +151,11 @@ impl PaletteBuilder {
     pub fn len(&self) -> usize {
         self.entries.len()
     }
+
+    /// Material IDs in palette order. Entry 0 is always MATERIAL_EMPTY.
+    pub fn entries(&self) -> &[u16] {
+        &self.entries
+    }
 }
 
 // ─── Per-voxel palette index buffer ────────────────────────────────────
@@ -119,6 +167,7 @@ impl PaletteBuilder {
 /// at the specified `bits_per_entry` for GPU upload.
 ///
 /// See: docs/Resident Representation/data/chunk-index-buf.md
+#[derive(Clone)]
 pub struct IndexBufBuilder {
     /// Raw per-voxel palette index. Layout: index_map[x * CS_P² + y * CS_P + z].
     /// 0 for unoccupied voxels (palette entry 0 = MATERIAL_EMPTY).
@@ -179,6 +228,26 @@ impl IndexBufBuilder {
         words
     }
 
+    /// Inverse of [`pack`](Self::pack). Returns `None` if `bpe` is not 1, 2,
+    /// 4 or 8 or `words` is shorter than `pack` produces.
+    pub fn unpack(words: &[u32], bpe: u8) -> Option<Self> {
+        if !matches!(bpe, 1 | 2 | 4 | 8) {
+            return None;
+        }
+        let word_count = (CS_P3 as usize * bpe as usize).div_ceil(32);
+        if words.len() < word_count {
+            return None;
+        }
+        let mask = (1u32 << bpe) - 1;
+        let index_map = (0..CS_P3 as usize)
+            .map(|i| {
+                let bit_offset = i * bpe as usize;
+                ((words[bit_offset >> 5] >> (bit_offset & 31)) & mask) as u8
+            })
+            .collect();
+        Some(Self { index_map })
+    }
+
     /// Build the packed palette_meta u32 for a given palette size.
     ///
     /// Layout: bits 0–15 = palette_size, bits 16–23 = bits_per_entry, bits 24–31 = 0.
@@ -191,6 +260,7 @@ impl IndexBufBuilder {
 // ─── Chunk data container ───────────────────────────────────────────────
 
 /// All CPU-side data needed to upload one chunk.
+#[derive(Clone)]
 pub struct ChunkData {
     pub coord: ChunkCoord,
     pub occupancy: OccupancyBuilder,
@@ -226,6 +296,30 @@ impl MaterialEntry {
             emissive_b_opacity: pack_f16_pair(emissive[2], opacity),
         }
     }
+
+    /// Unpacked RGB albedo.
+    pub fn albedo(&self) -> [f32; 3] {
+        let (r, g) = unpack_f16_pair(self.albedo_rg);
+        let (b, _) = unpack_f16_pair(self.albedo_b_roughness);
+        [r, g, b]
+    }
+
+    /// Unpacked roughness.
+    pub fn roughness(&self) -> f32 {
+        unpack_f16_pair(self.albedo_b_roughness).1
+    }
+
+    /// Unpacked RGB emissive.
+    pub fn emissive(&self) -> [f32; 3] {
+        let (r, g) = unpack_f16_pair(self.emissive_rg);
+        let (b, _) = unpack_f16_pair(self.emissive_b_opacity);
+        [r, g, b]
+    }
+
+    /// Unpacked opacity.
+    pub fn opacity(&self) -> f32 {
+        unpack_f16_pair(self.emissive_b_opacity).1
+    }
 }
 
 /// Pack two f32 values into a u32 as two f16 values.
@@ -235,6 +329,24 @@ fn pack_f16_pair(a: f32, b: f32) -> u32 {
     (a16 as u32) | ((b16 as u32) << 16)
 }
 
+/// Unpack a u32 into its two f16 values as f32.
+fn unpack_f16_pair(packed: u32) -> (f32, f32) {
+    (f16_to_f32(packed as u16), f16_to_f32((packed >> 16) as u16))
+}
+
+/// Convert f16 to f32. Subnormals (never produced by `f32_to_f16`) read as 0.
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+    let bits = match exponent {
+        0 => sign,
+        0x1F => sign | 0x7F80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
 /// Convert f32 to f16 (IEEE 754 half-precision). Truncates, no rounding.
 fn f32_to_f16(val: f32) -> u16 {
     let bits = val.to_bits();
@@ -624,6 +736,18 @@ mod tests {
         assert_eq!(b.popcount(), 3);
     }
 
+    #[test]
+    fn occupancy_builder_interior_popcount() {
+        let mut b = OccupancyBuilder::new();
+        b.set(1, 1, 1);
+        b.set(62, 62, 62);
+        b.set(0, 5, 5);
+        b.set(5, 63, 5);
+        b.set(5, 5, 63);
+        assert_eq!(b.popcount(), 5);
+        assert_eq!(b.interior_popcount(), 2);
+    }
+
     #[test]
     fn occupancy_builder_word_count() {
         let b = OccupancyBuilder::new();
@@ -682,6 +806,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn material_entry_unpack_roundtrip() {
+        let m = MaterialEntry::new([0.8, 0.25, 0.5], 0.75, [4.0, 0.0, 1.5], 0.5);
+        let albedo = m.albedo();
+        for (got, want) in albedo.iter().zip([0.8, 0.25, 0.5]) {
+            assert!((got - want).abs() < 1e-3, "albedo {got} vs {want}");
+        }
+        assert_eq!(m.roughness(), 0.75);
+        assert_eq!(m.emissive(), [4.0, 0.0, 1.5]);
+        assert_eq!(m.opacity(), 0.5);
+    }
+
     #[test]
     fn generate_room_has_walls_floor_ceiling() {
         let chunk = generate_room(ChunkCoord { x: 0, y: 0, z: 0 });
@@ -870,6 +1006,31 @@ mod tests {
         assert_eq!(decode(0, 0, 0), 0);
     }
 
+    #[test]
+    fn builders_roundtrip_through_words() {
+        let mut occ = OccupancyBuilder::new();
+        occ.set(3, 40, 7);
+        let occ2 = OccupancyBuilder::from_words(occ.as_words()).unwrap();
+        assert!(occ2.get(3, 40, 7));
+        assert!(OccupancyBuilder::from_words(&[0; 4]).is_none());
+
+        let mut pal = PaletteBuilder::new();
+        pal.add(MAT_STONE);
+        pal.add(MAT_BLUE);
+        let pal2 = PaletteBuilder::from_words(&pal.as_words(), pal.len()).unwrap();
+        assert_eq!(pal2.entries(), &[MATERIAL_EMPTY, MAT_STONE, MAT_BLUE]);
+        assert!(PaletteBuilder::from_words(&pal.as_words(), 9).is_none());
+
+        let mut ib = IndexBufBuilder::new();
+        ib.set(1, 2, 3, 2);
+        ib.set(63, 63, 63, 1);
+        let ib2 = IndexBufBuilder::unpack(&ib.pack(2), 2).unwrap();
+        assert_eq!(ib2.get(1, 2, 3), 2);
+        assert_eq!(ib2.get(63, 63, 63), 1);
+        assert_eq!(ib2.get(0, 0, 0), 0);
+        assert!(IndexBufBuilder::unpack(&[0; 8], 2).is_none());
+    }
+
     #[test]
     fn index_buf_palette_meta_packing() {
         let meta = IndexBufBuilder::palette_meta(5);
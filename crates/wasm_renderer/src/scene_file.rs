@@ -0,0 +1,512 @@
+//! Native Gestalt scene file — no wasm_bindgen, no JS types.
+//!
+//! Stores resident chunk data exactly as it is uploaded (occupancy words,
+//! palette words, packed index buffer, palette_meta) plus the material table
+//! and scene parameters, so a scene can be reloaded without re-voxelizing.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! header   "GSCN" | version u32 | reserved u32
+//! section  tag [u8; 4] | length u32 | payload[length]
+//! ```
+//!
+//! Sections: `PARM` (scene parameters), `MATS` (material table), one `CHNK`
+//! per chunk, then `END `. Unknown sections are skipped so later versions
+//! can add data without breaking older readers.
+//!
+//! `CHNK` payload: coord (3 × i32), encoding u8, 3 reserved bytes,
+//! palette_meta u32, then the chunk body — occupancy words, palette words
+//! and packed index words — stored raw or word-RLE compressed.
+
+use std::fmt;
+
+use crate::pool::*;
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
+use crate::voxelizer_cpu::VoxelizeResult;
+
+const MAGIC: &[u8; 4] = b"GSCN";
+
+/// Current format version. Readers reject files with a newer version.
+pub const SCENE_FILE_VERSION: u32 = 1;
+
+const TAG_PARAMS: &[u8; 4] = b"PARM";
+const TAG_MATERIALS: &[u8; 4] = b"MATS";
+const TAG_CHUNK: &[u8; 4] = b"CHNK";
+const TAG_END: &[u8; 4] = b"END ";
+
+/// `CHNK` body stored as plain words.
+const ENCODING_RAW: u8 = 0;
+/// `CHNK` body stored with [`rle_encode`].
+const ENCODING_RLE: u8 = 1;
+
+/// RLE token flag: the low 31 bits count repeats of the next word.
+/// Without it they count literal words that follow.
+const RLE_RUN: u32 = 0x8000_0000;
+
+/// Scene parameters stored alongside the chunks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneParams {
+    pub voxel_size: f32,
+    pub grid_origin: [f32; 3],
+    pub mesh_center: [f32; 3],
+    pub mesh_extent: f32,
+}
+
+/// Why a scene file could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneFileError {
+    /// Missing `GSCN` magic.
+    BadMagic,
+    /// Written by a newer format version.
+    UnsupportedVersion(u32),
+    /// A section or field runs past the end of the data.
+    Truncated(&'static str),
+    /// Structurally invalid content.
+    Corrupt(String),
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneFileError::BadMagic => write!(f, "not a Gestalt scene file"),
+            SceneFileError::UnsupportedVersion(v) => write!(
+                f,
+                "scene file version {v} is newer than supported version {SCENE_FILE_VERSION}"
+            ),
+            SceneFileError::Truncated(what) => write!(f, "truncated {what}"),
+            SceneFileError::Corrupt(msg) => write!(f, "corrupt scene file: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneFileError {}
+
+/// Serialize a scene. Chunks are written in coordinate order.
+///
+/// With `compress`, each chunk body is RLE-encoded when that makes it
+/// smaller; otherwise every chunk is stored raw.
+pub fn write_scene<'a>(
+    chunks: impl IntoIterator<Item = &'a ChunkData>,
+    materials: &[MaterialEntry],
+    params: &SceneParams,
+    compress: bool,
+) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&SCENE_FILE_VERSION.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut parm = Vec::with_capacity(32);
+    for v in [params.voxel_size]
+        .into_iter()
+        .chain(params.grid_origin)
+        .chain(params.mesh_center)
+        .chain([params.mesh_extent])
+    {
+        parm.extend_from_slice(&v.to_le_bytes());
+    }
+    write_section(&mut out, TAG_PARAMS, &parm);
+
+    let mut mats = Vec::with_capacity(4 + materials.len() * 16);
+    mats.extend_from_slice(&(materials.len() as u32).to_le_bytes());
+    mats.extend_from_slice(bytemuck::cast_slice(materials));
+    write_section(&mut out, TAG_MATERIALS, &mats);
+
+    let mut sorted: Vec<&ChunkData> = chunks.into_iter().collect();
+    sorted.sort_by_key(|c| (c.coord.x, c.coord.y, c.coord.z));
+    for chunk in sorted {
+        let palette_len = chunk.palette.len();
+        let bpe = IndexBufBuilder::bits_per_entry(palette_len);
+        let mut body: Vec<u32> = chunk.occupancy.as_words().to_vec();
+        body.extend(chunk.palette.as_words());
+        body.extend(chunk.index_buf.pack(bpe));
+
+        let (encoding, body) = match compress {
+            true => {
+                let encoded = rle_encode(&body);
+                if encoded.len() < body.len() {
+                    (ENCODING_RLE, encoded)
+                } else {
+                    (ENCODING_RAW, body)
+                }
+            }
+            false => (ENCODING_RAW, body),
+        };
+
+        let mut payload = Vec::with_capacity(20 + body.len() * 4);
+        for c in [chunk.coord.x, chunk.coord.y, chunk.coord.z] {
+            payload.extend_from_slice(&c.to_le_bytes());
+        }
+        payload.extend_from_slice(&[encoding, 0, 0, 0]);
+        payload.extend_from_slice(&IndexBufBuilder::palette_meta(palette_len).to_le_bytes());
+        payload.extend_from_slice(bytemuck::cast_slice(&body));
+        write_section(&mut out, TAG_CHUNK, &payload);
+    }
+
+    write_section(&mut out, TAG_END, &[]);
+    out
+}
+
+/// Parse a scene file into chunk data, materials and scene parameters.
+pub fn read_scene(bytes: &[u8]) -> Result<VoxelizeResult, SceneFileError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(SceneFileError::BadMagic);
+    }
+    let version = read_u32(bytes, 4).ok_or(SceneFileError::Truncated("header"))?;
+    if version > SCENE_FILE_VERSION {
+        return Err(SceneFileError::UnsupportedVersion(version));
+    }
+    read_u32(bytes, 8).ok_or(SceneFileError::Truncated("header"))?;
+
+    let mut params = None;
+    let mut materials = None;
+    let mut chunks = Vec::new();
+    let mut pos = 12;
+    let mut ended = false;
+    while pos < bytes.len() {
+        let tag = bytes
+            .get(pos..pos + 4)
+            .ok_or(SceneFileError::Truncated("section header"))?;
+        let len =
+            read_u32(bytes, pos + 4).ok_or(SceneFileError::Truncated("section header"))? as usize;
+        let payload = bytes
+            .get(pos + 8..pos + 8 + len)
+            .ok_or(SceneFileError::Truncated("section payload"))?;
+        pos += 8 + len;
+
+        match tag {
+            t if t == TAG_PARAMS => params = Some(read_params(payload)?),
+            t if t == TAG_MATERIALS => materials = Some(read_materials(payload)?),
+            t if t == TAG_CHUNK => chunks.push(read_chunk(payload)?),
+            t if t == TAG_END => {
+                ended = true;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    if !ended {
+        return Err(SceneFileError::Truncated("scene (missing END section)"));
+    }
+    let params =
+        params.ok_or_else(|| SceneFileError::Corrupt("missing PARM section".to_string()))?;
+    let materials =
+        materials.ok_or_else(|| SceneFileError::Corrupt("missing MATS section".to_string()))?;
+    Ok(VoxelizeResult {
+        chunks,
+        materials,
+        voxel_size: params.voxel_size,
+        grid_origin: params.grid_origin,
+        mesh_center: params.mesh_center,
+        mesh_extent: params.mesh_extent,
+        quantize: Default::default(),
+    })
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+fn write_section(out: &mut Vec<u8>, tag: &[u8; 4], payload: &[u8]) {
+    out.extend_from_slice(tag);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn words_of(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn read_params(payload: &[u8]) -> Result<SceneParams, SceneFileError> {
+    if payload.len() < 32 {
+        return Err(SceneFileError::Truncated("PARM section"));
+    }
+    let f: Vec<f32> = words_of(&payload[..32])
+        .into_iter()
+        .map(f32::from_bits)
+        .collect();
+    Ok(SceneParams {
+        voxel_size: f[0],
+        grid_origin: [f[1], f[2], f[3]],
+        mesh_center: [f[4], f[5], f[6]],
+        mesh_extent: f[7],
+    })
+}
+
+fn read_materials(payload: &[u8]) -> Result<Vec<MaterialEntry>, SceneFileError> {
+    let count = read_u32(payload, 0).ok_or(SceneFileError::Truncated("MATS section"))? as usize;
+    if count > MAX_MATERIALS as usize {
+        return Err(SceneFileError::Corrupt(format!(
+            "{count} materials exceeds MAX_MATERIALS"
+        )));
+    }
+    let data = payload
+        .get(4..4 + count * 16)
+        .ok_or(SceneFileError::Truncated("MATS section"))?;
+    Ok(words_of(data)
+        .chunks_exact(4)
+        .map(|w| MaterialEntry {
+            albedo_rg: w[0],
+            albedo_b_roughness: w[1],
+            emissive_rg: w[2],
+            emissive_b_opacity: w[3],
+        })
+        .collect())
+}
+
+fn read_chunk(payload: &[u8]) -> Result<ChunkData, SceneFileError> {
+    if payload.len() < 20 {
+        return Err(SceneFileError::Truncated("CHNK section"));
+    }
+    let head = words_of(&payload[..20]);
+    let coord = ChunkCoord {
+        x: head[0] as i32,
+        y: head[1] as i32,
+        z: head[2] as i32,
+    };
+    let encoding = payload[12];
+    let meta = head[4];
+    let corrupt = |msg: &str| {
+        SceneFileError::Corrupt(format!(
+            "chunk ({},{},{}): {msg}",
+            coord.x, coord.y, coord.z
+        ))
+    };
+
+    let palette_len = (meta & 0xFFFF) as usize;
+    let bpe = ((meta >> 16) & 0xFF) as u8;
+    if palette_len == 0 || palette_len > MAX_PALETTE_ENTRIES as usize {
+        return Err(corrupt("palette size out of range"));
+    }
+    if meta != IndexBufBuilder::palette_meta(palette_len) {
+        return Err(corrupt("palette_meta does not match palette size"));
+    }
+
+    let occ_words = OCCUPANCY_WORDS_PER_SLOT as usize;
+    let pal_words = palette_len.div_ceil(2);
+    let idx_words = (CS_P3 as usize * bpe as usize).div_ceil(32);
+    let expected = occ_words + pal_words + idx_words;
+
+    let raw = words_of(&payload[20..]);
+    let body = match encoding {
+        ENCODING_RAW => raw,
+        ENCODING_RLE => rle_decode(&raw, expected).ok_or_else(|| corrupt("invalid RLE data"))?,
+        other => return Err(corrupt(&format!("unknown encoding {other}"))),
+    };
+    if body.len() != expected {
+        return Err(corrupt("body length does not match palette_meta"));
+    }
+
+    let (occ, rest) = body.split_at(occ_words);
+    let (pal, idx) = rest.split_at(pal_words);
+    Ok(ChunkData {
+        coord,
+        occupancy: OccupancyBuilder::from_words(occ).ok_or_else(|| corrupt("bad occupancy"))?,
+        palette: PaletteBuilder::from_words(pal, palette_len)
+            .ok_or_else(|| corrupt("bad palette"))?,
+        index_buf: IndexBufBuilder::unpack(idx, bpe).ok_or_else(|| corrupt("bad index buffer"))?,
+    })
+}
+
+/// Word-level run-length encoding. Runs of 3+ equal words become
+/// `RLE_RUN | count, word`; everything else is `count, words…` literals.
+fn rle_encode(words: &[u32]) -> Vec<u32> {
+    let mut out = Vec::new();
+    let mut literals: Vec<u32> = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let mut run = 1;
+        while i + run < words.len() && words[i + run] == words[i] && run < (RLE_RUN - 1) as usize {
+            run += 1;
+        }
+        if run >= 3 {
+            if !literals.is_empty() {
+                out.push(literals.len() as u32);
+                out.append(&mut literals);
+            }
+            out.push(RLE_RUN | run as u32);
+            out.push(words[i]);
+            i += run;
+        } else {
+            literals.push(words[i]);
+            i += 1;
+        }
+    }
+    if !literals.is_empty() {
+        out.push(literals.len() as u32);
+        out.append(&mut literals);
+    }
+    out
+}
+
+/// Inverse of [`rle_encode`]. Returns `None` on malformed input or if the
+/// output would exceed `limit` words.
+fn rle_decode(tokens: &[u32], limit: usize) -> Option<Vec<u32>> {
+    let mut out = Vec::with_capacity(limit);
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        let count = (token & !RLE_RUN) as usize;
+        if out.len() + count > limit {
+            return None;
+        }
+        if token & RLE_RUN != 0 {
+            let word = *tokens.get(i + 1)?;
+            out.resize(out.len() + count, word);
+            i += 2;
+        } else {
+            out.extend_from_slice(tokens.get(i + 1..i + 1 + count)?);
+            i += 1 + count;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene;
+
+    fn params() -> SceneParams {
+        SceneParams {
+            voxel_size: 0.25,
+            grid_origin: [-1.0, 2.0, 3.5],
+            mesh_center: [4.0, 5.0, 6.0],
+            mesh_extent: 12.5,
+        }
+    }
+
+    fn assert_chunks_equal(a: &ChunkData, b: &ChunkData) {
+        assert_eq!(a.coord, b.coord);
+        assert_eq!(a.occupancy.as_words(), b.occupancy.as_words());
+        assert_eq!(a.palette.entries(), b.palette.entries());
+        let bpe = IndexBufBuilder::bits_per_entry(a.palette.len());
+        assert_eq!(a.index_buf.pack(bpe), b.index_buf.pack(bpe));
+    }
+
+    #[test]
+    fn roundtrip_cornell_box() {
+        let (chunks, materials) = scene::generate_cornell_box();
+        for compress in [false, true] {
+            let bytes = write_scene(&chunks, &materials, &params(), compress);
+            let loaded = read_scene(&bytes).unwrap();
+            assert_eq!(loaded.chunks.len(), chunks.len());
+            for (a, b) in chunks.iter().zip(&loaded.chunks) {
+                assert_chunks_equal(a, b);
+            }
+            assert_eq!(
+                bytemuck::cast_slice::<_, u32>(&loaded.materials),
+                bytemuck::cast_slice::<_, u32>(&materials)
+            );
+            assert_eq!(loaded.voxel_size, 0.25);
+            assert_eq!(loaded.grid_origin, [-1.0, 2.0, 3.5]);
+            assert_eq!(loaded.mesh_center, [4.0, 5.0, 6.0]);
+            assert_eq!(loaded.mesh_extent, 12.5);
+        }
+    }
+
+    #[test]
+    fn compression_shrinks_sparse_chunks() {
+        let (chunks, materials) = scene::generate_cornell_box();
+        let raw = write_scene(&chunks, &materials, &params(), false);
+        let packed = write_scene(&chunks, &materials, &params(), true);
+        assert!(packed.len() < raw.len());
+
+        let mut palette = PaletteBuilder::new();
+        let mut occupancy = OccupancyBuilder::new();
+        let mut index_buf = IndexBufBuilder::new();
+        let idx = palette.add(scene::MAT_STONE);
+        occupancy.set(10, 20, 30);
+        index_buf.set(10, 20, 30, idx);
+        let sparse = [ChunkData {
+            coord: ChunkCoord { x: 0, y: 0, z: 0 },
+            occupancy,
+            palette,
+            index_buf,
+        }];
+        let raw = write_scene(&sparse, &[], &params(), false);
+        let packed = write_scene(&sparse, &[], &params(), true);
+        assert!(
+            packed.len() * 50 < raw.len(),
+            "{} vs {}",
+            packed.len(),
+            raw.len()
+        );
+    }
+
+    #[test]
+    fn chunks_written_in_coord_order() {
+        let (mut chunks, materials) = scene::generate_test_scene();
+        chunks.reverse();
+        let loaded = read_scene(&write_scene(&chunks, &materials, &params(), true)).unwrap();
+        let coords: Vec<_> = loaded
+            .chunks
+            .iter()
+            .map(|c| (c.coord.x, c.coord.y, c.coord.z))
+            .collect();
+        let mut sorted = coords.clone();
+        sorted.sort();
+        assert_eq!(coords, sorted);
+    }
+
+    #[test]
+    fn unknown_sections_skipped() {
+        let (chunks, materials) = scene::generate_cornell_box();
+        let mut bytes = write_scene(&chunks[..1], &materials, &params(), true);
+        // Splice an unknown section in right after the header.
+        let mut extra = Vec::new();
+        write_section(&mut extra, b"XTRA", &[1, 2, 3]);
+        bytes.splice(12..12, extra);
+        assert_eq!(read_scene(&bytes).unwrap().chunks.len(), 1);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(read_scene(b"nope").err(), Some(SceneFileError::BadMagic));
+
+        let (chunks, materials) = scene::generate_cornell_box();
+        let bytes = write_scene(&chunks[..1], &materials, &params(), true);
+
+        let mut newer = bytes.clone();
+        newer[4..8].copy_from_slice(&(SCENE_FILE_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            read_scene(&newer).err(),
+            Some(SceneFileError::UnsupportedVersion(SCENE_FILE_VERSION + 1))
+        );
+
+        assert!(matches!(
+            read_scene(&bytes[..bytes.len() - 8]),
+            Err(SceneFileError::Truncated(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_mismatched_palette_meta() {
+        let (chunks, materials) = scene::generate_cornell_box();
+        let bytes = write_scene(&chunks[..1], &materials, &params(), false);
+        // First CHNK follows header (12) + PARM (8 + 32) + MATS (8 + 4 + n * 16).
+        let chunk_at = 12 + 40 + 12 + materials.len() * 16;
+        assert_eq!(&bytes[chunk_at..chunk_at + 4], TAG_CHUNK);
+        let mut bad = bytes.clone();
+        let meta_at = chunk_at + 8 + 16;
+        bad[meta_at + 2] ^= 0x01; // flip a bpe bit
+        assert!(matches!(read_scene(&bad), Err(SceneFileError::Corrupt(_))));
+    }
+
+    #[test]
+    fn rle_roundtrip() {
+        let words = [0, 0, 0, 0, 7, 8, 9, 9, 9, 9, 9, 1, 2];
+        let encoded = rle_encode(&words);
+        assert_eq!(rle_decode(&encoded, words.len()).unwrap(), words);
+        assert!(rle_decode(&encoded, words.len() - 1).is_none());
+        assert!(rle_decode(&[RLE_RUN | 4], 10).is_none());
+        assert_eq!(rle_encode(&[]), Vec::<u32>::new());
+    }
+}
@@ -0,0 +1,682 @@
+//! Signed-distance scene description — no GPU, no JS types.
+//!
+//! A tree of primitives (sphere, box, cylinder, torus, capsule) combined
+//! with CSG operators and rigid transforms, rasterized into chunks. Voxel
+//! `g` is solid where the field at point `g` is ≤ 0, one voxel per world
+//! unit like the test scene. Scenes can be built in Rust or parsed from
+//! JSON with [`SdfScene::from_json`], so tests and QA describe content
+//! declaratively instead of writing a generator per case.
+
+use std::fmt;
+
+use glam::{EulerRot, Quat, Vec2, Vec3};
+
+use crate::json::{parse_json, JsonError, JsonValue};
+use crate::pool::*;
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
+use crate::voxelizer_cpu::VoxelizeResult;
+
+/// Why an SDF scene could not be parsed or rasterized.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SdfError {
+    /// The text is not valid JSON.
+    Json(JsonError),
+    /// Valid JSON that does not describe a scene. Messages start with the
+    /// path of the offending node, e.g. `root.children[1]`.
+    Scene(String),
+    /// The scene's bounds span more chunks than the pool has slots.
+    TooManyChunks { span: u64 },
+}
+
+impl fmt::Display for SdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdfError::Json(e) => write!(f, "{e}"),
+            SdfError::Scene(msg) => write!(f, "SDF scene: {msg}"),
+            SdfError::TooManyChunks { span } => write!(
+                f,
+                "SDF scene bounds span {span} chunks, more than the {MAX_SLOTS} pool slots"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SdfError {}
+
+/// A primitive in its local frame, centered on the origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Sphere { radius: f32 },
+    /// Box with edges rounded by `rounding` (0 for sharp), which stays
+    /// inside `half_size`.
+    Box { half_size: Vec3, rounding: f32 },
+    /// Capped cylinder along Y.
+    Cylinder { radius: f32, half_height: f32 },
+    /// Ring in the XZ plane: `radius` to the tube center, `tube` thick.
+    Torus { radius: f32, tube: f32 },
+    /// Segment `a`–`b` swept by a sphere.
+    Capsule { a: Vec3, b: Vec3, radius: f32 },
+}
+
+/// Rigid transform with uniform scale; non-uniform scale would break the
+/// distance bound the rasterizer relies on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 }
+    }
+}
+
+/// A node of the scene tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SdfNode {
+    /// `material` indexes [`SdfScene::materials`].
+    Primitive { shape: Shape, material: u16 },
+    Union(Vec<SdfNode>),
+    /// Keeps the first child's material.
+    Intersect(Vec<SdfNode>),
+    /// The first child minus the rest; keeps the first child's material.
+    Subtract(Vec<SdfNode>),
+    /// Union blended over roughly `k` voxels; each point takes the material
+    /// of the nearest child.
+    SmoothUnion { k: f32, children: Vec<SdfNode> },
+    Transform { transform: Transform, child: Box<SdfNode> },
+}
+
+impl SdfNode {
+    /// Signed distance at `p` and the material index there.
+    pub fn eval(&self, p: Vec3) -> (f32, u16) {
+        match self {
+            SdfNode::Primitive { shape, material } => (shape.distance(p), *material),
+            SdfNode::Union(children) => children
+                .iter()
+                .map(|c| c.eval(p))
+                .fold((f32::INFINITY, 0), |a, b| if b.0 < a.0 { b } else { a }),
+            SdfNode::Intersect(children) => fold_first(children, p, |acc, d| acc.max(d)),
+            SdfNode::Subtract(children) => fold_first(children, p, |acc, d| acc.max(-d)),
+            SdfNode::SmoothUnion { k, children } => {
+                children.iter().map(|c| c.eval(p)).fold((f32::INFINITY, 0), |(a, ma), (b, mb)| {
+                    let material = if b < a { mb } else { ma };
+                    (smooth_min(a, b, *k), material)
+                })
+            }
+            SdfNode::Transform { transform, child } => {
+                let local = transform.rotation.inverse() * (p - transform.translation);
+                let (d, material) = child.eval(local / transform.scale);
+                (d * transform.scale, material)
+            }
+        }
+    }
+
+    /// Conservative world-space bounds of the solid region, `None` if empty.
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        match self {
+            SdfNode::Primitive { shape, .. } => Some(shape.bounds()),
+            SdfNode::Union(children) => children.iter().filter_map(SdfNode::bounds).reduce(merge),
+            SdfNode::Intersect(children) => {
+                let mut all = children.iter().map(SdfNode::bounds);
+                let first = all.next()??;
+                all.try_fold(first, |(lo, hi), b| {
+                    let (blo, bhi) = b?;
+                    let (lo, hi) = (lo.max(blo), hi.min(bhi));
+                    lo.cmple(hi).all().then_some((lo, hi))
+                })
+            }
+            SdfNode::Subtract(children) => children.first()?.bounds(),
+            SdfNode::SmoothUnion { k, children } => {
+                // The polynomial blend bulges out by at most k / 4
+                let (lo, hi) = children.iter().filter_map(SdfNode::bounds).reduce(merge)?;
+                let pad = Vec3::splat(k.max(0.0) * 0.25);
+                Some((lo - pad, hi + pad))
+            }
+            SdfNode::Transform { transform, child } => {
+                let (lo, hi) = child.bounds()?;
+                (0..8)
+                    .map(|i| {
+                        let corner = Vec3::new(
+                            if i & 1 == 0 { lo.x } else { hi.x },
+                            if i & 2 == 0 { lo.y } else { hi.y },
+                            if i & 4 == 0 { lo.z } else { hi.z },
+                        );
+                        transform.rotation * (corner * transform.scale) + transform.translation
+                    })
+                    .map(|p| (p, p))
+                    .reduce(merge)
+            }
+        }
+    }
+}
+
+impl Shape {
+    /// Signed distance from `p` in the local frame.
+    pub fn distance(&self, p: Vec3) -> f32 {
+        match *self {
+            Shape::Sphere { radius } => p.length() - radius,
+            Shape::Box { half_size, rounding } => {
+                let r = rounding.clamp(0.0, half_size.min_element().max(0.0));
+                let q = p.abs() - (half_size - Vec3::splat(r));
+                q.max(Vec3::ZERO).length() + q.max_element().min(0.0) - r
+            }
+            Shape::Cylinder { radius, half_height } => {
+                let q = Vec2::new(p.x.hypot(p.z) - radius, p.y.abs() - half_height);
+                q.max(Vec2::ZERO).length() + q.max_element().min(0.0)
+            }
+            Shape::Torus { radius, tube } => {
+                Vec2::new(p.x.hypot(p.z) - radius, p.y).length() - tube
+            }
+            Shape::Capsule { a, b, radius } => {
+                let (pa, ba) = (p - a, b - a);
+                let h = (pa.dot(ba) / ba.length_squared().max(f32::MIN_POSITIVE)).clamp(0.0, 1.0);
+                (pa - ba * h).length() - radius
+            }
+        }
+    }
+
+    /// Local-frame bounds.
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let extent = match *self {
+            Shape::Sphere { radius } => Vec3::splat(radius),
+            Shape::Box { half_size, .. } => half_size,
+            Shape::Cylinder { radius, half_height } => Vec3::new(radius, half_height, radius),
+            Shape::Torus { radius, tube } => Vec3::new(radius + tube, tube, radius + tube),
+            Shape::Capsule { a, b, radius } => {
+                let r = Vec3::splat(radius);
+                return (a.min(b) - r, a.max(b) + r);
+            }
+        };
+        (-extent, extent)
+    }
+}
+
+/// A scene tree and the materials its primitives index.
+#[derive(Clone)]
+pub struct SdfScene {
+    pub root: SdfNode,
+    /// Material `i` gets material ID `i + 2`. At most
+    /// `MAX_PALETTE_ENTRIES - 1` so every chunk fits one palette.
+    pub materials: Vec<MaterialEntry>,
+}
+
+impl SdfScene {
+    /// Parse a scene from JSON:
+    ///
+    /// ```json
+    /// { "materials": [{ "color": [0.8, 0.2, 0.2], "roughness": 0.5 }],
+    ///   "root": { "type": "subtract", "children": [
+    ///     { "type": "box", "size": [40, 20, 40], "rounding": 2 },
+    ///     { "type": "sphere", "radius": 14, "translate": [0, 10, 0] } ] } }
+    /// ```
+    ///
+    /// Node types and their members:
+    ///
+    /// - `sphere`: `radius`
+    /// - `box`: `size` (full edge lengths), `rounding`
+    /// - `cylinder`: `radius`, `height` (along Y)
+    /// - `torus`: `radius`, `tube`
+    /// - `capsule`: `a`, `b`, `radius`
+    /// - `union`, `intersect`, `subtract`: `children`
+    /// - `smoothUnion`: `children`, `k`
+    ///
+    /// Primitives take `material` (index into `materials`, default 0). Any
+    /// node may carry `translate`, `rotate` (XYZ Euler degrees) and uniform
+    /// `scale`. Materials take `color`, `roughness` and `emissive`; a scene
+    /// without `materials` gets one mid-gray material.
+    pub fn from_json(text: &str) -> Result<Self, SdfError> {
+        let root = parse_json(text).map_err(SdfError::Json)?;
+        if root.as_object().is_none() {
+            return Err(scene_error("", "expected an object"));
+        }
+        let materials = match root.get("materials") {
+            None => vec![MaterialEntry::new([0.5; 3], 0.8, [0.0; 3], 1.0)],
+            Some(v) => v
+                .as_array()
+                .ok_or_else(|| scene_error("materials", "must be an array"))?
+                .iter()
+                .enumerate()
+                .map(|(i, m)| parse_material(m, &format!("materials[{i}]")))
+                .collect::<Result<_, _>>()?,
+        };
+        let node = root.get("root").ok_or_else(|| scene_error("root", "missing"))?;
+        let scene = Self { root: parse_node(node, "root")?, materials };
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    /// Rasterize every chunk the scene's bounds touch. Chunks the field
+    /// proves empty are skipped without visiting their voxels. Bounds that
+    /// span more than [`MAX_SLOTS`] chunks are rejected before any work.
+    pub fn rasterize(&self) -> Result<VoxelizeResult, SdfError> {
+        self.validate()?;
+        let mut materials =
+            vec![MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0); MAX_MATERIALS as usize];
+        materials[MATERIAL_DEFAULT as usize] =
+            MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0);
+        for (i, material) in self.materials.iter().enumerate() {
+            materials[i + 2] = *material;
+        }
+
+        let mut chunks = Vec::new();
+        let (mut center, mut extent) = ([0.0; 3], 0.0);
+        if let Some((lo, hi)) = self.root.bounds() {
+            let cs = CS as i32;
+            let lo_chunk = lo.to_array().map(|c| (c.ceil() as i32).div_euclid(cs));
+            let hi_chunk = hi.to_array().map(|c| (c.floor() as i32).div_euclid(cs));
+            let span = (0..3)
+                .map(|i| (hi_chunk[i] as i64 - lo_chunk[i] as i64 + 1).max(0) as u64)
+                .fold(1u64, u64::saturating_mul);
+            if span > MAX_SLOTS as u64 {
+                return Err(SdfError::TooManyChunks { span });
+            }
+            for x in lo_chunk[0]..=hi_chunk[0] {
+                for y in lo_chunk[1]..=hi_chunk[1] {
+                    for z in lo_chunk[2]..=hi_chunk[2] {
+                        chunks.extend(self.chunk(ChunkCoord { x, y, z }));
+                    }
+                }
+            }
+            center = ((lo + hi) * 0.5).to_array();
+            extent = (hi - lo).max_element();
+        }
+
+        Ok(VoxelizeResult {
+            chunks,
+            materials,
+            voxel_size: 1.0,
+            grid_origin: [0.0; 3],
+            mesh_center: center,
+            mesh_extent: extent,
+            quantize: Default::default(),
+        })
+    }
+
+    fn validate(&self) -> Result<(), SdfError> {
+        if self.materials.is_empty() || self.materials.len() >= MAX_PALETTE_ENTRIES as usize {
+            return Err(scene_error(
+                "materials",
+                &format!("need 1 to {}, got {}", MAX_PALETTE_ENTRIES - 1, self.materials.len()),
+            ));
+        }
+        check_node(&self.root, "root", self.materials.len())
+    }
+
+    /// Rasterize one chunk. `None` if it holds no voxels.
+    fn chunk(&self, coord: ChunkCoord) -> Option<ChunkData> {
+        let cs = CS as i32;
+        let base = [coord.x * cs, coord.y * cs, coord.z * cs];
+        // Every node's field changes by at most one per voxel of travel, so
+        // a center further out than the half-diagonal leaves the chunk empty
+        let half = (cs - 1) as f32 * 0.5;
+        let middle = Vec3::from(base.map(|c| c as f32)) + Vec3::splat(half);
+        if self.root.eval(middle).0 > half * 3f32.sqrt() {
+            return None;
+        }
+
+        let mut occupancy = OccupancyBuilder::new();
+        let mut palette = PaletteBuilder::new();
+        let mut index_buf = IndexBufBuilder::new();
+        let mut palette_index = vec![None; self.materials.len()];
+        let mut any = false;
+        for lx in 0..cs {
+            for ly in 0..cs {
+                for lz in 0..cs {
+                    let g = [base[0] + lx, base[1] + ly, base[2] + lz];
+                    let (d, material) = self.root.eval(Vec3::from(g.map(|c| c as f32)));
+                    if d > 0.0 {
+                        continue;
+                    }
+                    let index = *palette_index[material as usize]
+                        .get_or_insert_with(|| palette.add(material + 2));
+                    let (px, py, pz) = ((lx + 1) as u32, (ly + 1) as u32, (lz + 1) as u32);
+                    occupancy.set(px, py, pz);
+                    index_buf.set(px, py, pz, index);
+                    any = true;
+                }
+            }
+        }
+
+        any.then_some(ChunkData { coord, occupancy, palette, index_buf })
+    }
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+fn scene_error(path: &str, message: &str) -> SdfError {
+    SdfError::Scene(format!("{path}: {message}"))
+}
+
+/// Fold the children's distances into the first child's, keeping its material.
+fn fold_first(children: &[SdfNode], p: Vec3, op: impl Fn(f32, f32) -> f32) -> (f32, u16) {
+    let Some((first, rest)) = children.split_first() else {
+        return (f32::INFINITY, 0);
+    };
+    let (d, material) = first.eval(p);
+    (rest.iter().fold(d, |acc, c| op(acc, c.eval(p).0)), material)
+}
+
+/// Polynomial smooth minimum; plain `min` for `k <= 0`.
+fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    if k <= 0.0 || !a.is_finite() {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+fn merge((alo, ahi): (Vec3, Vec3), (blo, bhi): (Vec3, Vec3)) -> (Vec3, Vec3) {
+    (alo.min(blo), ahi.max(bhi))
+}
+
+/// Reject materials out of range and shapes whose sizes are not finite and
+/// non-negative.
+fn check_node(node: &SdfNode, path: &str, materials: usize) -> Result<(), SdfError> {
+    let finite = |values: &[f32]| values.iter().all(|v| v.is_finite() && *v >= 0.0);
+    match node {
+        SdfNode::Primitive { shape, material } => {
+            if *material as usize >= materials {
+                return Err(scene_error(path, &format!("no material {material}")));
+            }
+            let ok = match *shape {
+                Shape::Sphere { radius } => finite(&[radius]),
+                Shape::Box { half_size, rounding } => {
+                    finite(&half_size.to_array()) && finite(&[rounding])
+                }
+                Shape::Cylinder { radius, half_height } => finite(&[radius, half_height]),
+                Shape::Torus { radius, tube } => finite(&[radius, tube]),
+                Shape::Capsule { a, b, radius } => {
+                    a.is_finite() && b.is_finite() && finite(&[radius])
+                }
+            };
+            if !ok {
+                return Err(scene_error(path, "sizes must be finite and non-negative"));
+            }
+            Ok(())
+        }
+        SdfNode::Union(children) | SdfNode::Intersect(children) | SdfNode::Subtract(children) => {
+            check_children(children, path, materials)
+        }
+        SdfNode::SmoothUnion { k, children } => {
+            if !finite(&[*k]) {
+                return Err(scene_error(path, "k must be finite and non-negative"));
+            }
+            check_children(children, path, materials)
+        }
+        SdfNode::Transform { transform, child } => {
+            let Transform { translation, rotation, scale } = *transform;
+            let valid = translation.is_finite() && rotation.is_finite() && scale.is_finite();
+            if !(valid && scale > 0.0) {
+                return Err(scene_error(path, "invalid transform"));
+            }
+            check_node(child, path, materials)
+        }
+    }
+}
+
+fn check_children(children: &[SdfNode], path: &str, materials: usize) -> Result<(), SdfError> {
+    if children.is_empty() {
+        return Err(scene_error(path, "needs at least one child"));
+    }
+    for (i, child) in children.iter().enumerate() {
+        check_node(child, &format!("{path}.children[{i}]"), materials)?;
+    }
+    Ok(())
+}
+
+fn number(node: &JsonValue, path: &str, key: &str) -> Result<Option<f32>, SdfError> {
+    node.get(key)
+        .map(|v| v.as_f32().ok_or_else(|| scene_error(path, &format!("{key} must be a number"))))
+        .transpose()
+}
+
+fn required(node: &JsonValue, path: &str, key: &str) -> Result<f32, SdfError> {
+    number(node, path, key)?.ok_or_else(|| scene_error(path, &format!("missing {key}")))
+}
+
+fn vec3(node: &JsonValue, path: &str, key: &str) -> Result<Option<Vec3>, SdfError> {
+    node.get(key)
+        .map(|v| match v.as_f32_vec().as_deref() {
+            Some(&[x, y, z]) => Ok(Vec3::new(x, y, z)),
+            _ => Err(scene_error(path, &format!("{key} must be [x, y, z]"))),
+        })
+        .transpose()
+}
+
+fn parse_material(value: &JsonValue, path: &str) -> Result<MaterialEntry, SdfError> {
+    let rgb = |key, default| -> Result<[f32; 3], SdfError> {
+        Ok(vec3(value, path, key)?.map_or(default, |v| v.to_array()))
+    };
+    Ok(MaterialEntry::new(
+        rgb("color", [0.5; 3])?,
+        number(value, path, "roughness")?.unwrap_or(0.8),
+        rgb("emissive", [0.0; 3])?,
+        1.0,
+    ))
+}
+
+fn parse_node(value: &JsonValue, path: &str) -> Result<SdfNode, SdfError> {
+    let kind = value
+        .get("type")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| scene_error(path, "missing type"))?;
+    let children = || -> Result<Vec<SdfNode>, SdfError> {
+        value
+            .get("children")
+            .and_then(JsonValue::as_array)
+            .ok_or_else(|| scene_error(path, "children must be an array"))?
+            .iter()
+            .enumerate()
+            .map(|(i, child)| parse_node(child, &format!("{path}.children[{i}]")))
+            .collect()
+    };
+    let shape = |shape| -> Result<SdfNode, SdfError> {
+        let material = match value.get("material") {
+            None => 0,
+            Some(v) => v
+                .as_usize()
+                .and_then(|m| u16::try_from(m).ok())
+                .ok_or_else(|| scene_error(path, "material must be an index"))?,
+        };
+        Ok(SdfNode::Primitive { shape, material })
+    };
+
+    let node = match kind {
+        "sphere" => shape(Shape::Sphere { radius: required(value, path, "radius")? })?,
+        "box" => shape(Shape::Box {
+            half_size: vec3(value, path, "size")?
+                .ok_or_else(|| scene_error(path, "missing size"))?
+                * 0.5,
+            rounding: number(value, path, "rounding")?.unwrap_or(0.0),
+        })?,
+        "cylinder" => shape(Shape::Cylinder {
+            radius: required(value, path, "radius")?,
+            half_height: required(value, path, "height")? * 0.5,
+        })?,
+        "torus" => shape(Shape::Torus {
+            radius: required(value, path, "radius")?,
+            tube: required(value, path, "tube")?,
+        })?,
+        "capsule" => shape(Shape::Capsule {
+            a: vec3(value, path, "a")?.ok_or_else(|| scene_error(path, "missing a"))?,
+            b: vec3(value, path, "b")?.ok_or_else(|| scene_error(path, "missing b"))?,
+            radius: required(value, path, "radius")?,
+        })?,
+        "union" => SdfNode::Union(children()?),
+        "intersect" => SdfNode::Intersect(children()?),
+        "subtract" => SdfNode::Subtract(children()?),
+        "smoothUnion" => SdfNode::SmoothUnion {
+            k: required(value, path, "k")?,
+            children: children()?,
+        },
+        other => return Err(scene_error(path, &format!("unknown type \"{other}\""))),
+    };
+
+    let translation = vec3(value, path, "translate")?;
+    let rotation = vec3(value, path, "rotate")?;
+    let scale = number(value, path, "scale")?;
+    if translation.is_none() && rotation.is_none() && scale.is_none() {
+        return Ok(node);
+    }
+    let [rx, ry, rz] = rotation.unwrap_or(Vec3::ZERO).to_array().map(f32::to_radians);
+    let transform = Transform {
+        translation: translation.unwrap_or(Vec3::ZERO),
+        rotation: Quat::from_euler(EulerRot::XYZ, rx, ry, rz),
+        scale: scale.unwrap_or(1.0),
+    };
+    Ok(SdfNode::Transform { transform, child: Box::new(node) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::generate_sphere;
+
+    fn voxel(result: &VoxelizeResult, g: [i32; 3]) -> Option<u16> {
+        let cs = CS as i32;
+        let coord = g.map(|c| c.div_euclid(cs));
+        let [x, y, z] = g.map(|c| (c.rem_euclid(cs) + 1) as u32);
+        let chunk = result
+            .chunks
+            .iter()
+            .find(|c| [c.coord.x, c.coord.y, c.coord.z] == coord)?;
+        chunk
+            .occupancy
+            .get(x, y, z)
+            .then(|| chunk.palette.entries()[chunk.index_buf.get(x, y, z) as usize])
+    }
+
+    fn gray() -> Vec<MaterialEntry> {
+        vec![MaterialEntry::new([0.5; 3], 0.5, [0.0; 3], 1.0)]
+    }
+
+    #[test]
+    fn sphere_matches_hand_coded_generator() {
+        let coord = ChunkCoord { x: 0, y: 0, z: 0 };
+        let expected = generate_sphere(coord, 32, 25, 32, 12);
+        // Padded (32, 25, 32) is grid (31, 24, 31)
+        let scene = SdfScene {
+            root: SdfNode::Transform {
+                transform: Transform {
+                    translation: Vec3::new(31.0, 24.0, 31.0),
+                    ..Default::default()
+                },
+                child: Box::new(SdfNode::Primitive {
+                    shape: Shape::Sphere { radius: 12.0 },
+                    material: 0,
+                }),
+            },
+            materials: gray(),
+        };
+        let result = scene.rasterize().unwrap();
+        assert_eq!(result.chunks.len(), 1);
+        let chunk = &result.chunks[0];
+        assert_eq!(chunk.occupancy.popcount(), expected.occupancy.popcount());
+        for x in 0..CS_P {
+            for y in 0..CS_P {
+                for z in 0..CS_P {
+                    assert_eq!(chunk.occupancy.get(x, y, z), expected.occupancy.get(x, y, z));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn primitives_contain_their_points() {
+        let inside = |shape: Shape, p: [f32; 3]| shape.distance(Vec3::from(p)) <= 0.0;
+        let cube = Shape::Box { half_size: Vec3::splat(4.0), rounding: 1.0 };
+        assert!(inside(cube, [3.9, 0.0, 0.0]));
+        assert!(!inside(cube, [3.9, 3.9, 3.9]), "rounded corner");
+        let cylinder = Shape::Cylinder { radius: 3.0, half_height: 5.0 };
+        assert!(inside(cylinder, [2.0, 4.5, 2.0]));
+        assert!(!inside(cylinder, [2.5, 0.0, 2.5]));
+        let torus = Shape::Torus { radius: 6.0, tube: 1.5 };
+        assert!(inside(torus, [0.0, 1.0, 6.0]));
+        assert!(!inside(torus, [0.0, 0.0, 0.0]), "hole");
+        let capsule = Shape::Capsule { a: Vec3::ZERO, b: Vec3::new(0.0, 10.0, 0.0), radius: 2.0 };
+        assert!(inside(capsule, [0.0, 11.5, 0.0]));
+        assert!(!inside(capsule, [2.0, 5.0, 1.0]));
+
+        for shape in [cube, cylinder, torus, capsule, Shape::Sphere { radius: 3.0 }] {
+            let (lo, hi) = shape.bounds();
+            assert!(shape.distance(hi + 0.01) > 0.0 && shape.distance(lo - 0.01) > 0.0);
+        }
+    }
+
+    #[test]
+    fn csg_and_materials_from_json() {
+        let scene = SdfScene::from_json(
+            r#"{
+              "materials": [{ "color": [1, 0, 0] }, { "color": [0, 0, 1], "roughness": 0.2 }],
+              "root": { "type": "union", "children": [
+                { "type": "subtract", "children": [
+                  { "type": "box", "size": [20, 20, 20] },
+                  { "type": "sphere", "radius": 6, "material": 1 } ] },
+                { "type": "cylinder", "radius": 2, "height": 10, "material": 1,
+                  "rotate": [0, 0, 90], "translate": [30, 0, 0] } ] }
+            }"#,
+        )
+        .unwrap();
+        let result = scene.rasterize().unwrap();
+
+        assert_eq!(voxel(&result, [9, 9, 9]), Some(2), "box corner");
+        assert_eq!(voxel(&result, [0, 0, 0]), None, "carved by the sphere");
+        assert_eq!(voxel(&result, [0, 7, 0]), Some(2), "subtract keeps the box material");
+        assert_eq!(voxel(&result, [34, 0, 0]), Some(3), "cylinder lies along X");
+        assert_eq!(voxel(&result, [30, 4, 0]), None);
+        assert_eq!(result.materials[3].albedo(), [0.0, 0.0, 1.0]);
+        assert!(result.chunks.iter().all(|c| c.occupancy.popcount() > 0));
+    }
+
+    #[test]
+    fn smooth_union_bridges_the_gap() {
+        let ball = |x: f32, material| SdfNode::Transform {
+            transform: Transform { translation: Vec3::new(x, 0.0, 0.0), ..Default::default() },
+            child: Box::new(SdfNode::Primitive { shape: Shape::Sphere { radius: 4.0 }, material }),
+        };
+        let scene = |root| SdfScene { root, materials: vec![gray()[0]; 2] };
+        let balls = || vec![ball(-5.0, 0), ball(5.0, 1)];
+        let hard = scene(SdfNode::Union(balls())).rasterize().unwrap();
+        let soft = scene(SdfNode::SmoothUnion { k: 6.0, children: balls() }).rasterize().unwrap();
+
+        assert_eq!(voxel(&hard, [0, 0, 0]), None);
+        assert!(voxel(&soft, [0, 0, 0]).is_some());
+        assert_eq!(voxel(&soft, [-5, 0, 0]), Some(2));
+        assert_eq!(voxel(&soft, [5, 0, 0]), Some(3));
+        let count =
+            |r: &VoxelizeResult| r.chunks.iter().map(|c| c.occupancy.popcount()).sum::<u32>();
+        assert!(count(&soft) > count(&hard));
+    }
+
+    #[test]
+    fn oversized_bounds_rejected() {
+        let sphere = |radius| SdfScene {
+            root: SdfNode::Primitive { shape: Shape::Sphere { radius }, material: 0 },
+            materials: gray(),
+        };
+        assert!(sphere(40.0).rasterize().is_ok());
+        match sphere(1e6).rasterize() {
+            Err(SdfError::TooManyChunks { span }) => assert!(span > MAX_SLOTS as u64),
+            _ => panic!("expected TooManyChunks"),
+        }
+    }
+
+    #[test]
+    fn invalid_scenes_name_the_node() {
+        let err = |text: &str| match SdfScene::from_json(text) {
+            Err(SdfError::Scene(msg)) => msg,
+            Err(e) => panic!("expected a scene error, got {e}"),
+            Ok(_) => panic!("expected a scene error"),
+        };
+        assert!(err(r#"{"root": {"type": "cone"}}"#).starts_with("root: unknown type"));
+        let msg = err(r#"{"root": {"type": "union", "children": [{"type": "sphere"}]}}"#);
+        assert_eq!(msg, "root.children[0]: missing radius");
+        let msg = err(r#"{"root": {"type": "sphere", "radius": 1, "material": 3}}"#);
+        assert_eq!(msg, "root: no material 3");
+        assert!(err(r#"{"root": {"type": "intersect", "children": []}}"#).contains("child"));
+        assert!(matches!(SdfScene::from_json("{"), Err(SdfError::Json(_))));
+    }
+}
@@ -0,0 +1,465 @@
+//! Pure Rust STL parser — no wasm_bindgen, no JS types.
+//!
+//! Reads binary and ASCII STL into the same [`ParsedObj`] triangle soup the
+//! OBJ path feeds to the voxelizer. Facet normals are ignored. Vertices are
+//! not welded; every triangle gets its own three positions.
+//!
+//! In binary files the 16-bit attribute word of each facet selects its
+//! material group: facets sharing a non-zero attribute share a group. When
+//! bit 15 is set the low 15 bits are read as a VisCAM/SolidView RGB555
+//! color (red in bits 10–14); otherwise the group gets a hashed color.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::obj_parser::ParsedObj;
+use crate::scene::MaterialEntry;
+
+const BINARY_HEADER_LEN: usize = 80;
+const BINARY_FACET_LEN: usize = 50;
+
+/// Bit 15 of the attribute word: the low bits hold an RGB555 color.
+const ATTRIBUTE_COLOR_VALID: u16 = 0x8000;
+
+/// Parsed STL mesh plus per-group colors.
+#[derive(Debug, Clone)]
+pub struct ParsedStl {
+    /// Triangle soup. Material group 0 is "(default)" (attribute word 0, or
+    /// any ASCII facet). Object groups are ASCII `solid` blocks; binary
+    /// files have a single object named from the header.
+    pub mesh: ParsedObj,
+    /// Decoded attribute color per material group, if any.
+    pub group_colors: Vec<Option<[f32; 3]>>,
+    /// Facets dropped because two or more vertices coincide.
+    pub degenerate_facets: usize,
+}
+
+impl ParsedStl {
+    /// Per-group material entries for `voxelize_with_material_entries`.
+    /// Groups without a decoded color fall back to the voxelizer's hash.
+    pub fn group_material_entries(&self) -> Vec<Option<MaterialEntry>> {
+        self.group_colors
+            .iter()
+            .map(|c| c.map(|rgb| MaterialEntry::new(rgb, 0.5, [0.0; 3], 1.0)))
+            .collect()
+    }
+}
+
+/// Why an STL file could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StlError {
+    /// Binary file shorter than its header + facet count require.
+    Truncated { expected: usize, actual: usize },
+    /// Not a binary STL and not valid UTF-8 text.
+    InvalidUtf8,
+    /// Malformed ASCII statement. `line` is 1-based.
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Truncated { expected, actual } => {
+                write!(
+                    f,
+                    "binary STL truncated: expected {expected} bytes, got {actual}"
+                )
+            }
+            StlError::InvalidUtf8 => write!(f, "STL is neither binary nor UTF-8 text"),
+            StlError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for StlError {}
+
+/// Parse an STL file, detecting binary vs ASCII.
+///
+/// A file is binary when its size matches the facet count in the header
+/// exactly, even if the header starts with `solid` (many exporters do this).
+/// Otherwise a file starting with `solid` is parsed as ASCII (falling back
+/// to binary if that fails), and anything else as binary.
+pub fn parse_stl(bytes: &[u8]) -> Result<ParsedStl, StlError> {
+    if is_exact_binary(bytes) || !looks_ascii(bytes) {
+        return parse_binary(bytes);
+    }
+    let ascii = std::str::from_utf8(bytes)
+        .map_err(|_| StlError::InvalidUtf8)
+        .and_then(parse_ascii);
+    match ascii {
+        Ok(parsed) => Ok(parsed),
+        Err(e) => parse_binary(bytes).map_err(|_| e),
+    }
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+fn facet_count(bytes: &[u8]) -> Option<usize> {
+    let b = bytes.get(BINARY_HEADER_LEN..BINARY_HEADER_LEN + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+}
+
+fn is_exact_binary(bytes: &[u8]) -> bool {
+    facet_count(bytes)
+        .and_then(|n| n.checked_mul(BINARY_FACET_LEN))
+        .is_some_and(|len| BINARY_HEADER_LEN + 4 + len == bytes.len())
+}
+
+fn looks_ascii(bytes: &[u8]) -> bool {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    bytes[start..].starts_with(b"solid")
+}
+
+fn empty_mesh() -> ParsedObj {
+    ParsedObj {
+        positions: Vec::new(),
+        triangles: Vec::new(),
+        triangle_materials: Vec::new(),
+        material_names: vec!["(default)".to_string()],
+        material_libs: Vec::new(),
+        triangle_objects: Vec::new(),
+        object_names: vec!["(default)".to_string()],
+        colors: Vec::new(),
+        texcoords: Vec::new(),
+        triangle_texcoords: Vec::new(),
+    }
+}
+
+/// Push one triangle unless it is degenerate. Returns false if skipped.
+fn push_triangle(mesh: &mut ParsedObj, v: [[f32; 3]; 3], material: u32, object: u32) -> bool {
+    if v[0] == v[1] || v[1] == v[2] || v[0] == v[2] {
+        return false;
+    }
+    let base = mesh.positions.len() as u32;
+    mesh.positions.extend_from_slice(&v);
+    mesh.triangles.push([base, base + 1, base + 2]);
+    mesh.triangle_materials.push(material);
+    mesh.triangle_objects.push(object);
+    true
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<ParsedStl, StlError> {
+    let count = facet_count(bytes).ok_or(StlError::Truncated {
+        expected: BINARY_HEADER_LEN + 4,
+        actual: bytes.len(),
+    })?;
+    let expected = count
+        .checked_mul(BINARY_FACET_LEN)
+        .and_then(|n| n.checked_add(BINARY_HEADER_LEN + 4))
+        .unwrap_or(usize::MAX);
+    if bytes.len() < expected {
+        return Err(StlError::Truncated {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+
+    let mut mesh = empty_mesh();
+    let mut group_colors = vec![None];
+    let header = String::from_utf8_lossy(&bytes[..BINARY_HEADER_LEN]);
+    let name = header.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    let name = name.strip_prefix("solid").map(str::trim).unwrap_or(name);
+    mesh.object_names.push(if name.is_empty() {
+        "stl".to_string()
+    } else {
+        name.to_string()
+    });
+
+    let mut groups: HashMap<u16, u32> = HashMap::new();
+    let mut degenerate_facets = 0;
+    let read_f32 =
+        |at: usize| f32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+
+    for i in 0..count {
+        let at = BINARY_HEADER_LEN + 4 + i * BINARY_FACET_LEN;
+        // Skip the 12-byte normal.
+        let mut v = [[0.0f32; 3]; 3];
+        for (k, vertex) in v.iter_mut().enumerate() {
+            let base = at + 12 + k * 12;
+            *vertex = [read_f32(base), read_f32(base + 4), read_f32(base + 8)];
+        }
+        let attribute = u16::from_le_bytes([bytes[at + 48], bytes[at + 49]]);
+
+        let material = if attribute == 0 {
+            0
+        } else {
+            *groups.entry(attribute).or_insert_with(|| {
+                mesh.material_names.push(format!("attr_{attribute:04x}"));
+                group_colors.push(decode_attribute_color(attribute));
+                (mesh.material_names.len() - 1) as u32
+            })
+        };
+        if !push_triangle(&mut mesh, v, material, 1) {
+            degenerate_facets += 1;
+        }
+    }
+
+    Ok(ParsedStl {
+        mesh,
+        group_colors,
+        degenerate_facets,
+    })
+}
+
+/// VisCAM/SolidView RGB555: red 10–14, green 5–9, blue 0–4, bit 15 valid.
+fn decode_attribute_color(attribute: u16) -> Option<[f32; 3]> {
+    if attribute & ATTRIBUTE_COLOR_VALID == 0 {
+        return None;
+    }
+    let channel = |shift: u16| ((attribute >> shift) & 0x1F) as f32 / 31.0;
+    Some([channel(10), channel(5), channel(0)])
+}
+
+fn parse_ascii(text: &str) -> Result<ParsedStl, StlError> {
+    let mut mesh = empty_mesh();
+    let mut degenerate_facets = 0;
+    let mut object = 0u32;
+    let mut loop_vertices: Option<Vec<[f32; 3]>> = None;
+
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let syntax = |message: &str| StlError::Syntax {
+            line: line_no,
+            message: message.to_string(),
+        };
+        let mut parts = line.split_whitespace();
+        let Some(keyword) = parts.next() else {
+            continue;
+        };
+
+        match keyword {
+            "solid" => {
+                let name = line.trim_start()["solid".len()..].trim();
+                mesh.object_names.push(if name.is_empty() {
+                    format!("solid_{}", mesh.object_names.len())
+                } else {
+                    name.to_string()
+                });
+                object = (mesh.object_names.len() - 1) as u32;
+            }
+            "outer" => {
+                if loop_vertices.is_some() {
+                    return Err(syntax("nested 'outer loop'"));
+                }
+                loop_vertices = Some(Vec::new());
+            }
+            "vertex" => {
+                let vertices = loop_vertices
+                    .as_mut()
+                    .ok_or_else(|| syntax("'vertex' outside 'outer loop'"))?;
+                let mut coords = [0.0f32; 3];
+                for c in &mut coords {
+                    let token = parts
+                        .next()
+                        .ok_or_else(|| syntax("vertex needs three coordinates"))?;
+                    *c = token
+                        .parse()
+                        .map_err(|_| syntax(&format!("invalid coordinate '{token}'")))?;
+                }
+                vertices.push(coords);
+            }
+            "endloop" => {
+                let vertices = loop_vertices
+                    .take()
+                    .ok_or_else(|| syntax("'endloop' without 'outer loop'"))?;
+                if vertices.len() < 3 {
+                    return Err(syntax("facet has fewer than three vertices"));
+                }
+                // Fan-triangulate the rare polygonal facet.
+                for i in 1..vertices.len() - 1 {
+                    if !push_triangle(
+                        &mut mesh,
+                        [vertices[0], vertices[i], vertices[i + 1]],
+                        0,
+                        object,
+                    ) {
+                        degenerate_facets += 1;
+                    }
+                }
+            }
+            "facet" | "endfacet" | "endsolid" => {}
+            other => return Err(syntax(&format!("unexpected keyword '{other}'"))),
+        }
+    }
+
+    if loop_vertices.is_some() {
+        return Err(StlError::Syntax {
+            line: text.lines().count(),
+            message: "unterminated 'outer loop'".to_string(),
+        });
+    }
+
+    Ok(ParsedStl {
+        mesh,
+        group_colors: vec![None],
+        degenerate_facets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary_stl(header: &[u8], facets: &[([[f32; 3]; 3], u16)]) -> Vec<u8> {
+        let mut out = vec![0u8; BINARY_HEADER_LEN];
+        out[..header.len()].copy_from_slice(header);
+        out.extend_from_slice(&(facets.len() as u32).to_le_bytes());
+        for (verts, attr) in facets {
+            out.extend_from_slice(&[0u8; 12]);
+            for v in verts {
+                for c in v {
+                    out.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            out.extend_from_slice(&attr.to_le_bytes());
+        }
+        out
+    }
+
+    const TRI_A: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    const TRI_B: [[f32; 3]; 3] = [[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [0.0, 1.0, 1.0]];
+
+    #[test]
+    fn parse_ascii_facets() {
+        let stl = "\
+solid part
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid part
+";
+        let parsed = parse_stl(stl.as_bytes()).unwrap();
+        assert_eq!(parsed.mesh.triangles, vec![[0, 1, 2]]);
+        assert_eq!(parsed.mesh.positions[1], [1.0, 0.0, 0.0]);
+        assert_eq!(parsed.mesh.object_names, vec!["(default)", "part"]);
+        assert_eq!(parsed.mesh.triangle_objects, vec![1]);
+        assert_eq!(parsed.mesh.triangle_materials, vec![0]);
+    }
+
+    #[test]
+    fn parse_ascii_multiple_solids() {
+        let stl = "\
+solid a
+facet normal 0 0 0
+outer loop
+vertex 0 0 0
+vertex 1 0 0
+vertex 0 1 0
+endloop
+endfacet
+endsolid a
+solid b
+facet normal 0 0 0
+outer loop
+vertex 0 0 1
+vertex 1 0 1
+vertex 0 1 1
+endloop
+endfacet
+endsolid b
+";
+        let parsed = parse_stl(stl.as_bytes()).unwrap();
+        assert_eq!(parsed.mesh.object_names, vec!["(default)", "a", "b"]);
+        assert_eq!(parsed.mesh.triangle_objects, vec![1, 2]);
+    }
+
+    #[test]
+    fn ascii_syntax_error_has_line() {
+        let stl = "solid x\nfacet normal 0 0 1\nouter loop\nvertex 0 0 nope\n";
+        let err = parse_stl(stl.as_bytes()).unwrap_err();
+        assert_eq!(
+            err,
+            StlError::Syntax {
+                line: 4,
+                message: "invalid coordinate 'nope'".into()
+            }
+        );
+    }
+
+    #[test]
+    fn ascii_short_facet_rejected() {
+        let stl = "solid x\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\n";
+        assert!(matches!(
+            parse_stl(stl.as_bytes()),
+            Err(StlError::Syntax { line: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_binary_facets() {
+        let stl = binary_stl(b"bracket", &[(TRI_A, 0), (TRI_B, 0)]);
+        let parsed = parse_stl(&stl).unwrap();
+        assert_eq!(parsed.mesh.triangles, vec![[0, 1, 2], [3, 4, 5]]);
+        assert_eq!(parsed.mesh.positions[5], [0.0, 1.0, 1.0]);
+        assert_eq!(parsed.mesh.object_names, vec!["(default)", "bracket"]);
+        assert_eq!(parsed.mesh.material_names, vec!["(default)"]);
+    }
+
+    #[test]
+    fn binary_with_solid_header_detected_by_size() {
+        let stl = binary_stl(b"solid exported by CAD", &[(TRI_A, 0)]);
+        let parsed = parse_stl(&stl).unwrap();
+        assert_eq!(parsed.mesh.triangles.len(), 1);
+        assert_eq!(parsed.mesh.object_names[1], "exported by CAD");
+    }
+
+    #[test]
+    fn binary_attribute_groups_and_colors() {
+        let red = ATTRIBUTE_COLOR_VALID | (31 << 10);
+        let stl = binary_stl(b"", &[(TRI_A, red), (TRI_B, 7), (TRI_A, red)]);
+        let parsed = parse_stl(&stl).unwrap();
+        assert_eq!(parsed.mesh.triangle_materials, vec![1, 2, 1]);
+        assert_eq!(
+            parsed.mesh.material_names,
+            vec!["(default)", "attr_fc00", "attr_0007"]
+        );
+        assert_eq!(parsed.group_colors, vec![None, Some([1.0, 0.0, 0.0]), None]);
+
+        let entries = parsed.group_material_entries();
+        assert!(entries[0].is_none() && entries[1].is_some() && entries[2].is_none());
+    }
+
+    #[test]
+    fn binary_truncated_rejected() {
+        let mut stl = binary_stl(b"", &[(TRI_A, 0), (TRI_B, 0)]);
+        stl.truncate(stl.len() - 10);
+        assert_eq!(
+            parse_stl(&stl).unwrap_err(),
+            StlError::Truncated {
+                expected: 184,
+                actual: 174
+            }
+        );
+    }
+
+    #[test]
+    fn degenerate_facets_dropped() {
+        let flat = [[0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0]];
+        let parsed = parse_stl(&binary_stl(b"", &[(flat, 0), (TRI_A, 0)])).unwrap();
+        assert_eq!(parsed.mesh.triangles.len(), 1);
+        assert_eq!(parsed.degenerate_facets, 1);
+    }
+
+    #[test]
+    fn voxelize_binary_stl() {
+        let stl = binary_stl(b"", &[(TRI_A, ATTRIBUTE_COLOR_VALID | 31)]);
+        let parsed = parse_stl(&stl).unwrap();
+        let entries = parsed.group_material_entries();
+        let result = crate::voxelizer_cpu::voxelize_with_material_entries(&parsed.mesh, &entries, 8)
+            .unwrap();
+        assert!(!result.chunks.is_empty());
+        let blue = MaterialEntry::new([0.0, 0.0, 1.0], 0.5, [0.0; 3], 1.0);
+        assert_eq!(result.materials[3].albedo_rg, blue.albedo_rg);
+        assert_eq!(
+            result.materials[3].albedo_b_roughness,
+            blue.albedo_b_roughness
+        );
+    }
+}
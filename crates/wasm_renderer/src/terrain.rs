@@ -0,0 +1,555 @@
+//! Heightmap terrain generator — no GPU, no JS types.
+//!
+//! Turns a grayscale heightmap (16-bit little-endian raw, or binary/ASCII
+//! PGM) into solid terrain chunks. Pixel `(x, row)` becomes the voxel column
+//! at grid `(x, row)` in X/Z, filled from [`HeightmapOptions::floor`] up to
+//! `sample * vertical_scale`. Each voxel takes the material of the first
+//! [`MaterialBand`] whose height and slope limits it meets. One voxel is one
+//! world unit, like the test scene.
+
+use std::fmt;
+
+use crate::json::{parse_json, JsonValue};
+use crate::pool::*;
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
+use crate::voxelizer_cpu::VoxelizeResult;
+
+/// Why a heightmap or its options could not be used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeightmapError {
+    /// Raw data does not hold `width * height` 16-bit samples.
+    Size { expected: usize, actual: usize },
+    /// Malformed PGM header or pixel data.
+    Pgm(String),
+    /// Invalid generator options.
+    Options(String),
+}
+
+impl fmt::Display for HeightmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeightmapError::Size { expected, actual } => {
+                write!(f, "expected {expected} bytes of 16-bit heights, got {actual}")
+            }
+            HeightmapError::Pgm(msg) => write!(f, "PGM: {msg}"),
+            HeightmapError::Options(msg) => write!(f, "heightmap options: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for HeightmapError {}
+
+/// A decoded heightmap, samples normalized to 0–1, rows top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    pub width: u32,
+    pub height: u32,
+    samples: Vec<f32>,
+}
+
+impl Heightmap {
+    /// PGM if `bytes` starts with a `P5`/`P2` magic (its header supplies the
+    /// size), raw 16-bit little-endian `width × height` samples otherwise.
+    pub fn parse(bytes: &[u8], width: u32, height: u32) -> Result<Self, HeightmapError> {
+        if bytes.starts_with(b"P5") || bytes.starts_with(b"P2") {
+            Self::from_pgm(bytes)
+        } else {
+            Self::from_raw(bytes, width, height)
+        }
+    }
+
+    /// Raw 16-bit little-endian samples, row by row.
+    pub fn from_raw(bytes: &[u8], width: u32, height: u32) -> Result<Self, HeightmapError> {
+        let expected = width as usize * height as usize * 2;
+        if expected == 0 || bytes.len() != expected {
+            return Err(HeightmapError::Size { expected, actual: bytes.len() });
+        }
+        let samples = bytes
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as f32 / u16::MAX as f32)
+            .collect();
+        Ok(Self { width, height, samples })
+    }
+
+    /// Binary (`P5`, 8- or 16-bit big-endian) or ASCII (`P2`) PGM.
+    pub fn from_pgm(bytes: &[u8]) -> Result<Self, HeightmapError> {
+        let mut header = PgmHeader { bytes, pos: 2 };
+        let binary = bytes.starts_with(b"P5");
+        if !binary && !bytes.starts_with(b"P2") {
+            return Err(HeightmapError::Pgm("missing P5/P2 magic".to_string()));
+        }
+        let width = header.number("width")?;
+        let height = header.number("height")?;
+        let max = header.number("maxval")?;
+        if width == 0 || height == 0 || !(1..=u16::MAX as u32).contains(&max) {
+            let msg = format!("invalid size {width}x{height} or maxval {max}");
+            return Err(HeightmapError::Pgm(msg));
+        }
+        let count = width as usize * height as usize;
+
+        let samples: Vec<u32> = if binary {
+            // Exactly one whitespace byte separates the header from the data
+            let data = bytes.get(header.pos + 1..).unwrap_or_default();
+            let wide = max > u8::MAX as u32;
+            let needed = count * if wide { 2 } else { 1 };
+            if data.len() < needed {
+                return Err(HeightmapError::Pgm(format!(
+                    "{} bytes of pixel data, expected {needed}",
+                    data.len()
+                )));
+            }
+            match wide {
+                true => data[..needed]
+                    .chunks_exact(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]) as u32)
+                    .collect(),
+                false => data[..count].iter().map(|&b| b as u32).collect(),
+            }
+        } else {
+            (0..count).map(|_| header.number("sample")).collect::<Result<_, _>>()?
+        };
+        let samples = samples.into_iter().map(|s| s.min(max) as f32 / max as f32).collect();
+        Ok(Self { width, height, samples })
+    }
+
+    /// Sample at pixel `(x, row)`, clamped to the edges.
+    pub fn sample(&self, x: i64, row: i64) -> f32 {
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let row = row.clamp(0, self.height as i64 - 1) as usize;
+        self.samples[row * self.width as usize + x]
+    }
+}
+
+/// Material for terrain voxels up to a height and slope.
+#[derive(Clone, Copy)]
+pub struct MaterialBand {
+    /// Highest voxel Y the band covers.
+    pub max_height: f32,
+    /// Steepest terrain the band covers, in degrees from horizontal.
+    pub max_slope: f32,
+    pub material: MaterialEntry,
+}
+
+/// How [`generate_terrain`] turns samples into voxels.
+#[derive(Clone)]
+pub struct HeightmapOptions {
+    /// Voxels between a black and a white sample.
+    pub vertical_scale: f32,
+    /// Lowest voxel Y; every column is solid from here to its surface.
+    pub floor: i32,
+    /// Checked in order per voxel; voxels no band covers use the last one.
+    /// At most `MAX_PALETTE_ENTRIES - 1` so every chunk fits one palette.
+    pub bands: Vec<MaterialBand>,
+}
+
+impl Default for HeightmapOptions {
+    /// 64 voxels of relief over a floor at 0: sand at the bottom, grass on
+    /// gentle slopes, rock on steep ones and snow on the peaks.
+    fn default() -> Self {
+        let band = |max_height, max_slope, albedo, roughness| MaterialBand {
+            max_height,
+            max_slope,
+            material: MaterialEntry::new(albedo, roughness, [0.0; 3], 1.0),
+        };
+        let rock = band(f32::INFINITY, 90.0, [0.36, 0.34, 0.32], 0.9);
+        Self {
+            vertical_scale: 64.0,
+            floor: 0,
+            bands: vec![
+                band(6.0, 30.0, [0.76, 0.70, 0.50], 0.95),
+                band(40.0, 35.0, [0.25, 0.45, 0.15], 0.9),
+                MaterialBand { max_height: 52.0, ..rock },
+                band(f32::INFINITY, 50.0, [0.92, 0.94, 0.96], 0.6),
+                rock,
+            ],
+        }
+    }
+}
+
+impl HeightmapOptions {
+    /// Options from a JSON object; missing members keep their defaults:
+    ///
+    /// ```json
+    /// { "verticalScale": 96, "floor": -8,
+    ///   "bands": [{ "maxHeight": 10, "maxSlope": 40, "color": [0.8, 0.7, 0.5],
+    ///               "roughness": 0.9, "emissive": [0, 0, 0] }] }
+    /// ```
+    ///
+    /// Band limits default to unbounded. An empty string gives the defaults.
+    pub fn from_json(text: &str) -> Result<Self, HeightmapError> {
+        let mut options = Self::default();
+        if text.trim().is_empty() {
+            return Ok(options);
+        }
+        let root = parse_json(text).map_err(|e| HeightmapError::Options(e.to_string()))?;
+        if root.as_object().is_none() {
+            return Err(HeightmapError::Options("expected an object".to_string()));
+        }
+        let number = |value: &JsonValue, key: &str| {
+            value.as_f32().ok_or_else(|| HeightmapError::Options(format!("{key} must be a number")))
+        };
+        if let Some(v) = root.get("verticalScale") {
+            options.vertical_scale = number(v, "verticalScale")?;
+        }
+        if let Some(v) = root.get("floor") {
+            options.floor = number(v, "floor")?.round() as i32;
+        }
+        if let Some(v) = root.get("bands") {
+            let bands = v
+                .as_array()
+                .ok_or_else(|| HeightmapError::Options("bands must be an array".to_string()))?;
+            options.bands = bands
+                .iter()
+                .map(|band| {
+                    let limit = |key| band.get(key).map_or(Ok(f32::INFINITY), |v| number(v, key));
+                    let rgb = |key, default| match band.get(key) {
+                        None => Ok(default),
+                        Some(v) => match v.as_f32_vec().as_deref() {
+                            Some(&[r, g, b]) => Ok([r, g, b]),
+                            _ => Err(HeightmapError::Options(format!("{key} must be [r, g, b]"))),
+                        },
+                    };
+                    let roughness =
+                        band.get("roughness").map_or(Ok(0.9), |v| number(v, "roughness"))?;
+                    Ok(MaterialBand {
+                        max_height: limit("maxHeight")?,
+                        max_slope: limit("maxSlope")?,
+                        material: MaterialEntry::new(
+                            rgb("color", [0.5; 3])?,
+                            roughness,
+                            rgb("emissive", [0.0; 3])?,
+                            1.0,
+                        ),
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        options.validate()?;
+        Ok(options)
+    }
+
+    fn validate(&self) -> Result<(), HeightmapError> {
+        if !(self.vertical_scale.is_finite() && self.vertical_scale >= 0.0) {
+            return Err(HeightmapError::Options(format!(
+                "invalid vertical scale {}",
+                self.vertical_scale
+            )));
+        }
+        if self.bands.is_empty() || self.bands.len() >= MAX_PALETTE_ENTRIES as usize {
+            return Err(HeightmapError::Options(format!(
+                "need 1 to {} material bands, got {}",
+                MAX_PALETTE_ENTRIES - 1,
+                self.bands.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Build solid terrain chunks for every chunk the heightmap's columns touch.
+/// Band `i` gets material ID `i + 2`. Maps whose chunk columns times chunk
+/// layers could exceed [`MAX_SLOTS`] are rejected before any sampling.
+pub fn generate_terrain(
+    map: &Heightmap,
+    options: &HeightmapOptions,
+) -> Result<VoxelizeResult, HeightmapError> {
+    options.validate()?;
+    let (w, h) = (map.width as i64, map.height as i64);
+
+    // Upper bound: samples are 0–1, so surfaces lie in 0..=vertical_scale
+    let cs = CS as i64;
+    let columns = ((w + cs - 1) / cs) * ((h + cs - 1) / cs);
+    let highest = (options.vertical_scale.round() as i64).div_euclid(cs);
+    let layers = highest - (options.floor.min(0) as i64).div_euclid(cs) + 1;
+    let estimated = columns.saturating_mul(layers);
+    if estimated > MAX_SLOTS as i64 {
+        return Err(HeightmapError::Options(format!(
+            "terrain needs up to {estimated} chunks ({columns} columns × {layers} layers), \
+             more than the {MAX_SLOTS} pool slots; lower verticalScale or the map size"
+        )));
+    }
+
+    // Surface Y and slope per column
+    let mut tops = Vec::with_capacity((w * h) as usize);
+    let mut slopes = Vec::with_capacity((w * h) as usize);
+    for row in 0..h {
+        for x in 0..w {
+            let top = (map.sample(x, row) * options.vertical_scale).round() as i32;
+            let dx = (map.sample(x + 1, row) - map.sample(x - 1, row)) * 0.5;
+            let dz = (map.sample(x, row + 1) - map.sample(x, row - 1)) * 0.5;
+            let gradient = (dx * dx + dz * dz).sqrt() * options.vertical_scale;
+            tops.push(top);
+            slopes.push(gradient.atan().to_degrees());
+        }
+    }
+    let max_top = tops.iter().copied().max().unwrap_or(0);
+    // Columns below the floor still get their surface voxel
+    let bottom = tops.iter().copied().min().unwrap_or(0).min(options.floor);
+
+    let mut materials =
+        vec![MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0); MAX_MATERIALS as usize];
+    materials[MATERIAL_DEFAULT as usize] = MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0);
+    for (i, band) in options.bands.iter().enumerate() {
+        materials[i + 2] = band.material;
+    }
+    let band_at = |y: i32, slope: f32| {
+        options
+            .bands
+            .iter()
+            .position(|b| y as f32 <= b.max_height && slope <= b.max_slope)
+            .unwrap_or(options.bands.len() - 1)
+    };
+
+    let cs = cs as i32;
+    let mut chunks = Vec::new();
+    for cy in bottom.div_euclid(cs)..=max_top.div_euclid(cs) {
+        for cz in 0..=((h - 1) / CS as i64) as i32 {
+            for cx in 0..=((w - 1) / CS as i64) as i32 {
+                let coord = ChunkCoord { x: cx, y: cy, z: cz };
+                if let Some(chunk) = terrain_chunk(coord, map, options, &tops, &slopes, band_at) {
+                    chunks.push(chunk);
+                }
+            }
+        }
+    }
+
+    let extent = [w as f32, (max_top - bottom + 1) as f32, h as f32];
+    Ok(VoxelizeResult {
+        chunks,
+        materials,
+        voxel_size: 1.0,
+        grid_origin: [0.0; 3],
+        mesh_center: [extent[0] * 0.5, bottom as f32 + extent[1] * 0.5, extent[2] * 0.5],
+        mesh_extent: extent[0].max(extent[1]).max(extent[2]),
+        quantize: Default::default(),
+    })
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+/// Fill the columns of one chunk. `None` if it holds no voxels.
+fn terrain_chunk(
+    coord: ChunkCoord,
+    map: &Heightmap,
+    options: &HeightmapOptions,
+    tops: &[i32],
+    slopes: &[f32],
+    band_at: impl Fn(i32, f32) -> usize,
+) -> Option<ChunkData> {
+    let cs = CS as i32;
+    let base = [coord.x * cs, coord.y * cs, coord.z * cs];
+    let mut occupancy = OccupancyBuilder::new();
+    let mut palette = PaletteBuilder::new();
+    let mut index_buf = IndexBufBuilder::new();
+    let mut band_index = vec![None; options.bands.len()];
+    let mut any = false;
+
+    for lz in 0..cs {
+        let row = base[2] + lz;
+        if row >= map.height as i32 {
+            break;
+        }
+        for lx in 0..cs {
+            let x = base[0] + lx;
+            if x >= map.width as i32 {
+                break;
+            }
+            let column = row as usize * map.width as usize + x as usize;
+            let top = tops[column];
+            let lo = options.floor.min(top).max(base[1]);
+            let hi = top.min(base[1] + cs - 1);
+            for y in lo..=hi {
+                let band = band_at(y, slopes[column]);
+                let index = *band_index[band].get_or_insert_with(|| palette.add(band as u16 + 2));
+                let (px, py, pz) = ((lx + 1) as u32, (y - base[1] + 1) as u32, (lz + 1) as u32);
+                occupancy.set(px, py, pz);
+                index_buf.set(px, py, pz, index);
+                any = true;
+            }
+        }
+    }
+
+    any.then_some(ChunkData { coord, occupancy, palette, index_buf })
+}
+
+/// Whitespace/comment-separated header fields of a PGM.
+struct PgmHeader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl PgmHeader<'_> {
+    /// Next decimal field; `pos` ends on the byte after it.
+    fn number(&mut self, what: &str) -> Result<u32, HeightmapError> {
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(b'#') => {
+                    while !matches!(self.bytes.get(self.pos), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| HeightmapError::Pgm(format!("missing {what}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(width: u32, height: u32, f: impl Fn(u32, u32) -> u16) -> Heightmap {
+        let bytes: Vec<u8> = (0..height)
+            .flat_map(|row| (0..width).map(move |x| (x, row)))
+            .flat_map(|(x, row)| f(x, row).to_le_bytes())
+            .collect();
+        Heightmap::from_raw(&bytes, width, height).unwrap()
+    }
+
+    fn voxel(result: &VoxelizeResult, g: [i32; 3]) -> Option<u16> {
+        let cs = CS as i32;
+        let coord = g.map(|c| c.div_euclid(cs));
+        let [x, y, z] = g.map(|c| (c.rem_euclid(cs) + 1) as u32);
+        let chunk = result
+            .chunks
+            .iter()
+            .find(|c| [c.coord.x, c.coord.y, c.coord.z] == coord)?;
+        chunk
+            .occupancy
+            .get(x, y, z)
+            .then(|| chunk.palette.entries()[chunk.index_buf.get(x, y, z) as usize])
+    }
+
+    fn one_band() -> Vec<MaterialBand> {
+        vec![MaterialBand {
+            max_height: f32::INFINITY,
+            max_slope: f32::INFINITY,
+            material: MaterialEntry::new([0.5; 3], 0.5, [0.0; 3], 1.0),
+        }]
+    }
+
+    #[test]
+    fn parse_raw_and_pgm() {
+        let map = raw(2, 1, |x, _| if x == 0 { 0 } else { u16::MAX });
+        assert_eq!((map.sample(0, 0), map.sample(1, 0)), (0.0, 1.0));
+        assert_eq!(map.sample(5, -3), 1.0, "samples clamp to the edges");
+        assert_eq!(
+            Heightmap::from_raw(&[0; 5], 2, 1),
+            Err(HeightmapError::Size { expected: 4, actual: 5 })
+        );
+
+        let p5 = [b"P5\n# comment\n2 1\n255\n".as_slice(), &[0, 255]].concat();
+        let map = Heightmap::parse(&p5, 0, 0).unwrap();
+        assert_eq!((map.width, map.height), (2, 1));
+        assert_eq!((map.sample(0, 0), map.sample(1, 0)), (0.0, 1.0));
+
+        let wide = [b"P5 1 1 1000\n".as_slice(), &500u16.to_be_bytes()].concat();
+        assert_eq!(Heightmap::parse(&wide, 0, 0).unwrap().sample(0, 0), 0.5);
+
+        let ascii = Heightmap::parse(b"P2\n2 2 # size\n4\n0 1\n2 4\n", 0, 0).unwrap();
+        assert_eq!(ascii.sample(0, 1), 0.5);
+        assert_eq!(ascii.sample(1, 1), 1.0);
+
+        assert!(matches!(Heightmap::parse(b"P5 2 1 255\n\x00", 0, 0), Err(HeightmapError::Pgm(_))));
+        assert!(matches!(Heightmap::parse(b"P2 2 1\n", 0, 0), Err(HeightmapError::Pgm(_))));
+    }
+
+    #[test]
+    fn columns_fill_from_floor_across_chunks() {
+        // A 70-wide ramp from 0 to 100 voxels, solid from y = -5
+        let map = raw(70, 1, |x, _| (x * u16::MAX as u32 / 69) as u16);
+        let options = HeightmapOptions { vertical_scale: 100.0, floor: -5, bands: one_band() };
+        let result = generate_terrain(&map, &options).unwrap();
+
+        assert_eq!(voxel(&result, [0, -5, 0]), Some(2));
+        assert_eq!(voxel(&result, [0, -6, 0]), None);
+        assert_eq!(voxel(&result, [0, 0, 0]), Some(2));
+        assert_eq!(voxel(&result, [0, 1, 0]), None);
+        assert_eq!(voxel(&result, [69, 100, 0]), Some(2));
+        assert_eq!(voxel(&result, [69, 101, 0]), None);
+        assert_eq!(voxel(&result, [70, 0, 0]), None);
+        assert_eq!(voxel(&result, [0, 0, 1]), None);
+
+        let count: u32 = result.chunks.iter().map(|c| c.occupancy.popcount()).sum();
+        let expected: i32 = (0..70).map(|x| (x * 100 + 34) / 69 + 6).sum();
+        assert_eq!(count as i32, expected);
+        // x spans 2 chunks, y from -5 to 100 spans 3
+        let mut coords: Vec<_> = result.chunks.iter().map(|c| (c.coord.x, c.coord.y)).collect();
+        coords.sort();
+        assert_eq!(coords, vec![(0, -1), (0, 0), (0, 1), (1, -1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn oversized_terrain_rejected() {
+        // 1 column, but a relief of 62 × 4096 voxels needs 4097 layers
+        let map = raw(2, 2, |_, _| 0);
+        let tall = (CS * MAX_SLOTS) as f32;
+        let options = HeightmapOptions { vertical_scale: tall, floor: 0, bands: one_band() };
+        let result = generate_terrain(&map, &options);
+        assert!(matches!(result, Err(HeightmapError::Options(msg)) if msg.contains("4097")));
+        let options = HeightmapOptions { vertical_scale: 64.0, ..options };
+        assert!(generate_terrain(&map, &options).is_ok());
+    }
+
+    #[test]
+    fn bands_by_height_and_slope() {
+        let band = |max_height, max_slope, v| MaterialBand {
+            max_height,
+            max_slope,
+            material: MaterialEntry::new([v; 3], 0.5, [0.0; 3], 1.0),
+        };
+        let options = HeightmapOptions {
+            vertical_scale: 20.0,
+            floor: 0,
+            bands: vec![
+                band(4.0, 30.0, 0.1),
+                band(f32::INFINITY, 30.0, 0.2),
+                band(f32::INFINITY, 90.0, 0.3),
+            ],
+        };
+        // Flat plateau at 10 on the left, cliff down to 0 on the right
+        let map = raw(8, 8, |x, _| if x < 4 { u16::MAX / 2 } else { 0 });
+        let result = generate_terrain(&map, &options).unwrap();
+        assert_eq!(voxel(&result, [0, 3, 0]), Some(2), "low and flat");
+        assert_eq!(voxel(&result, [0, 10, 0]), Some(3), "high and flat");
+        assert_eq!(voxel(&result, [3, 8, 0]), Some(4), "steep cliff edge");
+        assert_eq!(voxel(&result, [7, 0, 0]), Some(2));
+        assert_eq!(result.materials[4].albedo()[0], band(0.0, 0.0, 0.3).material.albedo()[0]);
+    }
+
+    #[test]
+    fn options_from_json() {
+        let options = HeightmapOptions::from_json(
+            r#"{ "verticalScale": 96, "floor": -8,
+                 "bands": [{ "maxHeight": 10, "color": [1, 0, 0] }, { "roughness": 0.2 }] }"#,
+        )
+        .unwrap();
+        assert_eq!((options.vertical_scale, options.floor), (96.0, -8));
+        assert_eq!(options.bands.len(), 2);
+        assert_eq!(options.bands[0].max_height, 10.0);
+        assert_eq!(options.bands[0].max_slope, f32::INFINITY);
+        assert_eq!(options.bands[0].material.albedo(), [1.0, 0.0, 0.0]);
+        assert!((options.bands[1].material.roughness() - 0.2).abs() < 1e-3);
+
+        assert_eq!(HeightmapOptions::from_json("").unwrap().bands.len(), 5);
+        for bad in [
+            "[]",
+            r#"{"floor": "low"}"#,
+            r#"{"bands": []}"#,
+            r#"{"bands": [{"color": [1, 0]}]}"#,
+            r#"{"verticalScale": -1}"#,
+        ] {
+            let result = HeightmapOptions::from_json(bad);
+            assert!(matches!(result, Err(HeightmapError::Options(_))), "{bad}");
+        }
+    }
+}
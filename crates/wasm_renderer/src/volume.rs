@@ -0,0 +1,878 @@
+//! Dense volume importer — no GPU, no JS types.
+//!
+//! Reads raw 8/16-bit sample grids (CT scans, simulation output), either
+//! headerless with the layout given explicitly or behind an NRRD header,
+//! and classifies each sample with an iso-threshold or a piecewise transfer
+//! function. Samples are stored X fastest, then Y, then Z, and map to grid
+//! X/Y/Z directly.
+//!
+//! Import streams one Z slice at a time: only the slice being read and the
+//! chunk layer it falls in are held, so volumes far larger than memory as a
+//! dense array still import, pulled from a reader or pushed in pieces
+//! through a [`VolumeStream`]. Anisotropic spacing repeats samples along
+//! the coarser axes so voxels stay cubic at the finest spacing. At most
+//! [`MAX_SLOTS`] non-empty chunks are accepted; the import stops at the
+//! first chunk past that.
+
+use std::fmt;
+use std::io::Read;
+
+use crate::json::{parse_json, JsonValue};
+use crate::pool::*;
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
+use crate::voxelizer_cpu::VoxelizeResult;
+
+/// Why a volume or its options could not be used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VolumeError {
+    /// The data holds fewer bytes than the layout needs.
+    Size { expected: u64, actual: u64 },
+    /// Malformed or unsupported NRRD header.
+    Nrrd(String),
+    /// Invalid layout or classification options.
+    Options(String),
+    /// The reader failed.
+    Io(String),
+    /// More non-empty chunks than the pool has slots.
+    TooManyChunks,
+}
+
+impl fmt::Display for VolumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VolumeError::Size { expected, actual } => {
+                write!(f, "expected {expected} bytes of samples, got {actual}")
+            }
+            VolumeError::Nrrd(msg) => write!(f, "NRRD: {msg}"),
+            VolumeError::Options(msg) => write!(f, "volume options: {msg}"),
+            VolumeError::Io(msg) => write!(f, "read failed: {msg}"),
+            VolumeError::TooManyChunks => write!(
+                f,
+                "volume has more than {MAX_SLOTS} non-empty chunks; raise the threshold or crop it"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VolumeError {}
+
+/// Storage type of one sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleType {
+    U8,
+    U16,
+    I16,
+}
+
+impl SampleType {
+    pub fn bytes(self) -> usize {
+        match self {
+            SampleType::U8 => 1,
+            SampleType::U16 | SampleType::I16 => 2,
+        }
+    }
+
+    /// Accepts the NRRD type names (`uchar`, `uint8`, `ushort`, `int16`, …).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "uchar" | "unsigned char" | "uint8" | "uint8_t" => Some(SampleType::U8),
+            "ushort" | "unsigned short" | "unsigned short int" | "uint16" | "uint16_t" => {
+                Some(SampleType::U16)
+            }
+            "short" | "short int" | "signed short" | "signed short int" | "int16" | "int16_t" => {
+                Some(SampleType::I16)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Memory layout of a sample grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeLayout {
+    /// Samples along X, Y and Z.
+    pub dims: [u32; 3],
+    /// World distance between samples along each axis.
+    pub spacing: [f32; 3],
+    pub sample: SampleType,
+    pub big_endian: bool,
+}
+
+impl VolumeLayout {
+    /// Bytes of sample data the layout needs.
+    pub fn data_len(&self) -> u64 {
+        self.dims.iter().map(|&d| d as u64).product::<u64>() * self.sample.bytes() as u64
+    }
+
+    /// Grid voxel size (the finest spacing) and how many voxels each sample
+    /// covers along each axis.
+    pub fn voxel_grid(&self) -> (f32, [u32; 3]) {
+        let size = self.spacing.iter().copied().fold(f32::INFINITY, f32::min);
+        (size, self.spacing.map(|s| ((s / size).round() as u32).max(1)))
+    }
+
+    fn validate(&self) -> Result<(), VolumeError> {
+        if self.dims.contains(&0) {
+            return Err(VolumeError::Options(format!("empty dimensions {:?}", self.dims)));
+        }
+        if !self.spacing.iter().all(|s| s.is_finite() && *s > 0.0) {
+            return Err(VolumeError::Options(format!("invalid spacing {:?}", self.spacing)));
+        }
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> f32 {
+        match self.sample {
+            SampleType::U8 => bytes[0] as f32,
+            SampleType::U16 | SampleType::I16 => {
+                let pair = [bytes[0], bytes[1]];
+                let bits = if self.big_endian {
+                    u16::from_be_bytes(pair)
+                } else {
+                    u16::from_le_bytes(pair)
+                };
+                match self.sample {
+                    SampleType::I16 => bits as i16 as f32,
+                    _ => bits as f32,
+                }
+            }
+        }
+    }
+}
+
+/// Samples in `min..=max` (raw units) take `material`.
+#[derive(Clone, Copy)]
+pub struct TransferRange {
+    pub min: f32,
+    pub max: f32,
+    pub material: MaterialEntry,
+}
+
+/// How samples become voxels.
+#[derive(Clone)]
+pub enum Classifier {
+    /// Samples at or above `iso` are solid.
+    Threshold { iso: f32, material: MaterialEntry },
+    /// Each sample takes the first range containing it; samples no range
+    /// contains are empty. At most `MAX_PALETTE_ENTRIES - 1` ranges so every
+    /// chunk fits one palette.
+    Transfer(Vec<TransferRange>),
+}
+
+impl Classifier {
+    /// Material ID for a sample, 0 for empty. Range `i` (or the threshold
+    /// material) gets material ID `i + 2`.
+    pub fn classify(&self, value: f32) -> u16 {
+        match self {
+            Classifier::Threshold { iso, .. } => if value >= *iso { 2 } else { 0 },
+            Classifier::Transfer(ranges) => ranges
+                .iter()
+                .position(|r| (r.min..=r.max).contains(&value))
+                .map_or(0, |i| i as u16 + 2),
+        }
+    }
+
+    /// Full material table for the IDs [`classify`](Self::classify) returns.
+    pub fn materials(&self) -> Vec<MaterialEntry> {
+        let mut table =
+            vec![MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0); MAX_MATERIALS as usize];
+        table[MATERIAL_DEFAULT as usize] = MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0);
+        match self {
+            Classifier::Threshold { material, .. } => table[2] = *material,
+            Classifier::Transfer(ranges) => {
+                for (i, range) in ranges.iter().enumerate() {
+                    table[i + 2] = range.material;
+                }
+            }
+        }
+        table
+    }
+
+    fn validate(&self) -> Result<(), VolumeError> {
+        if let Classifier::Transfer(ranges) = self {
+            if ranges.is_empty() || ranges.len() >= MAX_PALETTE_ENTRIES as usize {
+                return Err(VolumeError::Options(format!(
+                    "need 1 to {} transfer ranges, got {}",
+                    MAX_PALETTE_ENTRIES - 1,
+                    ranges.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Import options: the layout of headerless data and the classifier.
+#[derive(Clone)]
+pub struct VolumeOptions {
+    /// Required for raw data; ignored for NRRD, whose header has it.
+    pub layout: Option<VolumeLayout>,
+    pub classifier: Classifier,
+}
+
+impl VolumeOptions {
+    /// Options from a JSON object:
+    ///
+    /// ```json
+    /// { "dims": [256, 256, 128], "spacing": [0.5, 0.5, 1.0],
+    ///   "type": "uint16", "endian": "little",
+    ///   "transfer": [{ "min": 300, "max": 3000, "color": [0.9, 0.9, 0.85],
+    ///                  "roughness": 0.6, "emissive": [0, 0, 0] }] }
+    /// ```
+    ///
+    /// `dims` and `type` give the raw layout (spacing defaults to 1,
+    /// endianness to little). Instead of `transfer`, `threshold` with an
+    /// optional `color`/`roughness`/`emissive` makes every sample at or
+    /// above it solid; with neither, any positive sample is solid. An empty
+    /// string gives no layout and that default threshold.
+    pub fn from_json(text: &str) -> Result<Self, VolumeError> {
+        let error = |msg: &str| VolumeError::Options(msg.to_string());
+        let text = if text.trim().is_empty() { "{}" } else { text };
+        let root = parse_json(text).map_err(|e| VolumeError::Options(e.to_string()))?;
+        if root.as_object().is_none() {
+            return Err(error("expected an object"));
+        }
+
+        let layout = match root.get("dims") {
+            None => None,
+            Some(dims) => {
+                let dims = match dims.as_array() {
+                    Some([x, y, z]) => [x, y, z].map(|v| v.as_usize().map(|n| n as u32)),
+                    _ => [None; 3],
+                };
+                let [Some(x), Some(y), Some(z)] = dims else {
+                    return Err(error("dims must be [x, y, z] sample counts"));
+                };
+                let spacing = match root.get("spacing").map(|v| v.as_f32_vec()) {
+                    None => [1.0; 3],
+                    Some(Some(s)) if s.len() == 3 => [s[0], s[1], s[2]],
+                    Some(_) => return Err(error("spacing must be [x, y, z]")),
+                };
+                let sample = root
+                    .get("type")
+                    .and_then(JsonValue::as_str)
+                    .and_then(SampleType::from_name)
+                    .ok_or_else(|| error("type must be uint8, uint16 or int16"))?;
+                let big_endian = match root.get("endian").map(|v| v.as_str()) {
+                    None | Some(Some("little")) => false,
+                    Some(Some("big")) => true,
+                    Some(_) => return Err(error("endian must be \"little\" or \"big\"")),
+                };
+                Some(VolumeLayout { dims: [x, y, z], spacing, sample, big_endian })
+            }
+        };
+
+        let classifier = match (root.get("transfer"), root.get("threshold")) {
+            (Some(_), Some(_)) => return Err(error("give either transfer or threshold")),
+            (Some(ranges), None) => Classifier::Transfer(
+                ranges
+                    .as_array()
+                    .ok_or_else(|| error("transfer must be an array"))?
+                    .iter()
+                    .map(|range| {
+                        let bound = |key, default| match range.get(key) {
+                            None => Ok(default),
+                            Some(v) => v
+                                .as_f32()
+                                .ok_or_else(|| error(&format!("{key} must be a number"))),
+                        };
+                        Ok(TransferRange {
+                            min: bound("min", f32::NEG_INFINITY)?,
+                            max: bound("max", f32::INFINITY)?,
+                            material: material(range)?,
+                        })
+                    })
+                    .collect::<Result<_, VolumeError>>()?,
+            ),
+            (None, threshold) => Classifier::Threshold {
+                iso: match threshold {
+                    None => f32::MIN_POSITIVE,
+                    Some(v) => v.as_f32().ok_or_else(|| error("threshold must be a number"))?,
+                },
+                material: material(&root)?,
+            },
+        };
+        classifier.validate()?;
+        Ok(Self { layout, classifier })
+    }
+}
+
+/// Parse an NRRD header. Returns the layout and the offset of the sample
+/// data. Only raw encoding with the data attached is supported.
+pub fn parse_nrrd(bytes: &[u8]) -> Result<(VolumeLayout, usize), VolumeError> {
+    let error = VolumeError::Nrrd;
+    if !bytes.starts_with(b"NRRD") {
+        return Err(error("missing NRRD magic".to_string()));
+    }
+    let Some(pos) = nrrd_header_len(bytes) else {
+        return Err(error("header has no terminating blank line".to_string()));
+    };
+    let lines: Vec<String> = bytes[..pos]
+        .split(|&b| b == b'\n')
+        .map(|line| String::from_utf8_lossy(line).trim_end().to_string())
+        .take_while(|line| !line.is_empty())
+        .collect();
+
+    let mut dims = None;
+    let mut spacing = [1.0; 3];
+    let mut sample = None;
+    let mut big_endian = false;
+    for line in &lines[1..] {
+        // Comments and `key:=value` pairs carry nothing the import needs
+        if line.starts_with('#') || line.contains(":=") {
+            continue;
+        }
+        let Some((field, value)) = line.split_once(": ") else {
+            return Err(error(format!("malformed line \"{line}\"")));
+        };
+        let numbers = |value: &str| -> Vec<f32> {
+            value.split_whitespace().filter_map(|n| n.parse().ok()).collect()
+        };
+        match field {
+            "type" => {
+                let kind = SampleType::from_name(value.trim())
+                    .ok_or_else(|| error(format!("unsupported type \"{value}\"")))?;
+                sample = Some(kind);
+            }
+            "dimension" if value.trim() != "3" => {
+                return Err(error(format!("need 3 dimensions, got {value}")));
+            }
+            "sizes" => {
+                let sizes: Vec<u32> =
+                    value.split_whitespace().filter_map(|n| n.parse().ok()).collect();
+                let &[x, y, z] = sizes.as_slice() else {
+                    return Err(error(format!("invalid sizes \"{value}\"")));
+                };
+                dims = Some([x, y, z]);
+            }
+            "spacings" => {
+                if let &[x, y, z] = numbers(value).as_slice() {
+                    spacing = [x, y, z];
+                }
+            }
+            "space directions" => {
+                // One "(dx,dy,dz)" vector per axis; spacing is its length
+                let vectors: Vec<f32> = value
+                    .split_whitespace()
+                    .map(|v| numbers(&v.replace(['(', ')', ','], " ")))
+                    .map(|v| v.iter().map(|c| c * c).sum::<f32>().sqrt())
+                    .collect();
+                if let &[x, y, z] = vectors.as_slice() {
+                    spacing = [x, y, z];
+                }
+            }
+            "endian" => big_endian = value.trim() == "big",
+            "encoding" if value.trim() != "raw" => {
+                return Err(error(format!("unsupported encoding \"{value}\"")));
+            }
+            "data file" | "datafile" => {
+                return Err(error("detached data files are not supported".to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    let layout = VolumeLayout {
+        dims: dims.ok_or_else(|| error("missing sizes".to_string()))?,
+        spacing,
+        sample: sample.ok_or_else(|| error("missing type".to_string()))?,
+        big_endian,
+    };
+    layout.validate().map_err(|e| error(e.to_string()))?;
+    Ok((layout, pos))
+}
+
+/// Stream a volume from `reader` and hand each finished, non-empty chunk to
+/// `emit`. Chunks arrive one Z layer at a time.
+pub fn import_volume(
+    mut reader: impl Read,
+    layout: &VolumeLayout,
+    classifier: &Classifier,
+    mut emit: impl FnMut(ChunkData),
+) -> Result<(), VolumeError> {
+    let mut slices = SliceImporter::new(layout, classifier)?;
+    let mut raw = vec![0u8; slices.slice_len()];
+    for z in 0..layout.dims[2] as usize {
+        read_slice(&mut reader, &mut raw, layout, z)?;
+        slices.push(&raw, &mut emit)?;
+    }
+    slices.finish(&mut emit)
+}
+
+/// Import a whole volume held in memory: NRRD if `bytes` starts with the
+/// NRRD magic, raw samples laid out as `options.layout` otherwise.
+pub fn load_volume(bytes: &[u8], options: &VolumeOptions) -> Result<VoxelizeResult, VolumeError> {
+    let mut stream = VolumeStream::new(options.clone());
+    stream.push(bytes)?;
+    stream.finish()
+}
+
+/// A volume import fed in pieces of any size, for data that can't be held
+/// in memory whole (a file read slice by slice from JS). Takes the same
+/// bytes as [`load_volume`]: an NRRD header is recognized in the first
+/// pieces. Holds at most one partial sample slice between pushes.
+pub struct VolumeStream {
+    options: VolumeOptions,
+    /// Header bytes until the layout is known, then a partial slice.
+    pending: Vec<u8>,
+    /// `None` until the layout is known.
+    slices: Option<SliceImporter>,
+    chunks: Vec<ChunkData>,
+}
+
+impl VolumeStream {
+    pub fn new(options: VolumeOptions) -> Self {
+        Self { options, pending: Vec::new(), slices: None, chunks: Vec::new() }
+    }
+
+    /// Feed the next bytes of the volume.
+    pub fn push(&mut self, mut bytes: &[u8]) -> Result<(), VolumeError> {
+        let Some(slices) = self.slices.as_mut() else {
+            self.pending.extend_from_slice(bytes);
+            return match self.header()? {
+                Some((layout, offset)) => self.begin(layout, offset),
+                None => Ok(()),
+            };
+        };
+        let len = slices.slice_len();
+        let mut emit = |chunk| self.chunks.push(chunk);
+        if !self.pending.is_empty() {
+            let take = (len - self.pending.len()).min(bytes.len());
+            self.pending.extend_from_slice(&bytes[..take]);
+            bytes = &bytes[take..];
+            if self.pending.len() < len {
+                return Ok(());
+            }
+            slices.push(&self.pending, &mut emit)?;
+            self.pending.clear();
+        }
+        let mut whole = bytes.chunks_exact(len);
+        for slice in &mut whole {
+            slices.push(slice, &mut emit)?;
+        }
+        self.pending.extend_from_slice(whole.remainder());
+        Ok(())
+    }
+
+    /// Finish the import once every byte has been pushed.
+    pub fn finish(mut self) -> Result<VoxelizeResult, VolumeError> {
+        if self.slices.is_none() {
+            // Fewer bytes than a complete header: NRRD reports what's missing,
+            // anything else is short raw data
+            if self.pending.starts_with(b"NRRD") {
+                parse_nrrd(&self.pending)?;
+            }
+            self.begin(self.raw_layout()?, 0)?;
+        }
+        let slices = self.slices.take().expect("begin sets up the importer");
+        let layout = slices.layout;
+        if slices.z < layout.dims[2] as usize {
+            let actual = slices.z * slices.slice_len() + self.pending.len();
+            return Err(VolumeError::Size { expected: layout.data_len(), actual: actual as u64 });
+        }
+        let mut chunks = std::mem::take(&mut self.chunks);
+        slices.finish(&mut |chunk| chunks.push(chunk))?;
+
+        let (voxel_size, repeat) = layout.voxel_grid();
+        let extent = [0, 1, 2].map(|a| (layout.dims[a] * repeat[a]) as f32 * voxel_size);
+        Ok(VoxelizeResult {
+            chunks,
+            materials: self.options.classifier.materials(),
+            voxel_size,
+            grid_origin: [0.0; 3],
+            mesh_center: extent.map(|e| e * 0.5),
+            mesh_extent: extent[0].max(extent[1]).max(extent[2]),
+            quantize: Default::default(),
+        })
+    }
+
+    /// The layout and sample data offset once `pending` shows them:
+    /// immediately for raw data, after the terminating blank line for NRRD.
+    fn header(&self) -> Result<Option<(VolumeLayout, usize)>, VolumeError> {
+        let magic = b"NRRD";
+        if self.pending.starts_with(magic) {
+            match nrrd_header_len(&self.pending) {
+                Some(_) => parse_nrrd(&self.pending).map(Some),
+                None if self.pending.len() > MAX_NRRD_HEADER => {
+                    Err(VolumeError::Nrrd("header too long".to_string()))
+                }
+                None => Ok(None),
+            }
+        } else if magic.starts_with(&self.pending) {
+            Ok(None) // too short to tell
+        } else {
+            Ok(Some((self.raw_layout()?, 0)))
+        }
+    }
+
+    /// Set up the importer and feed it the sample data already in `pending`.
+    fn begin(&mut self, layout: VolumeLayout, offset: usize) -> Result<(), VolumeError> {
+        self.slices = Some(SliceImporter::new(&layout, &self.options.classifier)?);
+        let data = std::mem::take(&mut self.pending);
+        self.push(&data[offset..])
+    }
+
+    fn raw_layout(&self) -> Result<VolumeLayout, VolumeError> {
+        self.options
+            .layout
+            .ok_or_else(|| VolumeError::Options("raw data needs dims and type".to_string()))
+    }
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+/// Longest NRRD header a [`VolumeStream`] buffers while looking for its end.
+const MAX_NRRD_HEADER: usize = 1 << 16;
+
+/// Length of an NRRD header through its terminating blank line, or `None`
+/// if `bytes` doesn't hold all of it.
+fn nrrd_header_len(bytes: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    loop {
+        let len = bytes[pos..].iter().position(|&b| b == b'\n')?;
+        let line = &bytes[pos..pos + len];
+        pos += len + 1;
+        if line.trim_ascii_end().is_empty() {
+            return Some(pos);
+        }
+    }
+}
+
+/// Classifies one Z slice of samples at a time into the current chunk layer.
+struct SliceImporter {
+    layout: VolumeLayout,
+    classifier: Classifier,
+    repeat: [u32; 3],
+    chunks_x: usize,
+    classes: Vec<u16>,
+    layer: ChunkLayer,
+    /// Slices pushed so far.
+    z: usize,
+    /// Chunks emitted so far.
+    emitted: usize,
+}
+
+impl SliceImporter {
+    fn new(layout: &VolumeLayout, classifier: &Classifier) -> Result<Self, VolumeError> {
+        layout.validate()?;
+        classifier.validate()?;
+        let (_, repeat) = layout.voxel_grid();
+        let [width, height, _] = layout.dims.map(|d| d as usize);
+        let cs = CS as usize;
+        let chunks_x = (width * repeat[0] as usize).div_ceil(cs);
+        let chunks_y = (height * repeat[1] as usize).div_ceil(cs);
+        Ok(Self {
+            layout: *layout,
+            classifier: classifier.clone(),
+            repeat,
+            chunks_x,
+            classes: vec![0; width * height],
+            layer: ChunkLayer { chunks: (0..chunks_x * chunks_y).map(|_| None).collect(), z: 0 },
+            z: 0,
+            emitted: 0,
+        })
+    }
+
+    /// Bytes in one Z slice of samples.
+    fn slice_len(&self) -> usize {
+        self.classes.len() * self.layout.sample.bytes()
+    }
+
+    /// Classify the next slice, `slice_len` bytes. Slices past the volume's
+    /// depth are ignored.
+    fn push(&mut self, raw: &[u8], emit: &mut impl FnMut(ChunkData)) -> Result<(), VolumeError> {
+        if self.z >= self.layout.dims[2] as usize {
+            return Ok(());
+        }
+        let (layout, repeat, cs) = (&self.layout, self.repeat, CS as usize);
+        let samples = raw.chunks_exact(layout.sample.bytes());
+        for (class, sample) in self.classes.iter_mut().zip(samples) {
+            *class = self.classifier.classify(layout.decode(sample));
+        }
+
+        let width = layout.dims[0] as usize;
+        let z = self.z;
+        self.z += 1;
+        for vz in z * repeat[2] as usize..(z + 1) * repeat[2] as usize {
+            let chunk_z = (vz / cs) as i32;
+            if chunk_z != self.layer.z {
+                self.flush(emit)?;
+                self.layer.z = chunk_z;
+            }
+            let pz = (vz % cs + 1) as u32;
+            for (y, row) in self.classes.chunks_exact(width).enumerate() {
+                for vy in y * repeat[1] as usize..(y + 1) * repeat[1] as usize {
+                    for (x, &material) in row.iter().enumerate() {
+                        if material == 0 {
+                            continue;
+                        }
+                        for vx in x * repeat[0] as usize..(x + 1) * repeat[0] as usize {
+                            let slot = (vy / cs) * self.chunks_x + vx / cs;
+                            let p = [(vx % cs + 1) as u32, (vy % cs + 1) as u32, pz];
+                            self.layer.set(slot, self.chunks_x, p, material);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit the last chunk layer.
+    fn finish(mut self, emit: &mut impl FnMut(ChunkData)) -> Result<(), VolumeError> {
+        self.flush(emit)
+    }
+
+    /// Emit the current layer's chunks, failing past [`MAX_SLOTS`] chunks.
+    fn flush(&mut self, emit: &mut impl FnMut(ChunkData)) -> Result<(), VolumeError> {
+        for (chunk, _) in self.layer.chunks.iter_mut().filter_map(Option::take) {
+            if self.emitted == MAX_SLOTS as usize {
+                return Err(VolumeError::TooManyChunks);
+            }
+            self.emitted += 1;
+            emit(chunk);
+        }
+        Ok(())
+    }
+}
+
+/// The chunks of one Z chunk layer, created on their first voxel.
+struct ChunkLayer {
+    /// Row-major by chunk Y then X; each with its material → palette index
+    /// cache (0 = not added yet).
+    chunks: Vec<Option<(ChunkData, Vec<u8>)>>,
+    z: i32,
+}
+
+impl ChunkLayer {
+    fn set(&mut self, slot: usize, chunks_x: usize, p: [u32; 3], material: u16) {
+        let z = self.z;
+        let (chunk, cache) = self.chunks[slot].get_or_insert_with(|| {
+            let coord = ChunkCoord {
+                x: (slot % chunks_x) as i32,
+                y: (slot / chunks_x) as i32,
+                z,
+            };
+            let chunk = ChunkData {
+                coord,
+                occupancy: OccupancyBuilder::new(),
+                palette: PaletteBuilder::new(),
+                index_buf: IndexBufBuilder::new(),
+            };
+            (chunk, vec![0; MAX_PALETTE_ENTRIES as usize + 1])
+        });
+        let cached = &mut cache[material as usize];
+        if *cached == 0 {
+            *cached = chunk.palette.add(material);
+        }
+        chunk.occupancy.set(p[0], p[1], p[2]);
+        chunk.index_buf.set(p[0], p[1], p[2], *cached);
+    }
+}
+
+/// Fill `buf` with slice `z`, turning a short read into a size error.
+fn read_slice(
+    reader: &mut impl Read,
+    buf: &mut [u8],
+    layout: &VolumeLayout,
+    z: usize,
+) -> Result<(), VolumeError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => {
+                return Err(VolumeError::Size {
+                    expected: layout.data_len(),
+                    actual: (z * buf.len() + filled) as u64,
+                });
+            }
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(VolumeError::Io(e.to_string())),
+        }
+    }
+    Ok(())
+}
+
+/// `color`/`roughness`/`emissive` members of a range or the options root.
+fn material(value: &JsonValue) -> Result<MaterialEntry, VolumeError> {
+    let rgb = |key, default| match value.get(key) {
+        None => Ok(default),
+        Some(v) => match v.as_f32_vec().as_deref() {
+            Some(&[r, g, b]) => Ok([r, g, b]),
+            _ => Err(VolumeError::Options(format!("{key} must be [r, g, b]"))),
+        },
+    };
+    let roughness = match value.get("roughness") {
+        None => 0.7,
+        Some(v) => v
+            .as_f32()
+            .ok_or_else(|| VolumeError::Options("roughness must be a number".to_string()))?,
+    };
+    Ok(MaterialEntry::new(rgb("color", [0.8; 3])?, roughness, rgb("emissive", [0.0; 3])?, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voxel(result: &VoxelizeResult, g: [i32; 3]) -> Option<u16> {
+        let cs = CS as i32;
+        let coord = g.map(|c| c.div_euclid(cs));
+        let [x, y, z] = g.map(|c| (c.rem_euclid(cs) + 1) as u32);
+        let chunk = result
+            .chunks
+            .iter()
+            .find(|c| [c.coord.x, c.coord.y, c.coord.z] == coord)?;
+        chunk
+            .occupancy
+            .get(x, y, z)
+            .then(|| chunk.palette.entries()[chunk.index_buf.get(x, y, z) as usize])
+    }
+
+    fn count(result: &VoxelizeResult) -> u32 {
+        result.chunks.iter().map(|c| c.occupancy.popcount()).sum()
+    }
+
+    fn gray() -> MaterialEntry {
+        MaterialEntry::new([0.5; 3], 0.5, [0.0; 3], 1.0)
+    }
+
+    /// `dims` u16 little-endian samples from `f(x, y, z)`.
+    fn samples(dims: [u32; 3], f: impl Fn(u32, u32, u32) -> u16) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for z in 0..dims[2] {
+            for y in 0..dims[1] {
+                for x in 0..dims[0] {
+                    bytes.extend_from_slice(&f(x, y, z).to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn threshold_streams_chunk_layers() {
+        // A 70³ gradient along X: solid where x >= 40, across two chunks per axis
+        let dims = [70, 70, 70];
+        let bytes = samples(dims, |x, _, _| x as u16 * 10);
+        let layout =
+            VolumeLayout { dims, spacing: [1.0; 3], sample: SampleType::U16, big_endian: false };
+        let classifier = Classifier::Threshold { iso: 400.0, material: gray() };
+
+        let mut layers = Vec::new();
+        import_volume(bytes.as_slice(), &layout, &classifier, |c| layers.push(c.coord.z)).unwrap();
+        assert_eq!(layers, [0, 0, 0, 0, 1, 1, 1, 1], "chunks arrive layer by layer");
+
+        let options = VolumeOptions { layout: Some(layout), classifier };
+        let result = load_volume(&bytes, &options).unwrap();
+        assert_eq!(voxel(&result, [40, 0, 0]), Some(2));
+        assert_eq!(voxel(&result, [39, 69, 69]), None);
+        assert_eq!(voxel(&result, [69, 69, 69]), Some(2));
+        assert_eq!(count(&result), 30 * 70 * 70);
+
+        let short = load_volume(&bytes[..bytes.len() - 1], &options);
+        let expected = bytes.len() as u64;
+        assert!(matches!(short, Err(VolumeError::Size { expected: e, .. }) if e == expected));
+    }
+
+    #[test]
+    fn stream_accepts_any_piece_size() {
+        let header = b"NRRD0004\ntype: uint8\ndimension: 3\nsizes: 70 3 65\nencoding: raw\n\n";
+        let data: Vec<u8> = (0..70 * 3 * 65).map(|i| (i % 3 == 0) as u8).collect();
+        let nrrd = [header.as_slice(), &data].concat();
+        let options = VolumeOptions::from_json("").unwrap();
+        let whole = load_volume(&nrrd, &options).unwrap();
+        assert_eq!(count(&whole), (0..70 * 3 * 65).filter(|i| data[*i] != 0).count() as u32);
+
+        for piece in [1, 5, 211, 70 * 3 + 1, nrrd.len()] {
+            let mut stream = VolumeStream::new(options.clone());
+            for bytes in nrrd.chunks(piece) {
+                stream.push(bytes).unwrap();
+            }
+            let result = stream.finish().unwrap();
+            assert_eq!(result.chunks.len(), whole.chunks.len(), "pieces of {piece}");
+            for (a, b) in result.chunks.iter().zip(&whole.chunks) {
+                assert_eq!(a.coord, b.coord);
+                assert_eq!(a.occupancy.as_words(), b.occupancy.as_words());
+            }
+        }
+
+        let mut short = VolumeStream::new(options.clone());
+        short.push(&nrrd[..nrrd.len() - 1]).unwrap();
+        assert!(matches!(short.finish(), Err(VolumeError::Size { .. })));
+        let mut headless = VolumeStream::new(options);
+        headless.push(&header[..20]).unwrap();
+        assert!(matches!(headless.finish(), Err(VolumeError::Nrrd(_))));
+    }
+
+    #[test]
+    fn import_stops_past_the_pool_size() {
+        // A 1×1 column with one chunk per layer, one more than the pool holds
+        let depth = CS * (MAX_SLOTS + 1);
+        let layout = VolumeLayout {
+            dims: [1, 1, depth],
+            spacing: [1.0; 3],
+            sample: SampleType::U8,
+            big_endian: false,
+        };
+        let classifier = Classifier::Threshold { iso: 1.0, material: gray() };
+        let bytes = vec![1u8; depth as usize];
+        let mut emitted = 0;
+        let result = import_volume(bytes.as_slice(), &layout, &classifier, |_| emitted += 1);
+        assert_eq!(result, Err(VolumeError::TooManyChunks));
+        assert_eq!(emitted, MAX_SLOTS);
+    }
+
+    #[test]
+    fn transfer_function_and_anisotropic_spacing() {
+        let dims = [4, 4, 2];
+        let bytes = samples(dims, |x, _, z| (x + 4 * z) as u16 * 100);
+        let options = VolumeOptions::from_json(
+            r#"{ "dims": [4, 4, 2], "spacing": [0.5, 0.5, 1.0], "type": "uint16",
+                 "transfer": [{ "min": 100, "max": 250, "color": [1, 0, 0] },
+                              { "min": 400, "color": [0, 0, 1] }] }"#,
+        )
+        .unwrap();
+        let result = load_volume(&bytes, &options).unwrap();
+
+        assert_eq!(result.voxel_size, 0.5);
+        // Sample (x, y, z) covers voxels (x, y, 2z..=2z+1)
+        assert_eq!(voxel(&result, [0, 0, 0]), None, "0 is in no range");
+        assert_eq!(voxel(&result, [1, 3, 1]), Some(2));
+        assert_eq!(voxel(&result, [2, 0, 0]), Some(2));
+        assert_eq!(voxel(&result, [3, 0, 0]), None, "300 falls between ranges");
+        assert_eq!(voxel(&result, [0, 0, 2]), Some(3));
+        assert_eq!(voxel(&result, [3, 2, 3]), Some(3));
+        assert_eq!(count(&result), (2 * 4 * 2) + (4 * 4 * 2));
+        assert_eq!(result.materials[3].albedo(), [0.0, 0.0, 1.0]);
+        assert_eq!(result.mesh_extent, 2.0);
+    }
+
+    #[test]
+    fn nrrd_header() {
+        let header = b"NRRD0004\n# CT\ntype: short\ndimension: 3\nsizes: 2 1 1\n\
+            space directions: (0.5,0,0) (0,0.5,0) (0,0,2)\nendian: big\nencoding: raw\n\
+            content:=scan\n\n";
+        let bytes = [header.as_slice(), &(-1000i16).to_be_bytes(), &500i16.to_be_bytes()].concat();
+        let (layout, offset) = parse_nrrd(&bytes).unwrap();
+        assert_eq!(layout.dims, [2, 1, 1]);
+        assert_eq!(layout.spacing, [0.5, 0.5, 2.0]);
+        assert_eq!((layout.sample, layout.big_endian), (SampleType::I16, true));
+        assert_eq!(offset, header.len());
+        assert_eq!(layout.voxel_grid(), (0.5, [1, 1, 4]));
+
+        let options = VolumeOptions::from_json(r#"{ "threshold": 0 }"#).unwrap();
+        let result = load_volume(&bytes, &options).unwrap();
+        assert_eq!(voxel(&result, [0, 0, 0]), None, "-1000 is below the threshold");
+        assert_eq!(voxel(&result, [1, 0, 3]), Some(2));
+        assert_eq!(count(&result), 4);
+
+        let gzip = String::from_utf8_lossy(header).replace("raw", "gzip");
+        assert!(matches!(parse_nrrd(gzip.as_bytes()), Err(VolumeError::Nrrd(_))));
+        assert!(matches!(
+            VolumeOptions::from_json(r#"{ "dims": [2, 2], "type": "uint8" }"#),
+            Err(VolumeError::Options(_))
+        ));
+    }
+}
@@ -0,0 +1,1148 @@
+//! MagicaVoxel `.vox` reader and writer — no wasm_bindgen, no JS types.
+//!
+//! `.vox` files are already voxels, so they bypass the mesh voxelizer and
+//! are written straight into per-chunk `OccupancyBuilder` / `PaletteBuilder`
+//! / `IndexBufBuilder` data. Supported chunks: `SIZE`/`XYZI` model pairs,
+//! `RGBA`, `MATL`, `LAYR` and the `nTRN`/`nGRP`/`nSHP` scene graph. Files
+//! without a scene graph place every model at the origin.
+//!
+//! [`write_vox`] goes the other way, so voxelized scenes can be edited in
+//! MagicaVoxel and loaded back.
+//!
+//! MagicaVoxel is Z-up; voxels are remapped to the renderer's Y-up frame as
+//! `(x, y, z) → (x, z, -y - 1)`, which preserves handedness. One voxel is one
+//! world unit, like the test scene.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::pool::*;
+use crate::quantize::{self, linear_to_oklab, srgb_to_linear, ColorItem};
+use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
+use crate::voxelizer_cpu::VoxelizeResult;
+
+const VOX_MAGIC: &[u8; 4] = b"VOX ";
+
+/// Scene graph nesting limit. Guards against cyclic node references.
+const MAX_GRAPH_DEPTH: usize = 64;
+
+/// Largest model edge MagicaVoxel accepts (voxel coordinates are bytes).
+const MAX_MODEL_DIM: i32 = 256;
+
+/// Palette slots usable by voxels (color index 0 is reserved).
+const MAX_VOX_COLORS: usize = 255;
+
+/// One `SIZE` + `XYZI` model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoxModel {
+    /// Model dimensions in MagicaVoxel axes (x, y, z-up).
+    pub size: [u32; 3],
+    /// Voxels as `[x, y, z, color_index]`. Color index 0 never appears.
+    pub voxels: Vec<[u8; 4]>,
+}
+
+/// `MATL` properties that map onto `MaterialEntry`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxMaterial {
+    /// `_rough`, if given.
+    pub roughness: Option<f32>,
+    /// `_emit` for `_emit` materials, otherwise 0.
+    pub emit: f32,
+    /// `_flux` (emission power exponent).
+    pub flux: f32,
+    /// `1 - _trans` (or `1 - _alpha`) for `_glass`/`_blend` materials, otherwise 1.
+    pub opacity: f32,
+}
+
+impl Default for VoxMaterial {
+    fn default() -> Self {
+        Self {
+            roughness: None,
+            emit: 0.0,
+            flux: 0.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// A placed model: voxel `v` lands at `rotation * (v - size / 2) + translation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxInstance {
+    pub model: usize,
+    /// Row-major signed permutation matrix.
+    pub rotation: [[i32; 3]; 3],
+    pub translation: [i32; 3],
+}
+
+/// A parsed `.vox` file.
+#[derive(Debug, Clone)]
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    /// Visible model placements after flattening the scene graph.
+    pub instances: Vec<VoxInstance>,
+    /// RGBA per color index; entry 0 is unused.
+    pub palette: Vec<[u8; 4]>,
+    /// `MATL` data per color index, where present.
+    pub materials: Vec<Option<VoxMaterial>>,
+}
+
+/// Why a `.vox` file could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VoxError {
+    /// Missing `VOX ` magic.
+    NotVox,
+    /// A chunk or field runs past the end of its container.
+    Truncated(&'static str),
+    /// An `XYZI` chunk without a preceding `SIZE`, or similar.
+    Malformed(String),
+}
+
+impl fmt::Display for VoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoxError::NotVox => write!(f, "not a MagicaVoxel file"),
+            VoxError::Truncated(what) => write!(f, "truncated {what}"),
+            VoxError::Malformed(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for VoxError {}
+
+/// MaterialId for a `.vox` color index (1–255 → 2–256).
+pub fn material_id(color_index: u8) -> u16 {
+    color_index as u16 + 1
+}
+
+/// Parse a `.vox` file.
+pub fn parse_vox(bytes: &[u8]) -> Result<VoxFile, VoxError> {
+    if !bytes.starts_with(VOX_MAGIC) {
+        return Err(VoxError::NotVox);
+    }
+    let mut reader = Reader { bytes, pos: 8 };
+    let main = reader.chunk()?.ok_or(VoxError::Truncated("MAIN chunk"))?;
+    if &main.id != b"MAIN" {
+        return Err(VoxError::Malformed("first chunk is not MAIN".to_string()));
+    }
+
+    let mut models = Vec::new();
+    let mut pending_size: Option<[u32; 3]> = None;
+    let mut palette = default_palette();
+    let mut materials = vec![None; 256];
+    let mut nodes: HashMap<i32, Node> = HashMap::new();
+    let mut hidden_layers: Vec<i32> = Vec::new();
+
+    let mut children = Reader {
+        bytes: main.children,
+        pos: 0,
+    };
+    while let Some(chunk) = children.chunk()? {
+        let mut r = Reader {
+            bytes: chunk.content,
+            pos: 0,
+        };
+        match &chunk.id {
+            b"SIZE" => pending_size = Some([r.u32()?, r.u32()?, r.u32()?]),
+            b"XYZI" => {
+                let size = pending_size
+                    .take()
+                    .ok_or_else(|| VoxError::Malformed("XYZI without SIZE".to_string()))?;
+                let count = r.u32()? as usize;
+                let data = r.take(count.checked_mul(4).ok_or(VoxError::Truncated("XYZI"))?)?;
+                let voxels = data
+                    .chunks_exact(4)
+                    .map(|v| [v[0], v[1], v[2], v[3]])
+                    .filter(|v| v[3] != 0)
+                    .collect();
+                models.push(VoxModel { size, voxels });
+            }
+            b"RGBA" => {
+                let data = r.take(256 * 4)?;
+                // RGBA entry k is color index k + 1; the last entry is unused.
+                for (k, c) in data.chunks_exact(4).take(255).enumerate() {
+                    palette[k + 1] = [c[0], c[1], c[2], c[3]];
+                }
+            }
+            b"MATL" => {
+                let id = r.i32()?;
+                let dict = r.dict()?;
+                if (1..=255).contains(&id) {
+                    materials[id as usize] = Some(parse_matl(&dict));
+                }
+            }
+            b"LAYR" => {
+                let id = r.i32()?;
+                let dict = r.dict()?;
+                if dict_get(&dict, "_hidden") == Some("1") {
+                    hidden_layers.push(id);
+                }
+            }
+            b"nTRN" => {
+                let id = r.i32()?;
+                let attrs = r.dict()?;
+                let child = r.i32()?;
+                let _reserved = r.i32()?;
+                let layer = r.i32()?;
+                let frames = r.i32()?;
+                let frame = if frames > 0 { r.dict()? } else { Vec::new() };
+                nodes.insert(
+                    id,
+                    Node::Transform {
+                        child,
+                        layer,
+                        hidden: dict_get(&attrs, "_hidden") == Some("1"),
+                        rotation: dict_get(&frame, "_r")
+                            .and_then(|s| s.trim().parse::<u8>().ok())
+                            .map(decode_rotation)
+                            .unwrap_or(IDENTITY),
+                        translation: dict_get(&frame, "_t")
+                            .map(parse_translation)
+                            .unwrap_or([0; 3]),
+                    },
+                );
+            }
+            b"nGRP" => {
+                let id = r.i32()?;
+                let _attrs = r.dict()?;
+                let count = r.u32()? as usize;
+                let mut children = Vec::with_capacity(count.min(64));
+                for _ in 0..count {
+                    children.push(r.i32()?);
+                }
+                nodes.insert(id, Node::Group { children });
+            }
+            b"nSHP" => {
+                let id = r.i32()?;
+                let _attrs = r.dict()?;
+                let count = r.u32()? as usize;
+                let mut shape_models = Vec::with_capacity(count.min(16));
+                for _ in 0..count {
+                    shape_models.push(r.i32()?);
+                    let _model_attrs = r.dict()?;
+                }
+                nodes.insert(
+                    id,
+                    Node::Shape {
+                        models: shape_models,
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let mut instances = Vec::new();
+    if nodes.contains_key(&0) {
+        walk_graph(
+            &nodes,
+            &hidden_layers,
+            0,
+            IDENTITY,
+            [0; 3],
+            0,
+            &mut instances,
+        )?;
+    } else {
+        instances.extend((0..models.len()).map(|model| VoxInstance {
+            model,
+            rotation: IDENTITY,
+            translation: [0; 3],
+        }));
+    }
+    if let Some(bad) = instances.iter().find(|i| i.model >= models.len()) {
+        return Err(VoxError::Malformed(format!(
+            "shape references missing model {}",
+            bad.model
+        )));
+    }
+
+    Ok(VoxFile {
+        models,
+        instances,
+        palette,
+        materials,
+    })
+}
+
+impl VoxFile {
+    /// Material table: color index `i` becomes MaterialId `i + 1`.
+    ///
+    /// Palette colors are sRGB and converted to linear albedo. `MATL`
+    /// roughness overrides the 0.5 default; emissive materials radiate their
+    /// albedo scaled by `_emit`, doubling per `_flux` step.
+    pub fn material_table(&self) -> Vec<MaterialEntry> {
+        let mut table =
+            vec![MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0); MAX_MATERIALS as usize];
+        table[MATERIAL_DEFAULT as usize] = MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0);
+        for index in 1..=255u8 {
+            let [r, g, b, _] = self.palette[index as usize];
+            let albedo = [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b)];
+            let matl = self.materials[index as usize].clone().unwrap_or_default();
+            let strength = matl.emit * 2f32.powf(matl.flux);
+            table[material_id(index) as usize] = MaterialEntry::new(
+                albedo,
+                matl.roughness.unwrap_or(0.5).clamp(0.0, 1.0),
+                albedo.map(|c| c * strength),
+                matl.opacity,
+            );
+        }
+        table
+    }
+
+    /// Voxel positions in the renderer's Y-up frame with their color index.
+    pub fn world_voxels(&self) -> Vec<([i32; 3], u8)> {
+        let mut out = Vec::new();
+        for inst in &self.instances {
+            let model = &self.models[inst.model];
+            let half = model.size.map(|s| (s / 2) as i32);
+            for &[x, y, z, c] in &model.voxels {
+                let local = [x as i32 - half[0], y as i32 - half[1], z as i32 - half[2]];
+                let p = add(mul(&inst.rotation, local), inst.translation);
+                out.push(([p[0], p[2], -p[1] - 1], c));
+            }
+        }
+        out
+    }
+
+    /// Build chunk data and scene parameters for upload.
+    ///
+    /// The grid starts at the minimum voxel, so `grid_origin` is that voxel's
+    /// world position. Where instances overlap, the later one wins.
+    pub fn to_chunks(&self) -> VoxelizeResult {
+        let voxels = self.world_voxels();
+        let materials = self.material_table();
+        if voxels.is_empty() {
+            return VoxelizeResult {
+                chunks: Vec::new(),
+                materials,
+                voxel_size: 1.0,
+                grid_origin: [0.0; 3],
+                mesh_center: [0.0; 3],
+                mesh_extent: 0.0,
+                quantize: Default::default(),
+            };
+        }
+
+        let mut min = [i32::MAX; 3];
+        let mut max = [i32::MIN; 3];
+        for (p, _) in &voxels {
+            for a in 0..3 {
+                min[a] = min[a].min(p[a]);
+                max[a] = max[a].max(p[a]);
+            }
+        }
+
+        let mut chunk_map: HashMap<(i32, i32, i32), ChunkData> = HashMap::new();
+        for (p, color) in voxels {
+            let g = [p[0] - min[0], p[1] - min[1], p[2] - min[2]];
+            let key = (g[0] / CS as i32, g[1] / CS as i32, g[2] / CS as i32);
+            let [lx, ly, lz] = g.map(|v| (v % CS as i32) as u32 + 1);
+            let chunk = chunk_map.entry(key).or_insert_with(|| ChunkData {
+                coord: ChunkCoord {
+                    x: key.0,
+                    y: key.1,
+                    z: key.2,
+                },
+                occupancy: OccupancyBuilder::new(),
+                palette: PaletteBuilder::new(),
+                index_buf: IndexBufBuilder::new(),
+            });
+            chunk.occupancy.set(lx, ly, lz);
+            let palette_idx = chunk.palette.add(material_id(color));
+            chunk.index_buf.set(lx, ly, lz, palette_idx);
+        }
+
+        let mut chunks: Vec<ChunkData> = chunk_map.into_values().collect();
+        chunks.sort_by_key(|c| (c.coord.x, c.coord.y, c.coord.z));
+
+        let extent = [0, 1, 2].map(|a| (max[a] - min[a] + 1) as f32);
+        VoxelizeResult {
+            chunks,
+            materials,
+            voxel_size: 1.0,
+            grid_origin: min.map(|v| v as f32),
+            mesh_center: [0, 1, 2].map(|a| min[a] as f32 + extent[a] * 0.5),
+            mesh_extent: extent[0].max(extent[1]).max(extent[2]),
+            quantize: Default::default(),
+        }
+    }
+}
+
+/// Serialize chunks as a `.vox` file.
+///
+/// Interior voxels are placed on the global grid (`coord * CS + local - 1`),
+/// mapped back to MagicaVoxel's Z-up axes and split into models of at most
+/// 256³, each positioned by its own `nTRN`. Materials are reduced to the
+/// 255-color palette — one color per material when they fit, otherwise a
+/// voxel-weighted median cut over Oklab albedo — and roughness, emission and
+/// opacity are written as `MATL` chunks. Occupied voxels whose palette entry
+/// is empty or unknown export as `MATERIAL_DEFAULT`.
+pub fn write_vox<'a>(
+    chunks: impl IntoIterator<Item = &'a ChunkData>,
+    materials: &[MaterialEntry],
+) -> Vec<u8> {
+    let voxels = collect_voxels(chunks);
+
+    let mut counts: BTreeMap<u16, u32> = BTreeMap::new();
+    for (_, mat) in &voxels {
+        *counts.entry(*mat).or_default() += 1;
+    }
+    let lookup = |id: u16| {
+        materials
+            .get(id as usize)
+            .copied()
+            .unwrap_or_else(|| MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0))
+    };
+    let items: Vec<ColorItem> = counts
+        .iter()
+        .map(|(&material, &weight)| ColorItem {
+            color: linear_to_oklab(lookup(material).albedo()),
+            weight: weight as u64,
+            id: material as u32,
+        })
+        .collect();
+
+    // Each palette box becomes one color index; its heaviest member supplies
+    // the MATL properties.
+    let boxes = quantize::median_cut(vec![items], MAX_VOX_COLORS);
+    let mut color_of: HashMap<u16, u8> = HashMap::new();
+    let mut palette = vec![[0u8; 4]; 256];
+    let mut matl = Vec::new();
+    for (i, items) in boxes.iter().enumerate() {
+        let index = (i + 1) as u8;
+        let total: f32 = items.iter().map(|c| c.weight as f32).sum();
+        let mean = [0, 1, 2].map(|a| {
+            items
+                .iter()
+                .map(|c| linear_to_srgb(lookup(c.id as u16).albedo()[a]) * c.weight as f32)
+                .sum::<f32>()
+                / total
+        });
+        let [r, g, b] = mean.map(|c| c.round().clamp(0.0, 255.0) as u8);
+        palette[i] = [r, g, b, 255];
+        for item in items {
+            color_of.insert(item.id as u16, index);
+        }
+        if let Some(heaviest) = quantize::heaviest(items) {
+            matl.push((index, matl_dict(&lookup(heaviest.id as u16))));
+        }
+    }
+
+    // Bin voxels into ≤256³ models, keyed by block for deterministic order.
+    let mut blocks: BTreeMap<[i32; 3], Vec<[u8; 4]>> = BTreeMap::new();
+    for (p, mat) in &voxels {
+        let block = p.map(|v| v.div_euclid(MAX_MODEL_DIM));
+        let local = p.map(|v| v.rem_euclid(MAX_MODEL_DIM) as u8);
+        blocks
+            .entry(block)
+            .or_default()
+            .push([local[0], local[1], local[2], color_of[mat]]);
+    }
+
+    let mut main = Vec::new();
+    let mut placements = Vec::new();
+    for (block, model_voxels) in &blocks {
+        let mut size = [1u32; 3];
+        for v in model_voxels {
+            for a in 0..3 {
+                size[a] = size[a].max(v[a] as u32 + 1);
+            }
+        }
+        let mut size_bytes = Vec::with_capacity(12);
+        for s in size {
+            size_bytes.extend_from_slice(&s.to_le_bytes());
+        }
+        write_chunk(&mut main, b"SIZE", &size_bytes);
+        let mut xyzi = Vec::with_capacity(4 + model_voxels.len() * 4);
+        xyzi.extend_from_slice(&(model_voxels.len() as u32).to_le_bytes());
+        for v in model_voxels {
+            xyzi.extend_from_slice(v);
+        }
+        write_chunk(&mut main, b"XYZI", &xyzi);
+        // Import places voxel v at v - size/2 + t, so t = block origin + size/2.
+        placements.push([0, 1, 2].map(|a| block[a] * MAX_MODEL_DIM + (size[a] / 2) as i32));
+    }
+
+    // Scene graph: root nTRN 0 → nGRP 1 → (nTRN 2k+2 → nSHP 2k+3) per model.
+    let group_children: Vec<i32> = (0..placements.len() as i32).map(|k| 2 * k + 2).collect();
+    write_chunk(&mut main, b"nTRN", &transform_node(0, 1, -1, &[]));
+    let mut grp = 1i32.to_le_bytes().to_vec();
+    write_dict(&mut grp, &[]);
+    grp.extend_from_slice(&(group_children.len() as u32).to_le_bytes());
+    for child in &group_children {
+        grp.extend_from_slice(&child.to_le_bytes());
+    }
+    write_chunk(&mut main, b"nGRP", &grp);
+    for (k, t) in placements.iter().enumerate() {
+        let id = 2 * k as i32 + 2;
+        let translation = format!("{} {} {}", t[0], t[1], t[2]);
+        write_chunk(
+            &mut main,
+            b"nTRN",
+            &transform_node(id, id + 1, 0, &[("_t", &translation)]),
+        );
+        let mut shp = (id + 1).to_le_bytes().to_vec();
+        write_dict(&mut shp, &[]);
+        shp.extend_from_slice(&1u32.to_le_bytes());
+        shp.extend_from_slice(&(k as i32).to_le_bytes());
+        write_dict(&mut shp, &[]);
+        write_chunk(&mut main, b"nSHP", &shp);
+    }
+
+    // RGBA entry k is color index k + 1.
+    let rgba: Vec<u8> = palette.iter().flatten().copied().collect();
+    write_chunk(&mut main, b"RGBA", &rgba);
+    for (index, dict) in &matl {
+        let mut content = (*index as i32).to_le_bytes().to_vec();
+        let pairs: Vec<(&str, &str)> = dict.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        write_dict(&mut content, &pairs);
+        write_chunk(&mut main, b"MATL", &content);
+    }
+
+    let mut out = VOX_MAGIC.to_vec();
+    out.extend_from_slice(&200u32.to_le_bytes());
+    out.extend_from_slice(b"MAIN");
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(main.len() as u32).to_le_bytes());
+    out.extend(main);
+    out
+}
+
+// ─── Internals ─────────────────────────────────────────────────────────────
+
+const IDENTITY: [[i32; 3]; 3] = [[1, 0, 0], [0, 1, 0], [0, 0, 1]];
+
+type Dict = Vec<(String, String)>;
+
+enum Node {
+    Transform {
+        child: i32,
+        layer: i32,
+        hidden: bool,
+        rotation: [[i32; 3]; 3],
+        translation: [i32; 3],
+    },
+    Group {
+        children: Vec<i32>,
+    },
+    Shape {
+        models: Vec<i32>,
+    },
+}
+
+struct Chunk<'a> {
+    id: [u8; 4],
+    content: &'a [u8],
+    children: &'a [u8],
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VoxError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .ok_or(VoxError::Truncated("chunk data"))?;
+        let data = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(VoxError::Truncated("chunk data"))?;
+        self.pos = end;
+        Ok(data)
+    }
+
+    fn u32(&mut self) -> Result<u32, VoxError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, VoxError> {
+        self.u32().map(|v| v as i32)
+    }
+
+    fn string(&mut self) -> Result<String, VoxError> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<Dict, VoxError> {
+        let count = self.u32()? as usize;
+        let mut dict = Vec::with_capacity(count.min(16));
+        for _ in 0..count {
+            dict.push((self.string()?, self.string()?));
+        }
+        Ok(dict)
+    }
+
+    /// Next chunk, or `None` at the end of the container.
+    fn chunk(&mut self) -> Result<Option<Chunk<'a>>, VoxError> {
+        if self.pos >= self.bytes.len() {
+            return Ok(None);
+        }
+        let id = self.take(4)?;
+        let content_len = self.u32()? as usize;
+        let children_len = self.u32()? as usize;
+        Ok(Some(Chunk {
+            id: [id[0], id[1], id[2], id[3]],
+            content: self.take(content_len)?,
+            children: self.take(children_len)?,
+        }))
+    }
+}
+
+fn dict_get<'a>(dict: &'a Dict, key: &str) -> Option<&'a str> {
+    dict.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+fn parse_matl(dict: &Dict) -> VoxMaterial {
+    let num = |key: &str| dict_get(dict, key).and_then(|v| v.trim().parse::<f32>().ok());
+    let kind = dict_get(dict, "_type").unwrap_or("_diffuse");
+    let mut matl = VoxMaterial {
+        roughness: num("_rough"),
+        ..Default::default()
+    };
+    if kind == "_emit" {
+        matl.emit = num("_emit").unwrap_or(0.0).max(0.0);
+        matl.flux = num("_flux").unwrap_or(0.0);
+    }
+    if matches!(kind, "_glass" | "_blend") {
+        let trans = num("_trans").or_else(|| num("_alpha")).unwrap_or(0.0);
+        matl.opacity = (1.0 - trans).clamp(0.0, 1.0);
+    }
+    matl
+}
+
+fn parse_translation(s: &str) -> [i32; 3] {
+    let mut out = [0; 3];
+    for (slot, part) in out.iter_mut().zip(s.split_whitespace()) {
+        *slot = part.parse().unwrap_or(0);
+    }
+    out
+}
+
+/// Decode the packed `_r` byte: bits 0–1 and 2–3 give the non-zero column of
+/// rows 0 and 1 (row 2 takes the remaining one), bits 4–6 their signs.
+fn decode_rotation(r: u8) -> [[i32; 3]; 3] {
+    let c0 = (r & 3) as usize;
+    let c1 = ((r >> 2) & 3) as usize;
+    if c0 > 2 || c1 > 2 || c0 == c1 {
+        return IDENTITY;
+    }
+    let c2 = 3 - c0 - c1;
+    let sign = |bit: u8| if (r >> bit) & 1 == 1 { -1 } else { 1 };
+    let mut m = [[0; 3]; 3];
+    m[0][c0] = sign(4);
+    m[1][c1] = sign(5);
+    m[2][c2] = sign(6);
+    m
+}
+
+fn mul(m: &[[i32; 3]; 3], v: [i32; 3]) -> [i32; 3] {
+    [0, 1, 2].map(|r| m[r][0] * v[0] + m[r][1] * v[1] + m[r][2] * v[2])
+}
+
+fn add(a: [i32; 3], b: [i32; 3]) -> [i32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn matmul(a: &[[i32; 3]; 3], b: &[[i32; 3]; 3]) -> [[i32; 3]; 3] {
+    [0, 1, 2].map(|r| [0, 1, 2].map(|c| a[r][0] * b[0][c] + a[r][1] * b[1][c] + a[r][2] * b[2][c]))
+}
+
+fn walk_graph(
+    nodes: &HashMap<i32, Node>,
+    hidden_layers: &[i32],
+    id: i32,
+    rotation: [[i32; 3]; 3],
+    translation: [i32; 3],
+    depth: usize,
+    out: &mut Vec<VoxInstance>,
+) -> Result<(), VoxError> {
+    if depth > MAX_GRAPH_DEPTH {
+        return Err(VoxError::Malformed(
+            "scene graph too deep or cyclic".to_string(),
+        ));
+    }
+    let node = nodes
+        .get(&id)
+        .ok_or_else(|| VoxError::Malformed(format!("scene graph references missing node {id}")))?;
+    match node {
+        Node::Transform {
+            child,
+            layer,
+            hidden,
+            rotation: r,
+            translation: t,
+        } => {
+            if *hidden || hidden_layers.contains(layer) {
+                return Ok(());
+            }
+            let world_rot = matmul(&rotation, r);
+            let world_t = add(mul(&rotation, *t), translation);
+            walk_graph(
+                nodes,
+                hidden_layers,
+                *child,
+                world_rot,
+                world_t,
+                depth + 1,
+                out,
+            )
+        }
+        Node::Group { children } => {
+            for &child in children {
+                walk_graph(
+                    nodes,
+                    hidden_layers,
+                    child,
+                    rotation,
+                    translation,
+                    depth + 1,
+                    out,
+                )?;
+            }
+            Ok(())
+        }
+        Node::Shape { models } => {
+            for &model in models {
+                let model = usize::try_from(model)
+                    .map_err(|_| VoxError::Malformed(format!("invalid model index {model}")))?;
+                out.push(VoxInstance {
+                    model,
+                    rotation,
+                    translation,
+                });
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Occupied interior voxels in MagicaVoxel axes with their material ID.
+fn collect_voxels<'a>(chunks: impl IntoIterator<Item = &'a ChunkData>) -> Vec<([i32; 3], u16)> {
+    let mut voxels = Vec::new();
+    for chunk in chunks {
+        let words = chunk.occupancy.as_words();
+        let entries = chunk.palette.entries();
+        let base = [chunk.coord.x, chunk.coord.y, chunk.coord.z].map(|c| c * CS as i32 - 1);
+        for lx in 1..=CS {
+            for lz in 1..=CS {
+                let col = ((lx * CS_P + lz) * 2) as usize;
+                if words[col] == 0 && words[col + 1] == 0 {
+                    continue;
+                }
+                for ly in 1..=CS {
+                    if !chunk.occupancy.get(lx, ly, lz) {
+                        continue;
+                    }
+                    let mat = match entries.get(chunk.index_buf.get(lx, ly, lz) as usize) {
+                        Some(&m) if m != MATERIAL_EMPTY => m,
+                        _ => MATERIAL_DEFAULT,
+                    };
+                    let g = [
+                        base[0] + lx as i32,
+                        base[1] + ly as i32,
+                        base[2] + lz as i32,
+                    ];
+                    // Inverse of the import remap (x, y, z) → (x, z, -y - 1).
+                    voxels.push(([g[0], -g[2] - 1, g[1]], mat));
+                }
+            }
+        }
+    }
+    voxels
+}
+
+/// MATL properties for a material; the inverse of [`VoxFile::material_table`].
+fn matl_dict(m: &MaterialEntry) -> Vec<(&'static str, String)> {
+    let mut dict = vec![("_rough", format!("{}", m.roughness()))];
+    let albedo_max = m.albedo().into_iter().fold(0.0f32, f32::max);
+    let emissive_max = m.emissive().into_iter().fold(0.0f32, f32::max);
+    if emissive_max > 0.0 {
+        let mut emit = if albedo_max > 0.0 {
+            emissive_max / albedo_max
+        } else {
+            emissive_max
+        };
+        let mut flux = 0;
+        while emit > 1.0 && flux < 4 {
+            emit /= 2.0;
+            flux += 1;
+        }
+        dict.insert(0, ("_type", "_emit".to_string()));
+        dict.push(("_emit", format!("{}", emit.min(1.0))));
+        dict.push(("_flux", format!("{flux}")));
+    } else if m.opacity() < 1.0 {
+        dict.insert(0, ("_type", "_glass".to_string()));
+        dict.push(("_trans", format!("{}", 1.0 - m.opacity().max(0.0))));
+    } else {
+        dict.insert(0, ("_type", "_diffuse".to_string()));
+    }
+    dict
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+fn write_dict(out: &mut Vec<u8>, pairs: &[(&str, &str)]) {
+    out.extend_from_slice(&(pairs.len() as u32).to_le_bytes());
+    for (k, v) in pairs {
+        out.extend_from_slice(&(k.len() as u32).to_le_bytes());
+        out.extend_from_slice(k.as_bytes());
+        out.extend_from_slice(&(v.len() as u32).to_le_bytes());
+        out.extend_from_slice(v.as_bytes());
+    }
+}
+
+/// `nTRN` content with a single frame.
+fn transform_node(id: i32, child: i32, layer: i32, frame: &[(&str, &str)]) -> Vec<u8> {
+    let mut c = id.to_le_bytes().to_vec();
+    write_dict(&mut c, &[]);
+    c.extend_from_slice(&child.to_le_bytes());
+    c.extend_from_slice(&(-1i32).to_le_bytes());
+    c.extend_from_slice(&layer.to_le_bytes());
+    c.extend_from_slice(&1i32.to_le_bytes());
+    write_dict(&mut c, frame);
+    c
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    let s = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    s * 255.0
+}
+
+/// MagicaVoxel's built-in palette, used when a file has no `RGBA` chunk:
+/// a 6×6×6 color cube without black, then red, green, blue and gray ramps.
+fn default_palette() -> Vec<[u8; 4]> {
+    const CUBE: [u8; 6] = [0xFF, 0xCC, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xEE, 0xDD, 0xBB, 0xAA, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    let mut palette = vec![[0, 0, 0, 0]];
+    for r in CUBE {
+        for g in CUBE {
+            for b in CUBE {
+                if (r, g, b) != (0, 0, 0) {
+                    palette.push([r, g, b, 0xFF]);
+                }
+            }
+        }
+    }
+    for channel in 0..3 {
+        for v in RAMP {
+            let mut c = [0, 0, 0, 0xFF];
+            c[channel] = v;
+            palette.push(c);
+        }
+    }
+    for v in RAMP {
+        palette.push([v, v, v, 0xFF]);
+    }
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        out.extend_from_slice(content);
+        out.extend_from_slice(children);
+        out
+    }
+
+    fn size_xyzi(size: [u32; 3], voxels: &[[u8; 4]]) -> Vec<u8> {
+        let mut size_bytes = Vec::new();
+        for s in size {
+            size_bytes.extend_from_slice(&s.to_le_bytes());
+        }
+        let mut xyzi = (voxels.len() as u32).to_le_bytes().to_vec();
+        for v in voxels {
+            xyzi.extend_from_slice(v);
+        }
+        let mut out = chunk(b"SIZE", &size_bytes, &[]);
+        out.extend(chunk(b"XYZI", &xyzi, &[]));
+        out
+    }
+
+    fn dict(pairs: &[(&str, &str)]) -> Vec<u8> {
+        let mut out = (pairs.len() as u32).to_le_bytes().to_vec();
+        for (k, v) in pairs {
+            out.extend_from_slice(&(k.len() as u32).to_le_bytes());
+            out.extend_from_slice(k.as_bytes());
+            out.extend_from_slice(&(v.len() as u32).to_le_bytes());
+            out.extend_from_slice(v.as_bytes());
+        }
+        out
+    }
+
+    fn ntrn(id: i32, child: i32, frame: &[(&str, &str)]) -> Vec<u8> {
+        let mut c = id.to_le_bytes().to_vec();
+        c.extend(dict(&[]));
+        c.extend_from_slice(&child.to_le_bytes());
+        c.extend_from_slice(&(-1i32).to_le_bytes());
+        c.extend_from_slice(&0i32.to_le_bytes());
+        c.extend_from_slice(&1i32.to_le_bytes());
+        c.extend(dict(frame));
+        chunk(b"nTRN", &c, &[])
+    }
+
+    fn ngrp(id: i32, children: &[i32]) -> Vec<u8> {
+        let mut c = id.to_le_bytes().to_vec();
+        c.extend(dict(&[]));
+        c.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        for ch in children {
+            c.extend_from_slice(&ch.to_le_bytes());
+        }
+        chunk(b"nGRP", &c, &[])
+    }
+
+    fn nshp(id: i32, model: i32) -> Vec<u8> {
+        let mut c = id.to_le_bytes().to_vec();
+        c.extend(dict(&[]));
+        c.extend_from_slice(&1u32.to_le_bytes());
+        c.extend_from_slice(&model.to_le_bytes());
+        c.extend(dict(&[]));
+        chunk(b"nSHP", &c, &[])
+    }
+
+    fn vox_file(children: &[u8]) -> Vec<u8> {
+        let mut out = VOX_MAGIC.to_vec();
+        out.extend_from_slice(&200u32.to_le_bytes());
+        out.extend(chunk(b"MAIN", &[], children));
+        out
+    }
+
+    #[test]
+    fn parse_single_model() {
+        let bytes = vox_file(&size_xyzi([2, 2, 2], &[[0, 0, 0, 1], [1, 1, 1, 5]]));
+        let vox = parse_vox(&bytes).unwrap();
+        assert_eq!(vox.models.len(), 1);
+        assert_eq!(vox.models[0].size, [2, 2, 2]);
+        assert_eq!(vox.models[0].voxels, vec![[0, 0, 0, 1], [1, 1, 1, 5]]);
+        assert_eq!(vox.instances.len(), 1);
+    }
+
+    #[test]
+    fn rgba_palette_offset_by_one() {
+        let mut rgba = vec![0u8; 1024];
+        rgba[0..4].copy_from_slice(&[255, 0, 0, 255]);
+        let mut children = size_xyzi([1, 1, 1], &[[0, 0, 0, 1]]);
+        children.extend(chunk(b"RGBA", &rgba, &[]));
+        let vox = parse_vox(&vox_file(&children)).unwrap();
+        assert_eq!(vox.palette[1], [255, 0, 0, 255]);
+
+        let table = vox.material_table();
+        let red = MaterialEntry::new([1.0, 0.0, 0.0], 0.5, [0.0; 3], 1.0);
+        assert_eq!(table[material_id(1) as usize].albedo_rg, red.albedo_rg);
+    }
+
+    #[test]
+    fn default_palette_layout() {
+        let p = default_palette();
+        assert_eq!(p.len(), 256);
+        assert_eq!(p[1], [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(p[2], [0xFF, 0xFF, 0xCC, 0xFF]);
+        assert_eq!(p[216], [0xEE, 0, 0, 0xFF]);
+        assert_eq!(p[255], [0x11, 0x11, 0x11, 0xFF]);
+    }
+
+    #[test]
+    fn matl_emission_and_roughness() {
+        let mut c = 3i32.to_le_bytes().to_vec();
+        c.extend(dict(&[
+            ("_type", "_emit"),
+            ("_emit", "0.5"),
+            ("_flux", "1"),
+            ("_rough", "0.2"),
+        ]));
+        let mut children = size_xyzi([1, 1, 1], &[[0, 0, 0, 3]]);
+        children.extend(chunk(b"MATL", &c, &[]));
+        let vox = parse_vox(&vox_file(&children)).unwrap();
+        let matl = vox.materials[3].as_ref().unwrap();
+        assert_eq!(matl.roughness, Some(0.2));
+        assert_eq!(matl.emit, 0.5);
+        assert_eq!(matl.flux, 1.0);
+
+        let entry = vox.material_table()[material_id(3) as usize];
+        assert_ne!(entry.emissive_rg, 0);
+    }
+
+    #[test]
+    fn scene_graph_translation_and_grouping() {
+        let mut children = size_xyzi([1, 1, 1], &[[0, 0, 0, 1]]);
+        children.extend(ntrn(0, 1, &[]));
+        children.extend(ngrp(1, &[2, 4]));
+        children.extend(ntrn(2, 3, &[("_t", "10 0 0")]));
+        children.extend(nshp(3, 0));
+        children.extend(ntrn(4, 5, &[("_t", "0 0 7")]));
+        children.extend(nshp(5, 0));
+        let vox = parse_vox(&vox_file(&children)).unwrap();
+        assert_eq!(vox.instances.len(), 2);
+        assert_eq!(vox.instances[0].translation, [10, 0, 0]);
+        assert_eq!(vox.instances[1].translation, [0, 0, 7]);
+
+        // Z-up → Y-up: vox (10, 0, 0) → (10, 0, -1); vox (0, 0, 7) → (0, 7, -1).
+        let world: Vec<[i32; 3]> = vox.world_voxels().into_iter().map(|(p, _)| p).collect();
+        assert_eq!(world, vec![[10, 0, -1], [0, 7, -1]]);
+    }
+
+    #[test]
+    fn rotation_decoding() {
+        assert_eq!(decode_rotation(0b0000_0100), IDENTITY);
+        // Rows: x ← y, y ← x (negated), z ← z.
+        let r = decode_rotation(0b0010_0001);
+        assert_eq!(r, [[0, 1, 0], [-1, 0, 0], [0, 0, 1]]);
+        assert_eq!(mul(&r, [1, 2, 3]), [2, -1, 3]);
+    }
+
+    #[test]
+    fn to_chunks_builds_chunk_data() {
+        // 64 voxels along x spans two chunks (62 usable per chunk).
+        let voxels: Vec<[u8; 4]> = (0..64).map(|x| [x, 0, 0, 2]).collect();
+        let vox = parse_vox(&vox_file(&size_xyzi([64, 1, 1], &voxels))).unwrap();
+        let result = vox.to_chunks();
+        assert_eq!(result.chunks.len(), 2);
+        assert_eq!(result.chunks[0].occupancy.popcount(), 62);
+        assert_eq!(result.chunks[1].occupancy.popcount(), 2);
+        assert_eq!(result.chunks[0].palette.len(), 2);
+        assert_eq!(result.chunks[0].index_buf.get(1, 1, 1), 1);
+        assert_eq!(result.voxel_size, 1.0);
+        assert_eq!(result.mesh_extent, 64.0);
+        assert_eq!(result.grid_origin, [-32.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn hidden_nodes_skipped() {
+        let mut children = size_xyzi([1, 1, 1], &[[0, 0, 0, 1]]);
+        let mut c = 0i32.to_le_bytes().to_vec();
+        c.extend(dict(&[("_hidden", "1")]));
+        c.extend_from_slice(&1i32.to_le_bytes());
+        c.extend_from_slice(&(-1i32).to_le_bytes());
+        c.extend_from_slice(&0i32.to_le_bytes());
+        c.extend_from_slice(&1i32.to_le_bytes());
+        c.extend(dict(&[]));
+        children.extend(chunk(b"nTRN", &c, &[]));
+        children.extend(nshp(1, 0));
+        let vox = parse_vox(&vox_file(&children)).unwrap();
+        assert!(vox.instances.is_empty());
+    }
+
+    #[test]
+    fn export_roundtrip_preserves_voxels() {
+        let mut rgba = vec![0u8; 1024];
+        rgba[0..4].copy_from_slice(&[200, 40, 40, 255]);
+        rgba[4..8].copy_from_slice(&[40, 200, 40, 255]);
+        let voxels = [[0, 0, 0, 1], [3, 1, 2, 2], [1, 4, 0, 1]];
+        let mut children = size_xyzi([4, 5, 3], &voxels);
+        children.extend(chunk(b"RGBA", &rgba, &[]));
+        let original = parse_vox(&vox_file(&children)).unwrap();
+        let result = original.to_chunks();
+
+        let bytes = write_vox(&result.chunks, &result.materials);
+        let reloaded = parse_vox(&bytes).unwrap();
+
+        let min = original
+            .world_voxels()
+            .iter()
+            .fold([i32::MAX; 3], |m, (p, _)| {
+                [m[0].min(p[0]), m[1].min(p[1]), m[2].min(p[2])]
+            });
+        let mut expected: Vec<([i32; 3], [u8; 4])> = original
+            .world_voxels()
+            .into_iter()
+            .map(|(p, c)| {
+                (
+                    [p[0] - min[0], p[1] - min[1], p[2] - min[2]],
+                    original.palette[c as usize],
+                )
+            })
+            .collect();
+        let mut got: Vec<([i32; 3], [u8; 4])> = reloaded
+            .world_voxels()
+            .into_iter()
+            .map(|(p, c)| (p, reloaded.palette[c as usize]))
+            .collect();
+        expected.sort();
+        got.sort();
+        assert_eq!(got.len(), expected.len());
+        for ((gp, gc), (ep, ec)) in got.iter().zip(&expected) {
+            assert_eq!(gp, ep);
+            for a in 0..3 {
+                assert!(
+                    (gc[a] as i32 - ec[a] as i32).abs() <= 2,
+                    "color {gc:?} vs {ec:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn export_splits_large_scenes_into_models() {
+        // Voxels 310 apart along x land in different 256-wide models.
+        let mut chunk_a = ChunkData {
+            coord: ChunkCoord { x: 0, y: 0, z: 0 },
+            occupancy: OccupancyBuilder::new(),
+            palette: PaletteBuilder::new(),
+            index_buf: IndexBufBuilder::new(),
+        };
+        let idx = chunk_a.palette.add(2);
+        chunk_a.occupancy.set(1, 1, 1);
+        chunk_a.index_buf.set(1, 1, 1, idx);
+        let mut chunk_b = ChunkData {
+            coord: ChunkCoord { x: 5, y: 0, z: 0 },
+            occupancy: OccupancyBuilder::new(),
+            palette: PaletteBuilder::new(),
+            index_buf: IndexBufBuilder::new(),
+        };
+        chunk_b.occupancy.set(1, 1, 1);
+        let materials = vec![MaterialEntry::new([1.0, 0.0, 0.0], 0.5, [0.0; 3], 1.0); 4];
+
+        let vox = parse_vox(&write_vox([&chunk_a, &chunk_b], &materials)).unwrap();
+        assert_eq!(vox.models.len(), 2);
+        assert_eq!(vox.instances.len(), 2);
+        let mut world: Vec<[i32; 3]> = vox.world_voxels().into_iter().map(|(p, _)| p).collect();
+        world.sort();
+        assert_eq!(world, vec![[0, 0, 0], [310, 0, 0]]);
+    }
+
+    #[test]
+    fn emissive_matl_roundtrip() {
+        let m = MaterialEntry::new([1.0, 0.5, 0.25], 0.3, [4.0, 2.0, 1.0], 1.0);
+        let dict: Dict = matl_dict(&m)
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let matl = parse_matl(&dict);
+        assert_eq!(matl.emit * 2f32.powf(matl.flux), 4.0);
+        assert!((matl.roughness.unwrap() - 0.3).abs() < 1e-3);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_vox(b"NOPE").unwrap_err(), VoxError::NotVox);
+        let mut bytes = vox_file(&size_xyzi([1, 1, 1], &[[0, 0, 0, 1]]));
+        bytes.truncate(bytes.len() - 2);
+        assert!(matches!(parse_vox(&bytes), Err(VoxError::Truncated(_))));
+
+        let cyclic = [ntrn(0, 1, &[]), ngrp(1, &[0])].concat();
+        assert!(matches!(
+            parse_vox(&vox_file(&cyclic)),
+            Err(VoxError::Malformed(_))
+        ));
+    }
+}
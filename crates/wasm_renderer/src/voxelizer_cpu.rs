@@ -4,13 +4,32 @@
 //! working implementation and the test oracle for the future GPU voxelizer.
 //!
 //! Algorithm ported from `crates/voxelizer/src/reference_cpu.rs`.
+//!
+//! Triangles are first binned into 8³-voxel bricks (conservative brick/triangle
+//! SAT, after the CUGB-CSR builders in `crates/voxelizer/src/csr.rs`), so each
+//! chunk is voxelized independently from its own triangle lists. Chunks run in
+//! parallel with rayon on native targets; on wasm, [`VoxelizeJob::step`] lets
+//! the caller spread the work over several event-loop turns.
+//!
+//! Surface voxels can take their color from vertex colors or a texture
+//! ([`ColorSource`]); colors become extra material entries, quantized by
+//! [`crate::quantize`] to fit the material table and each chunk's palette.
+//!
+//! [`Fill::Solid`] additionally fills the interior of closed meshes by
+//! counting signed ray crossings (winding number) along voxel columns.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use glam::Vec3;
+use glam::{Mat4, Vec3};
 
-use crate::obj_parser::ParsedObj;
+use crate::mtl_parser::MtlMaterial;
+use crate::obj_parser::{ParsedObj, NO_TEXCOORD};
 use crate::pool::*;
+use crate::quantize::{
+    self, linear_to_oklab, oklab_to_linear, srgb_to_linear, ColorItem, QuantizeReport,
+    QuantizeStats,
+};
 use crate::scene::{ChunkData, IndexBufBuilder, MaterialEntry, OccupancyBuilder, PaletteBuilder};
 
 /// Result of voxelization: chunk data ready for upload + material table + scene params.
@@ -19,119 +38,506 @@ pub struct VoxelizeResult {
     pub materials: Vec<MaterialEntry>,
     /// Size of one voxel in world units. world_pos = grid_pos * voxel_size + grid_origin.
     pub voxel_size: f32,
-    /// World-space origin of the voxel grid. Derived from mesh AABB.
+    /// World-space origin of the voxel grid. Derived from mesh AABB unless given explicitly.
     pub grid_origin: [f32; 3],
     /// World-space center of the source mesh.
     pub mesh_center: [f32; 3],
     /// Longest axis of the source mesh in world units.
     pub mesh_extent: f32,
+    /// Color error from fitting colors into the material table and chunk
+    /// palettes; zero when nothing had to be merged.
+    pub quantize: QuantizeReport,
+}
+
+/// How the voxelizer treats the inside of a mesh.
+#[derive(Clone, Copy)]
+pub enum Fill {
+    /// Only voxels touched by a triangle: closed meshes become hollow shells.
+    Surface,
+    /// Surface voxels plus the interior of closed meshes.
+    ///
+    /// `interior` is the material for filled voxels; `None` inherits the
+    /// material of the surface each column entered through. Meshes that are
+    /// not watertight fall back to a majority vote of rays along X, Y and Z.
+    Solid { interior: Option<MaterialEntry> },
+}
+
+/// How the voxelizer picks the material of a voxel touched by triangles of
+/// different materials. Ties go to the earlier triangle in mesh order.
+/// Solid-fill interior voxels are not affected.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum MaterialConflict {
+    /// The first triangle in mesh order wins.
+    #[default]
+    First,
+    /// The material with the most triangle area inside the voxel wins.
+    AreaWeighted,
+    /// The material of the triangle closest to the voxel center wins.
+    Closest,
+    /// The material whose name comes first in the list wins; materials not
+    /// in the list rank after every listed one.
+    Priority(Vec<String>),
+}
+
+/// Where surface voxels get their color. Triangles without the required
+/// data (no vertex colors, no texcoords) keep their group material.
+#[derive(Clone, Default)]
+pub enum ColorSource {
+    /// The material group of the triangle that wins the voxel.
+    #[default]
+    Material,
+    /// [`ParsedObj::colors`] interpolated at the voxel center.
+    VertexColors,
+    /// The texture sampled at the voxel center's interpolated texcoord.
+    Texture(Arc<Texture>),
+}
+
+/// Decoded RGBA8 image for [`ColorSource::Texture`], rows top to bottom.
+pub struct Texture {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl Texture {
+    /// `None` unless `rgba` holds exactly `width * height` pixels.
+    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Option<Self> {
+        let pixels = width as u64 * height as u64;
+        (pixels > 0 && rgba.len() as u64 == pixels * 4).then_some(Self { width, height, rgba })
+    }
+
+    /// Nearest texel at `uv`, wrapping outside 0..1. `v = 0` is the bottom
+    /// row, as in OBJ.
+    pub fn sample(&self, uv: [f32; 2]) -> [u8; 3] {
+        let x = (uv[0].rem_euclid(1.0) * self.width as f32) as u32;
+        let y = ((1.0 - uv[1].rem_euclid(1.0)) * self.height as f32) as u32;
+        let (x, y) = (x.min(self.width - 1), y.min(self.height - 1));
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [self.rgba[i], self.rgba[i + 1], self.rgba[i + 2]]
+    }
 }
 
 /// Voxelize a parsed OBJ mesh into chunk data.
 ///
 /// `resolution` controls voxels along the mesh's longest axis.
 /// Returns chunk data and a material table ready for upload.
-pub fn voxelize(parsed: &ParsedObj, resolution: u32) -> VoxelizeResult {
-    let resolution = resolution.max(1);
+/// Every material group gets a hashed color; see [`voxelize_with_materials`].
+pub fn voxelize(parsed: &ParsedObj, resolution: u32) -> Result<VoxelizeResult, VoxelizeError> {
+    voxelize_with_materials(parsed, &[], resolution)
+}
+
+/// Voxelize a parsed OBJ mesh, taking material properties from MTL definitions.
+///
+/// Material groups whose name matches an entry in `library` use its albedo,
+/// roughness, emissive and opacity. Groups with no definition fall back to a
+/// deterministic hashed color.
+pub fn voxelize_with_materials(
+    parsed: &ParsedObj,
+    library: &[MtlMaterial],
+    resolution: u32,
+) -> Result<VoxelizeResult, VoxelizeError> {
+    let group_materials = resolve_group_materials(parsed, library);
+    voxelize_with_material_entries(parsed, &group_materials, resolution)
+}
+
+/// Look up each material group of `parsed` by name in an MTL library.
+pub fn resolve_group_materials(
+    parsed: &ParsedObj,
+    library: &[MtlMaterial],
+) -> Vec<Option<MaterialEntry>> {
+    parsed
+        .material_names
+        .iter()
+        .map(|name| library.iter().find(|m| &m.name == name).map(|m| m.to_material_entry()))
+        .collect()
+}
+
+/// Voxelize a parsed mesh with explicit per-group material entries.
+///
+/// `group_materials[i]` supplies the entry for material group `i`; groups that
+/// are `None` or past the end of the slice fall back to a hashed color. Used
+/// by importers (glTF) whose materials are not MTL definitions.
+///
+/// Fails like [`voxelize_with_options`] when the mesh needs more chunks than
+/// the pool has slots.
+pub fn voxelize_with_material_entries(
+    parsed: &ParsedObj,
+    group_materials: &[Option<MaterialEntry>],
+    resolution: u32,
+) -> Result<VoxelizeResult, VoxelizeError> {
+    voxelize_with_options(parsed, group_materials, &VoxelizeOptions::from_resolution(resolution))
+}
+
+/// Grid placement, model transform, fill mode, material conflict policy and
+/// color source for [`voxelize_with_options`].
+#[derive(Clone)]
+pub struct VoxelizeOptions {
+    /// Voxels along the transformed mesh's longest axis. Ignored when
+    /// `voxel_size` is set.
+    pub resolution: u32,
+    /// World-space voxel edge length. `None` derives it from `resolution`.
+    pub voxel_size: Option<f32>,
+    /// World-space grid origin. `None` places the grid half a voxel below the
+    /// transformed mesh's AABB minimum.
+    pub grid_origin: Option<[f32; 3]>,
+    /// Affine model-to-world transform (translate/rotate/scale) applied to
+    /// every vertex before voxelization.
+    pub transform: Mat4,
+    pub fill: Fill,
+    /// Material for voxels covered by several materials.
+    pub conflict: MaterialConflict,
+    pub color: ColorSource,
+}
+
+impl VoxelizeOptions {
+    /// Mesh-relative grid: `resolution` voxels along the longest axis, origin
+    /// at the mesh AABB. Matches [`voxelize`].
+    pub fn from_resolution(resolution: u32) -> Self {
+        Self {
+            resolution,
+            voxel_size: None,
+            grid_origin: None,
+            transform: Mat4::IDENTITY,
+            fill: Fill::Surface,
+            conflict: MaterialConflict::First,
+            color: ColorSource::Material,
+        }
+    }
+
+    /// World-anchored grid. Models voxelized with the same `voxel_size` and
+    /// `grid_origin` land on one consistent grid, whatever their transforms.
+    pub fn world(voxel_size: f32, grid_origin: [f32; 3]) -> Self {
+        Self {
+            voxel_size: Some(voxel_size),
+            grid_origin: Some(grid_origin),
+            ..Self::from_resolution(1)
+        }
+    }
+}
+
+/// Estimate of a voxelization, computed from the mesh bounds and triangle
+/// AABBs without touching any voxels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preflight {
+    pub voxel_size: f32,
+    pub grid_origin: [f32; 3],
+    /// Inclusive grid voxel range covered by the transformed mesh.
+    pub grid_min: [i32; 3],
+    pub grid_max: [i32; 3],
+    /// Upper bound on the chunks the voxelization produces. Counting stops
+    /// once it exceeds `MAX_SLOTS`.
+    pub estimated_chunks: usize,
+}
+
+/// Why [`voxelize_with_options`] refused to run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VoxelizeError {
+    /// `voxel_size` is not a positive finite number.
+    InvalidVoxelSize(f32),
+    /// The model would need more chunks than the pool has slots.
+    TooManyChunks { estimated: usize },
+}
+
+impl std::fmt::Display for VoxelizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoxelizeError::InvalidVoxelSize(size) => write!(f, "invalid voxel size {size}"),
+            VoxelizeError::TooManyChunks { estimated } => write!(
+                f,
+                "model needs about {estimated} chunks, more than the {MAX_SLOTS} pool slots; \
+                 increase the voxel size"
+            ),
+        }
+    }
+}
 
-    // Compute mesh AABB
-    let (mesh_min, mesh_max) = mesh_aabb(parsed);
+impl std::error::Error for VoxelizeError {}
+
+/// Resolve the grid for `options` and estimate the chunk count.
+///
+/// Surface voxelization touches only chunks overlapped by some triangle's
+/// AABB; solid fill also spans every chunk between them in a chunk column.
+pub fn preflight(
+    parsed: &ParsedObj,
+    options: &VoxelizeOptions,
+) -> Result<Preflight, VoxelizeError> {
+    let positions = transformed_positions(parsed, &options.transform);
+    let (mesh_min, mesh_max) = mesh_aabb(&positions);
     let extent = mesh_max - mesh_min;
     let longest = extent.x.max(extent.y).max(extent.z);
 
     if longest <= 0.0 || parsed.triangles.is_empty() {
-        return VoxelizeResult {
-            chunks: Vec::new(),
-            materials: build_material_table(parsed),
-            voxel_size: 1.0,
-            grid_origin: [0.0; 3],
-            mesh_center: [0.0; 3],
-            mesh_extent: 0.0,
-        };
+        return Ok(Preflight {
+            voxel_size: options.voxel_size.unwrap_or(1.0),
+            grid_origin: options.grid_origin.unwrap_or([0.0; 3]),
+            grid_min: [0; 3],
+            grid_max: [-1; 3],
+            estimated_chunks: 0,
+        });
     }
 
-    let voxel_size = longest / resolution as f32;
-    // Grid origin: offset so mesh sits at (0,0,0) in grid space with a small margin
-    let margin = Vec3::splat(voxel_size * 0.5);
-    let grid_origin = mesh_min - margin;
-
-    // Grid dimensions in voxels
-    let grid_dims = [
-        ((extent.x + margin.x * 2.0) / voxel_size).ceil() as u32,
-        ((extent.y + margin.y * 2.0) / voxel_size).ceil() as u32,
-        ((extent.z + margin.z * 2.0) / voxel_size).ceil() as u32,
-    ];
-
-    // Per-chunk accumulators keyed by chunk coord
-    let mut chunk_map: HashMap<(i32, i32, i32), ChunkAccum> = HashMap::new();
+    let voxel_size = options
+        .voxel_size
+        .unwrap_or(longest / options.resolution.max(1) as f32);
+    if !(voxel_size.is_finite() && voxel_size > 0.0) {
+        return Err(VoxelizeError::InvalidVoxelSize(voxel_size));
+    }
+    // Default origin: offset so the mesh sits at (0,0,0) in grid space with a small margin
+    let grid_origin = options
+        .grid_origin
+        .map(Vec3::from)
+        .unwrap_or(mesh_min - Vec3::splat(voxel_size * 0.5));
 
-    let half = Vec3::splat(0.5);
-    let epsilon = 0.01_f32;
-
-    for (tri_idx, tri) in parsed.triangles.iter().enumerate() {
-        // Transform triangle to grid space
-        let v0 = (pos(parsed, tri[0]) - grid_origin) / voxel_size;
-        let v1 = (pos(parsed, tri[1]) - grid_origin) / voxel_size;
-        let v2 = (pos(parsed, tri[2]) - grid_origin) / voxel_size;
-
-        // Triangle AABB with epsilon expansion
-        let tri_min = v0.min(v1).min(v2) - Vec3::splat(epsilon);
-        let tri_max = v0.max(v1).max(v2) + Vec3::splat(epsilon);
-
-        let min_x = (tri_min.x.floor() as i32).max(0);
-        let min_y = (tri_min.y.floor() as i32).max(0);
-        let min_z = (tri_min.z.floor() as i32).max(0);
-        let max_x = (tri_max.x.floor() as i32).min(grid_dims[0] as i32 - 1);
-        let max_y = (tri_max.y.floor() as i32).min(grid_dims[1] as i32 - 1);
-        let max_z = (tri_max.z.floor() as i32).min(grid_dims[2] as i32 - 1);
-
-        let mat_group = parsed.triangle_materials[tri_idx] as u16;
-
-        for gz in min_z..=max_z {
-            for gy in min_y..=max_y {
-                for gx in min_x..=max_x {
-                    let center = Vec3::new(gx as f32 + 0.5, gy as f32 + 0.5, gz as f32 + 0.5);
-                    if triangle_box_overlap(center, half, v0, v1, v2) {
-                        // Compute chunk coord and local position
-                        let cx = floor_div(gx, CS as i32);
-                        let cy = floor_div(gy, CS as i32);
-                        let cz = floor_div(gz, CS as i32);
-
-                        let lx = (euclidean_mod(gx, CS as i32) + 1) as u32;
-                        let ly = (euclidean_mod(gy, CS as i32) + 1) as u32;
-                        let lz = (euclidean_mod(gz, CS as i32) + 1) as u32;
-
-                        let accum = chunk_map
-                            .entry((cx, cy, cz))
-                            .or_insert_with(ChunkAccum::new);
-                        accum.occupancy.set(lx, ly, lz);
+    let epsilon = Vec3::splat(VOXEL_EPSILON);
+    let grid_min = (((mesh_min - grid_origin) / voxel_size) - epsilon).floor().as_ivec3();
+    let grid_max = (((mesh_max - grid_origin) / voxel_size) + epsilon).floor().as_ivec3();
 
-                        // Material: +2 offset (0=MATERIAL_EMPTY, 1=MATERIAL_DEFAULT)
-                        let mat_id = mat_group + 2;
-                        accum.set_material(lx, ly, lz, mat_id);
+    // Distinct chunks touched by triangle AABBs, stopping just past the pool size
+    let limit = MAX_SLOTS as usize;
+    let cs = CS as i32;
+    let mut touched: std::collections::HashSet<(i32, i32, i32)> = std::collections::HashSet::new();
+    'tris: for tri in &parsed.triangles {
+        let v = tri.map(|i| (positions[i as usize] - grid_origin) / voxel_size);
+        let lo = ((v[0].min(v[1]).min(v[2]) - epsilon).floor().as_ivec3()).max(grid_min);
+        let hi = ((v[0].max(v[1]).max(v[2]) + epsilon).floor().as_ivec3()).min(grid_max);
+        for cx in floor_div(lo.x, cs)..=floor_div(hi.x, cs) {
+            for cy in floor_div(lo.y, cs)..=floor_div(hi.y, cs) {
+                for cz in floor_div(lo.z, cs)..=floor_div(hi.z, cs) {
+                    touched.insert((cx, cy, cz));
+                    if touched.len() > limit {
+                        break 'tris;
                     }
                 }
             }
         }
     }
 
-    // Convert accumulators to ChunkData
-    let mut chunks: Vec<ChunkData> = chunk_map
-        .into_iter()
-        .map(|((cx, cy, cz), accum)| accum.into_chunk_data(ChunkCoord { x: cx, y: cy, z: cz }))
-        .collect();
+    let estimated_chunks = match options.fill {
+        Fill::Surface => touched.len(),
+        Fill::Solid { .. } => {
+            let mut columns: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+            for &(cx, cy, cz) in &touched {
+                let span = columns.entry((cx, cz)).or_insert((cy, cy));
+                span.0 = span.0.min(cy);
+                span.1 = span.1.max(cy);
+            }
+            columns.values().map(|&(lo, hi)| (hi - lo + 1) as usize).sum()
+        }
+    };
 
-    // Sort by coord for deterministic output
-    chunks.sort_by_key(|c| (c.coord.x, c.coord.y, c.coord.z));
+    Ok(Preflight {
+        voxel_size,
+        grid_origin: grid_origin.to_array(),
+        grid_min: grid_min.to_array(),
+        grid_max: grid_max.to_array(),
+        estimated_chunks,
+    })
+}
+
+/// Voxelize a mesh into the grid described by `options`.
+///
+/// Runs [`preflight`] first and fails without voxelizing if the estimated
+/// chunk count exceeds `MAX_SLOTS`.
+pub fn voxelize_with_options(
+    parsed: &ParsedObj,
+    group_materials: &[Option<MaterialEntry>],
+    options: &VoxelizeOptions,
+) -> Result<VoxelizeResult, VoxelizeError> {
+    let plan = checked_preflight(parsed, options)?;
+    Ok(VoxelizeJob::planned(parsed.clone(), group_materials.to_vec(), options.clone(), plan)
+        .finish())
+}
+
+/// Expansion of triangle AABBs (in voxels) before picking candidate voxels.
+const VOXEL_EPSILON: f32 = 0.01;
+
+/// [`preflight`], failing if the estimated chunk count exceeds `MAX_SLOTS`.
+fn checked_preflight(
+    parsed: &ParsedObj,
+    options: &VoxelizeOptions,
+) -> Result<Preflight, VoxelizeError> {
+    let plan = preflight(parsed, options)?;
+    if plan.estimated_chunks > MAX_SLOTS as usize {
+        return Err(VoxelizeError::TooManyChunks { estimated: plan.estimated_chunks });
+    }
+    Ok(plan)
+}
+
+/// A voxelization that can be advanced a few chunks at a time.
+///
+/// Creating the job bins every triangle into the bricks it overlaps; each
+/// [`step`](Self::step) then voxelizes the surface of the next chunks from
+/// their brick lists, and [`finish`](Self::finish) runs the solid fill and
+/// builds the chunk data. Output does not depend on how the work was stepped.
+///
+/// The job owns its mesh, so it can be kept between calls from JS.
+pub struct VoxelizeJob {
+    parsed: ParsedObj,
+    group_materials: Vec<Option<MaterialEntry>>,
+    options: VoxelizeOptions,
+    plan: Preflight,
+    /// Transformed mesh AABB; `None` when there is nothing to voxelize.
+    mesh_bounds: Option<(Vec3, Vec3)>,
+    /// Triangles in grid space.
+    tris: Vec<[Vec3; 3]>,
+    /// Per-chunk brick lists, sorted by chunk coord.
+    bins: Vec<ChunkBins>,
+    conflict: ConflictRule,
+    /// Chunks in `bins` already voxelized.
+    next: usize,
+    chunk_map: HashMap<(i32, i32, i32), ChunkAccum>,
+}
+
+impl VoxelizeJob {
+    /// Plan a voxelization with the same checks as [`voxelize_with_options`].
+    pub fn new(
+        parsed: ParsedObj,
+        group_materials: Vec<Option<MaterialEntry>>,
+        options: &VoxelizeOptions,
+    ) -> Result<Self, VoxelizeError> {
+        let plan = checked_preflight(&parsed, options)?;
+        Ok(Self::planned(parsed, group_materials, options.clone(), plan))
+    }
 
-    let center = (mesh_min + mesh_max) * 0.5;
+    fn planned(
+        parsed: ParsedObj,
+        group_materials: Vec<Option<MaterialEntry>>,
+        options: VoxelizeOptions,
+        plan: Preflight,
+    ) -> Self {
+        let positions = transformed_positions(&parsed, &options.transform);
+        let mut job = Self {
+            conflict: ConflictRule::new(&options.conflict, &parsed),
+            parsed,
+            group_materials,
+            options,
+            plan,
+            mesh_bounds: None,
+            tris: Vec::new(),
+            bins: Vec::new(),
+            next: 0,
+            chunk_map: HashMap::new(),
+        };
+
+        // Compute mesh AABB
+        let (mesh_min, mesh_max) = mesh_aabb(&positions);
+        let extent = mesh_max - mesh_min;
+        let longest = extent.x.max(extent.y).max(extent.z);
+        if longest <= 0.0 || job.parsed.triangles.is_empty() {
+            return job;
+        }
+        job.mesh_bounds = Some((mesh_min, mesh_max));
+
+        // Triangles in grid space
+        let grid_origin = Vec3::from(plan.grid_origin);
+        job.tris = job
+            .parsed
+            .triangles
+            .iter()
+            .map(|t| t.map(|i| (positions[i as usize] - grid_origin) / plan.voxel_size))
+            .collect();
+        job.bins = bin_triangles(&job.tris, (plan.grid_min, plan.grid_max));
+        job
+    }
+
+    /// Chunks whose surface has been voxelized, and the total to do.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next, self.bins.len())
+    }
+
+    /// Voxelize the surface of up to `max_chunks` more chunks. Returns `true`
+    /// once every chunk is done.
+    pub fn step(&mut self, max_chunks: usize) -> bool {
+        let end = self.next.saturating_add(max_chunks).min(self.bins.len());
+        let batch: Vec<&ChunkBins> = self.bins[self.next..end].iter().collect();
+        let ctx = SurfaceContext {
+            tris: &self.tris,
+            groups: &self.parsed.triangle_materials,
+            rule: &self.conflict,
+            colors: ColorSampler::new(&self.options.color, &self.parsed),
+            bounds: (self.plan.grid_min, self.plan.grid_max),
+        };
+        let accums = par_map(batch, |bins| (bins.coord, voxelize_chunk(bins, &ctx)));
+        for (coord, accum) in accums {
+            if let Some(accum) = accum {
+                self.chunk_map.insert(coord, accum);
+            }
+        }
+        self.next = end;
+        self.next == self.bins.len()
+    }
+
+    /// Voxelize any remaining chunks, fill the interior for [`Fill::Solid`]
+    /// and build the result.
+    pub fn finish(mut self) -> VoxelizeResult {
+        self.step(usize::MAX);
+        let (parsed, group_materials) = (&self.parsed, &self.group_materials);
+        let options = &self.options;
+        let Some((mesh_min, mesh_max)) = self.mesh_bounds else {
+            return empty_result(parsed, group_materials, options);
+        };
+
+        let interior = match options.fill {
+            Fill::Solid { interior: Some(entry) } => Some((interior_material_id(parsed), entry)),
+            _ => None,
+        };
+        if let Fill::Solid { .. } = options.fill {
+            let bounds = (self.plan.grid_min, self.plan.grid_max);
+            let interior_id = interior.map(|(id, _)| id);
+            fill_interior(parsed, &self.tris, bounds, interior_id, &mut self.chunk_map);
+        }
+
+        // Convert accumulators to ChunkData, sorted by coord for deterministic output
+        let mut accums: Vec<_> = self.chunk_map.into_iter().collect();
+        accums.sort_by_key(|&(coord, _)| coord);
+        let mut materials = build_material_table(parsed, group_materials, interior);
+        let first_color_id = interior_material_id(parsed) as usize + 1;
+        let mut quantize = QuantizeReport {
+            materials: assign_color_materials(&mut accums, &mut materials, first_color_id),
+            ..QuantizeReport::default()
+        };
+        let limited = par_map(accums, |((cx, cy, cz), mut accum)| {
+            let stats = limit_palette(&mut accum, &materials);
+            (accum.into_chunk_data(ChunkCoord { x: cx, y: cy, z: cz }), stats)
+        });
+        let mut chunks = Vec::with_capacity(limited.len());
+        for (chunk, stats) in limited {
+            quantize.palettes.merge(&stats);
+            chunks.push(chunk);
+        }
+
+        let extent = mesh_max - mesh_min;
+        let center = (mesh_min + mesh_max) * 0.5;
+        VoxelizeResult {
+            chunks,
+            materials,
+            voxel_size: self.plan.voxel_size,
+            grid_origin: self.plan.grid_origin,
+            mesh_center: [center.x, center.y, center.z],
+            mesh_extent: extent.x.max(extent.y).max(extent.z),
+            quantize,
+        }
+    }
+}
+
+/// Result for a mesh with nothing to voxelize.
+fn empty_result(
+    parsed: &ParsedObj,
+    group_materials: &[Option<MaterialEntry>],
+    options: &VoxelizeOptions,
+) -> VoxelizeResult {
+    let interior = match options.fill {
+        Fill::Solid { interior: Some(entry) } => Some((interior_material_id(parsed), entry)),
+        _ => None,
+    };
     VoxelizeResult {
-        chunks,
-        materials: build_material_table(parsed),
-        voxel_size,
-        grid_origin: [grid_origin.x, grid_origin.y, grid_origin.z],
-        mesh_center: [center.x, center.y, center.z],
-        mesh_extent: longest,
+        chunks: Vec::new(),
+        materials: build_material_table(parsed, group_materials, interior),
+        voxel_size: 1.0,
+        grid_origin: [0.0; 3],
+        mesh_center: [0.0; 3],
+        mesh_extent: 0.0,
+        quantize: Default::default(),
     }
 }
 
@@ -143,6 +549,9 @@ struct ChunkAccum {
     /// Per-voxel material ID (only for occupied voxels).
     /// Key: (lx, ly, lz) packed as u32.
     voxel_materials: HashMap<u32, u16>,
+    /// Colored surface voxels: material group and RGB, same keys. Turned
+    /// into material IDs by [`assign_color_materials`].
+    voxel_colors: HashMap<u32, (u32, [u8; 3])>,
 }
 
 impl ChunkAccum {
@@ -150,20 +559,22 @@ impl ChunkAccum {
         Self {
             occupancy: OccupancyBuilder::new(),
             voxel_materials: HashMap::new(),
+            voxel_colors: HashMap::new(),
         }
     }
 
     fn set_material(&mut self, lx: u32, ly: u32, lz: u32, mat_id: u16) {
-        let key = lx * CS_P * CS_P + ly * CS_P + lz;
-        self.voxel_materials.entry(key).or_insert(mat_id);
+        self.voxel_materials.entry(voxel_key(lx, ly, lz)).or_insert(mat_id);
     }
 
     fn into_chunk_data(self, coord: ChunkCoord) -> ChunkData {
         let mut palette = PaletteBuilder::new();
         let mut index_buf = IndexBufBuilder::new();
 
-        // Scan all occupied voxels, build palette + per-voxel index assignments
-        for (&key, &mat_id) in &self.voxel_materials {
+        // Scan all occupied voxels in index order, build palette + per-voxel index assignments
+        let mut voxels: Vec<(u32, u16)> = self.voxel_materials.into_iter().collect();
+        voxels.sort_unstable_by_key(|&(key, _)| key);
+        for (key, mat_id) in voxels {
             let lx = key / (CS_P * CS_P);
             let ly = (key / CS_P) % CS_P;
             let lz = key % CS_P;
@@ -180,21 +591,50 @@ impl ChunkAccum {
     }
 }
 
-/// Get vertex position as Vec3.
-fn pos(parsed: &ParsedObj, idx: u32) -> Vec3 {
-    let p = parsed.positions[idx as usize];
-    Vec3::new(p[0], p[1], p[2])
+/// `ChunkAccum::voxel_materials` key of a padded local position.
+fn voxel_key(lx: u32, ly: u32, lz: u32) -> u32 {
+    lx * CS_P * CS_P + ly * CS_P + lz
+}
+
+/// Mark grid voxel `g` occupied. The first material written to a voxel wins.
+fn set_voxel(chunk_map: &mut HashMap<(i32, i32, i32), ChunkAccum>, g: [i32; 3], mat_id: u16) {
+    let (key, [lx, ly, lz]) = chunk_local(g);
+    let accum = chunk_map.entry(key).or_insert_with(ChunkAccum::new);
+    accum.occupancy.set(lx, ly, lz);
+    accum.set_material(lx, ly, lz, mat_id);
+}
+
+/// Whether grid voxel `g` is already occupied.
+fn is_set(chunk_map: &HashMap<(i32, i32, i32), ChunkAccum>, g: [i32; 3]) -> bool {
+    let (key, [lx, ly, lz]) = chunk_local(g);
+    chunk_map.get(&key).is_some_and(|a| a.occupancy.get(lx, ly, lz))
+}
+
+/// Split a grid position into chunk coord and padded local position.
+fn chunk_local(g: [i32; 3]) -> ((i32, i32, i32), [u32; 3]) {
+    let cs = CS as i32;
+    let key = (floor_div(g[0], cs), floor_div(g[1], cs), floor_div(g[2], cs));
+    let local = g.map(|c| (euclidean_mod(c, cs) + 1) as u32);
+    (key, local)
+}
+
+/// Vertex positions mapped through the model transform.
+fn transformed_positions(parsed: &ParsedObj, transform: &Mat4) -> Vec<Vec3> {
+    parsed
+        .positions
+        .iter()
+        .map(|&p| transform.transform_point3(Vec3::from(p)))
+        .collect()
 }
 
 /// Compute mesh AABB.
-fn mesh_aabb(parsed: &ParsedObj) -> (Vec3, Vec3) {
-    if parsed.positions.is_empty() {
+fn mesh_aabb(positions: &[Vec3]) -> (Vec3, Vec3) {
+    if positions.is_empty() {
         return (Vec3::ZERO, Vec3::ZERO);
     }
     let mut min = Vec3::splat(f32::MAX);
     let mut max = Vec3::splat(f32::MIN);
-    for p in &parsed.positions {
-        let v = Vec3::new(p[0], p[1], p[2]);
+    for &v in positions {
         min = min.min(v);
         max = max.max(v);
     }
@@ -204,7 +644,13 @@ fn mesh_aabb(parsed: &ParsedObj) -> (Vec3, Vec3) {
 /// Build material table from parsed OBJ material groups.
 /// Material group i in the OBJ maps to MaterialId (i + 2) in the table.
 /// IDs 0 and 1 are reserved for MATERIAL_EMPTY and MATERIAL_DEFAULT.
-fn build_material_table(parsed: &ParsedObj) -> Vec<MaterialEntry> {
+/// Groups with an entry in `group_materials` use it; the rest get hashed colors.
+/// `interior` places the solid-fill material at its reserved ID.
+fn build_material_table(
+    parsed: &ParsedObj,
+    group_materials: &[Option<MaterialEntry>],
+    interior: Option<(u16, MaterialEntry)>,
+) -> Vec<MaterialEntry> {
     let mut table = vec![
         MaterialEntry::new([0.0; 3], 0.0, [0.0; 3], 0.0);
         MAX_MATERIALS as usize
@@ -215,19 +661,30 @@ fn build_material_table(parsed: &ParsedObj) -> Vec<MaterialEntry> {
     table[MATERIAL_DEFAULT as usize] =
         MaterialEntry::new([0.5, 0.5, 0.5], 0.5, [0.0; 3], 1.0);
 
-    // Assign deterministic colors to material groups
-    for (i, _name) in parsed.material_names.iter().enumerate() {
+    // Supplied entry if present, otherwise a deterministic hashed color
+    for i in 0..parsed.material_names.len() {
         let mat_id = (i as u16) + 2;
         if (mat_id as usize) < table.len() {
-            let color = hash_color_f32(i as u32);
-            table[mat_id as usize] =
-                MaterialEntry::new(color, 0.5, [0.0; 3], 1.0);
+            table[mat_id as usize] = match group_materials.get(i).copied().flatten() {
+                Some(entry) => entry,
+                None => MaterialEntry::new(hash_color_f32(i as u32), 0.5, [0.0; 3], 1.0),
+            };
         }
     }
 
+    if let Some((id, entry)) = interior {
+        table[id as usize] = entry;
+    }
+
     table
 }
 
+/// Material ID reserved for solid-fill interior voxels: the first ID after
+/// the mesh's material groups.
+fn interior_material_id(parsed: &ParsedObj) -> u16 {
+    (parsed.material_names.len() + 2).min(MAX_MATERIALS as usize - 1) as u16
+}
+
 /// Deterministic color from an integer — produces visually distinct hues.
 fn hash_color_f32(id: u32) -> [f32; 3] {
     let mut x = id.wrapping_mul(1664525).wrapping_add(1013904223);
@@ -253,6 +710,797 @@ fn euclidean_mod(a: i32, b: i32) -> i32 {
     if r < 0 { r + b } else { r }
 }
 
+// ─── Brick binning ─────────────────────────────────────────────────────────
+
+/// Edge length of a binning brick in voxels. Bricks tile each chunk's
+/// interior from its low corner; the last brick along each axis is narrower.
+const BRICK_DIM: i32 = 8;
+const BRICKS_PER_AXIS: i32 = (CS as i32 + BRICK_DIM - 1) / BRICK_DIM;
+const BRICKS_PER_CHUNK: usize = (BRICKS_PER_AXIS * BRICKS_PER_AXIS * BRICKS_PER_AXIS) as usize;
+
+/// Padding (in voxels) added to a brick for its triangle overlap test, so
+/// rounding never drops a triangle that overlaps one of the brick's voxels.
+const BRICK_MARGIN: f32 = 0.5;
+
+/// Candidate triangles for the bricks of one chunk, in CSR form: brick `b`
+/// owns `tri_indices[brick_offsets[b]..brick_offsets[b + 1]]`, in mesh order.
+struct ChunkBins {
+    coord: (i32, i32, i32),
+    brick_offsets: Vec<u32>,
+    tri_indices: Vec<u32>,
+}
+
+/// Inclusive voxel range a triangle is tested against: its AABB grown by
+/// `VOXEL_EPSILON`, clipped to `bounds`. Empty when `lo > hi` on some axis.
+fn triangle_voxel_range(tri: &[Vec3; 3], bounds: GridBounds) -> GridBounds {
+    let [v0, v1, v2] = *tri;
+    let tri_min = v0.min(v1).min(v2) - Vec3::splat(VOXEL_EPSILON);
+    let tri_max = v0.max(v1).max(v2) + Vec3::splat(VOXEL_EPSILON);
+    let lo = [0, 1, 2].map(|a| (tri_min[a].floor() as i32).max(bounds.0[a]));
+    let hi = [0, 1, 2].map(|a| (tri_max[a].floor() as i32).min(bounds.1[a]));
+    (lo, hi)
+}
+
+/// Brick-local index `(x * n + y) * n + z` of brick coords within a chunk.
+fn brick_index(b: [i32; 3]) -> usize {
+    ((b[0] * BRICKS_PER_AXIS + b[1]) * BRICKS_PER_AXIS + b[2]) as usize
+}
+
+fn brick_coords(index: usize) -> [i32; 3] {
+    let i = index as i32;
+    let n = BRICKS_PER_AXIS;
+    [i / (n * n), (i / n) % n, i % n]
+}
+
+/// Bin every triangle into the bricks its voxel range overlaps, keeping only
+/// bricks whose (padded) box passes the triangle SAT test.
+fn bin_triangles(tris: &[[Vec3; 3]], bounds: GridBounds) -> Vec<ChunkBins> {
+    let cs = CS as i32;
+    let mut refs: HashMap<(i32, i32, i32), Vec<(u16, u32)>> = HashMap::new();
+
+    for (tri_idx, tri) in tris.iter().enumerate() {
+        let (lo, hi) = triangle_voxel_range(tri, bounds);
+        if (0..3).any(|a| lo[a] > hi[a]) {
+            continue;
+        }
+        let c_lo = lo.map(|v| floor_div(v, cs));
+        let c_hi = hi.map(|v| floor_div(v, cs));
+        for cx in c_lo[0]..=c_hi[0] {
+            for cy in c_lo[1]..=c_hi[1] {
+                for cz in c_lo[2]..=c_hi[2] {
+                    let base = [cx * cs, cy * cs, cz * cs];
+                    let b_lo = [0, 1, 2].map(|a| (lo[a].max(base[a]) - base[a]) / BRICK_DIM);
+                    let b_hi =
+                        [0, 1, 2].map(|a| (hi[a].min(base[a] + cs - 1) - base[a]) / BRICK_DIM);
+                    for bx in b_lo[0]..=b_hi[0] {
+                        for by in b_lo[1]..=b_hi[1] {
+                            for bz in b_lo[2]..=b_hi[2] {
+                                let b = [bx, by, bz];
+                                let start = [0, 1, 2].map(|a| base[a] + b[a] * BRICK_DIM);
+                                let size = b.map(|c| BRICK_DIM.min(cs - c * BRICK_DIM) as f32);
+                                let half = Vec3::from(size) * 0.5;
+                                let center = Vec3::from(start.map(|c| c as f32)) + half;
+                                let half = half + Vec3::splat(BRICK_MARGIN);
+                                if triangle_box_overlap(center, half, tri[0], tri[1], tri[2]) {
+                                    refs.entry((cx, cy, cz))
+                                        .or_default()
+                                        .push((brick_index(b) as u16, tri_idx as u32));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut bins: Vec<ChunkBins> = refs
+        .into_iter()
+        .map(|(coord, mut list)| {
+            // Stable: triangles stay in mesh order within each brick
+            list.sort_by_key(|&(brick, _)| brick);
+            let mut brick_offsets = vec![0u32; BRICKS_PER_CHUNK + 1];
+            for &(brick, _) in &list {
+                brick_offsets[brick as usize + 1] += 1;
+            }
+            for b in 0..BRICKS_PER_CHUNK {
+                brick_offsets[b + 1] += brick_offsets[b];
+            }
+            let tri_indices = list.into_iter().map(|(_, tri)| tri).collect();
+            ChunkBins { coord, brick_offsets, tri_indices }
+        })
+        .collect();
+    bins.sort_by_key(|b| b.coord);
+    bins
+}
+
+/// Read-only inputs shared by every chunk of a [`VoxelizeJob`] step.
+struct SurfaceContext<'a> {
+    /// Triangles in grid space.
+    tris: &'a [[Vec3; 3]],
+    /// Material group per triangle.
+    groups: &'a [u32],
+    rule: &'a ConflictRule,
+    colors: ColorSampler<'a>,
+    bounds: GridBounds,
+}
+
+/// Surface-voxelize one chunk from its brick lists. Each voxel belongs to a
+/// single brick and sees its triangles in mesh order, so the conflict rule
+/// breaks ties in favor of the earlier triangle. The rule picks the voxel's
+/// material group; when coloring, the group's triangle nearest the voxel
+/// center supplies the color. `None` if nothing was hit.
+fn voxelize_chunk(bins: &ChunkBins, ctx: &SurfaceContext) -> Option<ChunkAccum> {
+    let cs = CS as i32;
+    let base = [bins.coord.0 * cs, bins.coord.1 * cs, bins.coord.2 * cs];
+    let half = Vec3::splat(0.5);
+    let mut accum = ChunkAccum::new();
+    let coloring = !matches!(ctx.colors, ColorSampler::None);
+    // Winner per voxel as `(score, group, nearest triangle of the group)`,
+    // lowest score first; for AreaWeighted, `(area, group, nearest)` per
+    // group. Triangles are `(distance², index)`.
+    type Candidate = (f32, u32, (f32, u32));
+    let mut best: HashMap<u32, Candidate> = HashMap::new();
+    let mut areas: HashMap<u32, Vec<Candidate>> = HashMap::new();
+
+    for (brick, range) in bins.brick_offsets.windows(2).enumerate() {
+        if range[0] == range[1] {
+            continue;
+        }
+        let b = brick_coords(brick);
+        let b_lo = [0, 1, 2].map(|a| base[a] + b[a] * BRICK_DIM);
+        let b_hi = [0, 1, 2].map(|a| (b_lo[a] + BRICK_DIM).min(base[a] + cs) - 1);
+        for &tri_idx in &bins.tri_indices[range[0] as usize..range[1] as usize] {
+            let tri = &ctx.tris[tri_idx as usize];
+            let (lo, hi) = triangle_voxel_range(tri, ctx.bounds);
+            let lo = [0, 1, 2].map(|a| lo[a].max(b_lo[a]));
+            let hi = [0, 1, 2].map(|a| hi[a].min(b_hi[a]));
+            let group = ctx.groups[tri_idx as usize];
+            for gz in lo[2]..=hi[2] {
+                for gy in lo[1]..=hi[1] {
+                    for gx in lo[0]..=hi[0] {
+                        let center = Vec3::new(gx as f32 + 0.5, gy as f32 + 0.5, gz as f32 + 0.5);
+                        if !triangle_box_overlap(center, half, tri[0], tri[1], tri[2]) {
+                            continue;
+                        }
+                        let [lx, ly, lz] = [gx - base[0], gy - base[1], gz - base[2]]
+                            .map(|c| (c + 1) as u32);
+                        accum.occupancy.set(lx, ly, lz);
+                        let key = voxel_key(lx, ly, lz);
+                        let dist = if coloring || matches!(ctx.rule, ConflictRule::Closest) {
+                            closest_point_on_triangle(center, tri).distance_squared(center)
+                        } else {
+                            0.0
+                        };
+                        let nearest = (dist, tri_idx);
+                        let score = match ctx.rule {
+                            ConflictRule::First => 0.0,
+                            ConflictRule::AreaWeighted => {
+                                let area = clipped_area(tri, center - half, center + half);
+                                let votes = areas.entry(key).or_default();
+                                match votes.iter_mut().find(|v| v.1 == group) {
+                                    Some(vote) => {
+                                        vote.0 += area;
+                                        if dist < vote.2 .0 {
+                                            vote.2 = nearest;
+                                        }
+                                    }
+                                    None => votes.push((area, group, nearest)),
+                                }
+                                continue;
+                            }
+                            ConflictRule::Closest => dist,
+                            ConflictRule::Priority(ranks) => ranks[group as usize] as f32,
+                        };
+                        let entry = best.entry(key).or_insert((score, group, nearest));
+                        if score < entry.0 {
+                            *entry = (score, group, nearest);
+                        } else if group == entry.1 && dist < entry.2 .0 {
+                            entry.2 = nearest;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let voted = areas.into_iter().map(|(key, votes)| {
+        // Strict comparison: on equal area the material seen first wins
+        let winner = votes.iter().fold(votes[0], |a, &b| if b.0 > a.0 { b } else { a });
+        (key, winner.1, winner.2 .1)
+    });
+    let chosen = best.into_iter().map(|(key, (_, group, (_, tri)))| (key, group, tri));
+    for (key, group, tri_idx) in chosen.chain(voted) {
+        let local = [key / (CS_P * CS_P), (key / CS_P) % CS_P, key % CS_P];
+        let center = Vec3::from([0, 1, 2].map(|a| (base[a] + local[a] as i32 - 1) as f32 + 0.5));
+        match ctx.colors.sample(tri_idx as usize, &ctx.tris[tri_idx as usize], center) {
+            Some(rgb) => accum.voxel_colors.insert(key, (group, rgb)).map(|_| ()),
+            // Material: +2 offset (0=MATERIAL_EMPTY, 1=MATERIAL_DEFAULT)
+            None => accum.voxel_materials.insert(key, group as u16 + 2).map(|_| ()),
+        };
+    }
+
+    (!(accum.voxel_materials.is_empty() && accum.voxel_colors.is_empty())).then_some(accum)
+}
+
+/// [`MaterialConflict`] resolved against a mesh's material groups.
+enum ConflictRule {
+    First,
+    AreaWeighted,
+    Closest,
+    /// Rank of each material group; lower wins.
+    Priority(Vec<u32>),
+}
+
+impl ConflictRule {
+    fn new(conflict: &MaterialConflict, parsed: &ParsedObj) -> Self {
+        match conflict {
+            MaterialConflict::First => ConflictRule::First,
+            MaterialConflict::AreaWeighted => ConflictRule::AreaWeighted,
+            MaterialConflict::Closest => ConflictRule::Closest,
+            MaterialConflict::Priority(names) => {
+                // Triangles may use groups past the end of material_names
+                let groups = parsed.triangle_materials.iter().max().map_or(0, |&g| g as usize + 1);
+                let ranks = (0..groups.max(parsed.material_names.len()))
+                    .map(|g| {
+                        let name = parsed.material_names.get(g);
+                        names
+                            .iter()
+                            .position(|n| Some(n) == name)
+                            .unwrap_or(names.len()) as u32
+                    })
+                    .collect();
+                ConflictRule::Priority(ranks)
+            }
+        }
+    }
+}
+
+/// Per-triangle color lookup for a [`ColorSource`].
+enum ColorSampler<'a> {
+    /// Group materials only.
+    None,
+    Vertex {
+        triangles: &'a [[u32; 3]],
+        colors: &'a [[f32; 3]],
+    },
+    Texture {
+        triangle_texcoords: &'a [[u32; 3]],
+        texcoords: &'a [[f32; 2]],
+        texture: &'a Texture,
+    },
+}
+
+impl<'a> ColorSampler<'a> {
+    fn new(source: &'a ColorSource, parsed: &'a ParsedObj) -> Self {
+        match source {
+            ColorSource::VertexColors if !parsed.colors.is_empty() => ColorSampler::Vertex {
+                triangles: &parsed.triangles,
+                colors: &parsed.colors,
+            },
+            ColorSource::Texture(texture) if !parsed.triangle_texcoords.is_empty() => {
+                ColorSampler::Texture {
+                    triangle_texcoords: &parsed.triangle_texcoords,
+                    texcoords: &parsed.texcoords,
+                    texture,
+                }
+            }
+            _ => ColorSampler::None,
+        }
+    }
+
+    /// Color of triangle `tri_idx` (`tri` in grid space) at its point nearest
+    /// `p`, or `None` to keep the group material.
+    fn sample(&self, tri_idx: usize, tri: &[Vec3; 3], p: Vec3) -> Option<[u8; 3]> {
+        if let ColorSampler::None = self {
+            return None;
+        }
+        let w = barycentric(closest_point_on_triangle(p, tri), tri)?;
+        match self {
+            ColorSampler::None => None,
+            ColorSampler::Vertex { triangles, colors } => {
+                let c = triangles[tri_idx].map(|v| Vec3::from(colors[v as usize]));
+                let rgb = c[0] * w[0] + c[1] * w[1] + c[2] * w[2];
+                Some(rgb.to_array().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+            }
+            ColorSampler::Texture { triangle_texcoords, texcoords, texture } => {
+                let t = triangle_texcoords[tri_idx];
+                if t[0] == NO_TEXCOORD {
+                    return None;
+                }
+                let uv = t.map(|i| texcoords[i as usize]);
+                let u = uv[0][0] * w[0] + uv[1][0] * w[1] + uv[2][0] * w[2];
+                let v = uv[0][1] * w[0] + uv[1][1] * w[1] + uv[2][1] * w[2];
+                Some(texture.sample([u, v]))
+            }
+        }
+    }
+}
+
+/// Barycentric weights of `p` (assumed in the triangle's plane). `None` for
+/// degenerate triangles.
+fn barycentric(p: Vec3, tri: &[Vec3; 3]) -> Option<[f32; 3]> {
+    let (e0, e1, ep) = (tri[1] - tri[0], tri[2] - tri[0], p - tri[0]);
+    let (d00, d01, d11) = (e0.dot(e0), e0.dot(e1), e1.dot(e1));
+    let (d20, d21) = (ep.dot(e0), ep.dot(e1));
+    let denom = d00 * d11 - d01 * d01;
+    if denom.abs() <= f32::EPSILON * d00 * d11 {
+        return None;
+    }
+    let v = (d11 * d20 - d01 * d21) / denom;
+    let w = (d00 * d21 - d01 * d20) / denom;
+    Some([1.0 - v - w, v, w])
+}
+
+/// Give colored voxels material IDs from `first_id` on: each
+/// (group, color) becomes a copy of the group's material with that albedo.
+/// Colors are median-cut within their group until every one fits in the
+/// table, each ID taking the voxel-weighted Oklab mean of its colors. With
+/// more groups than free entries, voxels keep their group material.
+fn assign_color_materials(
+    accums: &mut [((i32, i32, i32), ChunkAccum)],
+    materials: &mut [MaterialEntry],
+    first_id: usize,
+) -> QuantizeStats {
+    let mut counts: HashMap<(u32, [u8; 3]), u64> = HashMap::new();
+    for (_, accum) in accums.iter() {
+        for &color in accum.voxel_colors.values() {
+            *counts.entry(color).or_default() += 1;
+        }
+    }
+    if counts.is_empty() {
+        return QuantizeStats::default();
+    }
+
+    // One starting box per group, colors in a fixed order
+    let mut keys: Vec<(u32, [u8; 3])> = counts.keys().copied().collect();
+    keys.sort_unstable();
+    let mut groups: Vec<Vec<ColorItem>> = Vec::new();
+    for (i, key) in keys.iter().enumerate() {
+        let item = ColorItem {
+            color: linear_to_oklab(key.1.map(srgb_to_linear)),
+            weight: counts[key],
+            id: i as u32,
+        };
+        match groups.last_mut() {
+            Some(group) if keys[group[0].id as usize].0 == key.0 => group.push(item),
+            _ => groups.push(vec![item]),
+        }
+    }
+
+    let budget = materials.len().saturating_sub(first_id);
+    let mut ids = vec![0u16; keys.len()];
+    let (boxes, representatives) = if groups.len() > budget {
+        // No room for any color: fall back to the group material
+        let representatives = groups
+            .iter()
+            .map(|g| {
+                let group = keys[g[0].id as usize].0;
+                for item in g {
+                    ids[item.id as usize] = group as u16 + 2;
+                }
+                let entry = materials.get(group as usize + 2).copied();
+                linear_to_oklab(entry.unwrap_or(materials[MATERIAL_DEFAULT as usize]).albedo())
+            })
+            .collect();
+        (groups, representatives)
+    } else {
+        let boxes = quantize::median_cut(groups, budget);
+        let mut representatives = Vec::with_capacity(boxes.len());
+        for (i, items) in boxes.iter().enumerate() {
+            let id = first_id + i;
+            let group_id = keys[items[0].id as usize].0 as usize + 2;
+            let base_id = if group_id < first_id { group_id } else { MATERIAL_DEFAULT as usize };
+            let base = materials[base_id];
+            let lab = quantize::mean(items);
+            materials[id] = MaterialEntry::new(
+                oklab_to_linear(lab),
+                base.roughness(),
+                base.emissive(),
+                base.opacity(),
+            );
+            representatives.push(lab);
+            for item in items {
+                ids[item.id as usize] = id as u16;
+            }
+        }
+        (boxes, representatives)
+    };
+
+    let index: HashMap<(u32, [u8; 3]), u16> =
+        keys.iter().zip(&ids).map(|(&key, &id)| (key, id)).collect();
+    for (_, accum) in accums.iter_mut() {
+        for (key, color) in std::mem::take(&mut accum.voxel_colors) {
+            accum.voxel_materials.insert(key, index[&color]);
+        }
+    }
+    QuantizeStats::measure(&boxes, &representatives)
+}
+
+/// Fold a chunk's rarest materials into similar ones so it fits one
+/// palette; see [`quantize::limit_palette`].
+fn limit_palette(accum: &mut ChunkAccum, materials: &[MaterialEntry]) -> QuantizeStats {
+    let mut counts: HashMap<u16, u32> = HashMap::new();
+    for &mat in accum.voxel_materials.values() {
+        *counts.entry(mat).or_default() += 1;
+    }
+    let (remap, stats) = quantize::limit_palette(&counts, materials);
+    if !remap.is_empty() {
+        for mat in accum.voxel_materials.values_mut() {
+            if let Some(&to) = remap.get(mat) {
+                *mat = to;
+            }
+        }
+    }
+    stats
+}
+
+/// Area of the part of a triangle inside the box `[lo, hi]`
+/// (Sutherland–Hodgman clip against the six faces).
+fn clipped_area(tri: &[Vec3; 3], lo: Vec3, hi: Vec3) -> f32 {
+    let mut poly = tri.to_vec();
+    for axis in 0..3 {
+        for (bound, below) in [(lo[axis], false), (hi[axis], true)] {
+            let inside = |p: Vec3| if below { p[axis] <= bound } else { p[axis] >= bound };
+            let mut clipped = Vec::with_capacity(poly.len() + 1);
+            for (i, &p) in poly.iter().enumerate() {
+                let q = poly[(i + 1) % poly.len()];
+                if inside(p) {
+                    clipped.push(p);
+                }
+                if inside(p) != inside(q) {
+                    let t = (bound - p[axis]) / (q[axis] - p[axis]);
+                    clipped.push(p + (q - p) * t);
+                }
+            }
+            poly = clipped;
+            if poly.len() < 3 {
+                return 0.0;
+            }
+        }
+    }
+    let mut doubled = Vec3::ZERO;
+    for i in 1..poly.len() - 1 {
+        doubled += (poly[i] - poly[0]).cross(poly[i + 1] - poly[0]);
+    }
+    doubled.length() * 0.5
+}
+
+/// Closest point to `p` on a triangle (Ericson, Real-Time Collision Detection 5.1.5).
+fn closest_point_on_triangle(p: Vec3, tri: &[Vec3; 3]) -> Vec3 {
+    let [a, b, c] = *tri;
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// Map `f` over `items` in parallel (rayon). Output keeps the input order.
+#[cfg(not(target_arch = "wasm32"))]
+fn par_map<T: Send, U: Send>(items: Vec<T>, f: impl Fn(T) -> U + Send + Sync) -> Vec<U> {
+    use rayon::prelude::*;
+    items.into_par_iter().map(f).collect()
+}
+
+/// Map `f` over `items`. No threads on wasm: callers split the work with
+/// [`VoxelizeJob::step`] instead.
+#[cfg(target_arch = "wasm32")]
+fn par_map<T, U>(items: Vec<T>, f: impl Fn(T) -> U) -> Vec<U> {
+    items.into_iter().map(f).collect()
+}
+
+// ─── Solid fill ────────────────────────────────────────────────────────────
+
+/// Inclusive grid voxel range `(min, max)`.
+type GridBounds = ([i32; 3], [i32; 3]);
+
+/// One ray/triangle crossing along an axis-aligned ray.
+#[derive(Clone, Copy)]
+struct Crossing {
+    /// Position along the ray axis, in grid units.
+    t: f32,
+    /// +1 entering (surface faces -axis), -1 leaving (surface faces +axis).
+    winding: i32,
+    /// Material group of the crossed triangle.
+    group: u16,
+}
+
+/// Sorted crossings for every voxel-center ray parallel to one axis.
+struct AxisRays {
+    /// Keyed by the ray's voxel indices on the two other axes,
+    /// `(axis + 1) % 3` then `(axis + 2) % 3`.
+    rays: HashMap<(i32, i32), Vec<Crossing>>,
+}
+
+impl AxisRays {
+    /// Intersect every triangle (in grid space) with the voxel-center rays
+    /// parallel to `axis` inside the inclusive voxel range `bounds`.
+    ///
+    /// Rays through a shared edge or vertex hit exactly one of the adjacent
+    /// triangles (top-left fill rule), so closed meshes give balanced columns.
+    fn build(tris: &[[Vec3; 3]], groups: &[u32], axis: usize, bounds: GridBounds) -> Self {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let (lo, hi) = bounds;
+        let mut rays: HashMap<(i32, i32), Vec<Crossing>> = HashMap::new();
+
+        for (tri, &group) in tris.iter().zip(groups) {
+            let mut p = tri.map(|q| [q[u], q[v], q[axis]]);
+            let mut area = edge_fn(p[0], p[1], p[2]);
+            if area == 0.0 {
+                continue; // edge-on to the rays
+            }
+            // Normal component along +axis means the ray leaves the solid here
+            let winding = if area > 0.0 { -1 } else { 1 };
+            if area < 0.0 {
+                p.swap(1, 2);
+                area = -area;
+            }
+
+            let lo_u = p[0][0].min(p[1][0]).min(p[2][0]);
+            let hi_u = p[0][0].max(p[1][0]).max(p[2][0]);
+            let lo_v = p[0][1].min(p[1][1]).min(p[2][1]);
+            let hi_v = p[0][1].max(p[1][1]).max(p[2][1]);
+            let iu0 = ((lo_u - 0.5).ceil() as i32).max(lo[u]);
+            let iu1 = ((hi_u - 0.5).floor() as i32).min(hi[u]);
+            let iv0 = ((lo_v - 0.5).ceil() as i32).max(lo[v]);
+            let iv1 = ((hi_v - 0.5).floor() as i32).min(hi[v]);
+
+            for iu in iu0..=iu1 {
+                for iv in iv0..=iv1 {
+                    let q = [iu as f32 + 0.5, iv as f32 + 0.5, 0.0];
+                    let w0 = edge_fn(p[1], p[2], q);
+                    let w1 = edge_fn(p[2], p[0], q);
+                    let w2 = edge_fn(p[0], p[1], q);
+                    let inside = covers(w0, p[1], p[2])
+                        && covers(w1, p[2], p[0])
+                        && covers(w2, p[0], p[1]);
+                    if !inside {
+                        continue;
+                    }
+                    let t = (w0 * p[0][2] + w1 * p[1][2] + w2 * p[2][2]) / area;
+                    rays.entry((iu, iv)).or_default().push(Crossing {
+                        t,
+                        winding,
+                        group: group as u16,
+                    });
+                }
+            }
+        }
+
+        for crossings in rays.values_mut() {
+            crossings.sort_by(|a, b| a.t.total_cmp(&b.t));
+        }
+        Self { rays }
+    }
+
+    /// Crossings of the ray keyed `key`, or `None` if they don't balance
+    /// (it escapes through a hole). Rays that hit nothing are empty.
+    fn balanced(&self, key: (i32, i32)) -> Option<&[Crossing]> {
+        let crossings = self.rays.get(&key).map_or(&[][..], |c| c.as_slice());
+        (crossings.iter().map(|c| c.winding).sum::<i32>() == 0).then_some(crossings)
+    }
+}
+
+/// Inside spans of a balanced ray: inclusive voxel ranges `(first, last)`
+/// along the ray whose centers have nonzero winding, each with the
+/// material group of the crossing that opens it.
+fn inside_spans(crossings: &[Crossing]) -> impl Iterator<Item = (i32, i32, u16)> + '_ {
+    let mut winding = 0;
+    crossings.windows(2).filter_map(move |pair| {
+        winding += pair[0].winding;
+        // Centers strictly past the opening crossing, up to the closing one
+        let first = (pair[0].t - 0.5).floor() as i32 + 1;
+        let last = (pair[1].t - 0.5).floor() as i32;
+        (winding != 0 && first <= last).then_some((first, last, pair[0].group))
+    })
+}
+
+/// Twice the signed area of (a, b, q) in the first two components.
+fn edge_fn(a: [f32; 3], b: [f32; 3], q: [f32; 3]) -> f32 {
+    (b[0] - a[0]) * (q[1] - a[1]) - (b[1] - a[1]) * (q[0] - a[0])
+}
+
+/// Top-left fill rule for a counter-clockwise triangle: points exactly on an
+/// edge belong to it only if it is a top or left edge.
+fn covers(w: f32, a: [f32; 3], b: [f32; 3]) -> bool {
+    w > 0.0 || (w == 0.0 && (b[1] < a[1] || (b[1] == a[1] && b[0] < a[0])))
+}
+
+/// Whether every edge of the mesh is matched by an edge running the other
+/// way, i.e. the surface is closed and consistently oriented. Vertices are
+/// compared by position so unwelded meshes (STL) qualify.
+fn is_watertight(parsed: &ParsedObj) -> bool {
+    let key = |i: u32| parsed.positions[i as usize].map(|c| (c + 0.0).to_bits());
+    let mut balance: HashMap<([u32; 3], [u32; 3]), i32> = HashMap::new();
+    for tri in &parsed.triangles {
+        let k = tri.map(key);
+        if k[0] == k[1] || k[1] == k[2] || k[2] == k[0] {
+            continue;
+        }
+        for (a, b) in [(k[0], k[1]), (k[1], k[2]), (k[2], k[0])] {
+            if a < b {
+                *balance.entry((a, b)).or_insert(0) += 1;
+            } else {
+                *balance.entry((b, a)).or_insert(0) -= 1;
+            }
+        }
+    }
+    balance.values().all(|&n| n == 0)
+}
+
+/// Fill interior voxels of the mesh into `chunk_map`, leaving surface voxels
+/// untouched.
+///
+/// Watertight meshes sweep the winding number along Y columns. Other meshes
+/// classify every grid voxel with rays along all three axes and take the
+/// majority of the rays that don't leak through a hole; each ray votes a
+/// whole span between crossings at a time, one chunk column per task.
+fn fill_interior(
+    parsed: &ParsedObj,
+    tris: &[[Vec3; 3]],
+    bounds: GridBounds,
+    interior_id: Option<u16>,
+    chunk_map: &mut HashMap<(i32, i32, i32), ChunkAccum>,
+) {
+    let (lo, hi) = bounds;
+    let mat_id = |group: Option<u16>| interior_id.unwrap_or(group.unwrap_or(0) + 2);
+
+    if is_watertight(parsed) {
+        let y_rays = AxisRays::build(tris, &parsed.triangle_materials, 1, bounds);
+        for (&(gz, gx), crossings) in &y_rays.rays {
+            if crossings.iter().map(|c| c.winding).sum::<i32>() != 0 {
+                continue; // numerically leaky column
+            }
+            let mut winding = 0;
+            let mut group = 0;
+            for pair in crossings.windows(2) {
+                if winding == 0 {
+                    group = pair[0].group;
+                }
+                winding += pair[0].winding;
+                if winding == 0 {
+                    continue;
+                }
+                let y0 = ((pair[0].t - 0.5).floor() as i32 + 1).max(lo[1]);
+                let y1 = ((pair[1].t - 0.5).ceil() as i32).min(hi[1] + 1);
+                for gy in y0..y1 {
+                    if !is_set(chunk_map, [gx, gy, gz]) {
+                        set_voxel(chunk_map, [gx, gy, gz], mat_id(Some(group)));
+                    }
+                }
+            }
+        }
+        return;
+    }
+
+    let groups = &parsed.triangle_materials;
+    let rays = [0, 1, 2].map(|axis| AxisRays::build(tris, groups, axis, bounds));
+    let cs = CS as i32;
+    let columns: Vec<(i32, i32)> = (floor_div(lo[0], cs)..=floor_div(hi[0], cs))
+        .flat_map(|cx| (floor_div(lo[2], cs)..=floor_div(hi[2], cs)).map(move |cz| (cx, cz)))
+        .collect();
+    let filled = par_map(columns, |(cx, cz)| {
+        let chunk_map = &*chunk_map;
+        let mut votes = VoteGrid::new();
+        let mut fill = Vec::new();
+        for cy in floor_div(lo[1], cs)..=floor_div(hi[1], cs) {
+            // This chunk's voxels, clipped to the bounds
+            let base = [cx * cs, cy * cs, cz * cs];
+            let first = [0, 1, 2].map(|a| base[a].max(lo[a]));
+            let last = [0, 1, 2].map(|a| (base[a] + cs - 1).min(hi[a]));
+            votes.clear();
+            // Y first so inherited materials match the watertight path
+            for axis in [1, 0, 2] {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                for iu in first[u]..=last[u] {
+                    for iv in first[v]..=last[v] {
+                        let mut g = [0; 3];
+                        (g[u], g[v]) = (iu, iv);
+                        let Some(crossings) = rays[axis].balanced((iu, iv)) else {
+                            for i in first[axis]..=last[axis] {
+                                g[axis] = i;
+                                votes.abstain(g, base);
+                            }
+                            continue;
+                        };
+                        for (i0, i1, group) in inside_spans(crossings) {
+                            for i in i0.max(first[axis])..=i1.min(last[axis]) {
+                                g[axis] = i;
+                                votes.inside(g, base, group);
+                            }
+                        }
+                    }
+                }
+            }
+            for gx in first[0]..=last[0] {
+                for gy in first[1]..=last[1] {
+                    for gz in first[2]..=last[2] {
+                        let g = [gx, gy, gz];
+                        if let Some(group) = votes.majority(g, base) {
+                            if !is_set(chunk_map, g) {
+                                fill.push((g, mat_id(group)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        fill
+    });
+    for (g, mat) in filled.into_iter().flatten() {
+        set_voxel(chunk_map, g, mat);
+    }
+}
+
+/// Per-voxel ray votes for one chunk. A voxel's three rays each vote
+/// inside, outside or (leaky) abstain; outside is the default.
+struct VoteGrid {
+    inside: Vec<u8>,
+    abstain: Vec<u8>,
+    /// Group of the first inside vote, in axis order.
+    group: Vec<Option<u16>>,
+}
+
+impl VoteGrid {
+    fn new() -> Self {
+        let n = (CS * CS * CS) as usize;
+        Self { inside: vec![0; n], abstain: vec![0; n], group: vec![None; n] }
+    }
+
+    fn clear(&mut self) {
+        self.inside.fill(0);
+        self.abstain.fill(0);
+        self.group.fill(None);
+    }
+
+    fn index(g: [i32; 3], base: [i32; 3]) -> usize {
+        let [x, y, z] = [0, 1, 2].map(|a| (g[a] - base[a]) as usize);
+        (x * CS as usize + y) * CS as usize + z
+    }
+
+    fn inside(&mut self, g: [i32; 3], base: [i32; 3], group: u16) {
+        let i = Self::index(g, base);
+        self.inside[i] += 1;
+        self.group[i].get_or_insert(group);
+    }
+
+    fn abstain(&mut self, g: [i32; 3], base: [i32; 3]) {
+        self.abstain[Self::index(g, base)] += 1;
+    }
+
+    /// `Some(group)` if more rays vote inside than outside.
+    fn majority(&self, g: [i32; 3], base: [i32; 3]) -> Option<Option<u16>> {
+        let i = Self::index(g, base);
+        let outside = 3 - self.inside[i] - self.abstain[i];
+        (self.inside[i] > outside).then_some(self.group[i])
+    }
+}
+
 // ─── SAT triangle-box overlap ──────────────────────────────────────────────
 // Ported from crates/voxelizer/src/reference_cpu.rs
 
@@ -329,6 +1577,10 @@ mod tests {
             triangles: vec![[0, 1, 2]],
             triangle_materials: vec![0],
             material_names: vec!["(default)".to_string()],
+            material_libs: Vec::new(),
+            triangle_objects: vec![0],
+            object_names: vec!["(default)".to_string()],
+            ..Default::default()
         }
     }
 
@@ -339,8 +1591,12 @@ mod tests {
             triangles: Vec::new(),
             triangle_materials: Vec::new(),
             material_names: vec!["(default)".to_string()],
+            material_libs: Vec::new(),
+            triangle_objects: Vec::new(),
+            object_names: vec!["(default)".to_string()],
+            ..Default::default()
         };
-        let result = voxelize(&parsed, 62);
+        let result = voxelize(&parsed, 62).unwrap();
         assert!(result.chunks.is_empty());
     }
 
@@ -351,7 +1607,7 @@ mod tests {
             [5.0, 0.0, 0.0],
             [0.0, 5.0, 0.0],
         );
-        let result = voxelize(&parsed, 10);
+        let result = voxelize(&parsed, 10).unwrap();
         assert!(!result.chunks.is_empty());
         let total: u32 = result.chunks.iter().map(|c| c.occupancy.popcount()).sum();
         assert!(total > 0, "expected occupied voxels");
@@ -381,13 +1637,302 @@ mod tests {
             ],
             triangle_materials: vec![0; 12],
             material_names: vec!["(default)".to_string()],
+            material_libs: Vec::new(),
+            triangle_objects: vec![0; 12],
+            object_names: vec!["(default)".to_string()],
+            ..Default::default()
         };
-        let result = voxelize(&parsed, 10);
+        let result = voxelize(&parsed, 10).unwrap();
         let total: u32 = result.chunks.iter().map(|c| c.occupancy.popcount()).sum();
         // A 10-voxel cube surface should have many voxels
         assert!(total >= 10, "expected >= 10 surface voxels, got {total}");
     }
 
+    fn unit_cube() -> ParsedObj {
+        ParsedObj {
+            positions: vec![
+                [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+                [0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0],
+            ],
+            triangles: vec![
+                [0, 2, 1], [0, 3, 2], // -Z
+                [4, 5, 6], [4, 6, 7], // +Z
+                [0, 1, 5], [0, 5, 4], // -Y
+                [3, 6, 2], [3, 7, 6], // +Y
+                [1, 2, 6], [1, 6, 5], // +X
+                [0, 4, 7], [0, 7, 3], // -X
+            ],
+            triangle_materials: vec![0; 12],
+            material_names: vec!["(default)".to_string()],
+            material_libs: Vec::new(),
+            triangle_objects: vec![0; 12],
+            object_names: vec!["(default)".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn pos(parsed: &ParsedObj, idx: u32) -> Vec3 {
+        Vec3::from(parsed.positions[idx as usize])
+    }
+
+    /// Material ID at grid position `g`, or `None` if empty.
+    fn material_at(result: &VoxelizeResult, g: [i32; 3]) -> Option<u16> {
+        let ((cx, cy, cz), [lx, ly, lz]) = chunk_local(g);
+        let chunk = result
+            .chunks
+            .iter()
+            .find(|c| (c.coord.x, c.coord.y, c.coord.z) == (cx, cy, cz))?;
+        chunk
+            .occupancy
+            .get(lx, ly, lz)
+            .then(|| chunk.palette.entries()[chunk.index_buf.get(lx, ly, lz) as usize])
+    }
+
+    fn popcount(result: &VoxelizeResult) -> u32 {
+        result.chunks.iter().map(|c| c.occupancy.popcount()).sum()
+    }
+
+    fn solid(interior: Option<MaterialEntry>) -> Fill {
+        Fill::Solid { interior }
+    }
+
+    fn options(resolution: u32, fill: Fill) -> VoxelizeOptions {
+        VoxelizeOptions { fill, ..VoxelizeOptions::from_resolution(resolution) }
+    }
+
+    #[test]
+    fn solid_fill_closed_cube() {
+        let parsed = unit_cube();
+        assert!(is_watertight(&parsed));
+        let surface = voxelize_with_options(&parsed, &[], &options(10, Fill::Surface)).unwrap();
+        let filled = voxelize_with_options(&parsed, &[], &options(10, solid(None))).unwrap();
+
+        assert_eq!(material_at(&surface, [5, 5, 5]), None, "surface mode is hollow");
+        // Voxel centers strictly inside the cube: grid 1..=9 on every axis
+        for x in 1..=9 {
+            for y in 1..=9 {
+                for z in 1..=9 {
+                    assert_eq!(material_at(&filled, [x, y, z]), Some(2), "({x},{y},{z})");
+                }
+            }
+        }
+        // Surface voxels are kept, nothing outside the shell is added
+        let mut expected = 9 * 9 * 9;
+        for x in 0..11 {
+            for y in 0..11 {
+                for z in 0..11 {
+                    let g = [x, y, z];
+                    let inner = g.iter().all(|c| (1..=9).contains(c));
+                    if material_at(&surface, g).is_some() {
+                        assert!(material_at(&filled, g).is_some());
+                        expected += u32::from(!inner);
+                    }
+                }
+            }
+        }
+        assert_eq!(popcount(&filled), expected);
+    }
+
+    #[test]
+    fn solid_fill_interior_material() {
+        let parsed = unit_cube();
+        let stone = MaterialEntry::new([0.4, 0.4, 0.4], 0.9, [0.0; 3], 1.0);
+        let result = voxelize_with_options(&parsed, &[], &options(10, solid(Some(stone)))).unwrap();
+        let id = interior_material_id(&parsed);
+        assert_eq!(id, 3);
+        assert_eq!(material_at(&result, [5, 5, 5]), Some(id));
+        assert_eq!(material_at(&result, [0, 5, 5]), Some(2), "surface keeps its group");
+        assert_eq!(result.materials[id as usize].albedo_b_roughness, stone.albedo_b_roughness);
+    }
+
+    #[test]
+    fn solid_fill_ignores_orientation_and_welding() {
+        // Inside-out winding still yields a non-zero winding number
+        let mut inverted = unit_cube();
+        for tri in &mut inverted.triangles {
+            tri.swap(1, 2);
+        }
+        let result = voxelize_with_options(&inverted, &[], &options(10, solid(None))).unwrap();
+        assert_eq!(material_at(&result, [5, 5, 5]), Some(2));
+
+        // Unwelded triangle soup (as from STL) is still watertight
+        let cube = unit_cube();
+        let mut soup = cube.clone();
+        soup.positions =
+            cube.triangles.iter().flatten().map(|&i| cube.positions[i as usize]).collect();
+        soup.triangles = (0..12).map(|t| [t * 3, t * 3 + 1, t * 3 + 2]).collect();
+        assert!(is_watertight(&soup));
+        let result = voxelize_with_options(&soup, &[], &options(10, solid(None))).unwrap();
+        assert_eq!(material_at(&result, [5, 5, 5]), Some(2));
+    }
+
+    #[test]
+    fn solid_fill_open_mesh_falls_back_to_voting() {
+        // Cube without its +Y face: Y columns leak, X and Z rays still agree
+        let mut open = unit_cube();
+        open.triangles.remove(7);
+        open.triangles.remove(6);
+        open.triangle_materials.truncate(10);
+        open.triangle_objects.truncate(10);
+        assert!(!is_watertight(&open));
+
+        let result = voxelize_with_options(&open, &[], &options(10, solid(None))).unwrap();
+        assert_eq!(material_at(&result, [5, 5, 5]), Some(2));
+        assert_eq!(material_at(&result, [5, 9, 5]), Some(2));
+    }
+
+    #[test]
+    fn solid_fill_single_triangle_adds_nothing() {
+        let parsed = make_triangle_obj([0.0, 0.0, 0.0], [5.0, 0.0, 0.0], [0.0, 5.0, 0.0]);
+        let surface = voxelize_with_options(&parsed, &[], &options(10, Fill::Surface)).unwrap();
+        let filled = voxelize_with_options(&parsed, &[], &options(10, solid(None))).unwrap();
+        assert_eq!(popcount(&surface), popcount(&filled));
+    }
+
+    #[test]
+    fn ray_crossings_use_fill_rule_on_shared_edges() {
+        // Rays through the cube's face diagonals must hit exactly one triangle
+        let parsed = unit_cube();
+        let tris: Vec<[Vec3; 3]> =
+            parsed.triangles.iter().map(|t| t.map(|i| pos(&parsed, i) * 2.0)).collect();
+        let rays = AxisRays::build(&tris, &parsed.triangle_materials, 1, ([0; 3], [1; 3]));
+        for gx in 0..2 {
+            for gz in 0..2 {
+                let crossings = rays.balanced((gz, gx)).unwrap();
+                assert_eq!(crossings.len(), 2, "column ({gx},{gz})");
+                assert_eq!(crossings[0].winding, 1);
+                assert_eq!(crossings[1].winding, -1);
+            }
+        }
+    }
+
+    #[test]
+    fn inside_spans_cover_enclosed_centers() {
+        let crossing = |t, winding, group| Crossing { t, winding, group };
+        // Centers at i + 0.5; a crossing exactly on a center leaves it outside
+        let ray = [
+            crossing(1.5, 1, 7), crossing(4.2, -1, 7),
+            crossing(6.0, 1, 3), crossing(6.9, -1, 3),
+        ];
+        let spans: Vec<_> = inside_spans(&ray).collect();
+        assert_eq!(spans, vec![(2, 3, 7), (6, 6, 3)]);
+        // Back-to-back crossings between two centers enclose nothing
+        let thin = [crossing(2.6, 1, 0), crossing(2.9, -1, 0)];
+        assert_eq!(inside_spans(&thin).count(), 0);
+    }
+
+    /// Occupied grid positions, sorted.
+    fn occupied(result: &VoxelizeResult) -> Vec<[i32; 3]> {
+        let cs = CS as i32;
+        let mut out = Vec::new();
+        for chunk in &result.chunks {
+            let base = [chunk.coord.x * cs, chunk.coord.y * cs, chunk.coord.z * cs];
+            for x in 1..=CS {
+                for y in 1..=CS {
+                    for z in 1..=CS {
+                        if chunk.occupancy.get(x, y, z) {
+                            let local = [x, y, z].map(|c| c as i32 - 1);
+                            out.push([base[0] + local[0], base[1] + local[1], base[2] + local[2]]);
+                        }
+                    }
+                }
+            }
+        }
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn world_grid_is_shared_between_models() {
+        let cube = unit_cube();
+        let a = VoxelizeOptions::world(0.1, [0.0; 3]);
+        let b = VoxelizeOptions {
+            transform: Mat4::from_translation(Vec3::new(1.0, -2.0, 0.5)),
+            ..a.clone()
+        };
+        let ra = voxelize_with_options(&cube, &[], &a).unwrap();
+        let rb = voxelize_with_options(&cube, &[], &b).unwrap();
+        assert_eq!(ra.voxel_size, rb.voxel_size);
+        assert_eq!(ra.grid_origin, rb.grid_origin);
+
+        // Translating by whole voxels shifts the voxel set exactly
+        let shifted: Vec<[i32; 3]> =
+            occupied(&ra).into_iter().map(|[x, y, z]| [x + 10, y - 20, z + 5]).collect();
+        assert_eq!(occupied(&rb), shifted);
+        assert!(
+            rb.chunks.iter().any(|c| c.coord.y < 0),
+            "negative grid coords land in negative chunks"
+        );
+    }
+
+    #[test]
+    fn transform_scales_and_rotates() {
+        let cube = unit_cube();
+        let options = VoxelizeOptions {
+            transform: Mat4::from_scale_rotation_translation(
+                Vec3::new(2.0, 1.0, 1.0),
+                glam::Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+                Vec3::ZERO,
+            ),
+            ..VoxelizeOptions::world(0.25, [0.0; 3])
+        };
+        let result = voxelize_with_options(&cube, &[], &options).unwrap();
+        // Rotating 90° about Y turns the stretched X extent into -Z
+        assert!((result.mesh_extent - 2.0).abs() < 1e-5);
+        assert!((result.mesh_center[2] + 1.0).abs() < 1e-5, "center {:?}", result.mesh_center);
+        let cells = occupied(&result);
+        let z_min = cells.iter().map(|c| c[2]).min().unwrap();
+        let x_max = cells.iter().map(|c| c[0]).max().unwrap();
+        assert_eq!(z_min, -8);
+        assert!(x_max <= 4);
+    }
+
+    #[test]
+    fn default_options_match_resolution_path() {
+        let cube = unit_cube();
+        let plain = voxelize(&cube, 10).unwrap();
+        let options = VoxelizeOptions::from_resolution(10);
+        let opts = voxelize_with_options(&cube, &[], &options).unwrap();
+        assert_eq!(occupied(&plain), occupied(&opts));
+        assert_eq!(plain.grid_origin, opts.grid_origin);
+        assert_eq!(plain.voxel_size, opts.voxel_size);
+    }
+
+    #[test]
+    fn preflight_bounds_chunk_count() {
+        let cube = unit_cube();
+        for fill in [Fill::Surface, solid(None)] {
+            let options = VoxelizeOptions { fill, ..VoxelizeOptions::world(0.01, [0.0; 3]) };
+            let plan = preflight(&cube, &options).unwrap();
+            let result = voxelize_with_options(&cube, &[], &options).unwrap();
+            assert!(plan.estimated_chunks >= result.chunks.len());
+            // Faces on voxel boundaries touch the neighbors on both sides
+            assert_eq!(plan.grid_min, [-1; 3]);
+            assert_eq!(plan.grid_max, [100; 3]);
+            assert!(plan.estimated_chunks <= 27);
+        }
+    }
+
+    #[test]
+    fn preflight_rejects_oversized_models() {
+        let cube = unit_cube();
+        let options = VoxelizeOptions::world(1e-4, [0.0; 3]);
+        match voxelize_with_options(&cube, &[], &options) {
+            Err(VoxelizeError::TooManyChunks { estimated }) => {
+                assert!(estimated > MAX_SLOTS as usize)
+            }
+            _ => panic!("expected TooManyChunks"),
+        }
+        assert!(matches!(
+            voxelize(&cube, 10_000),
+            Err(VoxelizeError::TooManyChunks { .. })
+        ));
+        assert!(matches!(
+            voxelize_with_options(&cube, &[], &VoxelizeOptions::world(0.0, [0.0; 3])),
+            Err(VoxelizeError::InvalidVoxelSize(_))
+        ));
+    }
+
     #[test]
     fn voxelize_multi_material() {
         let parsed = ParsedObj {
@@ -398,8 +1943,12 @@ mod tests {
             triangles: vec![[0, 1, 2], [3, 4, 5]],
             triangle_materials: vec![0, 1],
             material_names: vec!["red".to_string(), "blue".to_string()],
+            material_libs: Vec::new(),
+            triangle_objects: vec![0, 0],
+            object_names: vec!["(default)".to_string()],
+            ..Default::default()
         };
-        let result = voxelize(&parsed, 10);
+        let result = voxelize(&parsed, 10).unwrap();
         assert!(!result.chunks.is_empty());
         // Material table should have entries for (default), red (id=2), blue (id=3)
         assert_ne!(
@@ -409,6 +1958,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn voxelize_uses_mtl_definitions() {
+        let mut parsed = make_triangle_obj([0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 2.0, 0.0]);
+        parsed.material_names = vec!["(default)".to_string(), "lamp".to_string()];
+        parsed.triangle_materials = vec![1];
+        let library = crate::mtl_parser::parse_mtl("newmtl lamp\nKd 1 1 1\nKe 5 4 3\nNs 0\n");
+
+        let result = voxelize_with_materials(&parsed, &library, 10).unwrap();
+        let lamp = result.materials[3];
+        let expected = MaterialEntry::new([1.0, 1.0, 1.0], 1.0, [5.0, 4.0, 3.0], 1.0);
+        assert_eq!(lamp.albedo_rg, expected.albedo_rg);
+        assert_eq!(lamp.albedo_b_roughness, expected.albedo_b_roughness);
+        assert_eq!(lamp.emissive_rg, expected.emissive_rg);
+        assert_eq!(lamp.emissive_b_opacity, expected.emissive_b_opacity);
+
+        // "(default)" has no definition and keeps its hashed color
+        let fallback = MaterialEntry::new(hash_color_f32(0), 0.5, [0.0; 3], 1.0);
+        assert_eq!(result.materials[2].albedo_rg, fallback.albedo_rg);
+    }
+
     #[test]
     fn voxelize_single_chunk_bounds() {
         // Small mesh that fits in one chunk (resolution=10)
@@ -417,7 +1986,7 @@ mod tests {
             [1.0, 0.0, 0.0],
             [0.0, 1.0, 0.0],
         );
-        let result = voxelize(&parsed, 10);
+        let result = voxelize(&parsed, 10).unwrap();
         assert_eq!(result.chunks.len(), 1, "small mesh should fit in one chunk");
     }
 
@@ -429,7 +1998,7 @@ mod tests {
             [3.0, 0.0, 0.0],
             [0.0, 3.0, 0.0],
         );
-        let result = voxelize(&parsed, 20);
+        let result = voxelize(&parsed, 20).unwrap();
         for chunk in &result.chunks {
             for x in 0..CS_P {
                 for z in 0..CS_P {
@@ -496,4 +2065,370 @@ mod tests {
         assert_eq!(euclidean_mod(-62, 62), 0);
         assert_eq!(euclidean_mod(-63, 62), 61);
     }
+
+    /// Unbinned single-threaded surface pass: every triangle against every
+    /// voxel of its AABB, in mesh order. Oracle for the brick-binned path.
+    fn voxelize_reference(parsed: &ParsedObj, options: &VoxelizeOptions) -> VoxelizeResult {
+        let plan = preflight(parsed, options).unwrap();
+        let positions = transformed_positions(parsed, &options.transform);
+        let origin = Vec3::from(plan.grid_origin);
+        let tris: Vec<[Vec3; 3]> = parsed
+            .triangles
+            .iter()
+            .map(|t| t.map(|i| (positions[i as usize] - origin) / plan.voxel_size))
+            .collect();
+        let bounds = (plan.grid_min, plan.grid_max);
+        let mut chunk_map = HashMap::new();
+        for (tri_idx, tri) in tris.iter().enumerate() {
+            let (lo, hi) = triangle_voxel_range(tri, bounds);
+            let mat_id = parsed.triangle_materials[tri_idx] as u16 + 2;
+            for gz in lo[2]..=hi[2] {
+                for gy in lo[1]..=hi[1] {
+                    for gx in lo[0]..=hi[0] {
+                        let center = Vec3::new(gx as f32 + 0.5, gy as f32 + 0.5, gz as f32 + 0.5);
+                        if triangle_box_overlap(center, Vec3::splat(0.5), tri[0], tri[1], tri[2]) {
+                            set_voxel(&mut chunk_map, [gx, gy, gz], mat_id);
+                        }
+                    }
+                }
+            }
+        }
+        if let Fill::Solid { interior } = options.fill {
+            let interior_id = interior.map(|_| interior_material_id(parsed));
+            fill_interior(parsed, &tris, bounds, interior_id, &mut chunk_map);
+        }
+        let mut chunks: Vec<ChunkData> = chunk_map
+            .into_iter()
+            .map(|((x, y, z), accum)| accum.into_chunk_data(ChunkCoord { x, y, z }))
+            .collect();
+        chunks.sort_by_key(|c| (c.coord.x, c.coord.y, c.coord.z));
+        VoxelizeResult {
+            chunks,
+            materials: Vec::new(),
+            voxel_size: plan.voxel_size,
+            grid_origin: plan.grid_origin,
+            mesh_center: [0.0; 3],
+            mesh_extent: 0.0,
+            quantize: Default::default(),
+        }
+    }
+
+    fn assert_same_chunks(a: &VoxelizeResult, b: &VoxelizeResult) {
+        assert_eq!(a.chunks.len(), b.chunks.len());
+        for (ca, cb) in a.chunks.iter().zip(&b.chunks) {
+            assert_eq!(ca.coord, cb.coord);
+            assert_eq!(ca.occupancy.as_words(), cb.occupancy.as_words(), "{:?}", ca.coord);
+            assert_eq!(ca.palette.entries(), cb.palette.entries(), "{:?}", ca.coord);
+            let bpe = IndexBufBuilder::bits_per_entry(ca.palette.len());
+            assert_eq!(ca.index_buf.pack(bpe), cb.index_buf.pack(bpe), "{:?}", ca.coord);
+        }
+    }
+
+    /// Deterministic soup of overlapping triangles in 4 material groups,
+    /// from tiny slivers up to ones spanning the whole mesh.
+    fn triangle_soup(count: usize, seed: u32) -> ParsedObj {
+        let mut state = seed;
+        let mut next = move || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1u32 << 24) as f32
+        };
+        let mut positions = Vec::new();
+        let mut triangles = Vec::new();
+        for i in 0..count {
+            let anchor = [next() * 10.0, next() * 10.0, next() * 10.0];
+            let scale = if i % 7 == 0 { 6.0 } else { 1.5 };
+            for _ in 0..3 {
+                positions.push(anchor.map(|c| c + (next() - 0.5) * scale));
+            }
+            let base = (i * 3) as u32;
+            triangles.push([base, base + 1, base + 2]);
+        }
+        ParsedObj {
+            positions,
+            triangles,
+            triangle_materials: (0..count as u32).map(|i| i % 4).collect(),
+            material_names: (0..4).map(|i| format!("m{i}")).collect(),
+            material_libs: Vec::new(),
+            triangle_objects: vec![0; count],
+            object_names: vec!["(default)".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn binned_voxelization_matches_reference() {
+        let soup = triangle_soup(150, 7);
+        let rotated = VoxelizeOptions {
+            transform: Mat4::from_rotation_y(0.7) * Mat4::from_scale(Vec3::splat(2.0)),
+            ..options(90, Fill::Surface)
+        };
+        let cases = [
+            (unit_cube(), options(10, Fill::Surface)),
+            (unit_cube(), options(70, solid(None))),
+            (soup, options(90, Fill::Surface)),
+            (triangle_soup(150, 7), rotated),
+            (triangle_soup(100, 99), VoxelizeOptions::world(0.08, [-3.1, -2.7, -4.3])),
+        ];
+        for (parsed, opts) in &cases {
+            let result = voxelize_with_options(parsed, &[], opts).ok().unwrap();
+            assert!(result.chunks.len() > 1 || opts.resolution == 10);
+            assert_same_chunks(&result, &voxelize_reference(parsed, opts));
+        }
+    }
+
+    #[test]
+    fn stepped_job_matches_one_shot() {
+        let parsed = triangle_soup(200, 3);
+        let opts = options(180, Fill::Surface);
+        let mut job = VoxelizeJob::new(parsed.clone(), Vec::new(), &opts).ok().unwrap();
+        let (_, total) = job.progress();
+        assert!(total > 1);
+        let mut steps = 0;
+        while !job.step(1) {
+            steps += 1;
+        }
+        assert_eq!(steps + 1, total);
+        assert_eq!(job.progress(), (total, total));
+        let stepped = job.finish();
+        let one_shot = voxelize_with_options(&parsed, &[], &opts).ok().unwrap();
+        assert_same_chunks(&stepped, &one_shot);
+    }
+
+    #[test]
+    fn bins_keep_mesh_order_per_brick() {
+        let parsed = triangle_soup(100, 11);
+        let opts = options(100, Fill::Surface);
+        let job = VoxelizeJob::new(parsed, Vec::new(), &opts).ok().unwrap();
+        for bins in &job.bins {
+            assert_eq!(bins.brick_offsets.len(), BRICKS_PER_CHUNK + 1);
+            assert_eq!(*bins.brick_offsets.last().unwrap() as usize, bins.tri_indices.len());
+            for range in bins.brick_offsets.windows(2) {
+                let list = &bins.tri_indices[range[0] as usize..range[1] as usize];
+                assert!(list.windows(2).all(|w| w[0] < w[1]));
+            }
+        }
+        assert_eq!(brick_coords(brick_index([3, 5, 7])), [3, 5, 7]);
+    }
+
+    /// Voxel (0, 0, 0) of a unit world grid holds a tiny `m0` triangle
+    /// through its center (first in mesh order) and a large `m1` triangle
+    /// crossing all of it further from the center.
+    fn contested_voxel() -> ParsedObj {
+        ParsedObj {
+            positions: vec![
+                // m0: small, through the voxel center
+                [0.45, 0.45, 0.5], [0.55, 0.45, 0.5], [0.5, 0.55, 0.5],
+                // m1: large, covers the whole voxel cross-section at z = 0.3
+                [-4.0, -4.0, 0.3], [6.0, -4.0, 0.3], [0.5, 6.0, 0.3],
+            ],
+            triangles: vec![[0, 1, 2], [3, 4, 5]],
+            triangle_materials: vec![0, 1],
+            material_names: vec!["m0".to_string(), "m1".to_string()],
+            material_libs: Vec::new(),
+            triangle_objects: vec![0; 2],
+            object_names: vec!["(default)".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn material_conflict_policies() {
+        let parsed = contested_voxel();
+        let (m0, m1) = (Some(2), Some(3));
+        let cases = [
+            (MaterialConflict::First, m0),
+            (MaterialConflict::AreaWeighted, m1),
+            (MaterialConflict::Closest, m0),
+            (MaterialConflict::Priority(vec!["m1".to_string()]), m1),
+            (MaterialConflict::Priority(vec!["m0".to_string(), "m1".to_string()]), m0),
+            (MaterialConflict::Priority(vec!["other".to_string()]), m0),
+        ];
+        for (conflict, expected) in cases {
+            let opts = VoxelizeOptions { conflict, ..VoxelizeOptions::world(1.0, [0.0; 3]) };
+            let result = voxelize_with_options(&parsed, &[], &opts).ok().unwrap();
+            assert_eq!(material_at(&result, [0, 0, 0]), expected, "{:?}", opts.conflict);
+            // Uncontested voxels keep their only material
+            assert_eq!(material_at(&result, [3, 0, 0]), m1);
+        }
+    }
+
+    #[test]
+    fn clipped_area_and_closest_point() {
+        let tri = [Vec3::ZERO, Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0)];
+        assert!((clipped_area(&tri, Vec3::splat(-0.5), Vec3::splat(1.0)) - 1.0).abs() < 1e-5);
+        assert!((clipped_area(&tri, Vec3::splat(-1.0), Vec3::splat(3.0)) - 2.0).abs() < 1e-5);
+        assert_eq!(clipped_area(&tri, Vec3::splat(5.0), Vec3::splat(6.0)), 0.0);
+
+        let p = closest_point_on_triangle(Vec3::new(0.5, 0.5, 3.0), &tri);
+        assert!(p.distance(Vec3::new(0.5, 0.5, 0.0)) < 1e-6);
+        let p = closest_point_on_triangle(Vec3::new(-1.0, -1.0, 0.0), &tri);
+        assert_eq!(p, Vec3::ZERO);
+        let p = closest_point_on_triangle(Vec3::new(2.0, 2.0, 0.0), &tri);
+        assert!(p.distance(Vec3::new(1.0, 1.0, 0.0)) < 1e-6);
+    }
+
+    /// Quad in the plane z = 0.5 spanning x 0..4, y 0..1, colored red at
+    /// x = 0 and blue at x = 4, with u running along x.
+    fn gradient_quad() -> ParsedObj {
+        ParsedObj {
+            positions: vec![[0.0, 0.0, 0.5], [4.0, 0.0, 0.5], [4.0, 1.0, 0.5], [0.0, 1.0, 0.5]],
+            triangles: vec![[0, 1, 2], [0, 2, 3]],
+            triangle_materials: vec![0, 0],
+            material_names: vec!["m".to_string()],
+            triangle_objects: vec![0; 2],
+            object_names: vec!["(default)".to_string()],
+            colors: vec![[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]],
+            texcoords: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            triangle_texcoords: vec![[0, 1, 2], [0, 2, 3]],
+            ..Default::default()
+        }
+    }
+
+    fn albedo_at(result: &VoxelizeResult, g: [i32; 3]) -> [f32; 3] {
+        result.materials[material_at(result, g).unwrap() as usize].albedo()
+    }
+
+    /// `actual` linear albedo matches sRGB `rgb`.
+    fn assert_albedo(actual: [f32; 3], rgb: [u8; 3]) {
+        for c in 0..3 {
+            let expected = srgb_to_linear(rgb[c]);
+            assert!((actual[c] - expected).abs() < 0.005, "{actual:?} vs {rgb:?}");
+        }
+    }
+
+    #[test]
+    fn vertex_colors_interpolate_at_voxel_centers() {
+        let parsed = gradient_quad();
+        let opts = VoxelizeOptions {
+            color: ColorSource::VertexColors,
+            ..VoxelizeOptions::world(1.0, [0.0; 3])
+        };
+        let result = voxelize_with_options(&parsed, &[], &opts).ok().unwrap();
+        // Red fades linearly to blue: 7/8, 5/8, 3/8, 1/8 red at the centers
+        for (x, red) in [(0, 223), (1, 159), (2, 96), (3, 32)] {
+            assert_albedo(albedo_at(&result, [x, 0, 0]), [red, 0, 255 - red]);
+            // Color materials start after the interior ID and keep the
+            // group's roughness
+            let id = material_at(&result, [x, 0, 0]).unwrap();
+            assert!(id > interior_material_id(&parsed));
+            assert_eq!(result.materials[id as usize].roughness(), 0.5);
+        }
+
+        // Without vertex colors the group material is used
+        let plain = ParsedObj { colors: Vec::new(), ..gradient_quad() };
+        let result = voxelize_with_options(&plain, &[], &opts).ok().unwrap();
+        assert_eq!(material_at(&result, [0, 0, 0]), Some(2));
+    }
+
+    #[test]
+    fn texture_sampled_at_voxel_centers() {
+        let texels = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 0]];
+        let rgba = texels.iter().flat_map(|&[r, g, b]| [r, g, b, 255]).collect();
+        let texture = Arc::new(Texture::new(4, 1, rgba).unwrap());
+
+        // A second triangle, away from the quad, has no texcoords
+        let mut parsed = gradient_quad();
+        parsed.positions.extend([[0.0, 5.0, 0.5], [1.0, 5.0, 0.5], [0.0, 6.0, 0.5]]);
+        parsed.triangles.push([4, 5, 6]);
+        parsed.triangle_materials.push(0);
+        parsed.triangle_objects.push(0);
+        parsed.triangle_texcoords.push([NO_TEXCOORD; 3]);
+
+        let opts = VoxelizeOptions {
+            color: ColorSource::Texture(texture),
+            ..VoxelizeOptions::world(1.0, [0.0; 3])
+        };
+        let result = voxelize_with_options(&parsed, &[], &opts).ok().unwrap();
+        for (x, rgb) in texels.into_iter().enumerate() {
+            assert_albedo(albedo_at(&result, [x as i32, 0, 0]), rgb);
+        }
+        assert_eq!(material_at(&result, [0, 5, 0]), Some(2));
+    }
+
+    #[test]
+    fn texture_wraps_and_flips_v() {
+        // Top row red, bottom row blue
+        let texture = Texture::new(1, 2, vec![255, 0, 0, 255, 0, 0, 255, 255]).unwrap();
+        assert_eq!(texture.sample([0.5, 0.25]), [0, 0, 255]);
+        assert_eq!(texture.sample([0.5, 0.75]), [255, 0, 0]);
+        assert_eq!(texture.sample([1.5, -0.75]), [0, 0, 255]);
+        assert_eq!(texture.sample([1.0, 1.0]), [0, 0, 255]);
+
+        assert!(Texture::new(2, 2, vec![0; 15]).is_none());
+        assert!(Texture::new(0, 0, Vec::new()).is_none());
+    }
+
+    #[test]
+    fn color_materials_fit_the_table() {
+        let gray = |v: u8| MaterialEntry::new([v as f32 / 255.0; 3], 0.25, [0.0; 3], 1.0);
+        let grays = || {
+            let mut accum = ChunkAccum::new();
+            for v in 0..64u32 {
+                accum.voxel_colors.insert(v, (0, [(v * 4) as u8; 3]));
+            }
+            vec![((0, 0, 0), accum)]
+        };
+
+        // Six free entries for 64 grays
+        let mut materials = vec![gray(0); 10];
+        let mut accums = grays();
+        let stats = assign_color_materials(&mut accums, &mut materials, 4);
+        assert_eq!((stats.colors_in, stats.colors_out, stats.voxels), (64, 6, 64));
+        assert!(stats.max_error > 0.0);
+        let accum_out = &accums[0].1;
+        assert!(accum_out.voxel_colors.is_empty());
+        let ids: std::collections::BTreeSet<u16> =
+            accum_out.voxel_materials.values().copied().collect();
+        assert_eq!(ids.into_iter().collect::<Vec<_>>(), (4..10).collect::<Vec<_>>());
+        // Albedos stay ordered like the grays they average
+        let first = materials[accum_out.voxel_materials[&0] as usize].albedo()[0];
+        let last = materials[accum_out.voxel_materials[&63] as usize].albedo()[0];
+        assert!(first < 0.05 && last > 0.6, "{first} {last}");
+        assert_eq!(materials[4].roughness(), 0.25);
+
+        // No free entries: fall back to the group material
+        let mut accums = grays();
+        assign_color_materials(&mut accums, &mut materials, 10);
+        assert!(accums[0].1.voxel_materials.values().all(|&id| id == 2));
+    }
+
+    #[test]
+    fn noisy_texture_fits_chunk_palettes() {
+        // 60×60 voxels, one random texel each: far more than one palette
+        let size = 60;
+        let mut seed = 7u32;
+        let rgba = (0..size * size)
+            .flat_map(|_| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                let [_, r, g, b] = seed.to_le_bytes();
+                [r, g, b, 255]
+            })
+            .collect();
+        let texture = Arc::new(Texture::new(size, size, rgba).unwrap());
+        let mut parsed = gradient_quad();
+        for p in &mut parsed.positions {
+            (p[0], p[1]) = (p[0] * size as f32 / 4.0, p[1] * size as f32);
+        }
+        let opts = VoxelizeOptions {
+            color: ColorSource::Texture(texture),
+            ..VoxelizeOptions::world(1.0, [0.0; 3])
+        };
+        let result = voxelize_with_options(&parsed, &[], &opts).ok().unwrap();
+
+        for chunk in &result.chunks {
+            assert!(chunk.palette.len() <= MAX_PALETTE_ENTRIES as usize);
+            for i in 0..CS_P3 {
+                let (x, y, z) = (i / (CS_P * CS_P), (i / CS_P) % CS_P, i % CS_P);
+                if chunk.occupancy.get(x, y, z) {
+                    assert_ne!(chunk.index_buf.get(x, y, z), 0, "voxel lost its material");
+                }
+            }
+        }
+        // Every texel fits the material table; the palettes had to merge
+        let stats = result.quantize;
+        assert_eq!(stats.materials.colors_in, stats.materials.colors_out);
+        assert_eq!(stats.materials.max_error, 0.0);
+        assert!(stats.palettes.colors_in > stats.palettes.colors_out);
+        assert!(stats.palettes.max_error > 0.0 && stats.palettes.mean_error < 0.1);
+    }
 }